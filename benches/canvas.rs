@@ -0,0 +1,162 @@
+//
+// Canvas benchmarks
+// Compare the canvas iterators and bulk operations with the loops over the
+// image planes that apps would otherwise write by hand
+//
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mterm::*;
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 80;
+
+/// An image with a different character in every cell.
+fn busy_image() -> Image {
+    let mut image = Image::new(WIDTH, HEIGHT);
+    image.fill_with(Rect::new(0, 0, WIDTH, HEIGHT), |x, y| {
+        let i = y as usize * WIDTH + x as usize;
+        Char::new(i as u8, PALETTE_CGA[i % 16], PALETTE_CGA[(i / 16) % 16])
+    });
+    image
+}
+
+fn iterators(c: &mut Criterion) {
+    let mut image = busy_image();
+    let mut group = c.benchmark_group("200x80 read");
+    group.bench_function("cells", |b| {
+        b.iter(|| {
+            let mut sum = 0u32;
+            for (x, y, ch) in black_box(&image).cells() {
+                sum = sum.wrapping_add(ch.ink ^ (x + y) as u32);
+            }
+            sum
+        })
+    });
+    group.bench_function("index loop", |b| {
+        b.iter(|| {
+            let image = black_box(&image);
+            let mut sum = 0u32;
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
+                    let i = y * WIDTH + x;
+                    sum = sum.wrapping_add(image.fore_image[i] ^ (x + y) as u32);
+                }
+            }
+            sum
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("200x80 write");
+    group.bench_function("cells_mut", |b| {
+        b.iter(|| {
+            for (x, _, cell) in black_box(&mut image).cells_mut() {
+                *cell.paper = *cell.ink ^ x as u32;
+            }
+        })
+    });
+    group.bench_function("index loop", |b| {
+        b.iter(|| {
+            let image = black_box(&mut image);
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
+                    let i = y * WIDTH + x;
+                    image.back_image[i] = image.fore_image[i] ^ x as u32;
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn blits(c: &mut Criterion) {
+    let mut screen = busy_image();
+    // A 40x20 panel, once with opaque colours, which blit_blend copies a row
+    // at a time, and once half transparent, which it blends cell by cell.
+    let row = "#".repeat(40);
+    let rows = [row.as_str(); 20];
+    let opaque = Image::from_strings(&rows, 0xff00_c0c0u32, 0xff20_2020u32);
+    let translucent = Image::from_strings(&rows, 0x8000_c0c0u32, 0x8020_2020u32);
+    let at = Point::new(80, 30);
+
+    let mut group = c.benchmark_group("40x20 blit onto 200x80");
+    group.bench_function("blit", |b| {
+        b.iter(|| screen.present(|input| input.blit(at, 40, 20, black_box(&opaque))))
+    });
+    group.bench_function("blit_blend opaque", |b| {
+        b.iter(|| screen.present(|input| input.blit_blend(at, black_box(&opaque))))
+    });
+    group.bench_function("blit_blend translucent", |b| {
+        b.iter(|| screen.present(|input| input.blit_blend(at, black_box(&translucent))))
+    });
+    group.finish();
+}
+
+fn fills(c: &mut Criterion) {
+    let mut image = busy_image();
+    let all = Rect::new(0, 0, WIDTH, HEIGHT);
+    let gradient =
+        |x: u32, y: u32| Char::new(b'#', colour(x as u8, y as u8, 0), Colour::Black.into());
+
+    let mut group = c.benchmark_group("200x80 fill");
+    group.bench_function("fill_with", |b| {
+        b.iter(|| black_box(&mut image).fill_with(all, gradient))
+    });
+    group.bench_function("map_cells", |b| {
+        b.iter(|| {
+            black_box(&mut image).map_cells(all, |x, y, ch| Char {
+                ink: gradient(x, y).ink,
+                ..ch
+            })
+        })
+    });
+    group.bench_function("index loop", |b| {
+        b.iter(|| {
+            let image = black_box(&mut image);
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
+                    let i = y * WIDTH + x;
+                    let ch = gradient(x as u32, y as u32);
+                    image.fore_image[i] = ch.ink;
+                    image.back_image[i] = ch.paper;
+                    image.text_image[i] = ch.ch;
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn diffs(c: &mut Criterion) {
+    let prev = busy_image();
+    let changed = |cells: &mut dyn Iterator<Item = usize>| {
+        let mut image = busy_image();
+        cells.for_each(|i| image.fore_image[i] ^= 1);
+        image
+    };
+    // One cell in a hundred changed, in a 16x10 block such as a moving
+    // sprite, or spread out so that every row changes, and every cell.
+    let in_block = |i: &usize| (30..40).contains(&(i / WIDTH)) && (90..106).contains(&(i % WIDTH));
+    let block = changed(&mut (0..WIDTH * HEIGHT).filter(in_block));
+    let spread = changed(&mut (0..WIDTH * HEIGHT).step_by(100));
+    let all = changed(&mut (0..WIDTH * HEIGHT));
+
+    let mut group = c.benchmark_group("200x80 diff");
+    let nexts = [
+        ("1% changed in a block", &block),
+        ("1% changed on every row", &spread),
+        ("100% changed", &all),
+    ];
+    for (name, next) in nexts {
+        group.bench_function(format!("diff, {}", name), |b| {
+            b.iter(|| diff(black_box(&prev), black_box(next)))
+        });
+        group.bench_function(format!("diff_rect, {}", name), |b| {
+            b.iter(|| diff_rect(black_box(&prev), black_box(next)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, iterators, blits, fills, diffs);
+criterion_main!(benches);
@@ -0,0 +1,124 @@
+//
+// Colour benchmarks
+// Time the colour helpers over the ink of an 80x50 window, the work an app
+// does each frame to fade, light or tint the whole screen
+//
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mterm::*;
+
+const WIDTH: usize = 80;
+const HEIGHT: usize = 50;
+
+/// A colour for every cell, with varied components and alphas.
+fn colours() -> Vec<u32> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let i = i as u32;
+            colour_rgba(i as u8, (i * 7) as u8, (i * 13) as u8, (i * 31) as u8)
+        })
+        .collect()
+}
+
+fn helpers(c: &mut Criterion) {
+    let inks = colours();
+    let mut out = vec![0u32; WIDTH * HEIGHT];
+    let mut group = c.benchmark_group("80x50 colour helpers");
+    group.bench_function("scale_colour", |b| {
+        b.iter(|| {
+            let factor = black_box(0.6);
+            for (out, &ink) in out.iter_mut().zip(&inks) {
+                *out = scale_colour(ink, factor);
+            }
+        })
+    });
+    group.bench_function("lerp_colour", |b| {
+        b.iter(|| {
+            let t = black_box(0.3);
+            for (out, &ink) in out.iter_mut().zip(&inks) {
+                *out = lerp_colour(ink, 0xff20_4060, t);
+            }
+        })
+    });
+    group.bench_function("lerp_colour_gamma", |b| {
+        b.iter(|| {
+            let t = black_box(0.3);
+            for (out, &ink) in out.iter_mut().zip(&inks) {
+                *out = lerp_colour_gamma(ink, 0xff20_4060, t);
+            }
+        })
+    });
+    group.bench_function("blend_over", |b| {
+        b.iter(|| {
+            for (out, &ink) in out.iter_mut().zip(&inks) {
+                *out = blend_over(ink, black_box(0xff20_4060));
+            }
+        })
+    });
+    group.finish();
+}
+
+/// The light from a torch in the middle of an 80x50 room, as worked out by the
+/// dungeon example.
+fn torch_light() -> Vec<f32> {
+    let (torch_x, torch_y, radius) = (WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0, 20.0);
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            let dx = (i % WIDTH) as f32 + 0.5 - torch_x;
+            let dy = ((i / WIDTH) as f32 + 0.5 - torch_y) * 2.0;
+            let distance = (dx * dx + dy * dy).sqrt();
+            (1.0 - distance / radius).max(0.1) * 2.0
+        })
+        .collect()
+}
+
+fn lighting(c: &mut Criterion) {
+    let mut image = Image::new(WIDTH, HEIGHT);
+    let inks = colours();
+    let light = torch_light();
+    let mut group = c.benchmark_group("80x50 dungeon lighting");
+    group.bench_function("apply_light_map", |b| {
+        b.iter(|| {
+            image.fore_image.copy_from_slice(&inks);
+            image.back_image.copy_from_slice(&inks);
+            image.apply_light_map(black_box(&light));
+        })
+    });
+    group.bench_function("mul_colour_rgb with a coloured torch", |b| {
+        b.iter(|| {
+            image.fore_image.copy_from_slice(&inks);
+            for (ink, &light) in image.fore_image.iter_mut().zip(black_box(&light)) {
+                *ink = mul_colour_rgb(*ink, light, light * 0.8, light * 0.5);
+            }
+        })
+    });
+    group.finish();
+}
+
+fn greyscale(c: &mut Criterion) {
+    let mut image = Image::new(WIDTH, HEIGHT);
+    let inks = colours();
+    let mut group = c.benchmark_group("80x50 desaturate");
+    group.bench_function("Image::desaturate halfway", |b| {
+        b.iter(|| {
+            image.fore_image.copy_from_slice(&inks);
+            image.back_image.copy_from_slice(&inks);
+            image.desaturate(black_box(0.5));
+        })
+    });
+    group.bench_function("to_greyscale", |b| {
+        b.iter(|| {
+            image.fore_image.copy_from_slice(&inks);
+            image.back_image.copy_from_slice(&inks);
+            image
+                .fore_image
+                .iter_mut()
+                .chain(image.back_image.iter_mut())
+                .for_each(|c| *c = to_greyscale(*c));
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, helpers, lighting, greyscale);
+criterion_main!(benches);
@@ -0,0 +1,236 @@
+//
+// Rendering benchmarks
+// Time whole frames of a headless renderer, from copying the character grid
+// to the graphics card to the frame being finished.  They are skipped on
+// machines with no graphics adapter; set MTERM_BACKEND=gl to run them on a
+// software GL driver.
+//
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use futures::executor::block_on;
+use mterm::*;
+
+/// A font whose glyphs are a single pixel, so that drawing a frame costs
+/// little next to copying the grid, even on a software driver.
+fn dot_font() -> FontData {
+    FontData {
+        data: (0..256)
+            .map(|i| if i % 2 == 0 { 0xffff_ffff } else { 0 })
+            .collect(),
+        width: 1,
+        height: 1,
+        columns: 16,
+        rows: 16,
+        glyph_count: 256,
+    }
+}
+
+/// Create a headless renderer for a grid of characters, filled with text, or
+/// None if there is no adapter to render with.
+fn renderer(columns: usize, rows: usize, font: &FontData) -> Option<RenderState> {
+    let (width, height) = (columns as u32 * font.width, rows as u32 * font.height);
+    let mut render = match block_on(RenderState::new_headless(width, height, font)) {
+        Ok(render) => render,
+        Err(RenderError::AdapterNotFound(..)) => {
+            eprintln!("skipping, there is no graphics adapter");
+            return None;
+        }
+        Err(err) => panic!("could not create a headless renderer, {}", err),
+    };
+
+    let mut image = Image::new(columns, rows);
+    image.fill_with(Rect::new(0, 0, columns, rows), |x, y| {
+        let i = y as usize * columns + x as usize;
+        Char::new(
+            b'a' + (i % 26) as u8,
+            Colour::LightGrey.into(),
+            Colour::Black.into(),
+        )
+    });
+    let (fore_image, back_image, text_image) = render.images();
+    fore_image.copy_from_slice(&image.fore_image);
+    back_image.copy_from_slice(&image.back_image);
+    text_image.copy_from_slice(&image.text_image);
+    frame(&mut render);
+    Some(render)
+}
+
+/// Render a frame and wait for the graphics card to finish it.
+fn frame(render: &mut RenderState) {
+    render.render().unwrap();
+    render.overlay_init().device.poll(wgpu::Maintain::Wait);
+}
+
+/// Render a few frames after `change`, and return how many bytes of the grid
+/// each copied to the graphics card.
+fn bytes_per_frame(render: &mut RenderState, mut change: impl FnMut(&mut RenderState)) -> u64 {
+    let before = render.frame_stats();
+    for _ in 0..10 {
+        change(render);
+        frame(render);
+    }
+    let after = render.frame_stats();
+    (after.bytes_uploaded - before.bytes_uploaded) / (after.frames - before.frames)
+}
+
+fn cursor_blink(c: &mut Criterion) {
+    const COLUMNS: usize = 200;
+    const ROWS: usize = 80;
+    let mut render = match renderer(COLUMNS, ROWS, &dot_font()) {
+        Some(render) => render,
+        None => return,
+    };
+
+    // Only the cursor in the bottom-left corner changes.
+    let cursor = (ROWS - 1) * COLUMNS;
+    let blink = |render: &mut RenderState| {
+        let paper = &mut render.images().1[cursor];
+        *paper ^= 0x00ff_ffff;
+    };
+    let blink_dirty = |render: &mut RenderState| {
+        blink(render);
+        render.set_dirty_region(Rect::new(0, ROWS as i32 - 1, 1, 1));
+    };
+    let everything = |render: &mut RenderState| {
+        render
+            .images()
+            .1
+            .iter_mut()
+            .for_each(|paper| *paper ^= 0x00ff_ffff);
+    };
+
+    eprintln!(
+        "bytes copied a frame: {} for a blink with a dirty region, {} for a blink \
+         without one, {} with every cell changed",
+        bytes_per_frame(&mut render, blink_dirty),
+        bytes_per_frame(&mut render, blink),
+        bytes_per_frame(&mut render, everything),
+    );
+
+    let mut group = c.benchmark_group("200x80 cursor blink frame");
+    group.bench_function("blink with a dirty region", |b| {
+        b.iter(|| {
+            blink_dirty(&mut render);
+            frame(&mut render);
+        })
+    });
+    group.bench_function("blink without a dirty region", |b| {
+        b.iter(|| {
+            blink(&mut render);
+            frame(&mut render);
+        })
+    });
+    group.bench_function("every cell changed", |b| {
+        b.iter(|| {
+            everything(&mut render);
+            frame(&mut render);
+        })
+    });
+    group.finish();
+}
+
+fn large_grid(c: &mut Criterion) {
+    const COLUMNS: usize = 320;
+    const ROWS: usize = 100;
+    let change_everything = |render: &mut RenderState| {
+        render
+            .images()
+            .0
+            .iter_mut()
+            .for_each(|ink| *ink ^= 0x00ff_ffff);
+    };
+
+    // Whole frames, with every cell changed, in the default font and in one
+    // so small that the copy to the graphics card is most of the work.
+    let mut group = c.benchmark_group("320x100 frame with every cell changed");
+    group.sample_size(20);
+    let fonts = [
+        ("default font", BuiltinFont::Default.font_data().unwrap()),
+        ("one-pixel font", dot_font()),
+    ];
+    for (name, font) in &fonts {
+        let mut render = match renderer(COLUMNS, ROWS, font) {
+            Some(render) => render,
+            None => return,
+        };
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                change_everything(&mut render);
+                frame(&mut render);
+            })
+        });
+    }
+    group.finish();
+
+    // The copy on its own, in the packed layout of one 16 byte texel a cell
+    // against the three 4 byte texels a cell used before.
+    let render = match renderer(COLUMNS, ROWS, &dot_font()) {
+        Some(render) => render,
+        None => return,
+    };
+    let init = render.overlay_init();
+    let size = wgpu::Extent3d {
+        width: COLUMNS as u32,
+        height: ROWS as u32,
+        depth_or_array_layers: 1,
+    };
+    let texture = |format: wgpu::TextureFormat| {
+        init.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Benchmark texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        })
+    };
+    let write = |texture: &wgpu::Texture, data: &[u8], texel_size: u32| {
+        init.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(texel_size * COLUMNS as u32),
+                rows_per_image: Some(ROWS as u32),
+            },
+            size,
+        );
+    };
+    let packed = texture(wgpu::TextureFormat::Rgba32Uint);
+    let planes = [
+        texture(wgpu::TextureFormat::Rgba8Unorm),
+        texture(wgpu::TextureFormat::Rgba8Unorm),
+        texture(wgpu::TextureFormat::Rgba8Unorm),
+    ];
+    let packed_data = vec![0x5a; 16 * COLUMNS * ROWS];
+    let plane_data = vec![0x5a; 4 * COLUMNS * ROWS];
+
+    let mut group = c.benchmark_group("320x100 grid copy");
+    group.bench_function("one Rgba32Uint texture", |b| {
+        b.iter(|| {
+            write(&packed, &packed_data, 16);
+            init.queue.submit(None);
+            init.device.poll(wgpu::Maintain::Wait);
+        })
+    });
+    group.bench_function("three Rgba8 textures", |b| {
+        b.iter(|| {
+            for plane in &planes {
+                write(plane, &plane_data, 4);
+            }
+            init.queue.submit(None);
+            init.device.poll(wgpu::Maintain::Wait);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, cursor_blink, large_grid);
+criterion_main!(benches);
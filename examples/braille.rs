@@ -0,0 +1,113 @@
+//
+// Braille example
+// A graph of recent frame times, plotted with `BrailleCanvas` next to text
+// labels.  The default font is given a second page of Braille patterns.
+//
+
+use std::{collections::VecDeque, time::Instant};
+
+use mterm::*;
+
+const WIDTH: usize = 60;
+const HEIGHT: usize = 16;
+
+/// The columns on the left kept for the labels.
+const LABEL_WIDTH: usize = 8;
+
+fn main() {
+    let font = braille_font(BuiltinFont::Default.font_data().unwrap());
+    let app = Box::new(BrailleApp {
+        times: VecDeque::new(),
+        last: None,
+    });
+    let (cell_width, cell_height) = BuiltinFont::Default.cell_size();
+    let app_builder = Builder::new()
+        .with_custom_font(font)
+        .with_initial_size(WIDTH * cell_width as usize, HEIGHT * cell_height as usize)
+        .with_window_title("Braille");
+    run(app, app_builder).unwrap();
+}
+
+/// Add the 256 Braille patterns to a font of 256 characters, as glyphs 256
+/// to 511.
+fn braille_font(mut font: FontData) -> FontData {
+    let (width, height) = (font.width, font.height);
+    let patterns = FontData::from_fn(width, height, |pattern, x, y| {
+        // Each dot fills the middle half of its part of the cell, which is
+        // half the cell's width and a quarter of its height.
+        let (column, row) = (x * 2 / width, y * 4 / height);
+        let (across, down) = (x * 2 % width, y * 4 % height);
+        let inside = |along: u32, size: u32| along * 4 >= size && along * 4 < size * 3;
+        let bit = match (row, column) {
+            (3, 0) => 6,
+            (3, _) => 7,
+            (row, 0) => row,
+            (row, _) => row + 3,
+        };
+        if pattern & (1 << bit) != 0 && inside(across, width) && inside(down, height) {
+            0xffffffff
+        } else {
+            0
+        }
+    });
+    font.data.extend_from_slice(&patterns.data);
+    font.rows += patterns.rows;
+    font.glyph_count += patterns.glyph_count;
+    font
+}
+
+struct BrailleApp {
+    /// Frame times in milliseconds, oldest first.
+    times: VecDeque<f32>,
+    last: Option<Instant>,
+}
+
+impl App for BrailleApp {
+    fn tick(&mut self, _tick_input: TickInput) -> TickResult {
+        let now = Instant::now();
+        if let Some(last) = self.last.replace(now) {
+            self.times.push_back((now - last).as_secs_f32() * 1000.0);
+            if self.times.len() > (WIDTH - LABEL_WIDTH) * 2 {
+                self.times.pop_front();
+            }
+        }
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        present_input.fill_rect(
+            Rect::new(0, 0, width, height),
+            Char::new(b' ', Colour::White.into(), Colour::Black.into()),
+        );
+
+        // The graph is scaled so that the slowest frame reaches the top.
+        let max = self.times.iter().copied().fold(1.0, f32::max);
+        draw_format!(
+            present_input,
+            0,
+            0,
+            Colour::Yellow,
+            Colour::Black,
+            "{:5.1}ms",
+            max
+        );
+        let bottom_left = Point::new(0, height as i32 - 1);
+        present_input.draw_text(bottom_left, "  0.0ms", Colour::Yellow, Colour::Black);
+
+        let bottom = (height * 4) as i32 - 1;
+        let mut braille = BrailleCanvas::new(&mut present_input, 256, Colour::Green);
+        let points = self.times.iter().enumerate().map(|(i, &time)| {
+            let x = (LABEL_WIDTH * 2 + i) as i32;
+            let y = bottom - (time / max * bottom as f32) as i32;
+            Point::new(x, y)
+        });
+        let mut last = None;
+        for point in points {
+            braille.draw_line(last.unwrap_or(point), point);
+            last = Some(point);
+        }
+
+        PresentResult::Changed
+    }
+}
@@ -0,0 +1,73 @@
+//
+// CRT example
+// The CRT post effect, turned on and off with Space.  The Up and Down keys
+// change the curvature of the picture, and Left and Right the scanlines.
+//
+
+use mterm::*;
+use winit::event::VirtualKeyCode;
+
+fn main() {
+    let app = Box::new(CrtApp {
+        enabled: true,
+        params: CrtParams::default(),
+    });
+    let app_builder = Builder::new()
+        .with_initial_size(800, 600)
+        .with_window_title("CRT")
+        .with_post_effect(PostEffect::Crt(CrtParams::default()));
+    run(app, app_builder).unwrap();
+}
+
+struct CrtApp {
+    enabled: bool,
+    params: CrtParams,
+}
+
+impl App for CrtApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        let key = tick_input.key;
+        if !key.pressed {
+            return TickResult::Continue;
+        }
+        let params = &mut self.params;
+        match key.vkey {
+            Some(VirtualKeyCode::Escape) => return TickResult::Stop,
+            Some(VirtualKeyCode::Space) => self.enabled = !self.enabled,
+            Some(VirtualKeyCode::Up) => params.curvature += 0.05,
+            Some(VirtualKeyCode::Down) => params.curvature = (params.curvature - 0.05).max(0.0),
+            Some(VirtualKeyCode::Right) => params.scanlines = (params.scanlines + 0.1).min(1.0),
+            Some(VirtualKeyCode::Left) => params.scanlines = (params.scanlines - 0.1).max(0.0),
+            _ => return TickResult::Continue,
+        }
+        tick_input.commands.set_post_effect(if self.enabled {
+            PostEffect::Crt(self.params)
+        } else {
+            PostEffect::None
+        });
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        present_input.fill_rect(
+            Rect::new(0, 0, width, height),
+            Char::new(b' ', Colour::Green.into(), Colour::Black.into()),
+        );
+        let lines = [
+            "READY.".to_string(),
+            format!("CURVATURE {:.2}", self.params.curvature),
+            format!("SCANLINES {:.1}", self.params.scanlines),
+            "SPACE TO TURN THE EFFECT ON AND OFF".to_string(),
+        ];
+        for (y, line) in lines.iter().enumerate() {
+            present_input.draw_text(
+                Point::new(1, 1 + y as i32),
+                line,
+                Colour::Green,
+                Colour::Black,
+            );
+        }
+        PresentResult::Changed
+    }
+}
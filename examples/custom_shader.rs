@@ -0,0 +1,72 @@
+//
+// Custom shader example
+// A custom post effect, from ripple.wgsl, that ripples the window and fades it
+// to grey and back.  Space turns the effect on and off.
+//
+
+use mterm::*;
+use winit::event::VirtualKeyCode;
+
+fn main() {
+    let app = Box::new(CustomShaderApp { enabled: true });
+    let app_builder = Builder::new()
+        .with_initial_size(800, 600)
+        .with_window_title("Custom shader")
+        .with_custom_post_shader(include_str!("ripple.wgsl"));
+    // A mistake in the shader is reported here, before the window opens.
+    if let Err(err) = run(app, app_builder) {
+        eprintln!("{}", err);
+    }
+}
+
+struct CustomShaderApp {
+    enabled: bool,
+}
+
+impl App for CustomShaderApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        let key = tick_input.key;
+        if !key.pressed {
+            return TickResult::Continue;
+        }
+        match key.vkey {
+            Some(VirtualKeyCode::Escape) => return TickResult::Stop,
+            Some(VirtualKeyCode::Space) => {
+                self.enabled = !self.enabled;
+                tick_input.commands.set_post_effect(if self.enabled {
+                    PostEffect::Custom
+                } else {
+                    PostEffect::None
+                });
+            }
+            _ => {}
+        }
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        let colours = [
+            Colour::Red,
+            Colour::Yellow,
+            Colour::Green,
+            Colour::Cyan,
+            Colour::Blue,
+            Colour::Magenta,
+        ];
+        for y in 0..height {
+            let paper = colours[y % colours.len()];
+            present_input.fill_rect(
+                Rect::new(0, y as i32, width, 1),
+                Char::new(b' ', Colour::White.into(), paper.into()),
+            );
+        }
+        present_input.draw_text(
+            Point::new(1, 1),
+            "SPACE TO TURN THE EFFECT ON AND OFF",
+            Colour::White,
+            Colour::Black,
+        );
+        PresentResult::Changed
+    }
+}
@@ -0,0 +1,105 @@
+//
+// Dungeon lighting example
+// A torch circles a room, lighting it with `Image::apply_light_map`.  The time
+// taken to light each frame is shown in the corner.
+//
+
+use std::time::Instant;
+
+use mterm::*;
+use winit::event::VirtualKeyCode;
+
+const MAP: &[&str] = &[
+    "##########################################",
+    "#........#...............................#",
+    "#........#.......####........#####.......#",
+    "#........#.......#..#........#...#.......#",
+    "#................####........#...#.......#",
+    "#........#...................##.##.......#",
+    "######.###...............................#",
+    "#........#.........#.........#...........#",
+    "#........#.........#.........#...........#",
+    "#........#.........#.........#...........#",
+    "#........#######.#####.#######...........#",
+    "#........................................#",
+    "#........#...............................#",
+    "##########################################",
+];
+
+fn main() {
+    let dungeon = Box::new(DungeonApp { time: 0.0 });
+    let (cell_width, cell_height) = BuiltinFont::Default.cell_size();
+    let app_builder = Builder::new()
+        .with_initial_size(
+            MAP[0].len() * cell_width as usize,
+            (MAP.len() + 2) * cell_height as usize,
+        )
+        .with_window_title("Dungeon");
+    run(dungeon, app_builder).unwrap();
+}
+
+struct DungeonApp {
+    time: f32,
+}
+
+impl App for DungeonApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        self.time += tick_input.dt.as_seconds_f32();
+        match tick_input.key {
+            KeyState {
+                pressed: true,
+                vkey: Some(VirtualKeyCode::Escape),
+                ..
+            } => TickResult::Stop,
+            _ => TickResult::Continue,
+        }
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        let mut image = Image::new(width, height);
+        image.clear(Colour::Black.into(), Colour::Black.into());
+
+        for (y, row) in MAP.iter().enumerate() {
+            for (x, cell) in row.bytes().enumerate() {
+                let ch = match cell {
+                    b'#' => Char::new(b'#', colours::SLATE_GRAY, colour(40, 40, 50)),
+                    _ => Char::new(b'.', colours::DIM_GRAY, colour(20, 16, 12)),
+                };
+                image.draw_char(Point::new(x as i32, y as i32), ch);
+            }
+        }
+
+        // The torch circles the middle of the room, flickering slightly.
+        let (cx, cy) = (MAP[0].len() as f32 / 2.0, MAP.len() as f32 / 2.0);
+        let torch_x = cx + cx * 0.7 * self.time.cos();
+        let torch_y = cy + cy * 0.6 * self.time.sin();
+        let radius = 9.0 + (self.time * 13.0).sin() * 0.4;
+        image.draw_char(
+            Point::new(torch_x as i32, torch_y as i32),
+            Char::new(b'*', colours::ORANGE, colour(20, 16, 12)),
+        );
+
+        let start = Instant::now();
+        let light_map = (0..width * height)
+            .map(|i| {
+                let dx = (i % width) as f32 + 0.5 - torch_x;
+                let dy = ((i / width) as f32 + 0.5 - torch_y) * 2.0;
+                let distance = (dx * dx + dy * dy).sqrt();
+                (1.0 - distance / radius).max(0.1) * 2.0
+            })
+            .collect::<Vec<_>>();
+        image.apply_light_map(&light_map);
+        let elapsed = start.elapsed();
+
+        image.draw_string(
+            Point::new(0, height as i32 - 1),
+            &format!("Lighting took {}us", elapsed.as_micros()),
+            Colour::White.into(),
+            Colour::Black.into(),
+        );
+
+        present_input.blit_screen(&image);
+        PresentResult::Changed
+    }
+}
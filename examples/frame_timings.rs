@@ -0,0 +1,79 @@
+//
+// Frame timings example
+// Fills the window with changing characters, and shows how long each part of
+// the last frame took in the top right corner.  Frames that take longer than
+// 20ms on the CPU are logged to the console.
+//
+
+use mterm::*;
+use winit::event::VirtualKeyCode;
+
+fn main() {
+    let app_builder = Builder::new()
+        .with_initial_size(1000, 600)
+        .with_window_title("Frame timings")
+        .with_slow_frame_warning(20);
+    run(Box::new(FrameTimingsApp::default()), app_builder).unwrap();
+}
+
+#[derive(Default)]
+struct FrameTimingsApp {
+    frame: u32,
+    lines: Vec<String>,
+}
+
+impl App for FrameTimingsApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        if let Some(VirtualKeyCode::Escape) = tick_input.key.vkey {
+            return TickResult::Stop;
+        }
+        self.frame = self.frame.wrapping_add(1);
+
+        let timings = tick_input.frame_timings;
+        let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+        self.lines = vec![
+            format!("tick    {:6.2}ms", ms(timings.tick)),
+            format!("present {:6.2}ms", ms(timings.present)),
+            format!("upload  {:6.2}ms", ms(timings.upload)),
+            format!("render  {:6.2}ms", ms(timings.render)),
+            format!("cpu     {:6.2}ms", ms(timings.cpu())),
+            match timings.gpu {
+                Some(gpu) => format!("gpu     {:6.2}ms", ms(gpu)),
+                None => "gpu          n/a".to_string(),
+            },
+        ];
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+
+        // Change every character each frame, so that the whole grid is
+        // uploaded and there is something to time.
+        let frame = self.frame as usize;
+        present_input.fill_with(Rect::new(0, 0, width, height), |x, y| {
+            let i = x as usize + y as usize * width + frame;
+            Char::new(
+                b'0' + (i % 10) as u8,
+                PALETTE_CGA[i % 16],
+                PALETTE_CGA[(i / 16) % 8],
+            )
+        });
+
+        let panel_width = self.lines.iter().map(|line| line.len()).max().unwrap_or(0) + 2;
+        let x = width.saturating_sub(panel_width);
+        present_input.fill_rect(
+            Rect::new(x as i32, 0, panel_width, self.lines.len() + 2),
+            Char::new(b' ', Colour::White.into(), Colour::Black.into()),
+        );
+        for (y, line) in self.lines.iter().enumerate() {
+            present_input.draw_text(
+                Point::new(x as i32 + 1, 1 + y as i32),
+                line,
+                Colour::Yellow,
+                Colour::Black,
+            );
+        }
+        PresentResult::Changed
+    }
+}
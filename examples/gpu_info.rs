@@ -0,0 +1,72 @@
+//
+// GPU info example
+// Run with `--gpu-info` to print the graphics adapters on the machine, as an
+// app would for a bug report.  Run with `--adapter N` to open the window on
+// the adapter at index N in that list.
+//
+
+use std::env;
+
+use mterm::*;
+use winit::event::VirtualKeyCode;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.iter().any(|arg| arg == "--gpu-info") {
+        print!("{}", diagnostics());
+        return;
+    }
+
+    let mut app_builder = Builder::new()
+        .with_initial_size(800, 300)
+        .with_window_title("GPU info");
+    if let Some(index) = args
+        .iter()
+        .position(|arg| arg == "--adapter")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|index| index.parse().ok())
+    {
+        app_builder = app_builder.with_adapter_index(index);
+    }
+    run(Box::new(GpuInfoApp::default()), app_builder).unwrap();
+}
+
+#[derive(Default)]
+struct GpuInfoApp {
+    lines: Vec<String>,
+}
+
+impl App for GpuInfoApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        if let Some(VirtualKeyCode::Escape) = tick_input.key.vkey {
+            return TickResult::Stop;
+        }
+        let adapter = tick_input.adapter;
+        self.lines = vec![
+            format!("Adapter:      {}", adapter.name),
+            format!("Back-end:     {:?}", adapter.backend),
+            format!("Device type:  {:?}", adapter.device_type),
+            format!("Present mode: {:?}", tick_input.present_mode),
+            String::new(),
+            "Run with --gpu-info to list every adapter".to_string(),
+        ];
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        present_input.fill_rect(
+            Rect::new(0, 0, width, height),
+            Char::new(b' ', Colour::White.into(), Colour::Black.into()),
+        );
+        for (y, line) in self.lines.iter().enumerate() {
+            present_input.draw_text(
+                Point::new(1, 1 + y as i32),
+                line,
+                Colour::White,
+                Colour::Black,
+            );
+        }
+        PresentResult::Changed
+    }
+}
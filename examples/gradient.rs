@@ -0,0 +1,85 @@
+//
+// Gradient example
+// Shows grey and colour ramps for checking that colours appear the same on
+// every platform.  Run with --no-srgb to turn off the sRGB conversion.
+//
+
+use mterm::*;
+use winit::event::VirtualKeyCode;
+
+fn main() {
+    let srgb_conversion = !std::env::args().any(|arg| arg == "--no-srgb");
+    let gradient = Box::new(GradientApp { srgb_conversion });
+    let app_builder = Builder::new()
+        .with_initial_size(800, 480)
+        .with_window_title("Gradient")
+        .with_srgb_conversion(srgb_conversion);
+    run(gradient, app_builder).unwrap();
+}
+
+struct GradientApp {
+    srgb_conversion: bool,
+}
+
+impl App for GradientApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        match tick_input.key {
+            KeyState {
+                pressed: true,
+                vkey: Some(VirtualKeyCode::Escape),
+                ..
+            } => TickResult::Stop,
+            _ => TickResult::Continue,
+        }
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        let mut image = Image::new(width, height);
+        image.clear(Colour::White.into(), Colour::Black.into());
+
+        // Each ramp goes from black to full brightness across the window.  The
+        // steps should look even, and the same on every platform.
+        let ramps = [
+            ("Grey", colour(255, 255, 255)),
+            ("Red", colour(255, 0, 0)),
+            ("Green", colour(0, 255, 0)),
+            ("Blue", colour(0, 0, 255)),
+        ];
+        let ramp_height = (height.saturating_sub(4) / ramps.len()).max(1);
+        for (i, &(name, full)) in ramps.iter().enumerate() {
+            let y = 2 + i * ramp_height;
+            for x in 0..width {
+                let t = x as f32 / (width.max(2) - 1) as f32;
+                let paper = lerp_colour(colour(0, 0, 0), full, t);
+                image.draw_rect_filled(
+                    Point::new(x as i32, y as i32),
+                    1,
+                    ramp_height,
+                    Char::new(b' ', Colour::White.into(), paper),
+                );
+            }
+            image.draw_string(
+                Point::new(1, y as i32),
+                name,
+                Colour::White.into(),
+                Colour::Black.into(),
+            );
+        }
+
+        let title = if self.srgb_conversion {
+            "sRGB conversion on (run with --no-srgb to turn it off)"
+        } else {
+            "sRGB conversion off"
+        };
+        image.draw_string(
+            Point::new(1, 0),
+            title,
+            Colour::White.into(),
+            Colour::Black.into(),
+        );
+
+        present_input.blit_screen(&image);
+        PresentResult::Changed
+    }
+}
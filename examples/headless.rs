@@ -0,0 +1,85 @@
+//
+// Headless example
+// Renders a page of characters on the graphics card without a window, and
+// checks it against the same page drawn on the CPU by `render_image_to_png`.
+// It exits with an error if they differ, so it can be run on a CI machine.
+//
+
+use std::{error, process};
+
+use futures::executor::block_on;
+use mterm::*;
+
+const COLUMNS: usize = 32;
+const ROWS: usize = 12;
+
+/// How far apart a colour component drawn by the graphics card and by the
+/// CPU can be, to allow for their rounding.
+const TOLERANCE: u8 = 2;
+
+fn main() -> std::result::Result<(), Box<dyn error::Error>> {
+    let font = BuiltinFont::Default.font_data()?;
+    let page = test_page();
+
+    let (cell_width, cell_height) = BuiltinFont::Default.cell_size();
+    let (width, height) = (COLUMNS as u32 * cell_width, ROWS as u32 * cell_height);
+    let mut render = block_on(RenderState::new_headless(width, height, &font))?;
+    println!("Rendering on {}", render.adapter_info().name);
+
+    let (fore_image, back_image, text_image) = render.images();
+    fore_image.copy_from_slice(&page.fore_image);
+    back_image.copy_from_slice(&page.back_image);
+    text_image.copy_from_slice(&page.text_image);
+    let gpu = image::RgbaImage::from_raw(width, height, render.render_to_buffer()?)
+        .ok_or("the rendered frame is the wrong size")?;
+    let cpu = image::load_from_memory(&render_image_to_png(&page, &font)?)?.to_rgb8();
+
+    let mismatches = gpu
+        .pixels()
+        .zip(cpu.pixels())
+        .filter(|(g, c)| (0..3).any(|i| g[i].abs_diff(c[i]) > TOLERANCE))
+        .count();
+    if mismatches == 0 {
+        println!("The frame matches the CPU renderer");
+        return Ok(());
+    }
+
+    eprintln!(
+        "{} of {} pixels differ from the CPU renderer, see headless_gpu.png and headless_cpu.png",
+        mismatches,
+        width * height
+    );
+    gpu.save("headless_gpu.png")?;
+    cpu.save("headless_cpu.png")?;
+    process::exit(1);
+}
+
+/// A page using every colour of the CGA palette, a spread of glyphs, and the
+/// attributes that are drawn the same way on every frame.
+fn test_page() -> Image {
+    let mut page = Image::new(COLUMNS, ROWS);
+    page.fill_with(Rect::new(0, 0, COLUMNS, ROWS), |x, y| {
+        let i = (y as usize * COLUMNS + x as usize) % 256;
+        Char::new(i as u8, PALETTE_CGA[i % 16], PALETTE_CGA[(i / 16 + 8) % 16])
+    });
+
+    page.draw_text(
+        Point::new(1, 1),
+        " The quick brown fox ",
+        Colour::White,
+        Colour::Black,
+    );
+    page.fill_rect(
+        Rect::new(1, 3, 10, 1),
+        Char::new(b'B', Colour::Yellow.into(), Colour::DarkBlue.into()).bold(),
+    );
+    page.fill_rect(
+        Rect::new(1, 4, 10, 1),
+        Char::new(b'U', Colour::Cyan.into(), Colour::DarkRed.into()).underline(),
+    );
+    page.fill_rect(
+        Rect::new(1, 5, 10, 1),
+        Char::new(b'R', Colour::Green.into(), Colour::Black.into()).reverse(),
+    );
+    page
+}
@@ -8,9 +8,8 @@ use winit::event::VirtualKeyCode;
 fn main() {
     let hello = Box::new(HelloApp {});
     let app_builder = Builder::new()
-        .with_inner_size(100, 100)
-        .with_title("Hello!")
-        .build();
+        .with_initial_size(100, 100)
+        .with_window_title("Hello!");
     run(hello, app_builder).unwrap();
 }
 
@@ -0,0 +1,83 @@
+//
+// Menu example
+// A scrolling inventory menu, moved with the cursor keys in `tick` and drawn
+// in a panel in `present`.
+//
+
+use mterm::*;
+
+const ITEMS: &[&str] = &[
+    "Short sword",
+    "Long sword",
+    "[yellow]Golden[/] dagger",
+    "Leather armour",
+    "Chain mail",
+    "Wooden shield",
+    "[red]Potion of healing[/]",
+    "[blue]Potion of mana[/]",
+    "[green]Potion of poison[/]",
+    "Scroll of light",
+    "Scroll of teleport",
+    "Torch",
+    "Rope",
+    "Iron rations",
+    "Skeleton key",
+];
+
+fn main() {
+    let app = Box::new(MenuApp {
+        menu: Menu::new(ITEMS),
+        message: String::from("Choose an item with Enter"),
+    });
+    let (cell_width, cell_height) = BuiltinFont::Default.cell_size();
+    let app_builder = Builder::new()
+        .with_initial_size(40 * cell_width as usize, 14 * cell_height as usize)
+        .with_window_title("Menu")
+        .with_exit_on_escape(false);
+    run(app, app_builder).unwrap();
+}
+
+struct MenuApp {
+    menu: Menu,
+    message: String,
+}
+
+impl App for MenuApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        match self.menu.handle_key(&tick_input.key) {
+            MenuEvent::Cancelled => return TickResult::Stop,
+            MenuEvent::Chosen(index) => {
+                self.message = format!("You chose item {}", index + 1);
+            }
+            MenuEvent::Moved | MenuEvent::Ignored => {}
+        }
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        present_input.fill_rect(
+            Rect::new(0, 0, width, height),
+            Char::new(b' ', Colour::White.into(), Colour::Black.into()),
+        );
+        let style = PanelStyle::new(Colour::White, Colour::Blue);
+        let inside = present_input.draw_panel(
+            Point::new(2, 1),
+            width.saturating_sub(4),
+            height.saturating_sub(4),
+            Some("Inventory"),
+            &style,
+        );
+
+        // The menu draws as many items as fit, scrolling to keep the
+        // selection in view.
+        let style =
+            MenuStyle::new(Colour::White, Colour::Blue).with_highlight(Colour::Black, Colour::Cyan);
+        self.menu.draw(&mut present_input, inside, &style);
+
+        let y = height as i32 - 2;
+        present_input.draw_string_centred_row(y, &self.message, Colour::Yellow, Colour::Black);
+
+        PresentResult::Changed
+    }
+}
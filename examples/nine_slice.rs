@@ -0,0 +1,76 @@
+//
+// Nine-slice example
+// A panel skinned with an ornate frame, stretched to fit the window with
+// `blit_nine_slice` as the window is resized.
+//
+
+use mterm::*;
+
+/// The frame, with a double line and a band of shading on every side.  The
+/// edges between the corners are repeated along the sides, and the middle
+/// across the panel.
+const FRAME: &[&str] = &["╔═══╗", "║▒▒▒║", "║▒ ▒║", "║▒▒▒║", "╚═══╝"];
+
+fn main() {
+    let app = Box::new(NineSliceApp {
+        frame: frame_image(),
+    });
+    let (cell_width, cell_height) = BuiltinFont::Default.cell_size();
+    let app_builder = Builder::new()
+        .with_initial_size(40 * cell_width as usize, 16 * cell_height as usize)
+        .with_window_title("Nine-slice");
+    run(app, app_builder).unwrap();
+}
+
+/// The frame in yellow on blue, with the shading in cyan.
+fn frame_image() -> Image {
+    let charmap = Charmap::cp437();
+    Image::from_strings_mapped(FRAME, |c| {
+        let ink = match c {
+            '▒' => Colour::Cyan,
+            _ => Colour::Yellow,
+        };
+        Char::new(
+            charmap.glyph_for(c).unwrap_or(b'?'),
+            ink.into(),
+            Colour::Blue.into(),
+        )
+    })
+}
+
+struct NineSliceApp {
+    frame: Image,
+}
+
+impl App for NineSliceApp {
+    fn tick(&mut self, _tick_input: TickInput) -> TickResult {
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        present_input.fill_rect(
+            Rect::new(0, 0, width, height),
+            Char::new(b' ', Colour::White.into(), Colour::Black.into()),
+        );
+
+        // The panel keeps a one cell gap around it, and the text sits inside
+        // the frame's border.
+        let panel = Rect::new(1, 1, width.saturating_sub(2), height.saturating_sub(2));
+        present_input.blit_nine_slice(panel, &self.frame, Margins::uniform(2));
+        present_input.draw_text(
+            Point::new(4, 4),
+            "Resize the window",
+            Colour::White,
+            Colour::Blue,
+        );
+        present_input.draw_text(
+            Point::new(4, 5),
+            "to stretch the frame",
+            Colour::White,
+            Colour::Blue,
+        );
+
+        PresentResult::Changed
+    }
+}
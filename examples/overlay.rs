@@ -0,0 +1,153 @@
+//
+// Overlay example
+// A waveform plotted with wgpu on top of the characters, through the
+// `App::init_overlay` and `App::render_overlay` hooks.
+//
+
+use std::time::Instant;
+
+use bytemuck::cast_slice;
+use mterm::*;
+use wgpu::{
+    BlendState, Buffer, BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites,
+    FragmentState, LoadOp, MultisampleState, Operations, PipelineLayoutDescriptor, PrimitiveState,
+    PrimitiveTopology, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, StoreOp, VertexBufferLayout,
+    VertexState, VertexStepMode,
+};
+use winit::event::VirtualKeyCode;
+
+/// The number of points along the waveform.
+const POINTS: u32 = 256;
+
+fn main() {
+    let app = Box::new(OverlayApp {
+        start: Instant::now(),
+        resources: None,
+    });
+    let app_builder = Builder::new()
+        .with_initial_size(800, 600)
+        .with_window_title("Overlay");
+    run(app, app_builder).unwrap();
+}
+
+struct OverlayApp {
+    start: Instant,
+    /// The pipeline and vertex buffer for the waveform, created by
+    /// `init_overlay`.
+    resources: Option<(RenderPipeline, Buffer)>,
+}
+
+impl App for OverlayApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        match tick_input.key.vkey {
+            Some(VirtualKeyCode::Escape) => TickResult::Stop,
+            _ => TickResult::Continue,
+        }
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        present_input.fill_rect(
+            Rect::new(0, 0, width, height),
+            Char::new(b' ', Colour::White.into(), Colour::DarkBlue.into()),
+        );
+        present_input.draw_text(
+            Point::new(1, 1),
+            "A waveform drawn with wgpu over the characters",
+            Colour::White,
+            Colour::DarkBlue,
+        );
+        // The waveform moves, so the window is drawn every frame.
+        PresentResult::Changed
+    }
+
+    fn init_overlay(&mut self, init: OverlayInit) {
+        let device = init.device;
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Waveform shader"),
+            source: ShaderSource::Wgsl(include_str!("waveform.wgsl").into()),
+        });
+        let layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Waveform pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Waveform pipeline"),
+            layout: Some(&layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[VertexBufferLayout {
+                    array_stride: 8,
+                    step_mode: VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                }],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: init.format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::LineStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+        let vertex_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Waveform vertex buffer"),
+            size: 8 * POINTS as u64,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.resources = Some((pipeline, vertex_buffer));
+    }
+
+    fn render_overlay(&mut self, context: OverlayContext) {
+        let (pipeline, vertex_buffer) = match &self.resources {
+            Some(resources) => resources,
+            None => return,
+        };
+
+        // Two sine waves beating against each other, across the lower half
+        // of the window in clip space.
+        let t = self.start.elapsed().as_secs_f32();
+        let points: Vec<[f32; 2]> = (0..POINTS)
+            .map(|i| {
+                let x = i as f32 / (POINTS - 1) as f32;
+                let y = (x * 20.0 + t * 3.0).sin() * (x * 3.0 - t).sin();
+                [x * 1.8 - 0.9, y * 0.3 - 0.4]
+            })
+            .collect();
+        context
+            .queue
+            .write_buffer(vertex_buffer, 0, cast_slice(&points));
+
+        // The frame is loaded rather than cleared, to keep the characters.
+        let mut render_pass = context.encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Waveform render pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: context.view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        render_pass.draw(0..POINTS, 0..1);
+    }
+}
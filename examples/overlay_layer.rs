@@ -0,0 +1,101 @@
+//
+// Overlay layer example
+// A menu drawn on the overlay layer, which dims the world below it without
+// the world being drawn again.  Space opens and closes the menu.
+//
+
+use std::cell::Cell;
+
+use mterm::*;
+use winit::event::VirtualKeyCode;
+
+fn main() {
+    let app = Box::new(OverlayLayerApp {
+        menu_open: false,
+        world_size: Cell::new(None),
+    });
+    let app_builder = Builder::new()
+        .with_initial_size(800, 600)
+        .with_window_title("Overlay layer")
+        .with_overlay_layer(true);
+    run(app, app_builder).unwrap();
+}
+
+struct OverlayLayerApp {
+    menu_open: bool,
+    /// The size of the grid the world was last drawn for, so that it is only
+    /// drawn again when the grid changes.
+    world_size: Cell<Option<(usize, usize)>>,
+}
+
+impl App for OverlayLayerApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        let key = tick_input.key;
+        if key.pressed {
+            match key.vkey {
+                Some(VirtualKeyCode::Escape) => return TickResult::Stop,
+                Some(VirtualKeyCode::Space) => self.menu_open = !self.menu_open,
+                _ => {}
+            }
+        }
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        let resized = self.world_size.replace(Some((width, height))) != Some((width, height));
+        if resized {
+            // A field of grass and trees, which never changes.
+            present_input.fill_with(Rect::new(0, 0, width, height), |x, y| {
+                let tree = (x * 7 + y * 13) % 11 == 0;
+                let ch = if tree { 5 } else { b'.' };
+                Char::new(ch, Colour::Green.into(), Colour::DarkGreen.into())
+            });
+        }
+
+        let overlay = match present_input.overlay.as_mut() {
+            Some(overlay) => overlay,
+            None => return PresentResult::NoChanges,
+        };
+        let screen = Rect::new(0, 0, width, height);
+        if self.menu_open {
+            // Half-transparent black paper dims the world, and the menu has
+            // opaque paper so that nothing shows through it.
+            overlay.fill_rect(
+                screen,
+                Char::new(b' ', Colour::Black.into(), colour_rgba(0, 0, 0, 160)),
+            );
+            let menu = Rect::new(width as i32 / 2 - 10, height as i32 / 2 - 3, 20, 6);
+            overlay.fill_rect(
+                menu,
+                Char::new(b' ', Colour::White.into(), Colour::DarkBlue.into()),
+            );
+            overlay.draw_box(
+                menu.pos(),
+                menu.width,
+                menu.height,
+                BoxStyle::Double,
+                Colour::White,
+                Colour::DarkBlue,
+            );
+            for (i, item) in ["Resume", "Options", "Quit"].iter().enumerate() {
+                overlay.draw_text(
+                    Point::new(menu.x + 3, menu.y + 1 + i as i32),
+                    item,
+                    Colour::Yellow,
+                    Colour::DarkBlue,
+                );
+            }
+        } else {
+            // A glyph word of 0 leaves the world showing.
+            overlay.fill_rect(
+                screen,
+                Char::new(0, Colour::Black.into(), Colour::Black.into()),
+            );
+        }
+
+        // Only the overlay is written each frame, and only its changes are
+        // copied to the graphics card.
+        PresentResult::NoChanges
+    }
+}
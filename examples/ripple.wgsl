@@ -0,0 +1,39 @@
+// A custom post effect for the custom_shader example.  Ripples spread out
+// from the centre of the window, and the colours fade to grey and back.
+
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var s_scene: sampler;
+
+struct PostInfo {
+    width: f32,
+    height: f32,
+    time: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> info: PostInfo;
+
+@fragment
+fn main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(info.width, info.height);
+    let uv = pos.xy / size;
+
+    // Each pixel is moved towards or away from the centre by a sine wave
+    // travelling outwards, a little less further out.
+    let centre = uv - vec2<f32>(0.5, 0.5);
+    let dist = length(centre);
+    let wave = sin(dist * 40.0 - info.time * 4.0) * 0.008 * (1.0 - dist);
+    var offset: vec2<f32> = vec2<f32>(0.0, 0.0);
+    if (dist > 0.0) {
+        offset = centre / dist * wave;
+    }
+    let colour = textureSample(t_scene, s_scene, uv + offset).rgb;
+
+    // Fade between full colour and greyscale every few seconds.
+    let grey = dot(colour, vec3<f32>(0.299, 0.587, 0.114));
+    let amount = sin(info.time * 0.5) * 0.5 + 0.5;
+    let faded = mix(colour, vec3<f32>(grey, grey, grey), amount);
+    return vec4<f32>(faded, 1.0);
+}
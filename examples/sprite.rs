@@ -0,0 +1,75 @@
+//
+// Sprite example
+// An '@' sprite wanders over a dungeon, drawn with `PresentInput::blit_keyed`
+// so that the floor shows through the spaces around it.
+//
+
+use mterm::*;
+use winit::event::VirtualKeyCode;
+
+const MAP: &[&str] = &[
+    "##############################",
+    "#............................#",
+    "#......####..........####....#",
+    "#......#..#..........#..#....#",
+    "#......####..........####....#",
+    "#............................#",
+    "#.........#######............#",
+    "#............................#",
+    "##############################",
+];
+
+const SPRITE: &[&str] = &[" o ", "-@-", "/ \\"];
+
+fn main() {
+    let sprite = Box::new(SpriteApp { time: 0.0 });
+    let (cell_width, cell_height) = BuiltinFont::Default.cell_size();
+    let app_builder = Builder::new()
+        .with_initial_size(
+            MAP[0].len() * cell_width as usize,
+            MAP.len() * cell_height as usize,
+        )
+        .with_window_title("Sprite");
+    run(sprite, app_builder).unwrap();
+}
+
+struct SpriteApp {
+    time: f32,
+}
+
+impl App for SpriteApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        self.time += tick_input.dt.as_seconds_f32();
+        match tick_input.key {
+            KeyState {
+                pressed: true,
+                vkey: Some(VirtualKeyCode::Escape),
+                ..
+            } => TickResult::Stop,
+            _ => TickResult::Continue,
+        }
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let mut background = Image::new(present_input.width, present_input.height);
+        background.clear(Colour::Black.into(), Colour::Black.into());
+        for (y, row) in MAP.iter().enumerate() {
+            for (x, cell) in row.bytes().enumerate() {
+                let ch = match cell {
+                    b'#' => Char::new(b'#', colours::SLATE_GRAY, colour(40, 40, 50)),
+                    _ => Char::new(b'.', colours::DIM_GRAY, colour(20, 16, 12)),
+                };
+                background.draw_char(Point::new(x as i32, y as i32), ch);
+            }
+        }
+        present_input.blit_screen(&background);
+
+        let sprite = Image::from_strings(SPRITE, Colour::Yellow, Colour::Blue);
+
+        // The spaces around the sprite are skipped, leaving the map visible.
+        let x = (MAP[0].len() as f32 / 2.0) * (1.0 + 0.8 * self.time.sin()) - 1.0;
+        present_input.blit_keyed(Point::new(x as i32, 3), &sprite, b' ' as u32);
+
+        PresentResult::Changed
+    }
+}
@@ -0,0 +1,83 @@
+//
+// Upload stats example
+// A page of text with a blinking cursor, printing how many bytes are copied to
+// the graphics card each frame.  Only the cursor's row is uploaded when it
+// blinks, rather than the whole grid.
+//
+
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use mterm::*;
+
+const BLINK: Duration = Duration::from_millis(500);
+const REPORT: Duration = Duration::from_secs(1);
+
+fn main() {
+    let app = Box::new(UploadStatsApp {
+        start: Instant::now(),
+        last_report: Instant::now(),
+        last_stats: FrameStats::default(),
+        page_size: Cell::new(None),
+    });
+    let app_builder = Builder::new()
+        .with_initial_size(1200, 800)
+        .with_window_title("Upload stats");
+    run(app, app_builder).unwrap();
+}
+
+struct UploadStatsApp {
+    start: Instant,
+    last_report: Instant,
+    last_stats: FrameStats,
+    /// The size of the grid the page was last drawn for, so that it is only
+    /// drawn again when the grid changes.
+    page_size: Cell<Option<(usize, usize)>>,
+}
+
+impl App for UploadStatsApp {
+    fn tick(&mut self, tick_input: TickInput) -> TickResult {
+        if self.last_report.elapsed() >= REPORT {
+            let stats = tick_input.frame_stats;
+            let frames = stats.frames - self.last_stats.frames;
+            let bytes = stats.bytes_uploaded - self.last_stats.bytes_uploaded;
+            let full = 16 * tick_input.width as u64 * tick_input.height as u64;
+            if frames > 0 {
+                println!(
+                    "{} frames, {} bytes uploaded per frame, {} for the whole grid",
+                    frames,
+                    bytes / frames,
+                    full
+                );
+            }
+            self.last_report = Instant::now();
+            self.last_stats = stats;
+        }
+        TickResult::Continue
+    }
+
+    fn present(&self, mut present_input: PresentInput) -> PresentResult {
+        let (width, height) = (present_input.width, present_input.height);
+        if self.page_size.replace(Some((width, height))) != Some((width, height)) {
+            present_input.fill_rect(
+                Rect::new(0, 0, width, height),
+                Char::new(b' ', Colour::White.into(), Colour::Black.into()),
+            );
+            let line = "The quick brown fox jumps over the lazy dog. ";
+            for y in 0..height as i32 - 1 {
+                let text = line.repeat(width / line.len() + 1);
+                present_input.draw_text(Point::new(0, y), &text, Colour::LightGrey, Colour::Black);
+            }
+        }
+
+        // The cursor is written every frame, but only changes when it blinks.
+        let on = (self.start.elapsed().as_millis() / BLINK.as_millis()) & 1 == 0;
+        let paper = if on { Colour::White } else { Colour::Black };
+        let cursor = Rect::new(0, height as i32 - 1, 1, 1);
+        present_input.fill_rect(cursor, Char::new(b' ', Colour::White.into(), paper.into()));
+
+        PresentResult::NoChanges
+    }
+}
@@ -1,6 +1,11 @@
 use time::Duration;
+use wgpu::{AdapterInfo, CommandEncoder, Device, PresentMode, Queue, TextureFormat, TextureView};
 use winit::event::VirtualKeyCode;
 
+use crate::{Commands, FrameStats, FrameTimings, Rect};
+#[cfg(feature = "recording")]
+use crate::{RecordingSummary, Result};
+
 /// Application trait for hooking into the main loop of `mterm`.
 ///
 /// `mterm` manages `Winit` and `wgpu` for you to provide an interface between
@@ -19,15 +24,49 @@ use winit::event::VirtualKeyCode;
 /// structure.  These arrays can be mutated to change what appears in the
 /// window. This method should return a `PresentResult` to tell the main loop if
 /// anything changed and needs to be rendered.
-
+///
+/// `on_resize` is optional and is called before the next `tick` whenever the
+/// size of the window in characters changes, such as when the window is resized
+/// or the font changes.
+///
+/// `on_device_lost` is optional and is called before the next `tick` when the
+/// graphics device was lost, such as after a driver reset, and the renderer
+/// has been rebuilt.  The window's contents are kept, so most apps have
+/// nothing to do, but it is a chance to reload anything tied to the old
+/// device or to log the event.
+///
+/// `on_recording_finished` is optional and is called before the next `tick`
+/// when a recording started with `Commands::start_recording` finishes, with
+/// the number of frames saved and dropped, or the error that stopped it.  It
+/// is only available with the `recording` feature.
+///
+/// `on_present_mode_changed` is optional and is called before the next `tick`
+/// for each `Commands::set_present_mode`, with the mode asked for and the mode
+/// actually used.  They differ when the window does not support the mode
+/// asked for, and `PresentMode::Fifo` is used instead, so that a settings menu
+/// can show what happened.
+///
+/// `init_overlay` and `render_overlay` are optional, for apps that draw
+/// something other than characters on top of the window, such as a plotted
+/// waveform, with `wgpu` directly.  `init_overlay` is called once the renderer
+/// is created, and again after the graphics device is lost, so that the app
+/// can create its pipelines and buffers.  `render_overlay` is called each time
+/// the window is drawn, after the characters and any post effect, to record
+/// its own render passes.
 pub trait App {
     fn tick(&mut self, tick_input: TickInput) -> TickResult;
     fn present(&self, present_input: PresentInput) -> PresentResult;
+    fn on_resize(&mut self, _width: u32, _height: u32) {}
+    fn on_device_lost(&mut self) {}
+    #[cfg(feature = "recording")]
+    fn on_recording_finished(&mut self, _result: Result<RecordingSummary>) {}
+    fn on_present_mode_changed(&mut self, _requested: PresentMode, _applied: PresentMode) {}
+    fn init_overlay(&mut self, _init: OverlayInit) {}
+    fn render_overlay(&mut self, _context: OverlayContext) {}
 }
 
 /// Provides feedback to `mterm`'s main loop instructing it whether to keep
 /// ticking or to stop and exit the application.
-
 pub enum TickResult {
     /// Instructs the main loop to continue ticking.
     Continue,
@@ -38,7 +77,11 @@ pub enum TickResult {
 /// Provides feedback to `mterm`'s main loop instructing whether the images were
 /// written to.
 ///
-
+/// Writes made through the `Canvas` methods are noticed by the main loop, so
+/// the window is redrawn after them even if `NoChanges` is returned.  Only
+/// writes made directly to the images, without `Canvas::mark_dirty`, need
+/// `Changed`.
+///
 pub enum PresentResult {
     /// Return this from the `present` method to signify that changes were made
     /// and the window will need to be redrawn.
@@ -49,10 +92,13 @@ pub enum PresentResult {
 }
 
 /// Contains information for the tick method in `App`.
-
-pub struct TickInput {
+pub struct TickInput<'a> {
     /// This is the delta time since last time `tick` was called.
     pub dt: Duration,
+    /// The number of milliseconds since the window was opened, from the same
+    /// clock as the time given to the shader, so that animations driven by
+    /// the app and by the shader stay in step.
+    pub time_ms: u32,
     /// Current width of the window in characters.
     pub width: u32,
     /// Current height of the window in characters.
@@ -62,6 +108,22 @@ pub struct TickInput {
     pub key: KeyState,
     /// May contain information on a mouse event such as a click or mouse movement.
     pub mouse: Option<MouseState>,
+    /// Information about the graphics adapter used for rendering, such as its
+    /// name and back-end.  Useful for diagnostics screens.
+    pub adapter: &'a AdapterInfo,
+    /// How finished frames are shown, as chosen by the builder or
+    /// `Commands::set_present_mode`.  Useful for showing in a settings menu.
+    pub present_mode: PresentMode,
+    /// Counts of the work done to render the window so far, such as how many
+    /// texture uploads were skipped because nothing had changed.
+    pub frame_stats: FrameStats,
+    /// How long the parts of the last frame took, such as the app's own
+    /// `tick` and `present` and the graphics card's drawing.  Useful for
+    /// finding out why frames are dropped.
+    pub frame_timings: FrameTimings,
+    /// Requests to change the window, such as switching fonts.  They are
+    /// carried out once `tick` returns.
+    pub commands: &'a mut Commands,
 }
 
 /// Can provide information about a key press or release, and will maintain the
@@ -81,8 +143,9 @@ pub struct KeyState {
     /// If a key has been pressed or released, this will contains its virtual
     /// key code as defined by the `winit` crate.
     pub vkey: Option<VirtualKeyCode>,
-    /// [NOT IMPLEMENTED YET] If a key was pressed, and is mappable to a
-    /// character, this will contain the character.
+    /// If a key was typed that produces a character, this will contain the
+    /// character, after the keyboard layout and shift keys are taken into
+    /// account.  Keys such as Backspace produce control characters.
     pub code: Option<char>,
 }
 
@@ -113,10 +176,10 @@ pub struct MouseState {
 /// `back_image` contains an array of `u32`s representing all the background
 /// colours (or paper colour) for each character on the window.  Finally,
 /// `text_image` contains all the ASCII character codes for each character on
-/// the window.  This also contains `u32`s but currently, only the lower 8 bits
-/// is considered for rendering.  In a future version, higher bits might be used
-/// for other effects (such as bold, underline etc).
-
+/// the window.  This also contains `u32`s.  The lower 8 bits are the character
+/// code and the next 8 bits are the page, which selects characters beyond the
+/// first 256 in fonts that have them.  The top 8 bits are attributes, such as
+/// `Char::BOLD`.
 pub struct PresentInput<'a> {
     /// The current width, in chars, of the application window.
     pub width: usize,
@@ -129,7 +192,85 @@ pub struct PresentInput<'a> {
     /// colours (or background colours) of each character on the window.
     pub back_image: &'a mut Vec<u32>,
     /// The array (of size width*height) of u32 values representing the ASCII
-    /// character codes of each character on the window.  Only the lower 8-bits
-    /// are currently used.
+    /// character codes of each character on the window.  The lower 16 bits
+    /// are the character code and page, and the top 8 bits are attributes.
     pub text_image: &'a mut Vec<u32>,
+    /// The colour the window is cleared to before the characters are drawn.
+    /// This shows as a border where the window does not divide evenly into
+    /// character cells.  Changing it requires `PresentResult::Changed` to be
+    /// returned for it to be seen.
+    pub clear_colour: &'a mut u32,
+    /// The layer of characters drawn over these, if it was turned on with
+    /// `Builder::with_overlay_layer`.
+    pub overlay: Option<CanvasView<'a>>,
+    /// The part of the window written to so far, as returned by
+    /// `Canvas::dirty_region`.
+    pub(crate) dirty: &'a mut Option<Rect>,
+}
+
+/// A second layer of characters, the same size as the window, that is drawn
+/// over the characters in `PresentInput` by the shader.  It is a canvas with
+/// the same three arrays, and is kept from one frame to the next in the same
+/// way.
+///
+/// Cells with a glyph word of 0, as they all are to begin with, are
+/// transparent.  Other cells are blended over the layer below by the alpha of
+/// their colours, so a paper with an alpha of 0 shows the layer below around
+/// the glyph, and a translucent black paper dims it.  This draws a menu or a
+/// darkened screen over a world without copying the world first.  The two
+/// layers are copied to the graphics card separately, so a frame that only
+/// changes this one leaves the other alone.
+pub struct CanvasView<'a> {
+    /// The width, in chars, of the layer.
+    pub width: usize,
+    /// The height, in chars, of the layer.
+    pub height: usize,
+    /// The ink colours of each character in the layer.
+    pub fore_image: &'a mut Vec<u32>,
+    /// The paper colours of each character in the layer.
+    pub back_image: &'a mut Vec<u32>,
+    /// The glyph words of each character in the layer, as in
+    /// `PresentInput::text_image`.
+    pub text_image: &'a mut Vec<u32>,
+    /// The part of the layer written to so far.
+    pub(crate) dirty: &'a mut Option<Rect>,
+}
+
+/// Gives `App::init_overlay` what it needs to create the resources for drawing
+/// an overlay.
+pub struct OverlayInit<'a> {
+    /// The graphics device that the window is drawn with.
+    pub device: &'a Device,
+    /// The queue for writing to buffers and textures on the device.
+    pub queue: &'a Queue,
+    /// The format of the window's frames, which an overlay's pipelines must
+    /// draw to.
+    pub format: TextureFormat,
+}
+
+/// Gives `App::render_overlay` what it needs to draw an overlay on top of the
+/// window.
+///
+/// Render passes recorded with the encoder are submitted together with
+/// `mterm`'s own, after them, and draw to the frame being rendered.  The
+/// encoder is the overlay's alone, and none of `mterm`'s pipelines or textures
+/// are given out, so an overlay cannot change how the characters are drawn.
+/// If the overlay's commands are invalid they are dropped with a warning, and
+/// the characters are still shown.  A render pass should load the frame rather
+/// than clear it, so that the characters are kept.
+pub struct OverlayContext<'a> {
+    /// The graphics device that the window is drawn with.
+    pub device: &'a Device,
+    /// The queue for writing to buffers and textures on the device.
+    pub queue: &'a Queue,
+    /// The encoder for the overlay's commands.
+    pub encoder: &'a mut CommandEncoder,
+    /// The frame being rendered, with the characters already drawn.
+    pub view: &'a TextureView,
+    /// The format of the frame.
+    pub format: TextureFormat,
+    /// The width of the frame in pixels.
+    pub width: u32,
+    /// The height of the frame in pixels.
+    pub height: u32,
 }
@@ -0,0 +1,233 @@
+//
+// BDF bitmap font loading
+//
+
+use std::collections::HashMap;
+
+use crate::{Charmap, FontData, FontError, Result};
+
+/// Load a font in the Glyph Bitmap Distribution Format (BDF) and generate a
+/// FontData structure.
+///
+/// # Arguments
+///
+/// * __data__ - The contents of the BDF file.
+/// * __charmap__ - Maps each of the 256 characters in the grid to the
+///   character encoding that is looked up in the BDF file.  Use
+///   `Charmap::cp437()` or `Charmap::latin1()` for fonts encoded in Unicode or
+///   ISO 8859-1.
+///
+/// # Notes
+///
+/// Each character is placed on a common baseline taken from the font's ascent.
+/// Proportional fonts are padded to the widest character so that every cell is
+/// the same size, and a warning is printed.  Characters missing from the font
+/// are left blank.
+///
+/// This function is only available with the `bdf` feature.
+pub fn load_font_bdf(data: &[u8], charmap: &Charmap) -> Result<FontData> {
+    let bdf = Bdf::parse(&String::from_utf8_lossy(data))
+        .ok_or_else(|| FontError::Malformed("the BDF file is malformed".to_string()))?;
+
+    let glyphs = (0..=255u8)
+        .map(|glyph| bdf.glyphs.get(&(charmap.char_for(glyph) as u32)))
+        .collect::<Vec<_>>();
+
+    // Every cell is as wide as the widest character so that proportional fonts
+    // still fit.
+    let advances = glyphs.iter().flatten().map(|glyph| glyph.advance);
+    let width = advances.clone().max().unwrap_or(bdf.bounding_box.0).max(1);
+    if advances.clone().min() != advances.max() {
+        eprintln!(
+            "mterm: BDF font is proportional, padding characters to {} pixels wide",
+            width
+        );
+    }
+    let height = (bdf.ascent + bdf.descent).max(1) as u32;
+
+    FontData::check_size(width as u32, height, 16, 16)?;
+    Ok(FontData::from_fn(width as u32, height, |glyph, x, y| {
+        let lit = glyphs[glyph as usize].is_some_and(|glyph| {
+            // The bitmap's bounding box is offset from the character's origin,
+            // which sits on the baseline.
+            let bx = x as i32 - glyph.bbx.2;
+            let by = y as i32 - (bdf.ascent - glyph.bbx.3 - glyph.bbx.1);
+            bx >= 0
+                && by >= 0
+                && bx < glyph.bbx.0
+                && by < glyph.bbx.1
+                && glyph.bitmap[by as usize]
+                    .get(bx as usize / 8)
+                    .is_some_and(|byte| byte & (0x80 >> (bx % 8)) != 0)
+        });
+        if lit {
+            0xffffffff
+        } else {
+            0xff000000
+        }
+    }))
+}
+
+/// The parts of a BDF file needed to build a font.
+struct Bdf {
+    /// The width, height and offsets of the font's bounding box.
+    bounding_box: (i32, i32, i32, i32),
+    /// Pixels above the baseline.
+    ascent: i32,
+    /// Pixels below the baseline.
+    descent: i32,
+    /// The characters, keyed by their encoding.
+    glyphs: HashMap<u32, BdfGlyph>,
+}
+
+/// A single character in a BDF file.
+struct BdfGlyph {
+    /// How far the origin moves after drawing the character.
+    advance: i32,
+    /// The width, height and offsets of the bitmap from the origin.
+    bbx: (i32, i32, i32, i32),
+    /// The rows of the bitmap, with the leftmost pixel in the top bit of the
+    /// first byte.
+    bitmap: Vec<Vec<u8>>,
+}
+
+impl Bdf {
+    /// Parse the text of a BDF file, returning None if it is malformed.
+    fn parse(text: &str) -> Option<Bdf> {
+        let mut lines = text.lines().map(str::trim);
+        let mut bounding_box = None;
+        let mut ascent = None;
+        let mut descent = None;
+        let mut glyphs = HashMap::new();
+
+        while let Some(line) = lines.next() {
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("FONTBOUNDINGBOX") => bounding_box = Some(parse_4(words)?),
+                Some("FONT_ASCENT") => ascent = Some(words.next()?.parse().ok()?),
+                Some("FONT_DESCENT") => descent = Some(words.next()?.parse().ok()?),
+                Some("STARTCHAR") => {
+                    let (encoding, glyph) = Self::parse_glyph(&mut lines, bounding_box?)?;
+                    if let Some(encoding) = encoding {
+                        glyphs.insert(encoding, glyph);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let bounding_box = bounding_box?;
+        Some(Bdf {
+            bounding_box,
+            ascent: ascent.unwrap_or(bounding_box.1 + bounding_box.3),
+            descent: descent.unwrap_or(-bounding_box.3),
+            glyphs,
+        })
+    }
+
+    /// Parse the lines of a character up to and including ENDCHAR.  The
+    /// encoding is None for characters without a standard encoding.
+    fn parse_glyph<'a>(
+        lines: &mut impl Iterator<Item = &'a str>,
+        bounding_box: (i32, i32, i32, i32),
+    ) -> Option<(Option<u32>, BdfGlyph)> {
+        let mut encoding = None;
+        let mut advance = bounding_box.0;
+        let mut bbx = bounding_box;
+
+        loop {
+            let line = lines.next()?;
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("ENCODING") => {
+                    let value: i64 = words.next()?.parse().ok()?;
+                    encoding = if value >= 0 { Some(value as u32) } else { None };
+                }
+                Some("DWIDTH") => advance = words.next()?.parse().ok()?,
+                Some("BBX") => bbx = parse_4(words)?,
+                Some("BITMAP") => break,
+                Some("ENDCHAR") => return None,
+                _ => {}
+            }
+        }
+
+        let mut bitmap = Vec::with_capacity(bbx.1.max(0) as usize);
+        loop {
+            let line = lines.next()?;
+            if line == "ENDCHAR" {
+                break;
+            }
+            let row = (0..line.len() / 2)
+                .map(|i| u8::from_str_radix(line.get(i * 2..i * 2 + 2)?, 16).ok())
+                .collect::<Option<Vec<u8>>>()?;
+            bitmap.push(row);
+        }
+        if bitmap.len() < bbx.1.max(0) as usize || bbx.0 < 0 {
+            return None;
+        }
+
+        Some((
+            encoding,
+            BdfGlyph {
+                advance,
+                bbx,
+                bitmap,
+            },
+        ))
+    }
+}
+
+/// Parse four integers, as used by bounding boxes.
+fn parse_4<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<(i32, i32, i32, i32)> {
+    let mut next = || words.next()?.parse().ok();
+    Some((next()?, next()?, next()?, next()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4x6 font with 'A', a 'g' that drops below the baseline, and a full
+    /// block.
+    const TINY: &[u8] = include_bytes!("../tests/fixtures/tiny.bdf");
+
+    /// The pixels of a glyph as rows of '#' and '.'.
+    fn rows(font: &FontData, glyph: u16) -> Vec<String> {
+        let glyph = font.glyph(glyph).unwrap();
+        (0..font.height)
+            .map(|y| {
+                (0..font.width)
+                    .map(|x| match glyph.pixel(x, y) {
+                        Some(0xffffffff) => '#',
+                        _ => '.',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loads_a_fixture() {
+        let font = load_font_bdf(TINY, &Charmap::cp437()).unwrap();
+        assert_eq!((font.width, font.height), (4, 6));
+        // Characters sit on the baseline, one pixel above the bottom.
+        assert_eq!(
+            rows(&font, 65),
+            ["....", ".#..", "#.#.", "###.", "#.#.", "...."]
+        );
+        assert_eq!(
+            rows(&font, 103),
+            ["....", "....", "....", "###.", "..#.", "##.."]
+        );
+        // U+2588 is glyph 219 in code page 437.
+        assert_eq!(rows(&font, 219), ["####"; 6]);
+        assert!(font.is_blank(66));
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        assert!(load_font_bdf(b"STARTFONT 2.1\nENDFONT\n", &Charmap::cp437()).is_err());
+        let truncated = &TINY[..TINY.len() / 2];
+        assert!(load_font_bdf(truncated, &Charmap::cp437()).is_err());
+    }
+}
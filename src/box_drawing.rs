@@ -0,0 +1,355 @@
+//
+// Box drawing
+// Borders made from the line glyphs of code page 437, joining up where they
+// meet other borders, and panels with titles built on them
+//
+
+use std::convert::TryFrom;
+
+use crate::{charmap::cp437_glyph, Align, Canvas, Char, Rect};
+
+/// The lines used for the border of a box.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BoxStyle {
+    /// Single lines, such as ┌─┐.
+    #[default]
+    Single,
+    /// Double lines, such as ╔═╗.
+    Double,
+}
+
+impl BoxStyle {
+    fn weight(self) -> u8 {
+        match self {
+            BoxStyle::Single => 1,
+            BoxStyle::Double => 2,
+        }
+    }
+}
+
+/// How `Canvas::draw_panel` draws a panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelStyle {
+    pub(crate) border: BoxStyle,
+    pub(crate) ink: u32,
+    pub(crate) paper: u32,
+    pub(crate) fill: u32,
+    pub(crate) title_ink: u32,
+    pub(crate) title_paper: u32,
+    pub(crate) title_align: Align,
+}
+
+impl PanelStyle {
+    /// Create a style with a single line border in these colours.  The inside
+    /// is filled with the paper colour, and the title is drawn in the same
+    /// colours as the border, in the centre.
+    pub fn new(ink: impl Into<u32>, paper: impl Into<u32>) -> Self {
+        let (ink, paper) = (ink.into(), paper.into());
+        PanelStyle {
+            border: BoxStyle::Single,
+            ink,
+            paper,
+            fill: paper,
+            title_ink: ink,
+            title_paper: paper,
+            title_align: Align::Centre,
+        }
+    }
+
+    /// Draw the border with these lines.
+    pub fn with_border(mut self, border: BoxStyle) -> Self {
+        self.border = border;
+        self
+    }
+
+    /// Fill the inside of the panel with this paper colour, rather than the
+    /// border's.
+    pub fn with_fill(mut self, fill: impl Into<u32>) -> Self {
+        self.fill = fill.into();
+        self
+    }
+
+    /// Draw the title in these colours, rather than the border's.
+    pub fn with_title_colours(mut self, ink: impl Into<u32>, paper: impl Into<u32>) -> Self {
+        self.title_ink = ink.into();
+        self.title_paper = paper.into();
+        self
+    }
+
+    /// Place the title at the left, centre or right of the top border.
+    pub fn with_title_align(mut self, align: Align) -> Self {
+        self.title_align = align;
+        self
+    }
+}
+
+/// The lines leaving the centre of a cell, as the weight of each: 0 for none,
+/// 1 for a single line and 2 for a double line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Arms {
+    up: u8,
+    down: u8,
+    left: u8,
+    right: u8,
+}
+
+/// Every line glyph in code page 437, with its arms as up, down, left and
+/// right.  Up and down always have the same weight, as do left and right.
+const BOX_GLYPHS: [(u8, [u8; 4]); 40] = [
+    (0xb3, [1, 1, 0, 0]),
+    (0xb4, [1, 1, 1, 0]),
+    (0xb5, [1, 1, 2, 0]),
+    (0xb6, [2, 2, 1, 0]),
+    (0xb7, [0, 2, 1, 0]),
+    (0xb8, [0, 1, 2, 0]),
+    (0xb9, [2, 2, 2, 0]),
+    (0xba, [2, 2, 0, 0]),
+    (0xbb, [0, 2, 2, 0]),
+    (0xbc, [2, 0, 2, 0]),
+    (0xbd, [2, 0, 1, 0]),
+    (0xbe, [1, 0, 2, 0]),
+    (0xbf, [0, 1, 1, 0]),
+    (0xc0, [1, 0, 0, 1]),
+    (0xc1, [1, 0, 1, 1]),
+    (0xc2, [0, 1, 1, 1]),
+    (0xc3, [1, 1, 0, 1]),
+    (0xc4, [0, 0, 1, 1]),
+    (0xc5, [1, 1, 1, 1]),
+    (0xc6, [1, 1, 0, 2]),
+    (0xc7, [2, 2, 0, 1]),
+    (0xc8, [2, 0, 0, 2]),
+    (0xc9, [0, 2, 0, 2]),
+    (0xca, [2, 0, 2, 2]),
+    (0xcb, [0, 2, 2, 2]),
+    (0xcc, [2, 2, 0, 2]),
+    (0xcd, [0, 0, 2, 2]),
+    (0xce, [2, 2, 2, 2]),
+    (0xcf, [1, 0, 2, 2]),
+    (0xd0, [2, 0, 1, 1]),
+    (0xd1, [0, 1, 2, 2]),
+    (0xd2, [0, 2, 1, 1]),
+    (0xd3, [2, 0, 0, 1]),
+    (0xd4, [1, 0, 0, 2]),
+    (0xd5, [0, 1, 0, 2]),
+    (0xd6, [0, 2, 0, 1]),
+    (0xd7, [2, 2, 1, 1]),
+    (0xd8, [1, 1, 2, 2]),
+    (0xd9, [1, 0, 1, 0]),
+    (0xda, [0, 1, 0, 1]),
+];
+
+impl Arms {
+    /// The arms of a glyph word, or none if it is not a line glyph.
+    fn of_glyph(glyph: u32) -> Arms {
+        BOX_GLYPHS
+            .iter()
+            .find(|&&(code, _)| glyph & 0xffff == code as u32)
+            .map_or_else(Arms::default, |&(_, [up, down, left, right])| Arms {
+                up,
+                down,
+                left,
+                right,
+            })
+    }
+
+    /// The glyph with these arms, if there is one.
+    fn glyph(self) -> Option<u8> {
+        let arms = [self.up, self.down, self.left, self.right];
+        BOX_GLYPHS
+            .iter()
+            .find(|&&(_, glyph_arms)| glyph_arms == arms)
+            .map(|&(code, _)| code)
+    }
+
+    /// Add the arms of a cell being drawn to those already there.
+    ///
+    /// A glyph cannot have a single line up and a double line down, or the
+    /// like, so each axis has one weight.  A line that already goes right
+    /// through the cell keeps its weight, so that a box touching the edge of
+    /// another does not break it, and otherwise the new weight is used.
+    fn merge(self, new: Arms) -> Arms {
+        let through = |arms: (u8, u8)| arms.0 != 0 && arms.1 != 0;
+        let axis = |old: (u8, u8), new: (u8, u8)| {
+            let (old_weight, new_weight) = (old.0.max(old.1), new.0.max(new.1));
+            let weight = if new_weight == 0 || (through(old) && !through(new)) {
+                old_weight
+            } else {
+                new_weight
+            };
+            let arm = |old: u8, new: u8| if old != 0 || new != 0 { weight } else { 0 };
+            (arm(old.0, new.0), arm(old.1, new.1))
+        };
+        let (up, down) = axis((self.up, self.down), (new.up, new.down));
+        let (left, right) = axis((self.left, self.right), (new.left, new.right));
+        Arms {
+            up,
+            down,
+            left,
+            right,
+        }
+    }
+
+    /// Turn a cell with fewer than two arms into a straight line, along the
+    /// arm if it has one and across otherwise, as there are no glyphs for the
+    /// ends of lines.
+    fn complete(self, weight: u8) -> Arms {
+        let arms = [self.up, self.down, self.left, self.right];
+        if arms.iter().filter(|&&w| w != 0).count() > 1 {
+            self
+        } else if self.up != 0 || self.down != 0 {
+            Arms {
+                up: weight,
+                down: weight,
+                ..Arms::default()
+            }
+        } else {
+            Arms {
+                left: weight,
+                right: weight,
+                ..Arms::default()
+            }
+        }
+    }
+}
+
+/// Draw the border of a box in a style, clipped to the canvas.  If `merge` is
+/// set, the lines join up with any line glyphs already on the canvas.
+///
+/// Boxes 1 cell wide are drawn as a vertical line, and those 1 cell high as a
+/// horizontal line.
+pub(crate) fn draw_border<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    rect: Rect,
+    style: BoxStyle,
+    ink: u32,
+    paper: u32,
+    merge: bool,
+) {
+    if rect.width == 0 || rect.height == 0 {
+        return;
+    }
+    let weight = style.weight();
+    let (left, top) = (rect.x as i64, rect.y as i64);
+    let width = i64::try_from(rect.width).unwrap_or(i64::MAX);
+    let height = i64::try_from(rect.height).unwrap_or(i64::MAX);
+    let (right, bottom) = (
+        left.saturating_add(width) - 1,
+        top.saturating_add(height) - 1,
+    );
+    let (canvas_width, canvas_height) = canvas.size();
+    let (canvas_width, canvas_height) = (canvas_width as i64, canvas_height as i64);
+
+    let draw_cell = |canvas: &mut C, x: i64, y: i64| {
+        if x < 0 || y < 0 || x >= canvas_width || y >= canvas_height {
+            return;
+        }
+        let on_row = y == top || y == bottom;
+        let on_column = x == left || x == right;
+        let mut arms = Arms {
+            up: if on_column && y > top { weight } else { 0 },
+            down: if on_column && y < bottom { weight } else { 0 },
+            left: if on_row && x > left { weight } else { 0 },
+            right: if on_row && x < right { weight } else { 0 },
+        };
+
+        let i = (y * canvas_width + x) as usize;
+        if merge {
+            arms = Arms::of_glyph(canvas.planes().2[i]).merge(arms);
+        }
+        // The ends of lines 1 cell thick only have one arm, and a box of a
+        // single cell has none.
+        let arms = arms.complete(weight);
+        if let Some(glyph) = arms.glyph() {
+            canvas.mark_dirty(Rect::new(x as i32, y as i32, 1, 1));
+            let (fore, back, text) = canvas.planes_mut();
+            fore[i] = ink;
+            back[i] = paper;
+            text[i] = glyph as u32;
+        }
+    };
+
+    // Only the parts of the edges on the canvas are visited, so huge boxes are
+    // as quick to draw as small ones.
+    for x in left.max(0)..=right.min(canvas_width - 1) {
+        draw_cell(canvas, x, top);
+        if bottom != top {
+            draw_cell(canvas, x, bottom);
+        }
+    }
+    for y in (top + 1).max(0)..=(bottom - 1).min(canvas_height - 1) {
+        draw_cell(canvas, left, y);
+        if right != left {
+            draw_cell(canvas, right, y);
+        }
+    }
+}
+
+/// Draw a title into the top border of a panel, between glyphs that join it
+/// to the border, such as "┤ Inventory ├".  Titles too long for the border
+/// are cut short with "...", and nothing is drawn if there is no room.
+pub(crate) fn draw_title<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    rect: Rect,
+    title: &str,
+    style: &PanelStyle,
+) {
+    // The corners, the joining glyphs and a space either side of the title
+    // take up 6 cells.
+    let room = rect.width.saturating_sub(6);
+    let mut glyphs = title
+        .chars()
+        .map(|c| cp437_glyph(c).unwrap_or(b'?'))
+        .collect::<Vec<_>>();
+    if glyphs.len() > room {
+        glyphs.truncate(room.saturating_sub(3));
+        glyphs.extend(b"...".iter().take(room - glyphs.len()));
+    }
+    if glyphs.is_empty() {
+        return;
+    }
+
+    // Keep a line either side of the title when aligning it to one end.
+    let space = rect.width - 6 - glyphs.len();
+    let offset = match style.title_align {
+        Align::Left => space.min(1),
+        Align::Centre => space / 2,
+        Align::Right => space - space.min(1),
+    };
+    let weight = style.border.weight();
+    let caps = [
+        Arms {
+            up: 1,
+            down: 1,
+            left: weight,
+            right: 0,
+        },
+        Arms {
+            up: 1,
+            down: 1,
+            left: 0,
+            right: weight,
+        },
+    ];
+    let (ink, paper) = (style.ink, style.paper);
+    let mut x = (rect.x as i64).saturating_add(1 + offset as i64);
+    let mut put = |canvas: &mut C, ch: Char| {
+        if let Ok(x) = i32::try_from(x) {
+            canvas.fill_rect(Rect::new(x, rect.y, 1, 1), ch);
+        }
+        x = x.saturating_add(1);
+    };
+
+    put(
+        canvas,
+        Char::new(caps[0].glyph().unwrap_or(b' '), ink, paper),
+    );
+    put(canvas, Char::new(b' ', style.title_ink, style.title_paper));
+    for &glyph in &glyphs {
+        put(canvas, Char::new(glyph, style.title_ink, style.title_paper));
+    }
+    put(canvas, Char::new(b' ', style.title_ink, style.title_paper));
+    put(
+        canvas,
+        Char::new(caps[1].glyph().unwrap_or(b' '), ink, paper),
+    );
+}
@@ -0,0 +1,165 @@
+//
+// Braille
+// Plotting at 2x4 dots per cell with the Braille patterns, for graphs and
+// sparklines
+//
+
+use crate::{Canvas, Char, Point, Rect};
+
+/// The bit of a Braille pattern for each dot, by row and then column.  These
+/// follow Unicode, where the pattern for a set of dots is U+2800 plus the
+/// bits.
+const DOT_BITS: [[u8; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// Draws dots on a canvas at twice its width and four times its height, with
+/// each cell showing a Braille pattern of 2x4 dots.
+///
+/// Code page 437 has no Braille patterns, so the font needs 256 extra glyphs
+/// holding them, in Unicode order: glyph `base + n` shows the pattern
+/// U+2800 + n.  A font of 512 glyphs with the patterns in the second page has
+/// a base of 256.
+///
+/// Cells holding a pattern are those with a glyph from `base` to `base + 255`.
+/// The other cells, such as labels, are left alone until a dot is set in
+/// them, so text and plots can sit side by side.
+pub struct BrailleCanvas<'a, C: Canvas + ?Sized> {
+    canvas: &'a mut C,
+    base: u16,
+    ink: u32,
+}
+
+impl<'a, C: Canvas + ?Sized> BrailleCanvas<'a, C> {
+    /// Plot on a canvas with the Braille patterns starting at glyph `base`.
+    /// Dots are set in the ink colour, and the paper is left as it is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `base` is above 0xff00, as the patterns would not all have
+    /// a glyph index.
+    pub fn new(canvas: &'a mut C, base: u16, ink: impl Into<u32>) -> Self {
+        assert!(
+            base <= 0xff00,
+            "the Braille patterns cannot start at glyph {}",
+            base
+        );
+        BrailleCanvas {
+            canvas,
+            base,
+            ink: ink.into(),
+        }
+    }
+
+    /// Set dots in this ink colour from now on.  Each cell has one ink
+    /// colour, so this changes the colour of the dots already in the cells
+    /// that are drawn in.
+    pub fn set_ink(&mut self, ink: impl Into<u32>) {
+        self.ink = ink.into();
+    }
+
+    /// The width and height in dots.
+    pub fn size(&self) -> (usize, usize) {
+        let (width, height) = self.canvas.size();
+        (width * 2, height * 4)
+    }
+
+    /// Set the dot at a position.  Positions outside the canvas are ignored.
+    pub fn set_dot(&mut self, p: Point) {
+        self.update_dot(p, true);
+    }
+
+    /// Clear the dot at a position.  Positions outside the canvas, or in
+    /// cells not holding a pattern, are ignored.
+    pub fn clear_dot(&mut self, p: Point) {
+        self.update_dot(p, false);
+    }
+
+    /// Return true if the dot at a position is set.
+    pub fn get_dot(&self, p: Point) -> bool {
+        let (cell, bit) = locate(p);
+        let pattern = self
+            .canvas
+            .index_of(cell)
+            .and_then(|i| self.pattern_of(self.canvas.planes().2[i]));
+        matches!(pattern, Some(pattern) if pattern & bit != 0)
+    }
+
+    /// Set the dots along a line between two positions, including both ends.
+    pub fn draw_line(&mut self, from: Point, to: Point) {
+        // Bresenham's algorithm, in i64 so that no distance overflows.
+        let (mut x, mut y) = (from.x as i64, from.y as i64);
+        let (to_x, to_y) = (to.x as i64, to.y as i64);
+        let (dx, dy) = ((to_x - x).abs(), -(to_y - y).abs());
+        let (step_x, step_y) = ((to_x - x).signum(), (to_y - y).signum());
+        let mut error = dx + dy;
+        loop {
+            self.set_dot(Point::new(x as i32, y as i32));
+            if x == to_x && y == to_y {
+                break;
+            }
+            let double = error * 2;
+            if double >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if double <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Clear the dots in every cell holding a pattern, leaving the other
+    /// cells alone.
+    pub fn clear(&mut self) {
+        let (width, height) = self.canvas.size();
+        let base = self.base as u32;
+        let mut cleared = false;
+        for text in self.canvas.planes_mut().2.iter_mut() {
+            if (*text & !Char::ATTRIBUTES).wrapping_sub(base) < 256 {
+                *text = (*text & Char::ATTRIBUTES) | base;
+                cleared = true;
+            }
+        }
+        if cleared {
+            self.canvas.mark_dirty(Rect::new(0, 0, width, height));
+        }
+    }
+
+    /// The dots of a cell's glyph word, or None if it is not a pattern.
+    fn pattern_of(&self, text: u32) -> Option<u8> {
+        let pattern = (text & !Char::ATTRIBUTES).wrapping_sub(self.base as u32);
+        if pattern < 256 {
+            Some(pattern as u8)
+        } else {
+            None
+        }
+    }
+
+    fn update_dot(&mut self, p: Point, set: bool) {
+        let (cell, bit) = locate(p);
+        let i = match self.canvas.index_of(cell) {
+            Some(i) => i,
+            None => return,
+        };
+        let pattern = match (self.pattern_of(self.canvas.planes().2[i]), set) {
+            (Some(pattern), true) => pattern | bit,
+            (None, true) => bit,
+            (Some(pattern), false) => pattern & !bit,
+            (None, false) => return,
+        };
+
+        self.canvas.mark_dirty(Rect::new(cell.x, cell.y, 1, 1));
+        let (fore, _, text) = self.canvas.planes_mut();
+        if set {
+            fore[i] = self.ink;
+        }
+        text[i] = (text[i] & Char::ATTRIBUTES) | (self.base as u32 + pattern as u32);
+    }
+}
+
+/// The cell holding a dot, and the dot's bit in the cell's pattern.
+fn locate(p: Point) -> (Point, u8) {
+    let cell = Point::new(p.x.div_euclid(2), p.y.div_euclid(4));
+    let bit = DOT_BITS[p.y.rem_euclid(4) as usize][p.x.rem_euclid(2) as usize];
+    (cell, bit)
+}
@@ -1,34 +1,72 @@
-use std::mem::replace;
+#[cfg(feature = "hot-reload")]
+use std::path::{Path, PathBuf};
 
-use bytemuck::cast_slice;
-use image::{EncodableLayout, GenericImageView, ImageFormat};
+use wgpu::{PowerPreference, PresentMode};
 
-use crate::{Error, Result};
+use crate::{
+    font_set::best_font, post_effect::check_post_shader, Backend, BuiltinFont, Error, FontData,
+    PostEffect, RenderOptions, Result,
+};
 
 /// Used to build the window to host the ASCII rendering.
-
+///
+/// Each `with_` method consumes the builder and returns it with the new
+/// setting, so they can be chained together.  The finished builder is passed
+/// straight to `run`.  Cloning a builder allows a partially configured builder
+/// to be kept around and reused.
+///
+/// `with_inner_size`, `with_title` and `with_font` still borrow the builder as
+/// they used to, but are deprecated in favour of `with_initial_size`,
+/// `with_window_title` and `with_custom_font`, and will be removed in the next
+/// release.
+#[derive(Clone)]
 pub struct Builder {
     /// The size of the inside of the window (in pixels).
     pub(crate) inner_size: (usize, usize),
+    /// The smallest size the window can be resized to.
+    pub(crate) min_size: Option<WindowSize>,
+    /// The largest size the window can be resized to.
+    pub(crate) max_size: Option<WindowSize>,
     /// The title of the window.
     pub(crate) title: String,
     /// The font used to render the text.
     pub(crate) font: Font,
+    /// The font used to render bold text, if any.
+    pub(crate) bold_font: Option<FontData>,
+    /// The options used to create the renderer.
+    pub(crate) render_options: RenderOptions,
+    /// Frames that take longer than this on the CPU, in milliseconds, are
+    /// logged.
+    pub(crate) slow_frame_ms: Option<u32>,
+    /// How long the window must stop changing size, in milliseconds, before
+    /// the character grid is resized to fit it.
+    pub(crate) resize_debounce_ms: u32,
+    /// Whether pressing Escape closes the window before the app sees it.
+    pub(crate) exit_on_escape: bool,
 }
 
 /// Represents the font type used in the window.
+#[derive(Clone)]
 pub(crate) enum Font {
-    /// Use the built-in font.
-    Default,
+    /// Use one of the fonts built into mterm.
+    Builtin(BuiltinFont),
     /// Use a custom font.
     Custom(FontData),
+    /// Use the font from a set that best suits the window's scale factor.
+    Set(Vec<(f64, FontData)>),
+    /// Use a font loaded from a file, which is reloaded whenever it changes.
+    #[cfg(feature = "hot-reload")]
+    Watched(PathBuf, FontData),
 }
 
-/// Contains the font pixel data for custom fonts.
-pub struct FontData {
-    pub data: Vec<u32>,
-    pub width: u32,
-    pub height: u32,
+/// Represents a window size limit given either in pixels or in characters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum WindowSize {
+    /// The size is given in pixels and will be rounded down to whole
+    /// characters.
+    Pixels(usize, usize),
+    /// The size is given in characters.
+    Chars(usize, usize),
 }
 
 //
@@ -43,8 +81,15 @@ impl Builder {
     pub fn new() -> Self {
         Builder {
             inner_size: (800, 600),
+            min_size: None,
+            max_size: None,
             title: "mterm".to_string(),
-            font: Font::Default,
+            font: Font::Builtin(BuiltinFont::Default),
+            bold_font: None,
+            render_options: RenderOptions::default(),
+            slow_frame_ms: None,
+            resize_debounce_ms: 100,
+            exit_on_escape: true,
         }
     }
 
@@ -53,12 +98,67 @@ impl Builder {
     /// The size given is the number of pixels inside the window's frame.  On
     /// creation the frame size will be reduced so that there are no margins
     /// around the characters.
+    pub fn with_initial_size(mut self, width: usize, height: usize) -> Self {
+        self.inner_size = (width, height);
+        self
+    }
+
+    /// Set the size of the window when it is created, on a borrowed builder.
+    #[deprecated(
+        since = "0.1.2",
+        note = "use `with_initial_size`, which consumes the builder like the other `with_` methods"
+    )]
     pub fn with_inner_size(&mut self, width: usize, height: usize) -> &mut Self {
         self.inner_size = (width, height);
         self
     }
 
+    /// Set the smallest size, in pixels, that the window can be resized to.
+    ///
+    /// The size is rounded down to fit whole characters once the font is known.
+    /// If no minimum size is given, the window can shrink to 20x20 characters.
+    pub fn with_min_inner_size(mut self, width: usize, height: usize) -> Self {
+        self.min_size = Some(WindowSize::Pixels(width, height));
+        self
+    }
+
+    /// Set the smallest size, in characters, that the window can be resized
+    /// to.
+    pub fn with_min_size_in_chars(mut self, width: usize, height: usize) -> Self {
+        self.min_size = Some(WindowSize::Chars(width, height));
+        self
+    }
+
+    /// Set the largest size, in pixels, that the window can be resized to.
+    ///
+    /// The size is rounded down to fit whole characters once the font is known.
+    /// Maximising the window will be clamped to this size, but going fullscreen
+    /// ignores it.
+    pub fn with_max_inner_size(mut self, width: usize, height: usize) -> Self {
+        self.max_size = Some(WindowSize::Pixels(width, height));
+        self
+    }
+
+    /// Set the largest size, in characters, that the window can be resized to.
+    ///
+    /// Setting the same minimum and maximum size will produce a window that
+    /// cannot be resized.
+    pub fn with_max_size_in_chars(mut self, width: usize, height: usize) -> Self {
+        self.max_size = Some(WindowSize::Chars(width, height));
+        self
+    }
+
     /// Set the title of the window.
+    pub fn with_window_title(mut self, title: &str) -> Self {
+        self.title = String::from(title);
+        self
+    }
+
+    /// Set the title of the window, on a borrowed builder.
+    #[deprecated(
+        since = "0.1.2",
+        note = "use `with_window_title`, which consumes the builder like the other `with_` methods"
+    )]
     pub fn with_title(&mut self, title: &str) -> &mut Self {
         self.title = String::from(title);
         self
@@ -68,55 +168,495 @@ impl Builder {
     ///
     /// A `FontData` structure can be created using the `load_font_image`.
     ///
-    /// The font image passed in must contain 256 characters on a 16x16 equally
-    /// sized grid.  The size of each character is determined by taking the
-    /// width and size of the data in `FontData` and dividing it by 16.
+    /// The font image is a grid of equally sized characters, normally 16x16
+    /// for 256 characters.  The size of each character and the dimensions of
+    /// the grid are given by the `FontData`.
+    pub fn with_custom_font(mut self, font: FontData) -> Self {
+        self.font = Font::Custom(font);
+        self
+    }
+
+    /// Choose a font for rendering, on a borrowed builder.
+    #[deprecated(
+        since = "0.1.2",
+        note = "use `with_custom_font`, which consumes the builder like the other `with_` methods"
+    )]
     pub fn with_font(&mut self, font: FontData) -> &mut Self {
         self.font = Font::Custom(font);
         self
     }
 
-    /// Finalise the builder and return an instance.
+    /// Choose a font image file for rendering, and reload it whenever it
+    /// changes on disk.
+    ///
+    /// The font is loaded straight away so that any problem with it is
+    /// reported here.  The image format is determined by the file's extension
+    /// and, like `load_font_image`, the image must contain a 16x16 grid of
+    /// characters.
+    ///
+    /// While the app is running, saving a new version of the file switches the
+    /// window to it, recalculating the character grid if the character size
+    /// has changed.  If the new version cannot be loaded, a message is logged
+    /// and the current font is kept.
+    ///
+    /// This function is only available with the `hot-reload` feature.
+    #[cfg(feature = "hot-reload")]
+    pub fn with_font_path_watched(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let font = crate::hot_reload::load_font_file(path)?;
+        self.font = Font::Watched(path.to_path_buf(), font);
+        Ok(self)
+    }
+
+    /// Choose fonts for rendering to suit the scale factor of the monitor the
+    /// window is on.
+    ///
+    /// Each font is given with the smallest scale factor it is used for, such
+    /// as 1.0 for a normal font and 2.0 for one twice the size.  The font with
+    /// the largest scale factor that the monitor reaches is used, or the one
+    /// with the smallest scale factor if it reaches none of them.
+    ///
+    /// When the window moves to a monitor with a different scale factor, the
+    /// font is switched once the scale factor has stayed the same for half a
+    /// second, and `App::on_resize` is called as the character grid changes.
+    pub fn with_font_set(mut self, fonts: Vec<(f64, FontData)>) -> Self {
+        self.font = Font::Set(fonts);
+        self
+    }
+
+    /// Choose one of the fonts built into mterm for rendering.
+    ///
+    /// Other than `BuiltinFont::Default`, each font needs its cargo feature to
+    /// be enabled.
+    pub fn with_builtin_font(mut self, font: BuiltinFont) -> Self {
+        self.font = Font::Builtin(font);
+        self
+    }
+
+    /// Choose a font for rendering bold text.
+    ///
+    /// Characters with the `Char::BOLD` attribute are drawn with this font.
+    /// It must have the same character size and grid as the main font.
+    /// Without a bold font, bold characters are drawn with a brightened ink
+    /// colour instead.
+    pub fn with_bold_font(mut self, font: FontData) -> Self {
+        self.bold_font = Some(font);
+        self
+    }
+
+    /// Draw each font character at an integer multiple of its size.
+    ///
+    /// This is useful for small fonts on high resolution displays.  The size
+    /// of the character grid is based on the scaled character size.  The scale
+    /// can be changed at runtime with Ctrl+Plus and Ctrl+Minus.  The default
+    /// scale is 1.
+    pub fn with_font_scale(mut self, font_scale: u32) -> Self {
+        self.render_options.font_scale = font_scale;
+        self
+    }
+
+    /// Choose the graphics API used for rendering.
+    ///
+    /// The default is `Backend::Auto`, which lets the platform decide.  The
+    /// `MTERM_BACKEND` environment variable overrides this setting so that a
+    /// broken back-end can be avoided without rebuilding the application.
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.render_options.backend = backend;
+        self
+    }
+
+    /// Choose whether to prefer a high performance or a low power graphics
+    /// adapter.
+    ///
+    /// This is useful on machines with both an integrated and discrete GPU.
+    pub fn with_power_preference(mut self, power_preference: PowerPreference) -> Self {
+        self.render_options.power_preference = power_preference;
+        self
+    }
+
+    /// Choose how finished frames are shown.
+    ///
+    /// The default, `PresentMode::Fifo`, waits for vertical sync so there is
+    /// no tearing, and is supported everywhere.  `PresentMode::Mailbox` and
+    /// `PresentMode::Immediate` show frames sooner, but not every platform
+    /// supports them and `Fifo` is used instead on those that don't.  It can
+    /// be changed at runtime with `Commands::set_present_mode`.
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.render_options.present_mode = present_mode;
+        self
+    }
+
+    /// Choose an effect drawn over the whole window after the characters,
+    /// such as `PostEffect::Crt` to make it look like an old monitor.
+    ///
+    /// The characters are drawn to a texture first, and the effect draws the
+    /// texture to the window.  There is no effect by default, and no extra
+    /// cost without one.  It can be changed, or its settings adjusted, at
+    /// runtime with `Commands::set_post_effect`.
+    pub fn with_post_effect(mut self, post_effect: PostEffect) -> Self {
+        self.render_options.post_effect = post_effect;
+        self
+    }
+
+    /// Draw the window with a post effect written in WGSL, and choose it with
+    /// `PostEffect::Custom`.
+    ///
+    /// The shader is given the window as it would have been drawn, and draws
+    /// each of its pixels.  It must have a fragment entry point called `main`,
+    /// taking the pixel's `@builtin(position)` as a `vec4<f32>` and
+    /// returning its colour at `@location(0)`.  The vertex shader is
+    /// provided.
+    ///
+    /// The shader can use any of three bindings in group 0.  Binding 0 is the
+    /// window as a `texture_2d<f32>`, and binding 1 a linear `sampler` for it
+    /// that is clamped at the edges.  Binding 2 is a struct in
+    /// `var<uniform>` whose first three fields are the `width` and `height` of
+    /// the window in pixels and the `time` in seconds since it opened, all
+    /// `f32`.
+    ///
+    /// The shader is compiled by `validate`, before the window is created, and
+    /// an `Error::BadShader` with the compiler's message is returned if it has
+    /// mistakes.  The effect can be turned off and on again at runtime with
+    /// `Commands::set_post_effect`.
+    pub fn with_custom_post_shader(mut self, wgsl_source: &str) -> Self {
+        self.render_options.custom_post_shader = Some(wgsl_source.to_string());
+        self.render_options.post_effect = PostEffect::Custom;
+        self
+    }
+
+    /// Add a second layer of characters, drawn over the first, which the app
+    /// writes to through `PresentInput::overlay`.  See `CanvasView` for how
+    /// it is drawn.  There is no second layer by default, and no extra cost
+    /// without one.
+    pub fn with_overlay_layer(mut self, overlay_layer: bool) -> Self {
+        self.render_options.overlay_layer = overlay_layer;
+        self
+    }
+
+    /// Choose a graphics adapter by name.
+    ///
+    /// The first adapter whose name contains the given string (ignoring case)
+    /// and can render to the window is used.  If none match, a warning is
+    /// printed and the default adapter is used instead.  The chosen adapter can
+    /// be found in `TickInput::adapter`.
+    pub fn with_adapter_name(mut self, name: &str) -> Self {
+        self.render_options.adapter_name = Some(String::from(name));
+        self
+    }
+
+    /// Choose a graphics adapter by its index in the list of adapters given by
+    /// `diagnostics` in `GpuReport::adapters`, which covers every back-end.
+    ///
+    /// This takes priority over `with_adapter_name` and the back-end.  If
+    /// there is no adapter at the index, or it cannot render to the window, a
+    /// warning is printed and the default adapter is used instead.
+    pub fn with_adapter_index(mut self, index: usize) -> Self {
+        self.render_options.adapter_index = Some(index);
+        self
+    }
+
+    /// Log frames that take longer than `threshold_ms` milliseconds on the
+    /// CPU, with how long each part of them took.
+    ///
+    /// Frames are not logged by default.  The same times are given to the app
+    /// in `TickInput::frame_timings` whether or not they are logged.
+    pub fn with_slow_frame_warning(mut self, threshold_ms: u32) -> Self {
+        self.slow_frame_ms = Some(threshold_ms);
+        self
+    }
+
+    /// Set how long the window must stop changing size, in milliseconds,
+    /// before the character grid is resized to fit it.
+    ///
+    /// While the window is being dragged to a new size, the surface follows
+    /// it straight away but the grid keeps its old size, with the clear colour
+    /// around it, so that its textures are not created again many times a
+    /// second.  `App::on_resize` is called once the grid has been resized.
+    /// The default is 100ms, and 0 resizes the grid on every change.
+    pub fn with_resize_debounce(mut self, interval_ms: u32) -> Self {
+        self.resize_debounce_ms = interval_ms;
+        self
+    }
+
+    /// Set whether pressing Escape closes the window.
+    ///
+    /// By default it does, straight away, so the app is never given the key.
+    /// Turn this off for apps that use Escape themselves, such as to cancel a
+    /// `Menu` or a `TextField`, and return `TickResult::Stop` to close the
+    /// window instead.
+    pub fn with_exit_on_escape(mut self, exit_on_escape: bool) -> Self {
+        self.exit_on_escape = exit_on_escape;
+        self
+    }
+
+    /// Set the colour the window is cleared to before the characters are
+    /// drawn.
+    ///
+    /// This colour shows as a border where the window does not divide evenly
+    /// into character cells.  It uses the same format as `colour()`, and can be
+    /// changed at runtime through `PresentInput::clear_colour`.  The default is
+    /// black.
+    pub fn with_clear_colour(mut self, clear_colour: u32) -> Self {
+        self.render_options.clear_colour = clear_colour;
+        self
+    }
+
+    /// Choose whether the character grid is centred in the window.
+    ///
+    /// When the window does not divide evenly into character cells, such as
+    /// when fullscreen or maximised, the leftover pixels show as a border in
+    /// the clear colour.  Normally the grid sits in the top-left corner with
+    /// the border on the right and bottom.  Centring the grid shares the
+    /// border equally between the four sides.  It can be changed at runtime
+    /// with `Commands::set_centre_grid`.  The grid is not centred by default.
+    pub fn with_centre_grid(mut self, centre_grid: bool) -> Self {
+        self.render_options.centre_grid = centre_grid;
+        self
+    }
+
+    /// Choose whether font pixels blend the ink and paper colours.
+    ///
+    /// With smoothing on, each font pixel's grey level (multiplied by its
+    /// alpha) is used as the amount of ink to mix with the paper, so
+    /// anti-aliased fonts have smooth edges.  With it off, each pixel is
+    /// either ink or paper depending on whether its level is at least half.
+    /// Fonts containing only black and white pixels look the same either way.
+    /// Smoothing is on by default.
+    pub fn with_font_smoothing(mut self, font_smoothing: bool) -> Self {
+        self.render_options.font_smoothing = font_smoothing;
+        self
+    }
+
+    /// Choose whether colours are converted for sRGB surfaces.
+    ///
+    /// Colours are sRGB values, as in most image editors, and by default they
+    /// appear the same whichever surface format the graphics adapter offers.
+    /// Turning the conversion off writes the colour components to the surface
+    /// unchanged, which makes colours look lighter on adapters that offer an
+    /// sRGB format.  This matches the output of older versions
+    /// of mterm.
+    pub fn with_srgb_conversion(mut self, srgb_conversion: bool) -> Self {
+        self.render_options.srgb_conversion = srgb_conversion;
+        self
+    }
+
+    /// Finalise the builder and return a copy of it.  The builder keeps its
+    /// settings, so building it again gives the same window.
+    #[deprecated(
+        since = "0.1.2",
+        note = "the builder can be passed straight to `run` without calling `build`"
+    )]
     pub fn build(&mut self) -> Self {
-        Builder {
-            inner_size: self.inner_size,
-            font: replace(&mut self.font, Font::Default),
-            title: self.title.clone(),
+        self.clone()
+    }
+}
+
+impl Builder {
+    /// Check that the settings can produce a working window.
+    ///
+    /// This is called by `run` before the window is created, but can be
+    /// called earlier to report problems.  An `Error::BadConfig` describing
+    /// the problem is returned for invalid settings, an `Error::BadFont` if a
+    /// custom font cannot be used, or an `Error::BadShader` if a custom post
+    /// shader does not compile.
+    pub fn validate(&self) -> Result<()> {
+        let bad_config = |message: String| Err(Error::BadConfig(message));
+
+        if self.inner_size.0 == 0 || self.inner_size.1 == 0 {
+            return bad_config(format!(
+                "the inner size must not be zero, but {}x{} was given",
+                self.inner_size.0, self.inner_size.1
+            ));
+        }
+
+        if let Font::Set(fonts) = &self.font {
+            if fonts.is_empty() {
+                return bad_config("the font set must contain at least one font".to_string());
+            }
+            for (_, font) in fonts {
+                font.check()?;
+            }
+        } else if let Some(font) = self.font.font_data() {
+            font.check()?;
+        }
+
+        if let Some(bold_font) = &self.bold_font {
+            bold_font.check()?;
+            // The built-in fonts all have a 16x16 grid.
+            let grid = self
+                .font
+                .font_data()
+                .map_or((16, 16), |font| (font.columns, font.rows));
+            let cell_size = self.font.cell_size();
+            if (bold_font.width, bold_font.height) != cell_size
+                || (bold_font.columns, bold_font.rows) != grid
+            {
+                return bad_config(format!(
+                    "the bold font has {}x{} characters of size {}x{}, but the font has {}x{} of size {}x{}",
+                    bold_font.columns,
+                    bold_font.rows,
+                    bold_font.width,
+                    bold_font.height,
+                    grid.0,
+                    grid.1,
+                    cell_size.0,
+                    cell_size.1
+                ));
+            }
+        }
+
+        match &self.render_options.custom_post_shader {
+            Some(source) => check_post_shader(source)?,
+            None if self.render_options.post_effect == PostEffect::Custom => {
+                return bad_config(
+                    "the custom post effect was chosen without a custom post shader".to_string(),
+                );
+            }
+            None => {}
         }
+
+        if self.render_options.font_scale == 0 {
+            return bad_config("the font scale must not be zero".to_string());
+        }
+
+        for (name, size) in [("minimum", self.min_size), ("maximum", self.max_size)].iter() {
+            if let Some(WindowSize::Pixels(width, height))
+            | Some(WindowSize::Chars(width, height)) = size
+            {
+                if *width == 0 || *height == 0 {
+                    return bad_config(format!(
+                        "the {} size must not be zero, but {}x{} was given",
+                        name, width, height
+                    ));
+                }
+            }
+        }
+
+        if let (Some(min_size), Some(max_size)) = (self.min_size, self.max_size) {
+            let cell_size = self.cell_size();
+            let min_size = min_size.to_pixels(cell_size);
+            let max_size = max_size.to_pixels(cell_size);
+            if min_size.0 > max_size.0 || min_size.1 > max_size.1 {
+                return bad_config(format!(
+                    "the minimum size ({}x{} pixels) is larger than the maximum size ({}x{} pixels)",
+                    min_size.0, min_size.1, max_size.0, max_size.1
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The size of a character cell in pixels, taking the font scale into
+    /// account.
+    pub(crate) fn cell_size(&self) -> (u32, u32) {
+        let (width, height) = self.font.cell_size();
+        let scale = self.render_options.font_scale;
+        (width * scale, height * scale)
     }
 }
 
-/// Load a font from a given image in a byte array and generate a FontData
-/// structure.
-///
-/// # Arguments
-///
-/// * __data__ - byte array that contains the image data.  You can use the
-///   `include_bytes!` macro to generate this from a file at compile time.
-/// * __format__ - The image::ImageFormat enum that declares the file format the
-///   image data is in.
-///
-/// # Notes
-///
-/// This function will assume that the image contains 256 characters in a 16x16
-/// grid of equally sized cells.
-
-pub fn load_font_image(data: &[u8], format: ImageFormat) -> Result<FontData> {
-    let font_image =
-        image::load_from_memory_with_format(data, format).map_err(|_| Error::BadFont)?;
-    let dimensions = font_image.dimensions();
-    let font_rgba = font_image.to_rgba8();
-    let font_data = font_rgba.as_bytes();
-    let data_u32: &[u32] = cast_slice(font_data);
-    let char_width = dimensions.0 / 16;
-    let char_height = dimensions.1 / 16;
-    if char_width == 0 || char_height == 0 {
-        return Err(Error::BadFont);
-    }
-
-    Ok(FontData {
-        width: char_width,
-        height: char_height,
-        data: Vec::from(data_u32),
-    })
+impl Default for Builder {
+    fn default() -> Self {
+        Builder::new()
+    }
+}
+
+//
+// Font implementation
+//
+
+impl Font {
+    /// The size of a character cell in pixels.
+    pub(crate) fn cell_size(&self) -> (u32, u32) {
+        match self {
+            Font::Builtin(font) => font.cell_size(),
+            Font::Custom(font) => (font.width, font.height),
+            Font::Set(_) => self
+                .font_data()
+                .map_or((0, 0), |font| (font.width, font.height)),
+            #[cfg(feature = "hot-reload")]
+            Font::Watched(_, font) => (font.width, font.height),
+        }
+    }
+
+    /// The pixel data of a custom font, or `None` for a built-in font.  For a
+    /// font set, this is the font used at a scale factor of 1.
+    fn font_data(&self) -> Option<&FontData> {
+        match self {
+            Font::Builtin(_) => None,
+            Font::Custom(font) => Some(font),
+            Font::Set(fonts) => best_font(fonts, 1.0).map(|index| &fonts[index].1),
+            #[cfg(feature = "hot-reload")]
+            Font::Watched(_, font) => Some(font),
+        }
+    }
+}
+
+//
+// WindowSize implementation
+//
+
+impl WindowSize {
+    /// Convert the size into pixels that fit whole characters of the given
+    /// cell size.
+    pub(crate) fn to_pixels(self, cell_size: (u32, u32)) -> (u32, u32) {
+        match self {
+            WindowSize::Pixels(width, height) => (
+                width as u32 / cell_size.0 * cell_size.0,
+                height as u32 / cell_size.1 * cell_size.1,
+            ),
+            WindowSize::Chars(width, height) => {
+                (width as u32 * cell_size.0, height as u32 * cell_size.1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The settings of a builder that are compared, with the font's pixels.
+    fn settings(builder: &Builder) -> ((usize, usize), String, Option<Vec<u32>>) {
+        let font = match &builder.font {
+            Font::Custom(font) => Some(font.data.clone()),
+            _ => None,
+        };
+        (builder.inner_size, builder.title.clone(), font)
+    }
+
+    #[test]
+    fn building_twice_gives_the_same_settings() {
+        let font = BuiltinFont::Default.font_data().unwrap();
+
+        let builder = Builder::new()
+            .with_initial_size(640, 480)
+            .with_window_title("Twice")
+            .with_custom_font(font.clone());
+        let first = builder.clone();
+        let second = builder;
+        assert_eq!(settings(&first), settings(&second));
+        assert_eq!(settings(&first).2, Some(font.data.clone()));
+
+        // The deprecated borrowing methods used to reset the font of the
+        // builder they were called on.
+        #[allow(deprecated)]
+        {
+            let mut builder = Builder::new();
+            builder
+                .with_inner_size(640, 480)
+                .with_title("Twice")
+                .with_font(font.clone());
+            let first = builder.build();
+            let second = builder.build();
+            assert_eq!(settings(&first), settings(&second));
+            assert_eq!(settings(&second).2, Some(font.data));
+        }
+    }
+
+    #[test]
+    fn default_matches_new() {
+        assert_eq!(settings(&Builder::default()), settings(&Builder::new()));
+    }
 }
@@ -0,0 +1,1852 @@
+//
+// Canvas
+// The operations shared by everything that holds a grid of characters
+//
+
+use std::{
+    convert::TryFrom,
+    fmt, mem,
+    slice::{Chunks, ChunksMut, Iter, IterMut},
+};
+
+use crate::{
+    box_drawing::{draw_border, draw_title},
+    charmap::cp437_glyph,
+    parse_markup,
+    progress::draw_progress,
+    Align, BarStyle, BoxStyle, CanvasView, Char, Charmap, Image, PanelStyle, Point, PresentInput,
+    Rect, Rgba, Span,
+};
+
+/// Draw formatted text on a canvas, in the same way as `format!`.
+///
+/// `draw_format!(canvas, x, y, ink, paper, "HP: {}/{}", hp, max_hp)` draws the
+/// text at `(x, y)` with `Canvas::draw_fmt`, without making a `String` for it.
+/// The canvas can be anything that implements `Canvas`, or a mutable reference
+/// to one.
+#[macro_export]
+macro_rules! draw_format {
+    ($canvas:expr, $x:expr, $y:expr, $ink:expr, $paper:expr, $($arg:tt)+) => {{
+        use $crate::Canvas as _;
+        $canvas.draw_fmt(
+            $crate::Point::new($x, $y),
+            $ink,
+            $paper,
+            format_args!($($arg)+),
+        )
+    }};
+}
+
+/// A grid of characters stored as three planes of `u32`s: the ink colours,
+/// the paper colours and the glyph words, each in row-major order.
+///
+/// Both `Image` and `PresentInput` are canvases, so anything written against
+/// this trait works on an off-screen image and directly on the window alike.
+/// Implementors only provide the size and the planes, and the rest of the
+/// methods are built on those.
+///
+/// Every method that writes to the canvas records the cells it wrote with
+/// `Canvas::mark_dirty`, so that `PresentInput` knows which part of the window
+/// changed.  Code that writes to the planes directly should call it too.
+pub trait Canvas {
+    /// The width and height of the canvas in characters.
+    fn size(&self) -> (usize, usize);
+
+    /// The ink, paper and glyph planes.  Each is `width * height` long.
+    fn planes(&self) -> (&[u32], &[u32], &[u32]);
+
+    /// The ink, paper and glyph planes, for writing.  Cells written through
+    /// these should be passed to `Canvas::mark_dirty`.
+    fn planes_mut(&mut self) -> (&mut [u32], &mut [u32], &mut [u32]);
+
+    /// Record that the cells in a rectangle have been written.  Canvases that
+    /// do not keep track of changes, such as `Image`, ignore this.
+    fn mark_dirty(&mut self, _rect: Rect) {}
+
+    /// The smallest rectangle holding every cell written so far, or None if
+    /// nothing has been written.  Canvases that do not keep track of changes
+    /// return the whole canvas.
+    fn dirty_region(&self) -> Option<Rect> {
+        let (width, height) = self.size();
+        if width == 0 || height == 0 {
+            None
+        } else {
+            Some(Rect::new(0, 0, width, height))
+        }
+    }
+
+    /// Return the index into the planes of a position, or None if it lies
+    /// outside the canvas.
+    fn index_of(&self, p: Point) -> Option<usize> {
+        let (width, height) = self.size();
+        if p.x >= 0 && p.y >= 0 && (p.x as usize) < width && (p.y as usize) < height {
+            Some(p.y as usize * width + p.x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Clip a rectangle to the canvas, returning the position and size of the
+    /// part inside it, or None if none of it is inside.
+    fn clip(&self, rect: Rect) -> Option<(usize, usize, usize, usize)> {
+        let (width, height) = self.size();
+        // Work in i64 so that no combination of position and size overflows.
+        let clip_axis = |start: i32, len: usize, limit: usize| {
+            let end = (start as i64)
+                .saturating_add(i64::try_from(len).unwrap_or(i64::MAX))
+                .min(limit as i64);
+            let start = (start as i64).max(0);
+            if start < end {
+                Some((start as usize, (end - start) as usize))
+            } else {
+                None
+            }
+        };
+        let (x, w) = clip_axis(rect.x, rect.width, width)?;
+        let (y, h) = clip_axis(rect.y, rect.height, height)?;
+
+        Some((x, y, w, h))
+    }
+
+    /// Change the ink colour at a position, leaving the paper and glyph alone.
+    /// Positions outside the canvas are ignored.
+    fn set_ink(&mut self, p: Point, ink: impl Into<u32>) {
+        if let Some(i) = self.index_of(p) {
+            self.mark_dirty(Rect::new(p.x, p.y, 1, 1));
+            self.planes_mut().0[i] = ink.into();
+        }
+    }
+
+    /// Change the paper colour at a position, leaving the ink and glyph alone.
+    /// Positions outside the canvas are ignored.
+    fn set_paper(&mut self, p: Point, paper: impl Into<u32>) {
+        if let Some(i) = self.index_of(p) {
+            self.mark_dirty(Rect::new(p.x, p.y, 1, 1));
+            self.planes_mut().1[i] = paper.into();
+        }
+    }
+
+    /// Change the character at a position, leaving the colours alone.  The
+    /// page is reset to 0 and the attributes, such as `Char::BOLD`, are kept.
+    /// Positions outside the canvas are ignored.
+    fn set_glyph(&mut self, p: Point, ch: u8) {
+        if let Some(i) = self.index_of(p) {
+            self.mark_dirty(Rect::new(p.x, p.y, 1, 1));
+            let text = &mut self.planes_mut().2[i];
+            *text = (*text & Char::ATTRIBUTES) | ch as u32;
+        }
+    }
+
+    /// Swap the ink and paper colours at a position, such as to highlight a
+    /// selection.  Positions outside the canvas are ignored.
+    fn invert(&mut self, p: Point) {
+        if let Some(i) = self.index_of(p) {
+            self.mark_dirty(Rect::new(p.x, p.y, 1, 1));
+            let (fore, back, _) = self.planes_mut();
+            mem::swap(&mut fore[i], &mut back[i]);
+        }
+    }
+
+    /// Change the ink colour of every cell in a rectangle, clipped to the
+    /// canvas.
+    fn set_ink_rect(&mut self, rect: Rect, ink: impl Into<u32>) {
+        let ink = ink.into();
+        self.for_each_row(rect, |fore, _, _| fore.fill(ink));
+    }
+
+    /// Change the paper colour of every cell in a rectangle, clipped to the
+    /// canvas.
+    fn set_paper_rect(&mut self, rect: Rect, paper: impl Into<u32>) {
+        let paper = paper.into();
+        self.for_each_row(rect, |_, back, _| back.fill(paper));
+    }
+
+    /// Change the character of every cell in a rectangle, clipped to the
+    /// canvas, in the same way as `set_glyph`.
+    fn set_glyph_rect(&mut self, rect: Rect, ch: u8) {
+        self.for_each_row(rect, |_, _, text| {
+            text.iter_mut()
+                .for_each(|text| *text = (*text & Char::ATTRIBUTES) | ch as u32)
+        });
+    }
+
+    /// Swap the ink and paper colours of every cell in a rectangle, clipped
+    /// to the canvas.
+    fn invert_rect(&mut self, rect: Rect) {
+        self.for_each_row(rect, |fore, back, _| fore.swap_with_slice(back));
+    }
+
+    /// Set every cell in a rectangle, clipped to the canvas, to a character.
+    fn fill_rect(&mut self, rect: Rect, ch: Char) {
+        self.for_each_row(rect, |fore, back, text| {
+            fore.fill(ch.ink);
+            back.fill(ch.paper);
+            text.fill(ch.ch);
+        });
+    }
+
+    /// Set every cell in a rectangle, clipped to the canvas, to the character
+    /// returned by a function, such as for a noise background or a plasma
+    /// effect.  The function is given the x and y of each cell on the canvas,
+    /// not within the rectangle, so that neighbouring rectangles filled with
+    /// the same function line up.  It is only called for cells on the canvas,
+    /// a row at a time from the top-left.
+    fn fill_with(&mut self, rect: Rect, mut f: impl FnMut(u32, u32) -> Char) {
+        self.map_cells(rect, |x, y, _| f(x, y));
+    }
+
+    /// Replace every cell in a rectangle, clipped to the canvas, with the
+    /// character a function returns for it, such as to darken cells outside
+    /// the player's view.  The function is given the x and y of each cell on
+    /// the canvas and the character there, in the same way as `fill_with`.
+    fn map_cells(&mut self, rect: Rect, mut f: impl FnMut(u32, u32, Char) -> Char) {
+        if let Some((x, y, w, h)) = self.clip(rect) {
+            self.mark_dirty(Rect::new(x as i32, y as i32, w, h));
+            let (width, _) = self.size();
+            let (fore, back, text) = self.planes_mut();
+            for row in y..y + h {
+                let cells = row * width + x..row * width + x + w;
+                let row_cells = fore[cells.clone()]
+                    .iter_mut()
+                    .zip(&mut back[cells.clone()])
+                    .zip(&mut text[cells]);
+                for (column, ((ink, paper), ch)) in (x..).zip(row_cells) {
+                    let old = Char {
+                        ch: *ch,
+                        ink: *ink,
+                        paper: *paper,
+                    };
+                    let new = f(column as u32, row as u32, old);
+                    *ink = new.ink;
+                    *paper = new.paper;
+                    *ch = new.ch;
+                }
+            }
+        }
+    }
+
+    /// Draw a row of `len` characters going right from a position, clipped to
+    /// the canvas.  A length of 0 draws nothing.
+    fn draw_hline(&mut self, p: Point, len: usize, ch: Char) {
+        self.fill_rect(Rect::new(p.x, p.y, len, 1), ch);
+    }
+
+    /// Draw a column of `len` characters going down from a position, clipped
+    /// to the canvas.  A length of 0 draws nothing.
+    fn draw_vline(&mut self, p: Point, len: usize, ch: Char) {
+        self.fill_rect(Rect::new(p.x, p.y, 1, len), ch);
+    }
+
+    /// Draw a horizontal line with the code page 437 line glyph (─), in the
+    /// same way as `draw_hline`.
+    fn draw_hline_box(&mut self, p: Point, len: usize, ink: impl Into<u32>, paper: impl Into<u32>) {
+        self.draw_hline(p, len, Char::new(0xc4, ink.into(), paper.into()));
+    }
+
+    /// Draw a vertical line with the code page 437 line glyph (│), in the same
+    /// way as `draw_vline`.
+    fn draw_vline_box(&mut self, p: Point, len: usize, ink: impl Into<u32>, paper: impl Into<u32>) {
+        self.draw_vline(p, len, Char::new(0xb3, ink.into(), paper.into()));
+    }
+
+    /// Draw the border of a box with the code page 437 line glyphs, such as
+    /// ┌─┐, clipped to the canvas.  The inside of the box is left alone.
+    ///
+    /// Boxes 1 cell wide are drawn as a vertical line, and those 1 cell high as
+    /// a horizontal line.
+    fn draw_box(
+        &mut self,
+        p: Point,
+        width: usize,
+        height: usize,
+        style: BoxStyle,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        let rect = Rect::new(p.x, p.y, width, height);
+        draw_border(self, rect, style, ink.into(), paper.into(), false);
+    }
+
+    /// Draw the border of a box in the same way as `draw_box`, but join its
+    /// lines up with any line glyphs already on the canvas, such as with a ┬
+    /// where another box's edge meets its top.  This is how panels that share
+    /// edges or cross each other are drawn.
+    fn draw_box_merge(
+        &mut self,
+        p: Point,
+        width: usize,
+        height: usize,
+        style: BoxStyle,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        let rect = Rect::new(p.x, p.y, width, height);
+        draw_border(self, rect, style, ink.into(), paper.into(), true);
+    }
+
+    /// Draw a panel: a box with its inside filled, and a title in its top
+    /// border if there is one.  Titles too long for the border are cut short
+    /// with "...".
+    ///
+    /// Returns the inside of the panel, so that its contents can be drawn
+    /// there without working out the offsets again.
+    fn draw_panel(
+        &mut self,
+        p: Point,
+        width: usize,
+        height: usize,
+        title: Option<&str>,
+        style: &PanelStyle,
+    ) -> Rect {
+        let rect = Rect::new(p.x, p.y, width, height);
+        draw_border(self, rect, style.border, style.ink, style.paper, false);
+        let inside = Rect::new(
+            p.x.saturating_add(1),
+            p.y.saturating_add(1),
+            width.saturating_sub(2),
+            height.saturating_sub(2),
+        );
+        self.fill_rect(inside, Char::new(b' ', style.ink, style.fill));
+        if let Some(title) = title {
+            draw_title(self, rect, title, style);
+        }
+        inside
+    }
+
+    /// Draw text that can span several lines, clipped to the canvas.
+    ///
+    /// Each '\n' starts a new line under the start of the first, '\r' is
+    /// skipped and '\t' moves on to the next multiple of 8 columns from the
+    /// start of the line.  Every other byte is drawn as a glyph, in the same way
+    /// as `Image::draw_string`.
+    fn draw_text(&mut self, p: Point, text: &str, ink: impl Into<u32>, paper: impl Into<u32>) {
+        self.draw_text_tabbed(p, text, 8, ink, paper);
+    }
+
+    /// Draw text in the same way as `draw_text`, with a tab stop every
+    /// `tab_width` columns.  A tab width of 0 draws '\t' as glyph 9.
+    fn draw_text_tabbed(
+        &mut self,
+        p: Point,
+        text: &str,
+        tab_width: usize,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        let (ink, paper) = (ink.into(), paper.into());
+        for (row, line) in text.split('\n').enumerate() {
+            let y = i32::try_from(p.y as i64 + row as i64);
+            let mut column = 0;
+            for &byte in line.as_bytes() {
+                let (ch, len) = match byte {
+                    b'\r' => continue,
+                    b'\t' if tab_width > 0 => (b' ', tab_width - column % tab_width),
+                    _ => (byte, 1),
+                };
+                // Lines beyond the range of an i32 are off the canvas anyway.
+                if let (Ok(x), Ok(y)) = (i32::try_from(p.x as i64 + column as i64), y) {
+                    self.draw_hline(Point::new(x, y), len, Char::new(ch, ink, paper));
+                }
+                column += len;
+            }
+        }
+    }
+
+    /// Draw a string on the middle row of a rectangle, aligned to its left,
+    /// centre or right, and clipped to both the rectangle and the canvas.
+    /// Every byte is drawn as a glyph, in the same way as `Image::draw_string`.
+    ///
+    /// Strings too long for the rectangle are cut short: left-aligned ones
+    /// lose their end, right-aligned ones lose their start, and centred ones
+    /// lose their middle, which is replaced with "...".  A centred string that
+    /// is an odd number of cells shorter than the rectangle has the extra space
+    /// on its right.
+    fn draw_string_aligned(
+        &mut self,
+        rect: Rect,
+        text: &str,
+        align: Align,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        if rect.height == 0 {
+            return;
+        }
+        let (text, width) = (text.as_bytes(), rect.width);
+        let shown = if text.len() <= width {
+            text.to_vec()
+        } else {
+            match align {
+                Align::Left => text[..width].to_vec(),
+                Align::Right => text[text.len() - width..].to_vec(),
+                Align::Centre if width <= 3 => text[..width].to_vec(),
+                Align::Centre => {
+                    let (start, end) = ((width - 2) / 2, (width - 3) / 2);
+                    [&text[..start], b"...", &text[text.len() - end..]].concat()
+                }
+            }
+        };
+        let space = width - shown.len();
+        let offset = match align {
+            Align::Left => 0,
+            Align::Centre => space / 2,
+            Align::Right => space,
+        };
+
+        let x = (rect.x as i64).saturating_add(offset as i64);
+        let middle = i64::try_from((rect.height - 1) / 2).unwrap_or(i64::MAX);
+        let y = (rect.y as i64).saturating_add(middle);
+        if let (Ok(x), Ok(y)) = (i32::try_from(x), i32::try_from(y)) {
+            draw_glyphs(self, Point::new(x, y), &shown, ink.into(), paper.into());
+        }
+    }
+
+    /// Draw a string centred in a rectangle, in the same way as
+    /// `draw_string_aligned`.
+    fn draw_string_centred(
+        &mut self,
+        rect: Rect,
+        text: &str,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        self.draw_string_aligned(rect, text, Align::Centre, ink, paper);
+    }
+
+    /// Draw a string against the right of a rectangle, in the same way as
+    /// `draw_string_aligned`.
+    fn draw_string_right(
+        &mut self,
+        rect: Rect,
+        text: &str,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        self.draw_string_aligned(rect, text, Align::Right, ink, paper);
+    }
+
+    /// Draw a string centred on a whole row of the canvas, in the same way as
+    /// `draw_string_aligned`.
+    fn draw_string_centred_row(
+        &mut self,
+        y: i32,
+        text: &str,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        let rect = Rect::new(0, y, self.size().0, 1);
+        self.draw_string_aligned(rect, text, Align::Centre, ink, paper);
+    }
+
+    /// Draw a string against the right of a whole row of the canvas, in the
+    /// same way as `draw_string_aligned`.
+    fn draw_string_right_row(
+        &mut self,
+        y: i32,
+        text: &str,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        let rect = Rect::new(0, y, self.size().0, 1);
+        self.draw_string_aligned(rect, text, Align::Right, ink, paper);
+    }
+
+    /// Draw markup such as "You hit the [red]orc[/]" on one line, clipped to
+    /// the canvas.  Text outside tags is drawn in `default_ink`.  See
+    /// `parse_markup()` for the tags that are understood.
+    fn draw_rich(
+        &mut self,
+        p: Point,
+        markup: &str,
+        default_ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        self.draw_spans(p, &parse_markup(markup), default_ink, paper);
+    }
+
+    /// Draw spans of text one after another on one line, clipped to the
+    /// canvas.  Spans without an ink colour are drawn in `default_ink`.
+    /// Characters are drawn as their code page 437 glyphs, in the same way as
+    /// `draw_str_unicode`.
+    fn draw_spans(
+        &mut self,
+        p: Point,
+        spans: &[Span],
+        default_ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        let (default_ink, paper) = (default_ink.into(), paper.into());
+        let (mut glyphs, mut inks) = (Vec::new(), Vec::new());
+        for span in spans {
+            let ink = span.ink.unwrap_or(default_ink);
+            for ch in span.text.chars() {
+                glyphs.push(cp437_glyph(ch).unwrap_or(b'?'));
+                inks.push(ink);
+            }
+        }
+        let papers = vec![paper; glyphs.len()];
+        draw_glyphs_multicolour(self, p, &glyphs, &inks, &papers);
+    }
+
+    /// Draw a string on one line, clipped to the canvas, turning each
+    /// character into its code page 437 glyph, so that "café" or "░▒▓" are
+    /// drawn as they read.  Characters that are not in code page 437 are drawn
+    /// as '?'.
+    ///
+    /// Use `draw_str_charmap` for fonts laid out in another code page.
+    fn draw_str_unicode(
+        &mut self,
+        p: Point,
+        text: &str,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        let glyph_for = |ch| cp437_glyph(ch).unwrap_or(b'?');
+        draw_chars(self, p, text, glyph_for, ink.into(), paper.into());
+    }
+
+    /// Draw a string on one line, clipped to the canvas, turning each
+    /// character into a glyph with a character map.  Characters without a
+    /// glyph are drawn as the character map's fallback glyph.
+    fn draw_str_charmap(
+        &mut self,
+        p: Point,
+        text: &str,
+        charmap: &Charmap,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+    ) {
+        let glyph_for = |ch| charmap.glyph_or_fallback(ch);
+        draw_chars(self, p, text, glyph_for, ink.into(), paper.into());
+    }
+
+    /// Draw formatted text on one line, clipped to the canvas, such as with
+    /// `format_args!("HP: {}/{}", hp, max_hp)`.  The text is drawn as it is
+    /// formatted, so no `String` is made for it.  Every byte is drawn as a
+    /// glyph, in the same way as `Image::draw_string`.
+    ///
+    /// The `draw_format!` macro is a shorter way of calling this.
+    fn draw_fmt(
+        &mut self,
+        p: Point,
+        ink: impl Into<u32>,
+        paper: impl Into<u32>,
+        args: fmt::Arguments,
+    ) {
+        let mut writer = GlyphWriter {
+            canvas: self,
+            x: p.x as i64,
+            y: p.y,
+            ink: ink.into(),
+            paper: paper.into(),
+        };
+        // Drawing never fails, so neither does formatting.
+        let _ = fmt::write(&mut writer, args);
+    }
+
+    /// Draw a string on one line with an ink and paper colour for each
+    /// character, such as for syntax highlighting, clipped to the canvas.
+    ///
+    /// The first character is drawn in the first colours of `inks` and
+    /// `papers`, and so on.  Drawing stops at the end of whichever of the
+    /// string and the two slices is shortest.  Every byte is drawn as a glyph,
+    /// in the same way as `Image::draw_string`.
+    fn draw_string_multicolour(&mut self, p: Point, text: &str, inks: &[u32], papers: &[u32]) {
+        draw_glyphs_multicolour(self, p, text.as_bytes(), inks, papers);
+    }
+
+    /// Draw a progress bar, such as for health or loading, filling the given
+    /// fraction of a rectangle from the left, clipped to the canvas.
+    /// Fractions outside 0 to 1 are clamped.
+    ///
+    /// The label, such as "34/50", is drawn in the centre of the bar's middle
+    /// row, cut short if it is wider than the bar.  Its colours are swapped
+    /// over the filled part so that it can always be read.  Characters are
+    /// drawn as their code page 437 glyphs, in the same way as
+    /// `draw_str_unicode`.
+    fn draw_progress(&mut self, rect: Rect, fraction: f32, label: Option<&str>, style: &BarStyle) {
+        draw_progress(self, rect, fraction, label, style);
+    }
+
+    /// Draw the outline of a circle, clipped to the canvas.
+    ///
+    /// Character cells are usually taller than they are wide, so on most fonts
+    /// this looks like a tall ellipse.  Use `draw_circle_aspect` for a circle
+    /// that looks round.
+    fn draw_circle(&mut self, centre: Point, radius: u32, ch: Char) {
+        self.draw_ellipse(centre, radius, radius, ch);
+    }
+
+    /// Draw a filled circle, clipped to the canvas.  Like `draw_circle`, it is
+    /// not corrected for the shape of the character cells.
+    fn draw_circle_filled(&mut self, centre: Point, radius: u32, ch: Char) {
+        self.draw_ellipse_filled(centre, radius, radius, ch);
+    }
+
+    /// Draw the outline of a circle that looks round on a font whose cells are
+    /// `aspect` times as tall as they are wide, such as 2 for an 8x16 font.
+    /// The radius is the number of rows from the centre to the edge.
+    fn draw_circle_aspect(&mut self, centre: Point, radius: u32, aspect: f32, ch: Char) {
+        self.draw_ellipse(centre, aspect_radius(radius, aspect), radius, ch);
+    }
+
+    /// Draw a filled circle that looks round, in the same way as
+    /// `draw_circle_aspect`.
+    fn draw_circle_filled_aspect(&mut self, centre: Point, radius: u32, aspect: f32, ch: Char) {
+        self.draw_ellipse_filled(centre, aspect_radius(radius, aspect), radius, ch);
+    }
+
+    /// Draw the outline of an ellipse with radii of `rx` columns and `ry` rows,
+    /// clipped to the canvas.  Radii of 0 draw a line or a single cell.
+    fn draw_ellipse(&mut self, centre: Point, rx: u32, ry: u32, ch: Char) {
+        ellipse_quadrant(rx, ry, |x, y| {
+            for &(x, y) in &[(x, y), (-x, y), (x, -y), (-x, -y)] {
+                fill_span(self, centre, (x, x), y, ch);
+            }
+        });
+    }
+
+    /// Draw a filled ellipse, clipped to the canvas.  It covers the same cells
+    /// as `draw_ellipse` and everything inside them.
+    fn draw_ellipse_filled(&mut self, centre: Point, rx: u32, ry: u32, ch: Char) {
+        // Each row is filled once, with the widest span found for it.
+        let fill_rows = |canvas: &mut Self, x: i64, y: i64| {
+            fill_span(canvas, centre, (-x, x), y, ch);
+            if y != 0 {
+                fill_span(canvas, centre, (-x, x), -y, ch);
+            }
+        };
+        let mut last = None;
+        ellipse_quadrant(rx, ry, |x, y| {
+            if let Some((last_x, last_y)) = last {
+                if last_y != y {
+                    fill_rows(self, last_x, last_y);
+                }
+            }
+            last = Some((x, y));
+        });
+        if let Some((x, y)) = last {
+            fill_rows(self, x, y);
+        }
+    }
+
+    /// Move everything on the canvas `dx` cells to the right and `dy` cells
+    /// down, filling the cells left behind with `fill`.  Negative offsets move
+    /// left and up, so a `dy` of -1 scrolls a log up by a line.
+    fn scroll(&mut self, dx: i32, dy: i32, fill: Char) {
+        let (width, height) = self.size();
+        self.scroll_region(Rect::new(0, 0, width, height), dx, dy, fill);
+    }
+
+    /// Move everything in a rectangle, clipped to the canvas, in the same way
+    /// as `scroll`.  Cells moved out of the rectangle are lost, and the rest of
+    /// the canvas is left alone.  If an offset is at least the size of the
+    /// rectangle, the whole rectangle is filled.
+    fn scroll_region(&mut self, rect: Rect, dx: i32, dy: i32, fill: Char) {
+        let (x, y, w, h) = match self.clip(rect) {
+            Some(clipped) => clipped,
+            None => return,
+        };
+        if dx == 0 && dy == 0 {
+            return;
+        }
+
+        // Find the part that stays in the rectangle, as its size and how far
+        // it is moved from one edge, relative to the rectangle.
+        let kept = |offset: i32, len: usize| {
+            let shift = offset.unsigned_abs() as usize;
+            let kept = len.checked_sub(shift).filter(|&kept| kept > 0)?;
+            if offset < 0 {
+                Some((shift, 0, kept))
+            } else {
+                Some((0, shift, kept))
+            }
+        };
+        let (src_x, dst_x, kept_w) = match kept(dx, w) {
+            Some(kept) => kept,
+            None => return self.fill_rect(Rect::new(x as i32, y as i32, w, h), fill),
+        };
+        let (src_y, dst_y, kept_h) = match kept(dy, h) {
+            Some(kept) => kept,
+            None => return self.fill_rect(Rect::new(x as i32, y as i32, w, h), fill),
+        };
+
+        // Move the rows in an order that reads each one before it is
+        // overwritten.  Moving within a row is handled by `copy_within`.
+        self.mark_dirty(Rect::new(x as i32, y as i32, w, h));
+        let (width, _) = self.size();
+        let (fore, back, text) = self.planes_mut();
+        for i in 0..kept_h {
+            let row = if dy > 0 { kept_h - 1 - i } else { i };
+            let src = (y + src_y + row) * width + x + src_x;
+            let dst = (y + dst_y + row) * width + x + dst_x;
+            fore.copy_within(src..src + kept_w, dst);
+            back.copy_within(src..src + kept_w, dst);
+            text.copy_within(src..src + kept_w, dst);
+        }
+
+        // Fill the rows and columns that were left behind.
+        let (x, y) = (x as i32, y as i32);
+        let (shift_x, shift_y) = (w - kept_w, h - kept_h);
+        let gap_x = if dx > 0 { x } else { x + kept_w as i32 };
+        let gap_y = if dy > 0 { y } else { y + kept_h as i32 };
+        self.fill_rect(Rect::new(x, gap_y, w, shift_y), fill);
+        self.fill_rect(Rect::new(gap_x, y, shift_x, h), fill);
+    }
+
+    /// Copy the cells in a rectangle to another position on the same canvas,
+    /// such as to move a panel's contents or duplicate a column.  The source
+    /// and destination can overlap.  Only the parts of the source on the
+    /// canvas are copied, to only the parts of the destination on it, and the
+    /// cells the source leaves behind are not changed.
+    fn copy_region(&mut self, src: Rect, dst: Point) {
+        let (width, height) = self.size();
+        let (src_x, src_y, w, h) = match self.clip(src) {
+            Some(clipped) => clipped,
+            None => return,
+        };
+
+        // Clip the destination, which is moved along with the source, and
+        // move the source along with it in turn.
+        let clip_axis = |src: usize, moved: i64, dst: i32, len: usize, limit: usize| {
+            let start = dst as i64 + moved;
+            let (first, end) = (start.max(0), (start + len as i64).min(limit as i64));
+            if first < end {
+                let skip = (first - start) as usize;
+                Some((src + skip, first as usize, (end - first) as usize))
+            } else {
+                None
+            }
+        };
+        let moved_x = src_x as i64 - src.x as i64;
+        let moved_y = src_y as i64 - src.y as i64;
+        let (src_x, dst_x, w) = match clip_axis(src_x, moved_x, dst.x, w, width) {
+            Some(clipped) => clipped,
+            None => return,
+        };
+        let (src_y, dst_y, h) = match clip_axis(src_y, moved_y, dst.y, h, height) {
+            Some(clipped) => clipped,
+            None => return,
+        };
+
+        // Copy the rows in an order that reads each one before it is
+        // overwritten, as `memmove` does.  Overlaps within a row are handled
+        // by `copy_within`.
+        self.mark_dirty(Rect::new(dst_x as i32, dst_y as i32, w, h));
+        let (fore, back, text) = self.planes_mut();
+        for i in 0..h {
+            let row = if dst_y > src_y { h - 1 - i } else { i };
+            let from = (src_y + row) * width + src_x;
+            let to = (dst_y + row) * width + dst_x;
+            fore.copy_within(from..from + w, to);
+            back.copy_within(from..from + w, to);
+            text.copy_within(from..from + w, to);
+        }
+    }
+
+    /// Call a function with the ink, paper and glyph slices of each row of a
+    /// rectangle, clipped to the canvas.
+    fn for_each_row(&mut self, rect: Rect, mut f: impl FnMut(&mut [u32], &mut [u32], &mut [u32])) {
+        if let Some((x, y, w, h)) = self.clip(rect) {
+            self.mark_dirty(Rect::new(x as i32, y as i32, w, h));
+            let (width, _) = self.size();
+            let (fore, back, text) = self.planes_mut();
+            for row in y..y + h {
+                let cells = row * width + x..row * width + x + w;
+                f(
+                    &mut fore[cells.clone()],
+                    &mut back[cells.clone()],
+                    &mut text[cells],
+                );
+            }
+        }
+    }
+
+    /// Return the glyphs of the canvas as text, with a line for each row, such
+    /// as for a bug report.  Glyphs are turned into characters with code page
+    /// 437, and control characters become spaces.
+    fn to_plain_text(&self) -> String {
+        let charmap = Charmap::cp437();
+        let mut plain = String::new();
+        for (_, _, text) in self.rows() {
+            plain.extend(text.iter().map(|&glyph| glyph_char(&charmap, glyph)));
+            plain.push('\n');
+        }
+        plain
+    }
+
+    /// Return the canvas as text with ANSI escape codes for the ink and paper
+    /// colours, so that printing it in a terminal with 24-bit colour shows
+    /// the canvas.  Glyphs are turned into characters as for `to_plain_text`.
+    /// The colours are only written when they change, and are reset at the
+    /// end of each line.
+    fn to_ansi_string(&self) -> String {
+        let charmap = Charmap::cp437();
+        let mut ansi = String::new();
+        for (fore, back, text) in self.rows() {
+            let mut current = None;
+            for ((&ink, &paper), &glyph) in fore.iter().zip(back).zip(text) {
+                let (new_ink, new_paper) = match current {
+                    Some((current_ink, current_paper)) => {
+                        (ink != current_ink, paper != current_paper)
+                    }
+                    None => (true, true),
+                };
+                if new_ink {
+                    ansi += &format!("\x1b[38;2;{}m", ansi_rgb(ink));
+                }
+                if new_paper {
+                    ansi += &format!("\x1b[48;2;{}m", ansi_rgb(paper));
+                }
+                current = Some((ink, paper));
+                ansi.push(glyph_char(&charmap, glyph));
+            }
+            ansi += "\x1b[0m\n";
+        }
+        ansi
+    }
+
+    /// Read back the character at a position, or None if it lies outside the
+    /// canvas.  The glyph word is returned as it was written, including the
+    /// page and attribute bits, which `Char::glyph` and `Char::attributes`
+    /// separate.
+    fn get_char(&self, p: Point) -> Option<Char> {
+        let i = self.index_of(p)?;
+        let (fore, back, text) = self.planes();
+        Some(Char {
+            ch: text[i],
+            ink: fore[i],
+            paper: back[i],
+        })
+    }
+
+    /// Iterate over the characters of a row, from left to right.  Rows
+    /// outside the canvas are empty.
+    fn get_row(&self, y: usize) -> Chars<'_> {
+        let (width, height) = self.size();
+        let (fore, back, text) = self.planes();
+        let row = if y < height {
+            y * width..(y + 1) * width
+        } else {
+            0..0
+        };
+        Chars {
+            fore: fore[row.clone()].iter(),
+            back: back[row.clone()].iter(),
+            text: text[row].iter(),
+        }
+    }
+
+    /// Iterate over every character with its coordinates, in row-major order.
+    ///
+    /// This does not allocate, and walks the three planes in step.  It is
+    /// still several times slower than a loop over the indices that the
+    /// compiler can vectorise, such as summing a plane, so hot loops over the
+    /// whole canvas are better written with `rows`.
+    fn cells(&self) -> Cells<'_> {
+        let (width, _) = self.size();
+        let (fore, back, text) = self.planes();
+        Cells {
+            chars: Chars {
+                fore: fore.iter(),
+                back: back.iter(),
+                text: text.iter(),
+            },
+            width,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    /// Iterate over every cell with its coordinates, in row-major order, for
+    /// changing them in place, such as `for (x, y, cell) in image.cells_mut()`.
+    ///
+    /// Like `cells`, this does not allocate.  The whole canvas is marked as
+    /// written.
+    fn cells_mut(&mut self) -> CellsMut<'_> {
+        let (width, height) = self.size();
+        self.mark_dirty(Rect::new(0, 0, width, height));
+        let (fore, back, text) = self.planes_mut();
+        CellsMut {
+            fore: fore.iter_mut(),
+            back: back.iter_mut(),
+            text: text.iter_mut(),
+            width,
+            x: 0,
+            y: 0,
+        }
+    }
+
+    /// Iterate over the rows from top to bottom, each as its ink, paper and
+    /// glyph slices.  Working on whole slices lets the compiler vectorise the
+    /// processing.
+    fn rows(&self) -> Rows<'_> {
+        // Chunks of 0 are not allowed, but a canvas with no width has no cells
+        // anyway.
+        let width = self.size().0.max(1);
+        let (fore, back, text) = self.planes();
+        Rows {
+            fore: fore.chunks(width),
+            back: back.chunks(width),
+            text: text.chunks(width),
+        }
+    }
+
+    /// Iterate over the rows from top to bottom, each as its ink, paper and
+    /// glyph slices, for changing them in place.  The whole canvas is marked
+    /// as written.
+    fn rows_mut(&mut self) -> RowsMut<'_> {
+        let (width, height) = self.size();
+        self.mark_dirty(Rect::new(0, 0, width, height));
+        let width = width.max(1);
+        let (fore, back, text) = self.planes_mut();
+        RowsMut {
+            fore: fore.chunks_mut(width),
+            back: back.chunks_mut(width),
+            text: text.chunks_mut(width),
+        }
+    }
+}
+
+/// The number of columns across a circle of `radius` rows, on a font whose
+/// cells are `aspect` times as tall as they are wide.
+fn aspect_radius(radius: u32, aspect: f32) -> u32 {
+    // Casting saturates, and turns NaN into 0.
+    (radius as f64 * aspect as f64).round() as u32
+}
+
+/// Find the cells of the quarter of an ellipse at its bottom right, relative
+/// to its centre, with the midpoint algorithm.  The cells are passed to `plot`
+/// in order from the bottom to the right, so `y` never increases and `x` never
+/// decreases.
+fn ellipse_quadrant(rx: u32, ry: u32, mut plot: impl FnMut(i64, i64)) {
+    // An ellipse with no height or width is a line, which the steps below
+    // would cut short.
+    if ry == 0 {
+        (0..=rx as i64).for_each(|x| plot(x, 0));
+        return;
+    }
+    if rx == 0 {
+        (0..=ry as i64).rev().for_each(|y| plot(0, y));
+        return;
+    }
+
+    // The decision values are scaled by 4 to keep them whole, and need more
+    // than 64 bits for the largest radii.
+    let (rx2, ry2) = (rx as i128 * rx as i128, ry as i128 * ry as i128);
+    let (mut x, mut y) = (0i128, ry as i128);
+    let (mut px, mut py) = (0, 2 * rx2 * y);
+
+    // Where the curve is flatter than 45 degrees, step across.
+    let mut p = 4 * ry2 - 4 * rx2 * y + rx2;
+    while px < py {
+        plot(x as i64, y as i64);
+        x += 1;
+        px += 2 * ry2;
+        if p < 0 {
+            p += 4 * (ry2 + px);
+        } else {
+            y -= 1;
+            py -= 2 * rx2;
+            p += 4 * (ry2 + px - py);
+        }
+    }
+
+    // Where it is steeper, step down.
+    let mut p = ry2 * (2 * x + 1) * (2 * x + 1) + 4 * rx2 * (y - 1) * (y - 1) - 4 * rx2 * ry2;
+    while y >= 0 {
+        plot(x as i64, y as i64);
+        y -= 1;
+        py -= 2 * rx2;
+        if p > 0 {
+            p += 4 * (rx2 - py);
+        } else {
+            x += 1;
+            px += 2 * ry2;
+            p += 4 * (rx2 - py + px);
+        }
+    }
+}
+
+/// Fill the cells from `span.0` to `span.1` columns across and `y` rows down
+/// from a point, clipped to the canvas.
+fn fill_span<C: Canvas + ?Sized>(canvas: &mut C, from: Point, span: (i64, i64), y: i64, ch: Char) {
+    let (width, height) = canvas.size();
+    let y = from.y as i64 + y;
+    let left = (from.x as i64 + span.0).max(0);
+    let right = (from.x as i64 + span.1).min(width as i64 - 1);
+    if y >= 0 && y < height as i64 && left <= right {
+        let rect = Rect::new(left as i32, y as i32, (right - left + 1) as usize, 1);
+        canvas.fill_rect(rect, ch);
+    }
+}
+
+/// Draw a row of glyphs from a point, clipped to the canvas.
+fn draw_glyphs<C: Canvas + ?Sized>(canvas: &mut C, p: Point, glyphs: &[u8], ink: u32, paper: u32) {
+    if let Some((x, y, w, _)) = canvas.clip(Rect::new(p.x, p.y, glyphs.len(), 1)) {
+        // Glyphs before the left edge are skipped, so the rest stay where they
+        // would have been.
+        let skip = (x as i64 - p.x as i64) as usize;
+        let i = y * canvas.size().0 + x;
+        canvas.mark_dirty(Rect::new(x as i32, y as i32, w, 1));
+        let (fore, back, text) = canvas.planes_mut();
+        fore[i..i + w].fill(ink);
+        back[i..i + w].fill(paper);
+        text[i..i + w]
+            .iter_mut()
+            .zip(&glyphs[skip..])
+            .for_each(|(text, &glyph)| *text = glyph as u32);
+    }
+}
+
+/// Draw a row of glyphs from a point with their own colours, clipped to the
+/// canvas.  Drawing stops at the end of the shortest slice.
+fn draw_glyphs_multicolour<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    p: Point,
+    glyphs: &[u8],
+    inks: &[u32],
+    papers: &[u32],
+) {
+    let len = glyphs.len().min(inks.len()).min(papers.len());
+    if let Some((x, y, w, _)) = canvas.clip(Rect::new(p.x, p.y, len, 1)) {
+        // Glyphs before the left edge are skipped along with their colours, so
+        // the rest stay where they would have been.
+        let skip = (x as i64 - p.x as i64) as usize;
+        let i = y * canvas.size().0 + x;
+        canvas.mark_dirty(Rect::new(x as i32, y as i32, w, 1));
+        let (fore, back, text) = canvas.planes_mut();
+        fore[i..i + w].copy_from_slice(&inks[skip..skip + w]);
+        back[i..i + w].copy_from_slice(&papers[skip..skip + w]);
+        text[i..i + w]
+            .iter_mut()
+            .zip(&glyphs[skip..])
+            .for_each(|(text, &glyph)| *text = glyph as u32);
+    }
+}
+
+/// Draw the characters of a string from a point, turning each into a glyph
+/// with a function, clipped to the canvas.
+fn draw_chars<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    p: Point,
+    text: &str,
+    glyph_for: impl Fn(char) -> u8,
+    ink: u32,
+    paper: u32,
+) {
+    let (width, height) = canvas.size();
+    if p.y < 0 || p.y as usize >= height {
+        return;
+    }
+    let row = p.y as usize * width;
+    let (fore, back, glyphs) = canvas.planes_mut();
+    let mut len = 0;
+    for (x, ch) in (p.x as i64..).zip(text.chars()) {
+        if x >= width as i64 {
+            break;
+        }
+        if x >= 0 {
+            let i = row + x as usize;
+            fore[i] = ink;
+            back[i] = paper;
+            glyphs[i] = glyph_for(ch) as u32;
+        }
+        len += 1;
+    }
+    canvas.mark_dirty(Rect::new(p.x, p.y, len, 1));
+}
+
+/// Draws the pieces of formatted text one after another, for
+/// `Canvas::draw_fmt`.
+struct GlyphWriter<'a, C: Canvas + ?Sized> {
+    canvas: &'a mut C,
+    x: i64,
+    y: i32,
+    ink: u32,
+    paper: u32,
+}
+
+impl<'a, C: Canvas + ?Sized> fmt::Write for GlyphWriter<'a, C> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Text beyond the range of an i32 is off the canvas anyway.
+        if let Ok(x) = i32::try_from(self.x) {
+            let p = Point::new(x, self.y);
+            draw_glyphs(self.canvas, p, s.as_bytes(), self.ink, self.paper);
+        }
+        self.x += s.len() as i64;
+        Ok(())
+    }
+}
+
+/// The character to show for a glyph when a canvas is written as text.
+fn glyph_char(charmap: &Charmap, glyph: u32) -> char {
+    match charmap.char_for(glyph as u8) {
+        c if c.is_control() => ' ',
+        c => c,
+    }
+}
+
+/// The red, green and blue of a colour as written in an ANSI escape code.
+fn ansi_rgb(c: u32) -> String {
+    let rgba = Rgba::from(c);
+    format!("{};{};{}", rgba.r, rgba.g, rgba.b)
+}
+
+/// A cell of a `Canvas` that can be changed in place, as given by
+/// `Canvas::cells_mut`.
+pub struct CellMut<'a> {
+    pub ink: &'a mut u32,
+    pub paper: &'a mut u32,
+    /// The glyph word, as described in `Char::ch`.
+    pub ch: &'a mut u32,
+}
+
+impl<'a> CellMut<'a> {
+    /// Return a copy of the cell's character.
+    pub fn get(&self) -> Char {
+        Char {
+            ch: *self.ch,
+            ink: *self.ink,
+            paper: *self.paper,
+        }
+    }
+
+    /// Replace the cell's character.
+    pub fn set(&mut self, ch: Char) {
+        *self.ch = ch.ch;
+        *self.ink = ch.ink;
+        *self.paper = ch.paper;
+    }
+}
+
+/// An iterator over the characters of a `Canvas` and their coordinates.
+pub struct Cells<'a> {
+    chars: Chars<'a>,
+    width: usize,
+    /// The coordinates of the next cell, kept as a pair rather than worked
+    /// out from its index, which would cost a division every cell.
+    x: usize,
+    y: usize,
+}
+
+impl<'a> Iterator for Cells<'a> {
+    type Item = (usize, usize, Char);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let ch = self.chars.next()?;
+        let (x, y) = (self.x, self.y);
+        self.x += 1;
+        if self.x == self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some((x, y, ch))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chars.size_hint()
+    }
+}
+
+/// An iterator over the cells of a `Canvas` and their coordinates, for
+/// changing them in place.
+pub struct CellsMut<'a> {
+    fore: IterMut<'a, u32>,
+    back: IterMut<'a, u32>,
+    text: IterMut<'a, u32>,
+    width: usize,
+    x: usize,
+    y: usize,
+}
+
+impl<'a> Iterator for CellsMut<'a> {
+    type Item = (usize, usize, CellMut<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cell = CellMut {
+            ink: self.fore.next()?,
+            paper: self.back.next()?,
+            ch: self.text.next()?,
+        };
+        let (x, y) = (self.x, self.y);
+        self.x += 1;
+        if self.x == self.width {
+            self.x = 0;
+            self.y += 1;
+        }
+        Some((x, y, cell))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.text.size_hint()
+    }
+}
+
+/// An iterator over the rows of a `Canvas` as ink, paper and glyph slices.
+pub struct Rows<'a> {
+    fore: Chunks<'a, u32>,
+    back: Chunks<'a, u32>,
+    text: Chunks<'a, u32>,
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = (&'a [u32], &'a [u32], &'a [u32]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.fore.next()?, self.back.next()?, self.text.next()?))
+    }
+}
+
+/// An iterator over the rows of a `Canvas` as ink, paper and glyph slices,
+/// for changing them in place.
+pub struct RowsMut<'a> {
+    fore: ChunksMut<'a, u32>,
+    back: ChunksMut<'a, u32>,
+    text: ChunksMut<'a, u32>,
+}
+
+impl<'a> Iterator for RowsMut<'a> {
+    type Item = (&'a mut [u32], &'a mut [u32], &'a mut [u32]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.fore.next()?, self.back.next()?, self.text.next()?))
+    }
+}
+
+/// An iterator over characters read back from a `Canvas`.
+pub struct Chars<'a> {
+    fore: Iter<'a, u32>,
+    back: Iter<'a, u32>,
+    text: Iter<'a, u32>,
+}
+
+impl<'a> Iterator for Chars<'a> {
+    type Item = Char;
+
+    fn next(&mut self) -> Option<Char> {
+        Some(Char {
+            ch: *self.text.next()?,
+            ink: *self.fore.next()?,
+            paper: *self.back.next()?,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.text.size_hint()
+    }
+}
+
+impl<'a> ExactSizeIterator for Chars<'a> {}
+
+impl Canvas for Image {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn planes(&self) -> (&[u32], &[u32], &[u32]) {
+        (&self.fore_image, &self.back_image, &self.text_image)
+    }
+
+    fn planes_mut(&mut self) -> (&mut [u32], &mut [u32], &mut [u32]) {
+        (
+            &mut self.fore_image,
+            &mut self.back_image,
+            &mut self.text_image,
+        )
+    }
+}
+
+impl<'a> Canvas for PresentInput<'a> {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn planes(&self) -> (&[u32], &[u32], &[u32]) {
+        (self.fore_image, self.back_image, self.text_image)
+    }
+
+    fn planes_mut(&mut self) -> (&mut [u32], &mut [u32], &mut [u32]) {
+        (self.fore_image, self.back_image, self.text_image)
+    }
+
+    fn mark_dirty(&mut self, rect: Rect) {
+        if let Some((x, y, w, h)) = self.clip(rect) {
+            let rect = Rect::new(x as i32, y as i32, w, h);
+            *self.dirty = Some(self.dirty.map_or(rect, |dirty| dirty.union(rect)));
+        }
+    }
+
+    fn dirty_region(&self) -> Option<Rect> {
+        *self.dirty
+    }
+}
+
+impl<'a> Canvas for CanvasView<'a> {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn planes(&self) -> (&[u32], &[u32], &[u32]) {
+        (self.fore_image, self.back_image, self.text_image)
+    }
+
+    fn planes_mut(&mut self) -> (&mut [u32], &mut [u32], &mut [u32]) {
+        (self.fore_image, self.back_image, self.text_image)
+    }
+
+    fn mark_dirty(&mut self, rect: Rect) {
+        if let Some((x, y, w, h)) = self.clip(rect) {
+            let rect = Rect::new(x as i32, y as i32, w, h);
+            *self.dirty = Some(self.dirty.map_or(rect, |dirty| dirty.union(rect)));
+        }
+    }
+
+    fn dirty_region(&self) -> Option<Rect> {
+        *self.dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Align, Char, Colour, Image};
+
+    #[test]
+    fn cells_are_in_row_major_order() {
+        let mut image = Image::new(3, 2);
+        image.fill_with(Rect::new(0, 0, 3, 2), |x, y| {
+            Char::new(
+                b'a' + (y * 3 + x) as u8,
+                Colour::White.into(),
+                Colour::Black.into(),
+            )
+        });
+        let cells: Vec<(usize, usize, u8)> = image
+            .cells()
+            .map(|(x, y, ch)| (x, y, ch.ch as u8))
+            .collect();
+        assert_eq!(
+            cells,
+            vec![
+                (0, 0, b'a'),
+                (1, 0, b'b'),
+                (2, 0, b'c'),
+                (0, 1, b'd'),
+                (1, 1, b'e'),
+                (2, 1, b'f'),
+            ]
+        );
+
+        let coords: Vec<(usize, usize)> = image.cells_mut().map(|(x, y, _)| (x, y)).collect();
+        assert_eq!(coords, vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+    }
+
+    /// A small xorshift generator, so the random tests repeat exactly.
+    fn random(seed: u64) -> impl FnMut() -> u64 {
+        let mut state = seed;
+        move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        }
+    }
+
+    #[test]
+    fn clip_matches_the_intersection() {
+        let mut rand = random(12345);
+        let edges = [i32::MIN, -1000, -5, -1, 0, 1, 3, 5, 6, 7, 1000, i32::MAX];
+        let sizes = [0, 1, 2, 3, 5, 6, 7, 1000, usize::MAX];
+        let image = Image::new(6, 4);
+        for _ in 0..20000 {
+            let mut pick_edge = || edges[rand() as usize % edges.len()];
+            let (x, y) = (pick_edge(), pick_edge());
+            let mut pick_size = || sizes[rand() as usize % sizes.len()];
+            let (width, height) = (pick_size(), pick_size());
+            let rect = Rect::new(x, y, width, height);
+
+            // Intersect in i128, where nothing can overflow.
+            let axis = |start: i32, len: usize, limit: i128| {
+                let end = (start as i128 + len as i128).min(limit);
+                let start = (start as i128).max(0);
+                (start, end - start)
+            };
+            let (ix, iw) = axis(x, width, 6);
+            let (iy, ih) = axis(y, height, 4);
+            let expected = if iw > 0 && ih > 0 {
+                Some((ix as usize, iy as usize, iw as usize, ih as usize))
+            } else {
+                None
+            };
+            assert_eq!(Canvas::clip(&image, rect), expected, "{:?}", rect);
+        }
+    }
+
+    #[test]
+    fn drawing_random_rectangles_stays_inside() {
+        let mut rand = random(54321);
+        let mut image = Image::new(6, 4);
+        let ch = Char::new(b'x', Colour::White.into(), Colour::Black.into());
+        for _ in 0..20000 {
+            let x = (rand() % 20) as i32 - 10;
+            let y = (rand() % 12) as i32 - 6;
+            let rect = Rect::new(x, y, (rand() % 12) as usize, (rand() % 12) as usize);
+            image.draw_rect(rect.pos(), rect.width, rect.height, ch);
+            image.fill_rect(rect, ch);
+            image.draw_string(
+                rect.pos(),
+                "hello world",
+                Colour::White.into(),
+                Colour::Black.into(),
+            );
+        }
+        assert_eq!(image.text_image.len(), 6 * 4);
+    }
+
+    #[test]
+    fn copy_region_overlapping_in_each_direction() {
+        let copy = |src: Rect, dst: Point| {
+            let mut image = Image::from_strings(&["abcde", "fghij", "klmno", "pqrst"], 1u32, 2u32);
+            image.copy_region(src, dst);
+            image.to_plain_text()
+        };
+        // Right, left, down and up by one.
+        assert_eq!(
+            copy(Rect::new(0, 0, 4, 2), Point::new(1, 0)),
+            "aabcd\nffghi\nklmno\npqrst\n"
+        );
+        assert_eq!(
+            copy(Rect::new(1, 0, 4, 2), Point::new(0, 0)),
+            "bcdee\nghijj\nklmno\npqrst\n"
+        );
+        assert_eq!(
+            copy(Rect::new(0, 0, 2, 3), Point::new(0, 1)),
+            "abcde\nabhij\nfgmno\nklrst\n"
+        );
+        assert_eq!(
+            copy(Rect::new(0, 1, 2, 3), Point::new(0, 0)),
+            "fgcde\nklhij\npqmno\npqrst\n"
+        );
+        // Diagonally both ways.
+        assert_eq!(
+            copy(Rect::new(0, 0, 3, 3), Point::new(1, 1)),
+            "abcde\nfabcj\nkfgho\npklmt\n"
+        );
+        assert_eq!(
+            copy(Rect::new(1, 1, 3, 3), Point::new(0, 0)),
+            "ghide\nlmnij\nqrsno\npqrst\n"
+        );
+        // Clipped at both ends.
+        assert_eq!(
+            copy(Rect::new(-1, -1, 4, 4), Point::new(2, 2)),
+            "abcde\nfghij\nklmno\npqrab\n"
+        );
+        assert_eq!(
+            copy(Rect::new(2, 2, 9, 9), Point::new(-1, -1)),
+            "stcde\nfghij\nklmno\npqrst\n"
+        );
+    }
+
+    #[test]
+    fn draw_format_matches_draw_string() {
+        let (hp, max_hp) = (7, 12);
+        // Each argument is written separately, so these cross the edges
+        // partway through a piece of the text as well as between pieces.
+        for &(x, y) in &[
+            (-9, 1),
+            (-3, 1),
+            (0, 0),
+            (2, 1),
+            (5, 1),
+            (9, 1),
+            (0, -1),
+            (0, 2),
+        ] {
+            let mut formatted = Image::new(9, 2);
+            let mut drawn = Image::new(9, 2);
+            crate::draw_format!(
+                formatted,
+                x,
+                y,
+                3u32,
+                4u32,
+                "HP: {}/{} {:>3}",
+                hp,
+                max_hp,
+                "x"
+            );
+            let text = format!("HP: {}/{} {:>3}", hp, max_hp, "x");
+            drawn.draw_string(Point::new(x, y), &text, 3u32, 4u32);
+            crate::assert_images_eq!(formatted, drawn, "at ({}, {})", x, y);
+        }
+
+        // A reference to a canvas works too.
+        let mut image = Image::new(4, 1);
+        let canvas = &mut image;
+        crate::draw_format!(canvas, 0, 0, 1u32, 2u32, "{:.1}", 1.25);
+        assert_eq!(image.to_plain_text(), "1.2 \n");
+    }
+
+    /// Draw a string aligned in a row of dots `width` cells wide.
+    fn aligned(width: usize, text: &str, align: Align) -> String {
+        let mut image = Image::new(width, 1);
+        image.fill_rect(Rect::new(0, 0, width, 1), Char::new(b'.', 1u32, 2u32));
+        image.draw_string_aligned(Rect::new(0, 0, width, 1), text, align, 1u32, 2u32);
+        image.to_plain_text().trim_end().to_string()
+    }
+
+    #[test]
+    fn centring_puts_odd_space_on_the_right() {
+        assert_eq!(aligned(6, "ab", Align::Centre), "..ab..");
+        assert_eq!(aligned(6, "abc", Align::Centre), ".abc..");
+        assert_eq!(aligned(5, "ab", Align::Centre), ".ab..");
+        assert_eq!(aligned(5, "abc", Align::Centre), ".abc.");
+        assert_eq!(aligned(5, "", Align::Centre), ".....");
+        assert_eq!(aligned(5, "abcde", Align::Centre), "abcde");
+    }
+
+    #[test]
+    fn long_strings_are_cut_short() {
+        assert_eq!(aligned(4, "abcdef", Align::Left), "abcd");
+        assert_eq!(aligned(4, "abcdef", Align::Right), "cdef");
+        assert_eq!(aligned(4, "ab", Align::Right), "..ab");
+        // Centred strings lose their middle, keeping more of the start when
+        // the rest cannot be split evenly.
+        assert_eq!(aligned(7, "abcdefghij", Align::Centre), "ab...ij");
+        assert_eq!(aligned(8, "abcdefghij", Align::Centre), "abc...ij");
+        assert_eq!(aligned(3, "abcdefghij", Align::Centre), "abc");
+    }
+
+    #[test]
+    fn aligned_strings_use_the_middle_row() {
+        let mut image = Image::new(5, 4);
+        image.draw_string_centred(Rect::new(0, 0, 5, 4), "ab", 1u32, 2u32);
+        image.draw_string_right_row(3, "cd", 1u32, 2u32);
+        image.draw_string_centred_row(2, "e", 1u32, 2u32);
+        let text = image.to_plain_text();
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows[1].trim_end(), " ab");
+        assert_eq!(rows[2].trim_end(), "  e");
+        assert_eq!(rows[3], "   cd");
+    }
+
+    /// Draw with '#' on an image of dots, returning its rows.
+    fn shape(width: usize, height: usize, draw: impl FnOnce(&mut Image, Char)) -> Vec<String> {
+        let mut image = Image::new(width, height);
+        image.fill_rect(Rect::new(0, 0, width, height), Char::new(b'.', 1, 2));
+        draw(&mut image, Char::new(b'#', 1, 2));
+        image.to_plain_text().lines().map(String::from).collect()
+    }
+
+    /// Draw a circle, outlined and filled, in an image that just fits it.
+    fn circle(radius: u32) -> (Vec<String>, Vec<String>) {
+        let size = 2 * radius as usize + 1;
+        let centre = Point::new(radius as i32, radius as i32);
+        (
+            shape(size, size, |image, ch| {
+                image.draw_circle(centre, radius, ch)
+            }),
+            shape(size, size, |image, ch| {
+                image.draw_circle_filled(centre, radius, ch)
+            }),
+        )
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn circles_match_known_rasterisations() {
+        assert_eq!(circle(1).0, [
+            ".#.",
+            "#.#",
+            ".#.",
+        ]);
+        assert_eq!(circle(2).0, [
+            ".###.",
+            "#...#",
+            "#...#",
+            "#...#",
+            ".###.",
+        ]);
+        assert_eq!(circle(3).0, [
+            "..###..",
+            ".#...#.",
+            "#.....#",
+            "#.....#",
+            "#.....#",
+            ".#...#.",
+            "..###..",
+        ]);
+        assert_eq!(circle(4).0, [
+            "...###...",
+            ".##...##.",
+            ".#.....#.",
+            "#.......#",
+            "#.......#",
+            "#.......#",
+            ".#.....#.",
+            ".##...##.",
+            "...###...",
+        ]);
+        assert_eq!(circle(5).0, [
+            "...#####...",
+            "..#.....#..",
+            ".#.......#.",
+            "#.........#",
+            "#.........#",
+            "#.........#",
+            "#.........#",
+            "#.........#",
+            ".#.......#.",
+            "..#.....#..",
+            "...#####...",
+        ]);
+    }
+
+    #[test]
+    fn filled_circles_cover_their_outlines() {
+        for radius in 1..=5 {
+            let (outline, filled) = circle(radius);
+            // Each row runs from the first cell of the outline to the last.
+            for (outline, filled) in outline.iter().zip(&filled) {
+                let left = outline.find('#').unwrap();
+                let right = outline.rfind('#').unwrap();
+                let expected: String = (0..outline.len())
+                    .map(|x| {
+                        if (left..=right).contains(&x) {
+                            '#'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                assert_eq!(filled, &expected, "radius {}", radius);
+            }
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn ellipses_match_known_rasterisations() {
+        let centre = Point::new(6, 3);
+        assert_eq!(shape(13, 7, |image, ch| image.draw_ellipse(centre, 6, 3, ch)), [
+            "...#######...",
+            ".##.......##.",
+            "#...........#",
+            "#...........#",
+            "#...........#",
+            ".##.......##.",
+            "...#######...",
+        ]);
+        assert_eq!(shape(13, 7, |image, ch| image.draw_ellipse_filled(centre, 6, 3, ch)), [
+            "...#######...",
+            ".###########.",
+            "#############",
+            "#############",
+            "#############",
+            ".###########.",
+            "...#######...",
+        ]);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn zero_radii_draw_lines() {
+        let across = [
+            ".............",
+            ".###########.",
+            ".............",
+        ];
+        let centre = Point::new(6, 1);
+        assert_eq!(shape(13, 3, |image, ch| image.draw_ellipse(centre, 5, 0, ch)), across);
+        assert_eq!(shape(13, 3, |image, ch| image.draw_ellipse_filled(centre, 5, 0, ch)), across);
+
+        let down = [".#.", ".#.", ".#.", ".#.", ".#."];
+        let centre = Point::new(1, 2);
+        assert_eq!(shape(3, 5, |image, ch| image.draw_ellipse(centre, 0, 2, ch)), down);
+        assert_eq!(shape(3, 5, |image, ch| image.draw_ellipse_filled(centre, 0, 2, ch)), down);
+
+        let dot = ["...", ".#.", "..."];
+        let centre = Point::new(1, 1);
+        assert_eq!(shape(3, 3, |image, ch| image.draw_ellipse(centre, 0, 0, ch)), dot);
+        assert_eq!(shape(3, 3, |image, ch| image.draw_circle(centre, 0, ch)), dot);
+
+        // A radius that rounds to nothing across still draws a line down.
+        let centre = Point::new(4, 2);
+        assert_eq!(shape(9, 5, |image, ch| image.draw_circle_aspect(centre, 2, 0.2, ch)), [
+            "....#....",
+            "....#....",
+            "....#....",
+            "....#....",
+            "....#....",
+        ]);
+        // And one that rounds to nothing down draws a line across.
+        let centre = Point::new(4, 0);
+        assert_eq!(shape(9, 1, |image, ch| image.draw_ellipse(centre, 3, 0, ch)), [
+            ".#######.",
+        ]);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn circles_are_clipped() {
+        assert_eq!(shape(6, 4, |image, ch| image.draw_circle(Point::new(1, 1), 3, ch)), [
+            "....#.",
+            "....#.",
+            "....#.",
+            "...#..",
+        ]);
+        assert_eq!(shape(6, 4, |image, ch| image.draw_circle_filled(Point::new(5, 3), 3, ch)), [
+            "....##",
+            "...###",
+            "..####",
+            "..####",
+        ]);
+        // Wholly outside.
+        let outside = shape(3, 3, |image, ch| {
+            image.draw_circle(Point::new(-10, -10), 5, ch);
+            image.draw_ellipse_filled(Point::new(1, 20), 4, 2, ch);
+        });
+        assert_eq!(outside, ["...", "...", "..."]);
+    }
+
+    #[test]
+    fn get_char_returns_what_was_drawn() {
+        let mut image = Image::new(4, 3);
+        let ch = Char::new_glyph(0x1ab, 1, 2).bold().blink();
+        image.draw_char(Point::new(2, 1), ch);
+        assert_eq!(image.get_char(Point::new(2, 1)), Some(ch));
+        let read = image.get_char(Point::new(2, 1)).unwrap();
+        assert_eq!(read.glyph(), 0x1ab);
+        assert_eq!(read.attributes(), Char::BOLD | Char::BLINK);
+
+        assert_eq!(image.get_char(Point::new(0, 0)), Some(Char::new(0, 0, 0)));
+        for &(x, y) in &[(-1, 0), (0, -1), (4, 0), (0, 3), (i32::MIN, i32::MAX)] {
+            assert_eq!(image.get_char(Point::new(x, y)), None, "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn get_row_returns_the_row() {
+        let mut image = Image::new(3, 2);
+        let ch = Char::new_glyph(0x1ab, 1, 2).bold().blink();
+        image.draw_char(Point::new(1, 1), ch);
+        let blank = Char::new(0, 0, 0);
+        assert_eq!(image.get_row(0).collect::<Vec<_>>(), [blank; 3]);
+        assert_eq!(image.get_row(1).collect::<Vec<_>>(), [blank, ch, blank]);
+        assert_eq!(image.get_row(2).count(), 0);
+        assert_eq!(image.get_row(usize::MAX).count(), 0);
+    }
+
+    /// A 6x4 image whose cells all have different inks, papers and glyphs,
+    /// with every other cell in bold.
+    fn patterned() -> Image {
+        let mut image = Image::new(6, 4);
+        image.fill_with(Rect::new(0, 0, 6, 4), |x, y| {
+            let i = y * 6 + x;
+            let ch = Char::new(b'a' + i as u8, 0xff000000 | i, 0xff000100 | i);
+            if i % 2 == 0 {
+                ch.bold()
+            } else {
+                ch
+            }
+        });
+        image
+    }
+
+    /// Apply a change to a patterned image and check every plane against the
+    /// expected change to the cells it covers.  The other cells, and the
+    /// planes the change should leave alone, must be as they were.
+    fn check_planes(
+        change: impl FnOnce(&mut Image),
+        covers: impl Fn(i32, i32) -> bool,
+        expected: impl Fn(Char) -> Char,
+    ) {
+        let before = patterned();
+        let mut after = before.clone();
+        change(&mut after);
+
+        let mut wanted = before.clone();
+        for (x, y, ch) in before.cells() {
+            let p = Point::new(x as i32, y as i32);
+            if covers(p.x, p.y) {
+                wanted.draw_char(p, expected(ch));
+            }
+        }
+        assert_eq!(after.fore_image, wanted.fore_image, "ink");
+        assert_eq!(after.back_image, wanted.back_image, "paper");
+        assert_eq!(after.text_image, wanted.text_image, "glyphs");
+    }
+
+    fn set_ink(ch: Char) -> Char {
+        Char { ink: 7, ..ch }
+    }
+
+    fn set_paper(ch: Char) -> Char {
+        Char { paper: 9, ..ch }
+    }
+
+    fn set_glyph(ch: Char) -> Char {
+        Char {
+            ch: (ch.ch & Char::ATTRIBUTES) | b'#' as u32,
+            ..ch
+        }
+    }
+
+    fn invert(ch: Char) -> Char {
+        Char {
+            ink: ch.paper,
+            paper: ch.ink,
+            ..ch
+        }
+    }
+
+    #[test]
+    fn setters_change_one_plane() {
+        for &(x, y) in &[
+            (0, 0),
+            (2, 1),
+            (5, 3),
+            (-1, 0),
+            (6, 0),
+            (0, 4),
+            (i32::MIN, 0),
+        ] {
+            let p = Point::new(x, y);
+            let covers = |cx, cy| (cx, cy) == (x, y);
+            check_planes(|image| image.set_ink(p, 7u32), covers, set_ink);
+            check_planes(|image| image.set_paper(p, 9u32), covers, set_paper);
+            check_planes(|image| image.set_glyph(p, b'#'), covers, set_glyph);
+            check_planes(|image| image.invert(p), covers, invert);
+        }
+    }
+
+    #[test]
+    fn rect_setters_change_one_plane() {
+        let rects = [
+            Rect::new(0, 0, 6, 4),
+            Rect::new(1, 1, 3, 2),
+            // Clipped.
+            Rect::new(-2, -1, 4, 3),
+            Rect::new(4, 2, 10, 10),
+            Rect::new(i32::MIN, 1, usize::MAX, 1),
+            // Wholly outside.
+            Rect::new(6, 0, 2, 2),
+            Rect::new(-3, 0, 3, 4),
+            Rect::new(0, 4, 6, 1),
+            Rect::new(1, 1, 0, 2),
+        ];
+        for &rect in &rects {
+            let covers = |x: i32, y: i32| {
+                let inside = |p: i32, start: i32, len: usize| {
+                    p as i128 >= start as i128 && (p as i128) < start as i128 + len as i128
+                };
+                inside(x, rect.x, rect.width) && inside(y, rect.y, rect.height)
+            };
+            check_planes(|image| image.set_ink_rect(rect, 7u32), covers, set_ink);
+            check_planes(|image| image.set_paper_rect(rect, 9u32), covers, set_paper);
+            check_planes(|image| image.set_glyph_rect(rect, b'#'), covers, set_glyph);
+            check_planes(|image| image.invert_rect(rect), covers, invert);
+        }
+    }
+}
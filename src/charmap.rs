@@ -0,0 +1,124 @@
+//
+// Character maps
+// Translates between Unicode characters and the 256 glyphs of a font
+//
+
+use std::collections::HashMap;
+
+/// Maps each of the 256 glyphs in a font to the Unicode character it
+/// represents.
+///
+/// Fonts are laid out by code page rather than by Unicode, so a character map
+/// is needed when building fonts from sources that are indexed by Unicode
+/// (such as TrueType fonts), and when drawing Unicode text with them.  The
+/// default is code page 437, the character set of the original IBM PC.
+///
+/// Characters without a glyph are drawn as the fallback glyph, which is '?'
+/// unless it is changed with `Charmap::with_fallback`.
+#[derive(Debug, Clone)]
+pub struct Charmap {
+    chars: [char; 256],
+    glyphs: HashMap<char, u8>,
+    fallback: u8,
+}
+
+impl Charmap {
+    /// Create a character map from the characters each glyph represents.
+    ///
+    /// If a character appears more than once, converting it back to a glyph
+    /// gives the first glyph it appears at.
+    pub fn new(chars: [char; 256]) -> Self {
+        let mut glyphs = HashMap::with_capacity(256);
+        for (glyph, ch) in chars.iter().enumerate() {
+            glyphs.entry(*ch).or_insert(glyph as u8);
+        }
+        Charmap {
+            chars,
+            glyphs,
+            fallback: b'?',
+        }
+    }
+
+    /// The character map for code page 437, as used by the IBM PC and most
+    /// ASCII art fonts.
+    pub fn cp437() -> Self {
+        Charmap::new(CP437)
+    }
+
+    /// The character map for ISO 8859-1 (Latin-1), where each glyph is the
+    /// Unicode character with the same value.
+    pub fn latin1() -> Self {
+        let mut chars = ['\0'; 256];
+        for (glyph, ch) in chars.iter_mut().enumerate() {
+            *ch = glyph as u8 as char;
+        }
+        Charmap::new(chars)
+    }
+
+    /// The character that a glyph represents.
+    pub fn char_for(&self, glyph: u8) -> char {
+        self.chars[glyph as usize]
+    }
+
+    /// The glyph that represents a character, if there is one.
+    pub fn glyph_for(&self, ch: char) -> Option<u8> {
+        self.glyphs.get(&ch).copied()
+    }
+
+    /// The glyph that represents a character, or the fallback glyph if there
+    /// is none.
+    pub fn glyph_or_fallback(&self, ch: char) -> u8 {
+        self.glyph_for(ch).unwrap_or(self.fallback)
+    }
+
+    /// Draw characters that have no glyph as this glyph, rather than '?'.
+    pub fn with_fallback(mut self, glyph: u8) -> Self {
+        self.fallback = glyph;
+        self
+    }
+
+    /// Draw another character as a glyph, such as a font's own symbol for a
+    /// character that is not in its code page, or a look-alike such as 'β' for
+    /// code page 437's 'ß'.  The glyph still converts back to its original
+    /// character.
+    pub fn with_char(mut self, ch: char, glyph: u8) -> Self {
+        self.glyphs.insert(ch, glyph);
+        self
+    }
+}
+
+/// The code page 437 glyph that represents a character, if there is one.  This
+/// gives the same glyphs as `Charmap::cp437()` without building one.
+pub(crate) fn cp437_glyph(ch: char) -> Option<u8> {
+    match ch {
+        ' '..='~' => Some(ch as u8),
+        _ => CP437.iter().position(|&c| c == ch).map(|glyph| glyph as u8),
+    }
+}
+
+impl Default for Charmap {
+    fn default() -> Self {
+        Charmap::cp437()
+    }
+}
+
+/// The characters represented by each glyph in code page 437.
+#[rustfmt::skip]
+const CP437: [char; 256] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⌂',
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
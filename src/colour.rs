@@ -1,12 +1,461 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::colours;
+
 /// Generate a u32 compatible with the presentation arrays from colour
 /// components.
-pub fn colour(r: u8, g: u8, b: u8) -> u32 {
+pub const fn colour(r: u8, g: u8, b: u8) -> u32 {
     0xff000000u32 + ((b as u32) << 16) + ((g as u32) << 8) + (r as u32)
 }
 
-/// Basic colours for convenience.
+/// Generate a u32 compatible with the presentation arrays from colour
+/// components and an alpha.
+///
+/// The components are packed the same way as `colour()`, as 0xAABBGGRR, so an
+/// alpha of 255 gives the same value as `colour()`.
+pub const fn colour_rgba(r: u8, g: u8, b: u8, a: u8) -> u32 {
+    ((a as u32) << 24) + ((b as u32) << 16) + ((g as u32) << 8) + (r as u32)
+}
+
+/// Replace the alpha of a colour generated by `colour()` or `colour_rgba()`.
+pub const fn with_alpha(c: u32, a: u8) -> u32 {
+    (c & 0x00ffffff) | ((a as u32) << 24)
+}
+
+/// Return the alpha of a colour generated by `colour()` or `colour_rgba()`.
+pub const fn alpha_of(c: u32) -> u8 {
+    (c >> 24) as u8
+}
+
+/// Generate an opaque colour from a hue, saturation and value.
+///
+/// The hue is in degrees and wraps around, so both -120 and 240 are blue.  The
+/// saturation and value are clamped to 0..1.  The primary and secondary
+/// colours are exact, so `colour_hsv(0.0, 1.0, 1.0) == colour(255, 0, 0)`.
+pub fn colour_hsv(h: f32, s: f32, v: f32) -> u32 {
+    let s = s.clamp(0.0, 1.0);
+    let v = v.clamp(0.0, 1.0);
+    let c = v * s;
+    from_hue(h, c, v - c)
+}
+
+/// Generate an opaque colour from a hue, saturation and lightness.
+///
+/// The hue is in degrees and wraps around.  The saturation and lightness are
+/// clamped to 0..1, and a lightness of 0.5 gives the fully saturated colours,
+/// so `colour_hsl(120.0, 1.0, 0.5) == colour(0, 255, 0)`.
+pub fn colour_hsl(h: f32, s: f32, l: f32) -> u32 {
+    let s = s.clamp(0.0, 1.0);
+    let l = l.clamp(0.0, 1.0);
+    let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    from_hue(h, c, l - c / 2.0)
+}
+
+/// Split a colour into its hue (in degrees, 0..360), saturation and value.
+///
+/// This is the inverse of `colour_hsv()`, and is useful for shifting the hue
+/// of existing colours.  The alpha is ignored.  Greys have a hue and
+/// saturation of 0.
+pub fn to_hsv(c: u32) -> (f32, f32, f32) {
+    let Rgba { r, g, b, .. } = Rgba::from(c);
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let chroma = max - r.min(g).min(b);
+
+    let h = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / chroma)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+    let s = if max == 0.0 { 0.0 } else { chroma / max };
+
+    (h.rem_euclid(360.0), s, max)
+}
+
+/// Build a colour from a hue, the chroma and the amount added to every
+/// component, as shared by HSV and HSL.
+fn from_hue(h: f32, c: f32, m: f32) -> u32 {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let component = |n: f32| ((n + m) * 255.0).round() as u8;
+    colour(component(r), component(g), component(b))
+}
+
+/// Mix two colours, going from `a` when `t` is 0 to `b` when `t` is 1.
+///
+/// Each component, including the alpha, is interpolated linearly and `t` is
+/// clamped to 0..1, so the ends give exactly `a` and `b`.  This is cheap enough
+/// to call for every cell on every frame.
+pub fn lerp_colour(a: u32, b: u32, t: f32) -> u32 {
+    // Work in 1/256ths so that the mix is integer maths.
+    let t = (t.clamp(0.0, 1.0) * 256.0) as u32;
+    map_components(a, b, |a, b| ((a * (256 - t) + b * t) >> 8) as u8)
+}
+
+/// Mix two colours like `lerp_colour()`, but in linear light.
+///
+/// The colours are stored with a gamma, so mixing them directly gives a midway
+/// colour that looks too dark.  This version approximates the gamma as 2,
+/// which gives smoother gradients between very different colours at the cost
+/// of some floating point maths.  The alpha is still mixed linearly.
+pub fn lerp_colour_gamma(a: u32, b: u32, t: f32) -> u32 {
+    let t = t.clamp(0.0, 1.0);
+    let mixed = map_components(a, b, |a, b| {
+        let (a, b) = (a as f32 / 255.0, b as f32 / 255.0);
+        ((a * a + (b * b - a * a) * t).sqrt() * 255.0).round() as u8
+    });
+    with_alpha(mixed, alpha_of(lerp_colour(a, b, t)))
+}
+
+/// Decode an sRGB colour component, in the range 0..1, to linear light.
+pub(crate) fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a colour component in linear light, in the range 0..1, as sRGB.
+pub(crate) fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Draw the colour `src` over `dst`, using `src`'s alpha.
+///
+/// This is the standard source-over compositing.  A fully opaque `src` gives
+/// `src`, and a fully transparent one gives `dst`.  If `dst` is opaque, so is
+/// the result.
+pub fn blend_over(src: u32, dst: u32) -> u32 {
+    let src_a = alpha_of(src) as u32;
+    // The amount of the destination that shows through the source.
+    let dst_a = (alpha_of(dst) as u32 * (255 - src_a) + 127) / 255;
+    let a = src_a + dst_a;
+    if a == 0 {
+        return 0;
+    }
+
+    let mixed = map_components(src, dst, |s, d| ((s * src_a + d * dst_a + a / 2) / a) as u8);
+    with_alpha(mixed, a as u8)
+}
+
+/// Multiply the red, green and blue of a colour by a factor, keeping its
+/// alpha.
 ///
-/// Use into() to convert to a u32.
+/// Factors below 1 dim the colour, and above 1 brighten it until each
+/// component reaches 255.  Negative factors give black.
+pub fn scale_colour(c: u32, factor: f32) -> u32 {
+    mul_colour_rgb(c, factor, factor, factor)
+}
+
+/// Multiply a colour by a light level, keeping its alpha.
+///
+/// This is `scale_colour()` under a name that goes with `mul_colour_rgb()`.
+pub fn mul_colour(c: u32, factor: f32) -> u32 {
+    scale_colour(c, factor)
+}
+
+/// Multiply the red, green and blue of a colour by separate factors, keeping
+/// its alpha.
+///
+/// This lights a colour with a coloured light, such as a torch at
+/// `(1.0, 0.8, 0.5)`.  Each component is clamped to 0..=255, so negative
+/// factors give 0.
+pub fn mul_colour_rgb(c: u32, r: f32, g: f32, b: f32) -> u32 {
+    let [cr, cg, cb, ca] = c.to_le_bytes();
+    // Casting a float to u8 clamps it to 0..=255.
+    let mul = |c: u8, factor: f32| (c as f32 * factor + 0.5) as u8;
+    u32::from_le_bytes([mul(cr, r), mul(cg, g), mul(cb, b), ca])
+}
+
+/// Return the grey with the same brightness as a colour, keeping its alpha.
+///
+/// The brightness is the colour's luma, which weights green the most and blue
+/// the least, as the eye does.
+pub fn to_greyscale(c: u32) -> u32 {
+    let [r, g, b, a] = c.to_le_bytes();
+    // The Rec. 601 weights, in 1/256ths.
+    let luma = ((77 * r as u32 + 150 * g as u32 + 29 * b as u32 + 128) >> 8) as u8;
+    u32::from_le_bytes([luma, luma, luma, a])
+}
+
+/// Remove some of the colour from a colour, keeping its alpha.
+///
+/// An amount of 0 leaves the colour unchanged and 1 turns it into the grey
+/// given by `to_greyscale()`.  The amount is clamped to 0..1.
+pub fn desaturate(c: u32, amount: f32) -> u32 {
+    lerp_colour(c, to_greyscale(c), amount)
+}
+
+/// The levels of each component in the 6x6x6 colour cube of the xterm
+/// 256-colour palette.
+const ANSI_CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Return the colour of an index in the xterm 256-colour palette.
+///
+/// Indices 0 to 15 are the ANSI colours black, red, green, yellow, blue,
+/// magenta, cyan and white, followed by their bright versions.  These use the
+/// same values as `Colour`.  Indices 16 to 231 are a 6x6x6 colour cube, and
+/// 232 to 255 are 24 greys from dark to light.
+pub fn ansi256_to_colour(index: u8) -> u32 {
+    match index {
+        0..=15 => {
+            const BASE: [Colour; 16] = [
+                Colour::Black,
+                Colour::DarkRed,
+                Colour::DarkGreen,
+                Colour::Brown,
+                Colour::DarkBlue,
+                Colour::DarkMagenta,
+                Colour::DarkCyan,
+                Colour::LightGrey,
+                Colour::DarkGrey,
+                Colour::Red,
+                Colour::Green,
+                Colour::Yellow,
+                Colour::Blue,
+                Colour::Magenta,
+                Colour::Cyan,
+                Colour::White,
+            ];
+            BASE[index as usize].into()
+        }
+        16..=231 => {
+            let i = (index - 16) as usize;
+            colour(
+                ANSI_CUBE_LEVELS[i / 36],
+                ANSI_CUBE_LEVELS[i / 6 % 6],
+                ANSI_CUBE_LEVELS[i % 6],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            colour(level, level, level)
+        }
+    }
+}
+
+/// Return the index in the xterm 256-colour palette closest to a colour.
+///
+/// The closest colour is the one with the smallest distance in RGB space, and
+/// the alpha is ignored.  Colours in the palette map back to an index of the
+/// same colour, although not always the same index, as some colours appear
+/// twice.
+pub fn colour_to_ansi256(c: u32) -> u8 {
+    let c = Rgba::from(c);
+    let distance = |index: u8| {
+        let p = Rgba::from(ansi256_to_colour(index));
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(c.r, p.r) + d(c.g, p.g) + d(c.b, p.b)
+    };
+    (0..=255).min_by_key(|&index| distance(index)).unwrap_or(0)
+}
+
+/// The ways a colour string given to `parse_colour()` can be wrong.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ColourParseError {
+    /// The colour had the wrong number of hex digits.
+    #[error("expected 3, 6 or 8 hex digits but found {0}")]
+    BadLength(usize),
+
+    /// The colour contained a character that is not a hex digit.
+    #[error("'{0}' is not a hex digit")]
+    InvalidDigit(char),
+
+    /// The colour was not hex and not the name of a colour.
+    #[error("'{0}' is not the name of a colour")]
+    UnknownName(String),
+}
+
+/// Parse a colour as written in a config file or theme.
+///
+/// The colour can be written in hex as `RGB`, `RRGGBB` or `RRGGBBAA`, with or
+/// without a leading `#`.  The short form doubles each digit, so `#f80` is the
+/// same as `#ff8800`.  Colours without an alpha are opaque.  The names of the
+/// `Colour` variants, such as `red` or `dark_grey`, are also understood, as
+/// are the CSS colour names in `colours`, such as `orange`.  Where the two
+/// disagree, such as `green`, the `Colour` wins.  Case does not matter.
+pub fn parse_colour(text: &str) -> Result<u32, ColourParseError> {
+    let text = text.trim();
+    let digits = match text.strip_prefix('#') {
+        Some(digits) => digits,
+        None => {
+            if let Some(c) = Colour::from_name(text) {
+                return Ok(c.into());
+            }
+            if let Some(c) = colours::lookup_named(text) {
+                return Ok(c);
+            }
+            // Names like "bad" are also hex, but anything else without a digit
+            // was probably meant to be a name.
+            if !text.chars().any(|c| c.is_ascii_digit())
+                && !text.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                return Err(ColourParseError::UnknownName(text.to_string()));
+            }
+            text
+        }
+    };
+
+    let values = digits
+        .chars()
+        .map(|c| c.to_digit(16).ok_or(ColourParseError::InvalidDigit(c)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let byte = |i: usize| (values[i] * 16 + values[i + 1]) as u8;
+    match values.len() {
+        3 => Ok(colour(
+            (values[0] * 17) as u8,
+            (values[1] * 17) as u8,
+            (values[2] * 17) as u8,
+        )),
+        6 => Ok(colour(byte(0), byte(2), byte(4))),
+        8 => Ok(colour_rgba(byte(0), byte(2), byte(4), byte(6))),
+        len => Err(ColourParseError::BadLength(len)),
+    }
+}
+
+/// Write a colour in the form read by `parse_colour()`.
+///
+/// Opaque colours are written as `#RRGGBB` and all others as `#RRGGBBAA`.
+pub fn format_colour(c: u32) -> String {
+    let c = Rgba::from(c);
+    if c.a == 255 {
+        format!("#{:02X}{:02X}{:02X}", c.r, c.g, c.b)
+    } else {
+        c.to_string()
+    }
+}
+
+/// Build a colour by combining each component of two colours.
+fn map_components(a: u32, b: u32, f: impl Fn(u32, u32) -> u8) -> u32 {
+    let (a, b) = (a.to_le_bytes(), b.to_le_bytes());
+    u32::from_le_bytes([
+        f(a[0] as u32, b[0] as u32),
+        f(a[1] as u32, b[1] as u32),
+        f(a[2] as u32, b[2] as u32),
+        f(a[3] as u32, b[3] as u32),
+    ])
+}
+
+/// A colour split into its red, green, blue and alpha components.
+///
+/// It converts to and from the u32s used by the presentation arrays, which are
+/// packed as 0xAABBGGRR (so red is in the lowest byte), the same as
+/// `colour()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Rgba { r, g, b, a }
+    }
+
+    pub fn r(self) -> u8 {
+        self.r
+    }
+
+    pub fn g(self) -> u8 {
+        self.g
+    }
+
+    pub fn b(self) -> u8 {
+        self.b
+    }
+
+    pub fn a(self) -> u8 {
+        self.a
+    }
+
+    /// Return the colour with a different alpha.
+    pub fn with_alpha(self, a: u8) -> Self {
+        Rgba { a, ..self }
+    }
+}
+
+impl From<Rgba> for u32 {
+    fn from(c: Rgba) -> Self {
+        u32::from_le_bytes([c.r, c.g, c.b, c.a])
+    }
+}
+
+impl From<u32> for Rgba {
+    fn from(c: u32) -> Self {
+        let [r, g, b, a] = c.to_le_bytes();
+        Rgba { r, g, b, a }
+    }
+}
+
+impl From<Colour> for Rgba {
+    fn from(c: Colour) -> Self {
+        Rgba::from(u32::from(c))
+    }
+}
+
+/// Formats the colour as `#RRGGBBAA`.
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{:02X}{:02X}{:02X}{:02X}",
+            self.r, self.g, self.b, self.a
+        )
+    }
+}
+
+/// The 16 colours of `Colour::ALL` as u32s, in the order of the VGA palette.
+/// This can be used in `const` items, such as to index the colours by a number
+/// stored in a level file.
+pub const PALETTE_CGA: [u32; 16] = [
+    Colour::Black.to_u32(),
+    Colour::DarkBlue.to_u32(),
+    Colour::DarkGreen.to_u32(),
+    Colour::DarkCyan.to_u32(),
+    Colour::DarkRed.to_u32(),
+    Colour::DarkMagenta.to_u32(),
+    Colour::Brown.to_u32(),
+    Colour::LightGrey.to_u32(),
+    Colour::DarkGrey.to_u32(),
+    Colour::Blue.to_u32(),
+    Colour::Green.to_u32(),
+    Colour::Cyan.to_u32(),
+    Colour::Red.to_u32(),
+    Colour::Magenta.to_u32(),
+    Colour::Yellow.to_u32(),
+    Colour::White.to_u32(),
+];
+
+// `to_u32()` must stay usable in constants and agree with `colour()`.
+const _: () = assert!(Colour::Red.to_u32() == colour(255, 0, 0));
+const _: () = assert!(PALETTE_CGA[12] == colour(255, 0, 0));
+
+/// The classic 16 colour palette for convenience.
+///
+/// Use into() or to_u32() to convert to a u32.  The dark colours and greys use
+/// the VGA/CGA values, while the bright colours are fully saturated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Colour {
     Black,
     Red,
@@ -16,11 +465,83 @@ pub enum Colour {
     Magenta,
     Cyan,
     White,
+    DarkGrey,
+    LightGrey,
+    DarkRed,
+    DarkGreen,
+    DarkBlue,
+    DarkCyan,
+    DarkMagenta,
+    Brown,
 }
 
-impl From<Colour> for u32 {
-    fn from(c: Colour) -> Self {
-        match c {
+impl Colour {
+    /// Another name for `Colour::Brown`, which is the dark version of yellow.
+    pub const DARK_YELLOW: Colour = Colour::Brown;
+
+    /// All 16 colours, in the order of the VGA palette.
+    pub const ALL: [Colour; 16] = [
+        Colour::Black,
+        Colour::DarkBlue,
+        Colour::DarkGreen,
+        Colour::DarkCyan,
+        Colour::DarkRed,
+        Colour::DarkMagenta,
+        Colour::Brown,
+        Colour::LightGrey,
+        Colour::DarkGrey,
+        Colour::Blue,
+        Colour::Green,
+        Colour::Cyan,
+        Colour::Red,
+        Colour::Magenta,
+        Colour::Yellow,
+        Colour::White,
+    ];
+
+    /// Iterate over all 16 colours, in the order of the VGA palette.
+    pub fn iter() -> impl Iterator<Item = Colour> {
+        Colour::ALL.iter().copied()
+    }
+
+    /// The name of the colour in lower case, with words separated by
+    /// underscores, such as `dark_grey`.  This is the name understood by
+    /// `parse_colour()`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Colour::Black => "black",
+            Colour::Red => "red",
+            Colour::Green => "green",
+            Colour::Yellow => "yellow",
+            Colour::Blue => "blue",
+            Colour::Magenta => "magenta",
+            Colour::Cyan => "cyan",
+            Colour::White => "white",
+            Colour::DarkGrey => "dark_grey",
+            Colour::LightGrey => "light_grey",
+            Colour::DarkRed => "dark_red",
+            Colour::DarkGreen => "dark_green",
+            Colour::DarkBlue => "dark_blue",
+            Colour::DarkCyan => "dark_cyan",
+            Colour::DarkMagenta => "dark_magenta",
+            Colour::Brown => "brown",
+        }
+    }
+
+    /// Find a colour by its name, ignoring case.  `dark_yellow` is accepted
+    /// for `Colour::Brown`.
+    pub(crate) fn from_name(name: &str) -> Option<Colour> {
+        let name = name.to_ascii_lowercase();
+        if name == "dark_yellow" {
+            return Some(Colour::DARK_YELLOW);
+        }
+        Colour::iter().find(|c| c.name() == name)
+    }
+
+    /// Convert the colour to a u32 in the same format as `colour()`.  This is
+    /// the same as `into()`, but can be used in `const` items.
+    pub const fn to_u32(self) -> u32 {
+        match self {
             Colour::Black => colour(0, 0, 0),
             Colour::Red => colour(255, 0, 0),
             Colour::Green => colour(0, 255, 0),
@@ -29,6 +550,157 @@ impl From<Colour> for u32 {
             Colour::Magenta => colour(255, 0, 255),
             Colour::Cyan => colour(0, 255, 255),
             Colour::White => colour(255, 255, 255),
+            Colour::DarkGrey => colour(85, 85, 85),
+            Colour::LightGrey => colour(170, 170, 170),
+            Colour::DarkRed => colour(170, 0, 0),
+            Colour::DarkGreen => colour(0, 170, 0),
+            Colour::DarkBlue => colour(0, 0, 170),
+            Colour::DarkCyan => colour(0, 170, 170),
+            Colour::DarkMagenta => colour(170, 0, 170),
+            Colour::Brown => colour(170, 85, 0),
+        }
+    }
+
+    /// Return the bright version of a colour.  Bright colours are returned
+    /// unchanged.
+    pub fn bright(self) -> Colour {
+        match self {
+            Colour::Black => Colour::DarkGrey,
+            Colour::DarkGrey | Colour::LightGrey => Colour::White,
+            Colour::DarkRed => Colour::Red,
+            Colour::DarkGreen => Colour::Green,
+            Colour::DarkBlue => Colour::Blue,
+            Colour::DarkCyan => Colour::Cyan,
+            Colour::DarkMagenta => Colour::Magenta,
+            Colour::Brown => Colour::Yellow,
+            bright => bright,
+        }
+    }
+
+    /// Return the dark version of a colour.  Dark colours are returned
+    /// unchanged.
+    pub fn dark(self) -> Colour {
+        match self {
+            Colour::White => Colour::LightGrey,
+            Colour::DarkGrey => Colour::Black,
+            Colour::Red => Colour::DarkRed,
+            Colour::Green => Colour::DarkGreen,
+            Colour::Blue => Colour::DarkBlue,
+            Colour::Cyan => Colour::DarkCyan,
+            Colour::Magenta => Colour::DarkMagenta,
+            Colour::Yellow => Colour::Brown,
+            dark => dark,
+        }
+    }
+}
+
+impl From<Colour> for u32 {
+    fn from(c: Colour) -> Self {
+        c.to_u32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgba_byte_order() {
+        assert_eq!(u32::from(Rgba::new(1, 2, 3, 4)), 0x04030201);
+        assert_eq!(Rgba::from(0x04030201), Rgba::new(1, 2, 3, 4));
+        assert_eq!(u32::from(Rgba::new(1, 2, 3, 255)), colour(1, 2, 3));
+        assert_eq!(u32::from(Rgba::new(1, 2, 3, 4)), colour_rgba(1, 2, 3, 4));
+    }
+
+    #[test]
+    fn const_colours_match_into() {
+        for (i, &c) in Colour::ALL.iter().enumerate() {
+            assert_eq!(c.to_u32(), u32::from(c), "{:?}", c);
+            assert_eq!(PALETTE_CGA[i], u32::from(c), "{:?}", c);
+        }
+    }
+
+    const HUES: [(f32, (u8, u8, u8)); 6] = [
+        (0.0, (255, 0, 0)),
+        (60.0, (255, 255, 0)),
+        (120.0, (0, 255, 0)),
+        (180.0, (0, 255, 255)),
+        (240.0, (0, 0, 255)),
+        (300.0, (255, 0, 255)),
+    ];
+
+    #[test]
+    fn hsv_and_hsl_hues_are_exact() {
+        for &(h, (r, g, b)) in HUES.iter() {
+            assert_eq!(colour_hsv(h, 1.0, 1.0), colour(r, g, b), "{}", h);
+            assert_eq!(colour_hsl(h, 1.0, 0.5), colour(r, g, b), "{}", h);
+        }
+    }
+
+    #[test]
+    fn hsv_and_hsl_hues_wrap() {
+        assert_eq!(colour_hsv(-120.0, 1.0, 1.0), colour_hsv(240.0, 1.0, 1.0));
+        assert_eq!(colour_hsv(360.0, 1.0, 1.0), colour_hsv(0.0, 1.0, 1.0));
+        assert_eq!(colour_hsv(480.0, 0.5, 0.5), colour_hsv(120.0, 0.5, 0.5));
+        assert_eq!(colour_hsl(-120.0, 1.0, 0.5), colour_hsl(240.0, 1.0, 0.5));
+        assert_eq!(colour_hsl(360.0, 1.0, 0.5), colour_hsl(0.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn hsv_and_hsl_clamp() {
+        assert_eq!(colour_hsv(0.0, 2.0, 1.0), colour(255, 0, 0));
+        assert_eq!(colour_hsv(0.0, -1.0, 1.0), colour(255, 255, 255));
+        assert_eq!(colour_hsv(0.0, 1.0, 2.0), colour(255, 0, 0));
+        assert_eq!(colour_hsv(0.0, 1.0, -1.0), colour(0, 0, 0));
+        assert_eq!(colour_hsl(0.0, 2.0, 0.5), colour(255, 0, 0));
+        assert_eq!(colour_hsl(0.0, -1.0, 0.5), colour(128, 128, 128));
+        assert_eq!(colour_hsl(0.0, 1.0, 2.0), colour(255, 255, 255));
+        assert_eq!(colour_hsl(0.0, 1.0, -1.0), colour(0, 0, 0));
+    }
+
+    #[test]
+    fn to_hsv_round_trips() {
+        for &(h, (r, g, b)) in HUES.iter() {
+            assert_eq!(to_hsv(colour(r, g, b)), (h, 1.0, 1.0));
+        }
+        assert_eq!(to_hsv(colour(128, 128, 128)), (0.0, 0.0, 128.0 / 255.0));
+
+        // Every colour survives the trip through HSV and back, ignoring alpha.
+        let levels = [0, 1, 37, 95, 128, 200, 254, 255];
+        for &r in levels.iter() {
+            for &g in levels.iter() {
+                for &b in levels.iter() {
+                    let c = colour_rgba(r, g, b, 100);
+                    let (h, s, v) = to_hsv(c);
+                    assert_eq!(colour_hsv(h, s, v), colour(r, g, b), "{:08x}", c);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ansi256_values() {
+        assert_eq!(ansi256_to_colour(1), Colour::DarkRed.into());
+        assert_eq!(ansi256_to_colour(16), colour(0, 0, 0));
+        assert_eq!(ansi256_to_colour(196), colour(255, 0, 0));
+        assert_eq!(ansi256_to_colour(231), colour(255, 255, 255));
+        assert_eq!(ansi256_to_colour(232), colour(8, 8, 8));
+        assert_eq!(ansi256_to_colour(255), colour(238, 238, 238));
+
+        // The cube's corners are also ANSI colours, which come first.
+        assert_eq!(colour_to_ansi256(colour(0, 0, 0)), 0);
+        assert_eq!(colour_to_ansi256(colour(255, 0, 0)), 9);
+        assert_eq!(colour_to_ansi256(colour(255, 255, 255)), 15);
+        assert_eq!(colour_to_ansi256(colour(8, 8, 8)), 232);
+        assert_eq!(colour_to_ansi256(colour(238, 238, 238)), 255);
+        assert_eq!(colour_to_ansi256(colour_rgba(8, 8, 8, 0)), 232);
+    }
+
+    #[test]
+    fn ansi256_round_trips() {
+        for index in 0..=255 {
+            let c = ansi256_to_colour(index);
+            assert_eq!(ansi256_to_colour(colour_to_ansi256(c)), c, "{}", index);
         }
     }
 }
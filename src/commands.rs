@@ -0,0 +1,100 @@
+use wgpu::PresentMode;
+
+#[cfg(feature = "recording")]
+use crate::Recording;
+use crate::{FontData, PostEffect};
+
+/// Requests that an `App` can make of the window while it is ticking.
+///
+/// A `Commands` is passed to `tick` in the `TickInput`.  The requests are
+/// queued up and carried out in order once `tick` returns, so their effects
+/// are seen by the next call to `tick` and `present`.
+
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+}
+
+/// A single request made through `Commands`.  Each is named after the
+/// `Commands` method that makes it.
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Command {
+    /// Replace the font used to render the window.
+    SetFont(FontData),
+    /// Choose whether the character grid is centred in the window.
+    SetCentreGrid(bool),
+    /// Change how finished frames are shown.
+    SetPresentMode(PresentMode),
+    /// Change the effect drawn over the window.
+    SetPostEffect(PostEffect),
+    /// Start recording the window.
+    #[cfg(feature = "recording")]
+    StartRecording(Recording),
+    /// Stop recording the window.
+    #[cfg(feature = "recording")]
+    StopRecording,
+}
+
+impl Commands {
+    pub(crate) fn new() -> Self {
+        Commands::default()
+    }
+
+    /// Switch the window to a different font.
+    ///
+    /// The character grid is recalculated for the new character size, so the
+    /// width and height given in the next `TickInput` and `PresentInput` may
+    /// change.  A font that fails the same checks as `Builder::validate` is
+    /// ignored with a warning.
+    pub fn set_font(&mut self, font: FontData) {
+        self.queue.push(Command::SetFont(font));
+    }
+
+    /// Choose whether the character grid is centred in the window, sharing
+    /// the border left where the window does not divide evenly into cells
+    /// between the four sides.  See `Builder::with_centre_grid`.
+    pub fn set_centre_grid(&mut self, centre_grid: bool) {
+        self.queue.push(Command::SetCentreGrid(centre_grid));
+    }
+
+    /// Change how finished frames are shown, such as turning vertical sync on
+    /// or off from a settings menu.  The window's contents are kept.  Once
+    /// the mode is changed, `App::on_present_mode_changed` is called with the
+    /// mode actually used, which is `PresentMode::Fifo` if the window does not
+    /// support the one asked for.  See `Builder::with_present_mode`.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) {
+        self.queue.push(Command::SetPresentMode(present_mode));
+    }
+
+    /// Choose the effect drawn over the window, such as turning the CRT
+    /// effect on or off from a settings menu.  It can be given every tick to
+    /// animate the effect's settings.  See `Builder::with_post_effect`.
+    pub fn set_post_effect(&mut self, post_effect: PostEffect) {
+        self.queue.push(Command::SetPostEffect(post_effect));
+    }
+
+    /// Start recording the frames shown in the window, such as when a key is
+    /// pressed.  A recording already in progress is finished first.  When
+    /// the recording finishes, after its duration or `stop_recording`,
+    /// `App::on_recording_finished` is called with how it went.
+    ///
+    /// This function is only available with the `recording` feature.
+    #[cfg(feature = "recording")]
+    pub fn start_recording(&mut self, recording: Recording) {
+        self.queue.push(Command::StartRecording(recording));
+    }
+
+    /// Stop the recording in progress, if any.  The frames waiting to be
+    /// encoded are finished first, which can hold up the window briefly.
+    ///
+    /// This function is only available with the `recording` feature.
+    #[cfg(feature = "recording")]
+    pub fn stop_recording(&mut self) {
+        self.queue.push(Command::StopRecording);
+    }
+
+    /// Take all the queued requests, leaving the queue empty.
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = Command> + '_ {
+        self.queue.drain(..)
+    }
+}
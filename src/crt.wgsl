@@ -0,0 +1,60 @@
+// The CRT post effect, drawn over the whole window from a texture holding the
+// rendered character grid.  The vertex shader is in post.wgsl.
+
+// The character grid as it would have been drawn to the window
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var s_scene: sampler;
+
+struct CrtInfo {
+    width: f32,
+    height: f32,
+    time: f32,
+    padding: f32,
+    scanlines: f32,
+    curvature: f32,
+    vignette: f32,
+    glow: f32,
+}
+
+@group(0) @binding(2)
+var<uniform> crt: CrtInfo;
+
+@fragment
+fn main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    // Coords from -1 to 1 across the window, with 0 at the centre.
+    let size = vec2<f32>(crt.width, crt.height);
+    var uv: vec2<f32> = pos.xy / size * 2.0 - vec2<f32>(1.0, 1.0);
+
+    // Barrel distortion pushes the picture out from the centre, more so
+    // further away, so that it looks as if it is on a curved tube.
+    uv = uv * (1.0 + crt.curvature * dot(uv, uv) * 0.25);
+    let tc = (uv + vec2<f32>(1.0, 1.0)) * 0.5;
+    var colour: vec3<f32> = textureSample(t_scene, s_scene, tc).rgb;
+
+    // The phosphor glow blurs bright pixels into their neighbours, from the
+    // average of four samples a glow radius away.
+    let d = vec2<f32>(crt.glow, crt.glow) / size;
+    let blur = (
+        textureSample(t_scene, s_scene, tc + vec2<f32>(d.x, 0.0)).rgb +
+        textureSample(t_scene, s_scene, tc - vec2<f32>(d.x, 0.0)).rgb +
+        textureSample(t_scene, s_scene, tc + vec2<f32>(0.0, d.y)).rgb +
+        textureSample(t_scene, s_scene, tc - vec2<f32>(0.0, d.y)).rgb) * 0.25;
+    if (crt.glow > 0.0) {
+        colour = max(colour, blur * 0.75);
+    }
+
+    // Scanlines darken every other row of pixels of the picture.
+    let line = f32(u32(max(tc.y * crt.height, 0.0)) % 2u);
+    colour = colour * (1.0 - crt.scanlines * line);
+
+    // The vignette darkens the corners.
+    colour = colour * clamp(1.0 - crt.vignette * dot(uv, uv) * 0.5, 0.0, 1.0);
+
+    // Outside the curved picture is the black of the tube's edge.
+    if (abs(uv.x) > 1.0 || abs(uv.y) > 1.0) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return vec4<f32>(colour, 1.0);
+}
@@ -0,0 +1,196 @@
+//
+// GPU diagnostics
+// A report of the graphics adapters on the machine and the one chosen for
+// rendering, for including in bug reports
+//
+
+use std::fmt;
+
+use futures::executor::block_on;
+use wgpu::{
+    AdapterInfo, Backends, DeviceType, Instance, InstanceDescriptor, PowerPreference, PresentMode,
+    RequestAdapterOptions, TextureFormat,
+};
+
+use crate::Backend;
+
+/// A graphics adapter found on the machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterReport {
+    /// The adapter's name, as given by its driver.
+    pub name: String,
+    /// The graphics API the adapter was found through.
+    pub backend: wgpu::Backend,
+    /// Whether the adapter is a discrete or integrated GPU, or runs on the
+    /// CPU.
+    pub device_type: DeviceType,
+}
+
+impl From<&AdapterInfo> for AdapterReport {
+    fn from(info: &AdapterInfo) -> Self {
+        AdapterReport {
+            name: info.name.clone(),
+            backend: info.backend,
+            device_type: info.device_type,
+        }
+    }
+}
+
+impl fmt::Display for AdapterReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let device_type = match self.device_type {
+            DeviceType::DiscreteGpu => "discrete GPU",
+            DeviceType::IntegratedGpu => "integrated GPU",
+            DeviceType::VirtualGpu => "virtual GPU",
+            DeviceType::Cpu => "CPU",
+            DeviceType::Other => "other",
+        };
+        write!(f, "{} ({:?}, {})", self.name, self.backend, device_type)
+    }
+}
+
+/// The limits a graphics device was created with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceLimits {
+    /// WGPU's default limits, which most GPUs offer.
+    Default,
+    /// The lower limits that older GPUs and the GL back-end offer, used when
+    /// the default limits cannot be had.
+    Downlevel,
+}
+
+impl fmt::Display for DeviceLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DeviceLimits::Default => "default",
+            DeviceLimits::Downlevel => "downlevel",
+        })
+    }
+}
+
+/// A report of the graphics adapters on the machine, the one chosen for
+/// rendering, and how it draws to the window.  It is returned by
+/// `diagnostics` and `RenderState::gpu_report`, and its `Display` output is
+/// meant for pasting into bug reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuReport {
+    /// Every adapter found, on every back-end.  An adapter can be chosen by
+    /// its index in this list with `Builder::with_adapter_index`.
+    pub adapters: Vec<AdapterReport>,
+    /// The index in `adapters` of the adapter chosen for rendering, if one
+    /// was chosen.
+    pub chosen: Option<usize>,
+    /// The limits the renderer's device was created with.  They are only
+    /// known once there is a renderer.
+    pub device_limits: Option<DeviceLimits>,
+    /// The texture format of the window's surface.  It is only known once
+    /// there is a window.
+    pub surface_format: Option<TextureFormat>,
+    /// The present modes supported by the window's surface.  They are only
+    /// known once there is a window.
+    pub present_modes: Vec<PresentMode>,
+}
+
+impl GpuReport {
+    /// Build a report listing every adapter, with the one described by
+    /// `chosen` marked as chosen.
+    pub(crate) fn new(
+        chosen: Option<&AdapterInfo>,
+        device_limits: Option<DeviceLimits>,
+        surface_format: Option<TextureFormat>,
+        present_modes: Vec<PresentMode>,
+    ) -> Self {
+        let adapters = enumerate_adapters();
+        let chosen = chosen.and_then(|info| {
+            let chosen = AdapterReport::from(info);
+            adapters.iter().position(|adapter| *adapter == chosen)
+        });
+        GpuReport {
+            adapters,
+            chosen,
+            device_limits,
+            surface_format,
+            present_modes,
+        }
+    }
+}
+
+impl fmt::Display for GpuReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Graphics adapters:")?;
+        if self.adapters.is_empty() {
+            writeln!(f, "  none found")?;
+        }
+        for (i, adapter) in self.adapters.iter().enumerate() {
+            let chosen = if self.chosen == Some(i) {
+                " (chosen)"
+            } else {
+                ""
+            };
+            writeln!(f, "  {}: {}{}", i, adapter, chosen)?;
+        }
+        if let Some(device_limits) = self.device_limits {
+            writeln!(f, "Device limits: {}", device_limits)?;
+        }
+        if let Some(format) = self.surface_format {
+            writeln!(f, "Surface format: {:?}", format)?;
+        }
+        if !self.present_modes.is_empty() {
+            writeln!(f, "Present modes: {:?}", self.present_modes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Report the graphics adapters on the machine, and the one that mterm would
+/// choose with the default settings or the back-end in `MTERM_BACKEND`.  No
+/// device or window is created, so the device limits, surface format and
+/// present modes are not known.  While the app is running,
+/// `RenderState::gpu_report` has them.
+///
+/// Apps can print this when run with a flag such as `--gpu-info`, so that
+/// users can include it in bug reports.
+pub fn diagnostics() -> GpuReport {
+    let backend = Backend::from_env().unwrap_or(Backend::Auto);
+    let instance = Instance::new(InstanceDescriptor {
+        backends: backend.backends(),
+        ..Default::default()
+    });
+    let adapter = block_on(instance.request_adapter(&RequestAdapterOptions {
+        power_preference: PowerPreference::default(),
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }));
+    GpuReport::new(
+        adapter.map(|adapter| adapter.get_info()).as_ref(),
+        None,
+        None,
+        Vec::new(),
+    )
+}
+
+/// List every adapter on every back-end, in the order that
+/// `Builder::with_adapter_index` counts them.
+pub(crate) fn enumerate_adapters() -> Vec<AdapterReport> {
+    let instance = Instance::new(InstanceDescriptor {
+        backends: Backends::all(),
+        ..Default::default()
+    });
+    instance
+        .enumerate_adapters(Backends::all())
+        .map(|adapter| AdapterReport::from(&adapter.get_info()))
+        .collect()
+}
+
+/// Describe the adapters found, for an error message.
+pub(crate) fn describe_adapters(adapters: &[AdapterReport]) -> String {
+    if adapters.is_empty() {
+        return "No graphics adapters were found on any back-end".to_string();
+    }
+    let names: Vec<String> = adapters
+        .iter()
+        .enumerate()
+        .map(|(i, adapter)| format!("{}: {}", i, adapter))
+        .collect();
+    format!("The adapters found were {}", names.join(", "))
+}
@@ -0,0 +1,86 @@
+//
+// Diffs
+// The cells that changed between two frames, for sending only the changes
+// somewhere else
+//
+
+use crate::{Char, Image, Point, Rect};
+
+/// A cell that changed between two images, with its new character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellChange {
+    pub p: Point,
+    pub ch: Char,
+}
+
+/// Find the cells that differ between two images, in row-major order, with
+/// their characters in `next`.  If the images are different sizes, every cell
+/// of `next` is returned.
+pub fn diff(prev: &Image, next: &Image) -> Vec<CellChange> {
+    let mut changes = Vec::new();
+    for_each_change(prev, next, |i| {
+        changes.push(CellChange {
+            p: Point::new((i % next.width) as i32, (i / next.width) as i32),
+            ch: Char {
+                ch: next.text_image[i],
+                ink: next.fore_image[i],
+                paper: next.back_image[i],
+            },
+        })
+    });
+    changes
+}
+
+/// Find the smallest rectangle holding every cell that differs between two
+/// images, or None if they are the same.  If the images are different sizes,
+/// the whole of `next` is returned.
+pub fn diff_rect(prev: &Image, next: &Image) -> Option<Rect> {
+    // The left, top, right and bottom cells changed.
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for_each_change(prev, next, |i| {
+        let (x, y) = (i % next.width, i / next.width);
+        bounds = Some(match bounds {
+            Some((left, top, right, _)) => (left.min(x), top, right.max(x), y),
+            None => (x, y, x, y),
+        });
+    });
+    bounds.map(|(left, top, right, bottom)| {
+        Rect::new(left as i32, top as i32, right - left + 1, bottom - top + 1)
+    })
+}
+
+/// Call a function with the index of every cell that differs between two
+/// images, in order.
+fn for_each_change(prev: &Image, next: &Image, mut f: impl FnMut(usize)) {
+    if (prev.width, prev.height) != (next.width, next.height) {
+        (0..next.text_image.len()).for_each(f);
+        return;
+    }
+
+    // Most rows of most frames are unchanged, and comparing whole rows is
+    // much quicker than comparing their cells one at a time.
+    let width = next.width.max(1);
+    for (y, (prev_row, next_row)) in rows(prev, width).zip(rows(next, width)).enumerate() {
+        if prev_row == next_row {
+            continue;
+        }
+        let ((prev_fore, prev_back), prev_text) = prev_row;
+        let ((next_fore, next_back), next_text) = next_row;
+        for x in 0..next_text.len() {
+            if prev_fore[x] != next_fore[x]
+                || prev_back[x] != next_back[x]
+                || prev_text[x] != next_text[x]
+            {
+                f(y * width + x);
+            }
+        }
+    }
+}
+
+/// The ink, paper and glyph slices of each row of an image.
+fn rows(image: &Image, width: usize) -> impl Iterator<Item = ((&[u32], &[u32]), &[u32])> {
+    let (fore, back, text) = (&image.fore_image, &image.back_image, &image.text_image);
+    fore.chunks(width)
+        .zip(back.chunks(width))
+        .zip(text.chunks(width))
+}
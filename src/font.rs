@@ -0,0 +1,709 @@
+//
+// Font loading and construction
+//
+
+use std::path::Path;
+
+use bytemuck::cast_slice;
+use image::{io::Reader, DynamicImage, EncodableLayout, GenericImageView, ImageError, ImageFormat};
+use thiserror::Error;
+use wgpu::Limits;
+
+use crate::{colour, Char, Image, Point, Result};
+
+/// The reasons a font could not be loaded or used.
+#[derive(Error, Debug)]
+pub enum FontError {
+    /// The font image could not be read or decoded.
+    #[error("the font image could not be decoded: {0}")]
+    DecodeFailed(#[from] ImageError),
+
+    /// The font image does not divide into a whole number of characters.
+    #[error(
+        "the font image's size of {width}x{height} does not divide into {columns}x{rows} characters"
+    )]
+    DimensionsNotDivisible {
+        width: u32,
+        height: u32,
+        columns: u32,
+        rows: u32,
+    },
+
+    /// The font's characters have no pixels.
+    #[error("the font's characters must not be zero sized, but {width}x{height} was given")]
+    CellTooSmall { width: u32, height: u32 },
+
+    /// The font's grid of characters is empty.
+    #[error("the font's character grid must not be empty, but {columns}x{rows} was given")]
+    EmptyGrid { columns: u32, rows: u32 },
+
+    /// The font's texture would be larger than graphics devices allow.
+    #[error("the font texture would be {width}x{height} pixels, but the limit is {max}x{max}")]
+    TooLarge { width: u32, height: u32, max: u32 },
+
+    /// The font has more characters than its grid can hold.
+    #[error("the font has {glyph_count} characters, but its grid only holds {capacity}")]
+    TooManyGlyphs { glyph_count: u32, capacity: u32 },
+
+    /// The font's pixel data is the wrong length for its size.
+    #[error("the font data contains {len} pixels, but its size needs {expected}")]
+    WrongDataLength { len: usize, expected: usize },
+
+    /// The font file is malformed, for the given reason.
+    #[error("{0}")]
+    Malformed(String),
+}
+
+/// Contains the font pixel data for custom fonts.
+#[derive(Clone)]
+pub struct FontData {
+    pub data: Vec<u32>,
+    pub width: u32,
+    pub height: u32,
+    /// The number of characters across the font image.
+    pub columns: u32,
+    /// The number of characters down the font image.
+    pub rows: u32,
+    /// The number of characters in the font, which can be less than
+    /// `columns * rows` if the last row is not full.  Character codes from
+    /// this number up are drawn as blank.
+    pub glyph_count: u32,
+}
+
+//
+// FontData implementation
+//
+
+impl FontData {
+    /// Create a font of 256 characters by calling a function for every pixel.
+    ///
+    /// The function is given the character code and the coordinates of the
+    /// pixel within the character, and returns the pixel's colour in the same
+    /// format as `colour()`.  The red component, scaled by the alpha, is the
+    /// amount of ink in the pixel, so white pixels are drawn in the ink colour
+    /// and black ones in the paper colour.  Grey levels in between blend the
+    /// two unless `Builder::with_font_smoothing` turns blending off.
+    ///
+    /// The characters are laid out in a 16x16 grid, exactly as if they were
+    /// loaded with `load_font_image`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the font's pixels do not fit in memory.  Fonts that are too
+    /// large for a texture are still built, but are rejected when the window
+    /// is created.
+    pub fn from_fn<F>(width: u32, height: u32, mut f: F) -> Self
+    where
+        F: FnMut(u32, u32, u32) -> u32,
+    {
+        let columns = 16;
+        let rows = 16;
+        let stride = columns as usize * width as usize;
+        let len = stride
+            .checked_mul(rows as usize * height as usize)
+            .expect("the font is too large to fit in memory");
+        let mut data = vec![0; len];
+
+        for glyph in 0..columns * rows {
+            let left = (glyph % columns) as usize * width as usize;
+            let top = (glyph / columns) as usize * height as usize;
+            for y in 0..height {
+                for x in 0..width {
+                    data[(top + y as usize) * stride + left + x as usize] = f(glyph, x, y);
+                }
+            }
+        }
+
+        FontData {
+            data,
+            width,
+            height,
+            columns,
+            rows,
+            glyph_count: columns * rows,
+        }
+    }
+
+    /// Check that the font has a usable size, fits in a texture and has enough
+    /// pixel data for its characters.
+    pub(crate) fn check(&self) -> std::result::Result<(), FontError> {
+        let texture_size = Self::check_size(self.width, self.height, self.columns, self.rows)?;
+        if self.glyph_count > self.columns * self.rows {
+            return Err(FontError::TooManyGlyphs {
+                glyph_count: self.glyph_count,
+                capacity: self.columns * self.rows,
+            });
+        }
+        let expected = (texture_size.0 * texture_size.1) as usize;
+        if self.data.len() != expected {
+            return Err(FontError::WrongDataLength {
+                len: self.data.len(),
+                expected,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that a font with characters of the given size, laid out in the
+    /// given grid, is not empty and fits in a texture.  Returns the size of the
+    /// texture in pixels.
+    pub(crate) fn check_size(
+        width: u32,
+        height: u32,
+        columns: u32,
+        rows: u32,
+    ) -> std::result::Result<(u64, u64), FontError> {
+        if width == 0 || height == 0 {
+            return Err(FontError::CellTooSmall { width, height });
+        }
+        if columns == 0 || rows == 0 {
+            return Err(FontError::EmptyGrid { columns, rows });
+        }
+        let max = Limits::default().max_texture_dimension_2d;
+        let texture_size = (columns as u64 * width as u64, rows as u64 * height as u64);
+        if texture_size.0 > max as u64 || texture_size.1 > max as u64 {
+            return Err(FontError::TooLarge {
+                width: texture_size.0.min(u32::MAX as u64) as u32,
+                height: texture_size.1.min(u32::MAX as u64) as u32,
+                max,
+            });
+        }
+        Ok(texture_size)
+    }
+
+    /// Create a font from 256 character bitmaps.
+    ///
+    /// Each bitmap contains `width * height` bytes, one per pixel, going from
+    /// left to right and top to bottom.  A byte is treated as a grey level, so
+    /// 0 is paper and 255 is ink.  An error is returned if there are not
+    /// exactly 256 bitmaps, any of them are the wrong size or the font would
+    /// not fit in a texture.
+    pub fn from_glyph_bitmaps<B>(width: u32, height: u32, glyphs: &[B]) -> Result<Self>
+    where
+        B: AsRef<[u8]>,
+    {
+        Self::check_size(width, height, 16, 16)?;
+        let glyph_size = width as usize * height as usize;
+        if glyphs.len() != 256 {
+            return Err(FontError::Malformed(format!(
+                "256 character bitmaps are needed, but {} were given",
+                glyphs.len()
+            ))
+            .into());
+        }
+        if let Some(glyph) = glyphs.iter().position(|g| g.as_ref().len() != glyph_size) {
+            return Err(FontError::Malformed(format!(
+                "character {} has {} pixels, but {} are needed",
+                glyph,
+                glyphs[glyph].as_ref().len(),
+                glyph_size
+            ))
+            .into());
+        }
+
+        Ok(Self::from_fn(width, height, |glyph, x, y| {
+            let level =
+                glyphs[glyph as usize].as_ref()[y as usize * width as usize + x as usize] as u32;
+            0xff000000 | (level << 16) | (level << 8) | level
+        }))
+    }
+
+    /// Make every pixel of the given colour transparent.
+    ///
+    /// Many fonts mark the paper with a key colour instead of using an alpha
+    /// channel, such as magenta (255, 0, 255) or, for REXPaint fonts, black.
+    /// Pixels whose red, green and blue components match the key, which is in
+    /// the same format as `colour()`, are replaced with transparent black so
+    /// that the paper colour shows through.  The key's alpha is ignored.
+    pub fn apply_colour_key(&mut self, key: u32) {
+        let key = key & 0x00ffffff;
+        self.data
+            .iter_mut()
+            .filter(|pixel| **pixel & 0x00ffffff == key)
+            .for_each(|pixel| *pixel = 0);
+    }
+
+    /// Look at the pixels of a single character, or `None` if the font does not
+    /// have that many characters.
+    pub fn glyph(&self, index: u16) -> Option<GlyphView<'_>> {
+        if (index as u32) < self.glyph_count && self.columns > 0 {
+            Some(GlyphView { font: self, index })
+        } else {
+            None
+        }
+    }
+
+    /// Check whether a character has no ink in it, such as a space.  Characters
+    /// beyond the end of the font are blank.
+    pub fn is_blank(&self, index: u16) -> bool {
+        self.glyph(index).map_or(true, |glyph| {
+            glyph.pixels().all(|pixel| coverage(pixel) == 0)
+        })
+    }
+
+    /// Draw the whole font into an `Image`, for previewing it in a font picker.
+    ///
+    /// Each cell of the image shows two pixels of the font, one above the
+    /// other, using the upper half block character (223 in code page 437).  Its
+    /// ink is the top pixel and its paper the bottom one, in grey levels
+    /// matching the amount of ink in each pixel.  The image is
+    /// `columns * width` cells wide and half of `rows * height` cells high,
+    /// rounded up.
+    pub fn to_image(&self) -> Image {
+        let width = (self.columns * self.width) as usize;
+        let height = (self.rows * self.height) as usize;
+        let mut image = Image::new(width, height.div_ceil(2));
+        let grey = |x: usize, y: usize| {
+            let level = if y < height {
+                coverage(self.data[y * width + x]) as u8
+            } else {
+                0
+            };
+            colour(level, level, level)
+        };
+
+        for y in 0..image.height {
+            for x in 0..width {
+                image.draw_char(
+                    Point::new(x as i32, y as i32),
+                    Char::new(223, grey(x, y * 2), grey(x, y * 2 + 1)),
+                );
+            }
+        }
+
+        image
+    }
+}
+
+/// A view of the pixels of a single character in a font, returned by
+/// `FontData::glyph`.
+#[derive(Clone, Copy)]
+pub struct GlyphView<'a> {
+    font: &'a FontData,
+    index: u16,
+}
+
+impl<'a> GlyphView<'a> {
+    /// The character's index in the font.
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    /// The width of the character in pixels.
+    pub fn width(&self) -> u32 {
+        self.font.width
+    }
+
+    /// The height of the character in pixels.
+    pub fn height(&self) -> u32 {
+        self.font.height
+    }
+
+    /// The colour of a pixel in the character, in the same format as
+    /// `colour()`, or `None` if the coordinates are outside the character.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<u32> {
+        if x < self.font.width && y < self.font.height {
+            let column = self.index as u32 % self.font.columns;
+            let row = self.index as u32 / self.font.columns;
+            let stride = self.font.columns * self.font.width;
+            let px = column * self.font.width + x;
+            let py = row * self.font.height + y;
+            Some(self.font.data[(py * stride + px) as usize])
+        } else {
+            None
+        }
+    }
+
+    /// The colours of all the pixels in the character, from left to right and
+    /// top to bottom.
+    pub fn pixels(&self) -> impl Iterator<Item = u32> + 'a {
+        let view = *self;
+        (0..self.font.height)
+            .flat_map(move |y| (0..view.font.width).map(move |x| (x, y)))
+            .filter_map(move |(x, y)| view.pixel(x, y))
+    }
+}
+
+/// The amount of ink in a font pixel from 0 to 255, which is its red component
+/// scaled by its alpha, as used by the shader.
+fn coverage(pixel: u32) -> u32 {
+    (pixel & 0xff) * (pixel >> 24) / 255
+}
+
+/// The fonts built into mterm.
+///
+/// Apart from `Default`, each font is only available with its cargo feature so
+/// that programs only embed the fonts they use.  All of them contain the 256
+/// characters of code page 437.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuiltinFont {
+    /// The 10x16 font that is used when no other font is chosen.
+    #[default]
+    Default,
+    /// A small 8x8 font.  Requires the `font-8x8` feature.
+    #[cfg(feature = "font-8x8")]
+    Small8,
+    /// A tall 8x16 font.  Requires the `font-8x16` feature.
+    #[cfg(feature = "font-8x16")]
+    Tall16,
+    /// A square 16x16 font, suitable for maps.  Requires the `font-16x16`
+    /// feature.
+    #[cfg(feature = "font-16x16")]
+    Square16,
+}
+
+//
+// BuiltinFont implementation
+//
+
+impl BuiltinFont {
+    /// The size of each character in pixels.
+    pub fn cell_size(self) -> (u32, u32) {
+        match self {
+            BuiltinFont::Default => (10, 16),
+            #[cfg(feature = "font-8x8")]
+            BuiltinFont::Small8 => (8, 8),
+            #[cfg(feature = "font-8x16")]
+            BuiltinFont::Tall16 => (8, 16),
+            #[cfg(feature = "font-16x16")]
+            BuiltinFont::Square16 => (16, 16),
+        }
+    }
+
+    /// Load the font's pixel data.
+    pub fn font_data(self) -> Result<FontData> {
+        load_font_image(self.image(), ImageFormat::Png)
+    }
+
+    /// The PNG image embedded for the font.
+    fn image(self) -> &'static [u8] {
+        match self {
+            BuiltinFont::Default => include_bytes!("font1.png"),
+            #[cfg(feature = "font-8x8")]
+            BuiltinFont::Small8 => include_bytes!("font_8x8.png"),
+            #[cfg(feature = "font-8x16")]
+            BuiltinFont::Tall16 => include_bytes!("font_8x16.png"),
+            #[cfg(feature = "font-16x16")]
+            BuiltinFont::Square16 => include_bytes!("font_16x16.png"),
+        }
+    }
+}
+
+/// Load a font from a given image in a byte array and generate a FontData
+/// structure.
+///
+/// # Arguments
+///
+/// * __data__ - byte array that contains the image data.  You can use the
+///   `include_bytes!` macro to generate this from a file at compile time.
+/// * __format__ - The image::ImageFormat enum that declares the file format the
+///   image data is in.
+///
+/// # Notes
+///
+/// This function will assume that the image contains 256 characters in a 16x16
+/// grid of equally sized cells.  Use `load_font_image_with_grid` for other
+/// layouts.
+pub fn load_font_image(data: &[u8], format: ImageFormat) -> Result<FontData> {
+    load_font_image_with_grid(data, format, 16, 16)
+}
+
+/// Load a font from a given image in a byte array, treating pixels of a key
+/// colour as transparent, and generate a FontData structure.
+///
+/// # Arguments
+///
+/// * __data__ - byte array that contains the image data.
+/// * __format__ - The image::ImageFormat enum that declares the file format the
+///   image data is in.
+/// * __key__ - The colour, in the same format as `colour()`, that marks the
+///   paper in the image.  Use `colour(255, 0, 255)` for the magenta used by
+///   many tilesets or `colour(0, 0, 0)` for REXPaint fonts.
+///
+/// # Notes
+///
+/// This function will assume that the image contains 256 characters in a 16x16
+/// grid of equally sized cells.  See `FontData::apply_colour_key` to key fonts
+/// loaded in other ways.
+pub fn load_font_image_keyed(data: &[u8], format: ImageFormat, key: u32) -> Result<FontData> {
+    let mut font = load_font_image(data, format)?;
+    font.apply_colour_key(key);
+    Ok(font)
+}
+
+/// Load a font from a given image in a byte array whose characters are laid
+/// out on a grid of the given size, and generate a FontData structure.
+///
+/// # Arguments
+///
+/// * __data__ - byte array that contains the image data.
+/// * __format__ - The image::ImageFormat enum that declares the file format the
+///   image data is in.
+/// * __columns__ - The number of characters across the image.
+/// * __rows__ - The number of characters down the image.
+///
+/// # Notes
+///
+/// The image's width and height must divide exactly by the number of columns
+/// and rows.  Characters are numbered from left to right, then top to bottom.
+/// Grids with more than 256 characters, such as 16x32, are reached with the
+/// page bits of the `text_image` cells (see `Char::new_glyph`).  Any character
+/// codes beyond the end of the grid are drawn as blank.
+pub fn load_font_image_with_grid(
+    data: &[u8],
+    format: ImageFormat,
+    columns: u32,
+    rows: u32,
+) -> Result<FontData> {
+    let font_image = image::load_from_memory_with_format(data, format)?;
+    font_from_image(&font_image, columns, rows)
+}
+
+/// Load a font from a given image in a byte array, guessing the image format
+/// from its contents, and generate a FontData structure.
+///
+/// # Notes
+///
+/// This function will assume that the image contains 256 characters in a 16x16
+/// grid of equally sized cells.  If the image cannot be decoded, the error from
+/// the `image` crate is returned in `FontError::DecodeFailed`.
+pub fn load_font_image_auto(data: &[u8]) -> Result<FontData> {
+    let font_image = image::load_from_memory(data)?;
+    font_from_image(&font_image, 16, 16)
+}
+
+/// Load a font from an image file and generate a FontData structure.
+///
+/// The image format is guessed from the file's extension, or from its contents
+/// if the extension is missing or wrong.
+///
+/// # Notes
+///
+/// This function will assume that the image contains 256 characters in a 16x16
+/// grid of equally sized cells.  If the file cannot be read or decoded, the
+/// error from the `image` crate is returned in `FontError::DecodeFailed`.
+pub fn load_font_from_path(path: impl AsRef<Path>) -> Result<FontData> {
+    let font_image = Reader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(ImageError::from)?
+        .decode()?;
+    font_from_image(&font_image, 16, 16)
+}
+
+/// Convert a decoded font image, whose characters are laid out on a grid of
+/// the given size, into a FontData structure.
+fn font_from_image(font_image: &DynamicImage, columns: u32, rows: u32) -> Result<FontData> {
+    let (width, height) = font_image.dimensions();
+    if columns == 0 || rows == 0 {
+        return Err(FontError::EmptyGrid { columns, rows }.into());
+    }
+    if width % columns != 0 || height % rows != 0 {
+        return Err(FontError::DimensionsNotDivisible {
+            width,
+            height,
+            columns,
+            rows,
+        }
+        .into());
+    }
+    let font_rgba = font_image.to_rgba8();
+    let font_data = font_rgba.as_bytes();
+    let data_u32: &[u32] = cast_slice(font_data);
+
+    let font = FontData {
+        width: width / columns,
+        height: height / rows,
+        columns,
+        rows,
+        glyph_count: columns * rows,
+        data: Vec::from(data_u32),
+    };
+    font.check()?;
+    Ok(font)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, Error};
+
+    const WIDTH: u32 = 3;
+    const HEIGHT: u32 = 2;
+
+    /// A checkerboard whose phase alternates between characters, so that
+    /// neighbouring characters in the atlas can be told apart.
+    fn checker(glyph: u32, x: u32, y: u32) -> u32 {
+        if (glyph + x + y) % 2 == 0 {
+            0xffffffff
+        } else {
+            0xff000000
+        }
+    }
+
+    fn checker_bitmaps() -> Vec<Vec<u8>> {
+        (0..256)
+            .map(|glyph| {
+                (0..WIDTH * HEIGHT)
+                    .map(|i| (checker(glyph, i % WIDTH, i / WIDTH) & 0xff) as u8)
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn assert_checker_layout(font: &FontData) {
+        assert_eq!((font.width, font.height), (WIDTH, HEIGHT));
+        assert_eq!((font.columns, font.rows, font.glyph_count), (16, 16, 256));
+        assert_eq!(font.data.len(), (16 * WIDTH * 16 * HEIGHT) as usize);
+        font.check().unwrap();
+
+        // Character g sits at column g % 16 and row g / 16 of the atlas.
+        let stride = 16 * WIDTH;
+        for (i, &pixel) in font.data.iter().enumerate() {
+            let (ax, ay) = (i as u32 % stride, i as u32 / stride);
+            let glyph = (ay / HEIGHT) * 16 + ax / WIDTH;
+            assert_eq!(
+                pixel,
+                checker(glyph, ax % WIDTH, ay % HEIGHT),
+                "({ax}, {ay})"
+            );
+        }
+
+        for glyph in 0..256 {
+            let view = font.glyph(glyph as u16).unwrap();
+            assert_eq!((view.width(), view.height()), (WIDTH, HEIGHT));
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
+                    assert_eq!(view.pixel(x, y), Some(checker(glyph, x, y)));
+                }
+            }
+            assert_eq!(view.pixel(WIDTH, 0), None);
+            assert_eq!(view.pixel(0, HEIGHT), None);
+        }
+    }
+
+    fn malformed(result: Result<FontData>) -> String {
+        match result {
+            Err(Error::BadFont(FontError::Malformed(message))) => message,
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("the font was accepted"),
+        }
+    }
+
+    #[test]
+    fn from_fn_lays_out_a_16x16_grid() {
+        assert_checker_layout(&FontData::from_fn(WIDTH, HEIGHT, checker));
+    }
+
+    #[test]
+    fn from_glyph_bitmaps_lays_out_a_16x16_grid() {
+        let font = FontData::from_glyph_bitmaps(WIDTH, HEIGHT, &checker_bitmaps()).unwrap();
+        assert_checker_layout(&font);
+    }
+
+    #[test]
+    fn from_glyph_bitmaps_needs_256_characters() {
+        let mut glyphs = checker_bitmaps();
+        glyphs.push(vec![0; (WIDTH * HEIGHT) as usize]);
+        assert_eq!(
+            malformed(FontData::from_glyph_bitmaps(WIDTH, HEIGHT, &glyphs)),
+            "256 character bitmaps are needed, but 257 were given"
+        );
+        glyphs.truncate(255);
+        assert_eq!(
+            malformed(FontData::from_glyph_bitmaps(WIDTH, HEIGHT, &glyphs)),
+            "256 character bitmaps are needed, but 255 were given"
+        );
+    }
+
+    #[test]
+    fn from_glyph_bitmaps_needs_whole_characters() {
+        let mut glyphs = checker_bitmaps();
+        glyphs[65].pop();
+        assert_eq!(
+            malformed(FontData::from_glyph_bitmaps(WIDTH, HEIGHT, &glyphs)),
+            "character 65 has 5 pixels, but 6 are needed"
+        );
+    }
+
+    #[test]
+    fn from_glyph_bitmaps_rejects_huge_characters() {
+        let glyphs = vec![Vec::<u8>::new(); 256];
+        let result = FontData::from_glyph_bitmaps(u32::MAX, u32::MAX, &glyphs);
+        assert!(matches!(
+            result,
+            Err(Error::BadFont(FontError::TooLarge { .. }))
+        ));
+        let result = FontData::from_glyph_bitmaps(0, HEIGHT, &glyphs);
+        assert!(matches!(
+            result,
+            Err(Error::BadFont(FontError::CellTooSmall { .. }))
+        ));
+    }
+
+    #[test]
+    fn builtin_font_blank_characters() {
+        let font = BuiltinFont::Default.font_data().unwrap();
+        assert!(font.is_blank(b' ' as u16));
+        assert!(!font.is_blank(b'A' as u16));
+        assert!(font.is_blank(256));
+    }
+
+    #[test]
+    fn builtin_font_has_256_characters() {
+        let font = BuiltinFont::Default.font_data().unwrap();
+        assert_eq!(font.glyph_count, 256);
+        assert_eq!(font.glyph(255).map(|glyph| glyph.index()), Some(255));
+        assert!(font.glyph(256).is_none());
+    }
+
+    #[test]
+    fn builtin_font_to_image() {
+        let font = BuiltinFont::Default.font_data().unwrap();
+        let (width, height) = BuiltinFont::Default.cell_size();
+        let image = font.to_image();
+        assert_eq!(image.width, (16 * width) as usize);
+        assert_eq!(image.height, (16 * height).div_ceil(2) as usize);
+
+        let stride = 16 * width;
+        let grey = |x: u32, y: u32| {
+            let level = coverage(font.data[(y * stride + x) as usize]) as u8;
+            colour(level, level, level)
+        };
+        for y in 0..image.height as u32 {
+            for x in 0..image.width as u32 {
+                let cell = image.get_char(Point::new(x as i32, y as i32)).unwrap();
+                assert_eq!(
+                    cell,
+                    Char::new(223, grey(x, y * 2), grey(x, y * 2 + 1)),
+                    "({}, {})",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn to_image_rounds_odd_heights_up() {
+        let font = FontData {
+            data: vec![0xffffffff, 0xff000000, 0xffffffff],
+            width: 1,
+            height: 3,
+            columns: 1,
+            rows: 1,
+            glyph_count: 1,
+        };
+        let image = font.to_image();
+        assert_eq!((image.width, image.height), (1, 2));
+        let white = colour(255, 255, 255);
+        let black = colour(0, 0, 0);
+        assert_eq!(
+            image.get_char(Point::new(0, 0)),
+            Some(Char::new(223, white, black))
+        );
+        assert_eq!(
+            image.get_char(Point::new(0, 1)),
+            Some(Char::new(223, white, black))
+        );
+    }
+}
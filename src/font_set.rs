@@ -0,0 +1,92 @@
+//
+// Choosing fonts by the window's scale factor
+//
+
+use std::time::{Duration, Instant};
+
+use crate::FontData;
+
+/// How long the scale factor must stay the same before the font is switched.
+/// This stops a window that straddles two monitors from flipping between fonts
+/// as the scale factor changes back and forth.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// A set of fonts for different scale factors, tracking which one is in use.
+pub(crate) struct FontSet {
+    /// The fonts, with the smallest scale factor each is used for.
+    fonts: Vec<(f64, FontData)>,
+    /// The index of the font in use.
+    current: usize,
+    /// The font to switch to, and when the scale factor changed to choose it.
+    pending: Option<(usize, Instant)>,
+}
+
+impl FontSet {
+    /// Create a font set, choosing the font for the given scale factor.
+    ///
+    /// The set must contain at least one font.
+    pub(crate) fn new(fonts: Vec<(f64, FontData)>, scale_factor: f64) -> Self {
+        let current = best_font(&fonts, scale_factor).unwrap_or(0);
+
+        FontSet {
+            fonts,
+            current,
+            pending: None,
+        }
+    }
+
+    /// The font in use.
+    pub(crate) fn current(&self) -> &FontData {
+        &self.fonts[self.current].1
+    }
+
+    /// Switch straight away to the font for the given scale factor, returning
+    /// it if it is different from the font in use.
+    pub(crate) fn select(&mut self, scale_factor: f64) -> Option<&FontData> {
+        let best = best_font(&self.fonts, scale_factor).unwrap_or(self.current);
+        self.pending = None;
+        if best != self.current {
+            self.current = best;
+            Some(self.current())
+        } else {
+            None
+        }
+    }
+
+    /// Note that the window's scale factor has changed.  The font is switched
+    /// by `poll` once the scale factor has settled.
+    pub(crate) fn scale_factor_changed(&mut self, scale_factor: f64) {
+        let best = best_font(&self.fonts, scale_factor).unwrap_or(self.current);
+        self.pending = if best != self.current {
+            Some((best, Instant::now()))
+        } else {
+            None
+        };
+    }
+
+    /// Return the font to switch to if the scale factor changed and has since
+    /// settled.
+    pub(crate) fn poll(&mut self) -> Option<&FontData> {
+        match self.pending {
+            Some((best, changed_at)) if changed_at.elapsed() >= SETTLE_TIME => {
+                self.pending = None;
+                self.current = best;
+                Some(self.current())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Find the index of the font with the largest scale factor threshold that the
+/// given scale factor reaches, or the one with the smallest threshold if it
+/// reaches none of them.  `None` is returned for an empty set.
+pub(crate) fn best_font(fonts: &[(f64, FontData)], scale_factor: f64) -> Option<usize> {
+    let thresholds = fonts.iter().map(|(threshold, _)| *threshold).enumerate();
+    thresholds
+        .clone()
+        .filter(|(_, threshold)| scale_factor >= *threshold)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .or_else(|| thresholds.min_by(|a, b| a.1.total_cmp(&b.1)))
+        .map(|(index, _)| index)
+}
@@ -0,0 +1,153 @@
+//
+// Timing the GPU
+// Measures how long the graphics card spends drawing each frame, with
+// timestamp queries on adapters that support them
+//
+
+use std::{
+    cell::Cell,
+    convert::TryInto,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    time::Duration,
+};
+
+use wgpu::{
+    Buffer, BufferAsyncError, BufferDescriptor, BufferUsages, CommandEncoder, Device, Features,
+    Maintain, MapMode, QuerySet, QuerySetDescriptor, QueryType, Queue, RenderPassTimestampWrites,
+};
+
+/// The size of the two timestamps written each frame.
+const TIMESTAMPS_SIZE: u64 = 16;
+
+/// Timestamps written at the start of the first render pass of a frame and
+/// the end of the last, and read back a frame later so that the CPU never
+/// waits for them.
+pub(crate) struct GpuTimer {
+    query_set: QuerySet,
+    /// The buffer the timestamps are resolved into.
+    resolve_buffer: Buffer,
+    /// The buffer the resolved timestamps are copied to, to be read back.
+    readback_buffer: Buffer,
+    /// The number of nanoseconds in a timestamp tick.
+    period: f32,
+    /// Receives the result of mapping the readback buffer, while it is being
+    /// mapped.  No timestamps are written until it has been read.
+    pending: Cell<Option<Receiver<Result<(), BufferAsyncError>>>>,
+    /// Whether timestamps are being written in the frame being drawn.
+    writing: Cell<bool>,
+    /// The time the graphics card spent on the last frame that was read back.
+    last: Cell<Option<Duration>>,
+}
+
+impl GpuTimer {
+    /// Create a timer if the device was created with timestamp queries.
+    /// Without them, the GPU time of frames is not known.
+    pub(crate) fn new(device: &Device, queue: &Queue) -> Option<Self> {
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("Timestamp query set"),
+            ty: QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Timestamp resolve buffer"),
+            size: TIMESTAMPS_SIZE,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Timestamp readback buffer"),
+            size: TIMESTAMPS_SIZE,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Some(GpuTimer {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            pending: Cell::new(None),
+            writing: Cell::new(false),
+            last: Cell::new(None),
+        })
+    }
+
+    /// Read back the last frame's timestamps if they have arrived, and decide
+    /// whether this frame writes any.  It does not while the last ones are
+    /// still on their way.
+    pub(crate) fn begin_frame(&self, device: &Device) {
+        if let Some(receiver) = self.pending.take() {
+            device.poll(Maintain::Poll);
+            match receiver.try_recv() {
+                Ok(Ok(())) => {
+                    let data = self.readback_buffer.slice(..).get_mapped_range();
+                    let start = u64::from_le_bytes(data[0..8].try_into().unwrap());
+                    let end = u64::from_le_bytes(data[8..16].try_into().unwrap());
+                    drop(data);
+                    self.readback_buffer.unmap();
+                    let nanos = end.saturating_sub(start) as f64 * self.period as f64;
+                    self.last.set(Some(Duration::from_nanos(nanos as u64)));
+                }
+                // The timestamps are lost, and the next frame writes more.
+                Ok(Err(_)) | Err(TryRecvError::Disconnected) => {}
+                Err(TryRecvError::Empty) => {
+                    self.pending.set(Some(receiver));
+                    self.writing.set(false);
+                    return;
+                }
+            }
+        }
+        self.writing.set(true);
+    }
+
+    /// The timestamps for the first render pass of the frame to write at its
+    /// start, and for the last to write at its end.  A frame with one pass
+    /// writes both.
+    pub(crate) fn writes(&self, first: bool, last: bool) -> Option<RenderPassTimestampWrites<'_>> {
+        if !self.writing.get() {
+            return None;
+        }
+        Some(RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: if first { Some(0) } else { None },
+            end_of_pass_write_index: if last { Some(1) } else { None },
+        })
+    }
+
+    /// Record the copying of the frame's timestamps to the readback buffer,
+    /// after its render passes.
+    pub(crate) fn resolve(&self, encoder: &mut CommandEncoder) {
+        if self.writing.get() {
+            encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+            encoder.copy_buffer_to_buffer(
+                &self.resolve_buffer,
+                0,
+                &self.readback_buffer,
+                0,
+                TIMESTAMPS_SIZE,
+            );
+        }
+    }
+
+    /// Start reading back the frame's timestamps, once it has been submitted.
+    pub(crate) fn end_frame(&self) {
+        if self.writing.get() {
+            let (sender, receiver) = mpsc::channel();
+            self.readback_buffer
+                .slice(..)
+                .map_async(MapMode::Read, move |result| {
+                    let _ = sender.send(result);
+                });
+            self.pending.set(Some(receiver));
+            self.writing.set(false);
+        }
+    }
+
+    /// The time the graphics card spent drawing the last frame whose
+    /// timestamps have been read back.
+    pub(crate) fn last(&self) -> Option<Duration> {
+        self.last.get()
+    }
+}
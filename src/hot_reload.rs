@@ -0,0 +1,91 @@
+//
+// Font hot-reloading
+//
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+};
+
+use image::{ImageError, ImageFormat};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{load_font_image, FontData, Result};
+
+/// Watches a font image on disk so that it can be reloaded when it changes.
+///
+/// The directory containing the file is watched rather than the file itself,
+/// because many image editors save by writing a new file and renaming it over
+/// the old one, which would end a watch on the file.
+pub(crate) struct FontWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    // Watching stops when the watcher is dropped, so it is kept alive here.
+    _watcher: RecommendedWatcher,
+}
+
+impl FontWatcher {
+    pub(crate) fn new(path: &Path) -> Result<Self> {
+        let path = path.canonicalize().map_err(ImageError::from)?;
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let dir = path.parent().unwrap_or(&path);
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        Ok(FontWatcher {
+            path,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// Check whether the font file has changed since the last call, and if so
+    /// reload it.
+    ///
+    /// A font that cannot be loaded is reported and `None` is returned, so
+    /// that the current font stays in use until the file is fixed.
+    pub(crate) fn poll(&self) -> Option<FontData> {
+        // Drain every pending event so that one save only causes one reload.
+        let mut changed = false;
+        for event in self.events.try_iter() {
+            match event {
+                Ok(event) => {
+                    changed |= matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+                        && event.paths.iter().any(|path| path == &self.path);
+                }
+                Err(err) => eprintln!("mterm: error watching font file: {}", err),
+            }
+        }
+        if !changed {
+            return None;
+        }
+
+        let font = load_font_file(&self.path).and_then(|font| {
+            font.check()?;
+            Ok(font)
+        });
+        match font {
+            Ok(font) => {
+                eprintln!("mterm: reloaded font from {}", self.path.display());
+                Some(font)
+            }
+            Err(err) => {
+                eprintln!(
+                    "mterm: keeping the current font, could not reload {}: {}",
+                    self.path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Load a font image from a file, using its extension to determine the image
+/// format.
+pub(crate) fn load_font_file(path: &Path) -> Result<FontData> {
+    let format = ImageFormat::from_path(path)?;
+    let data = fs::read(path).map_err(ImageError::from)?;
+    load_font_image(&data, format)
+}
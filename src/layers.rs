@@ -0,0 +1,203 @@
+//
+// Layers
+// Images stacked in order and drawn over one another, such as a world, its
+// effects and a user interface over both
+//
+
+use crate::{BlitOptions, Image, Point, PresentInput};
+
+/// How a layer is drawn over the layers below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerMode {
+    /// Every cell replaces the cell below it.
+    Opaque,
+    /// Cells whose glyph word is the key, or whose paper has an alpha of 0,
+    /// are skipped, as with `BlitOptions::with_key`.
+    Keyed(u32),
+    /// The colours are blended over those below, as with
+    /// `BlitOptions::with_blend`.  The glyph replaces the one below only
+    /// where the paper's alpha is at least this much.
+    Blend(u8),
+}
+
+/// An image in a `LayerStack`, with where and how it is drawn.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    image: Image,
+    z: i32,
+    visible: bool,
+    offset: Point,
+    mode: LayerMode,
+}
+
+impl Layer {
+    /// Create a visible, opaque layer at z-order 0, drawn at the top-left
+    /// corner of the window.
+    pub fn new(image: Image) -> Self {
+        Layer {
+            image,
+            z: 0,
+            visible: true,
+            offset: Point::new(0, 0),
+            mode: LayerMode::Opaque,
+        }
+    }
+
+    /// Draw the layer above those with a lower z-order.  Layers with the
+    /// same z-order are drawn in the order they were added.
+    pub fn with_z(mut self, z: i32) -> Self {
+        self.z = z;
+        self
+    }
+
+    /// Draw the layer with its top-left corner at this position on the
+    /// window.
+    pub fn with_offset(mut self, offset: Point) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Draw the layer over the layers below in this way.
+    pub fn with_mode(mut self, mode: LayerMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Show or hide the layer.
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
+    /// The layer's image.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// The layer's image, for drawing on.
+    pub fn image_mut(&mut self) -> &mut Image {
+        &mut self.image
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    pub fn set_z(&mut self, z: i32) {
+        self.z = z;
+    }
+
+    pub fn offset(&self) -> Point {
+        self.offset
+    }
+
+    /// Move the layer.  Moving a layer larger than the window by minus the
+    /// position of a camera makes it a viewport onto a larger world.
+    pub fn set_offset(&mut self, offset: Point) {
+        self.offset = offset;
+    }
+
+    pub fn mode(&self) -> LayerMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: LayerMode) {
+        self.mode = mode;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+}
+
+/// Identifies a layer in a `LayerStack`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerId(usize);
+
+/// A stack of layers, drawn to the window from the lowest z-order to the
+/// highest with `LayerStack::composite_to`.
+///
+/// Layers can be any size.  Those smaller than the window, such as a dialog,
+/// only cover part of it, and those larger, such as a world map, are clipped
+/// to it.
+#[derive(Debug, Clone, Default)]
+pub struct LayerStack {
+    layers: Vec<Layer>,
+}
+
+impl LayerStack {
+    pub fn new() -> Self {
+        LayerStack::default()
+    }
+
+    /// Add a layer to the stack, returning its id.
+    pub fn add(&mut self, layer: Layer) -> LayerId {
+        self.layers.push(layer);
+        LayerId(self.layers.len() - 1)
+    }
+
+    /// The layer with an id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id is from another stack with more layers.
+    pub fn layer(&self, id: LayerId) -> &Layer {
+        &self.layers[id.0]
+    }
+
+    /// The layer with an id, for changing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id is from another stack with more layers.
+    pub fn layer_mut(&mut self, id: LayerId) -> &mut Layer {
+        &mut self.layers[id.0]
+    }
+
+    /// The image of the layer with an id, for drawing on.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id is from another stack with more layers.
+    pub fn image_mut(&mut self, id: LayerId) -> &mut Image {
+        self.layers[id.0].image_mut()
+    }
+
+    /// The number of layers.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Return true if there are no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Draw the visible layers to the window, from the lowest z-order to the
+    /// highest.  Nothing is cleared first, so the bottom layer should
+    /// usually be opaque and cover the window.
+    pub fn composite_to(&self, present_input: &mut PresentInput) {
+        let mut layers = self
+            .layers
+            .iter()
+            .filter(|layer| layer.visible)
+            .collect::<Vec<_>>();
+        // The sort is stable, so layers with the same z-order keep the order
+        // they were added in.
+        layers.sort_by_key(|layer| layer.z);
+
+        for layer in layers {
+            let options = match layer.mode {
+                LayerMode::Opaque => BlitOptions::new(),
+                LayerMode::Keyed(key) => BlitOptions::new().with_key(key),
+                LayerMode::Blend(glyph_alpha) => BlitOptions::new().with_blend(glyph_alpha),
+            };
+            let image = &layer.image;
+            present_input.blit_with(layer.offset, image.width, image.height, image, &options);
+        }
+    }
+}
@@ -4,17 +4,74 @@
 //
 
 mod app;
+#[cfg(feature = "bdf")]
+mod bdf;
+mod box_drawing;
+mod braille;
 mod builder;
+mod canvas;
+mod charmap;
 mod colour;
+/// The named colours of CSS and X11, such as `colours::ORANGE`, as `u32`s in
+/// the same format as `colour()`.
+pub mod colours;
+mod commands;
+mod diagnostics;
+mod diff;
+mod font;
+mod font_set;
+mod gpu_timer;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+mod layers;
 mod main_loop;
+mod markup;
+mod menu;
+mod palette;
+mod post_effect;
 mod present;
+mod progress;
+mod psf;
+#[cfg(feature = "recording")]
+mod recorder;
 mod render;
 mod result;
+#[cfg(feature = "rexpaint")]
+mod rexpaint;
+mod screenshot;
+#[cfg(feature = "serde")]
+mod serde_impls;
+mod text_field;
+mod theme;
+#[cfg(feature = "ttf")]
+mod ttf;
 
 pub use app::*;
+#[cfg(feature = "bdf")]
+pub use bdf::*;
+pub use box_drawing::*;
+pub use braille::*;
 pub use builder::*;
+pub use canvas::*;
+pub use charmap::*;
 pub use colour::*;
+pub use commands::*;
+pub use diagnostics::*;
+pub use diff::*;
+pub use font::*;
+pub use layers::*;
 pub use main_loop::*;
+pub use markup::*;
+pub use menu::*;
+pub use palette::*;
+pub use post_effect::*;
 pub use present::*;
+pub use progress::*;
+pub use psf::*;
+#[cfg(feature = "recording")]
+pub use recorder::*;
 pub use render::*;
 pub use result::*;
+pub use screenshot::*;
+pub use text_field::*;
+pub use theme::*;
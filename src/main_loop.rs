@@ -1,20 +1,32 @@
 use futures::executor::block_on;
-use image::ImageFormat;
-use std::cmp::max;
+use std::{
+    cmp::{max, min},
+    time::Instant,
+};
 use time::Duration;
-use wgpu::SwapChainError;
+use wgpu::SurfaceError;
 use winit::{
     dpi::PhysicalSize,
     event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
-    window::{Fullscreen, WindowBuilder},
+    window::{Fullscreen, Window, WindowBuilder},
 };
 
+#[cfg(feature = "hot-reload")]
+use crate::hot_reload::FontWatcher;
+#[cfg(feature = "recording")]
+use crate::recorder::Recorder;
 use crate::{
-    load_font_image, App, Builder, Font, KeyState, PresentInput, PresentResult, RenderState,
-    Result, TickInput, TickResult,
+    builder::WindowSize, font_set::FontSet, App, Builder, CanvasView, Command, Commands, Font,
+    FrameTimings, KeyState, OverlayContext, PresentInput, PresentResult, Rect, RenderState, Result,
+    TickInput, TickResult,
 };
 
+/// The number of frames in a row that can fail to render before the main loop
+/// gives up and exits.  Frames fail now and then, such as when the surface
+/// times out, but a surface that keeps failing is broken for good.
+const MAX_FAILED_FRAMES: u32 = 60;
+
 /// Start the main loop.
 ///
 /// This function does not exit unless an error occurs during start up.
@@ -29,34 +41,86 @@ use crate::{
 /// # Returns
 ///
 /// Returns a `TermResult`.
-
 pub fn run(app: Box<dyn App>, builder: Builder) -> Result<()> {
     block_on(run_internal(app, builder))
 }
 
 pub async fn run_internal(mut app: Box<dyn App>, builder: Builder) -> Result<()> {
+    builder.validate()?;
+
+    let event_loop = EventLoop::new();
+
+    #[cfg(feature = "hot-reload")]
+    let mut font_watcher = None;
+    let mut font_set = None;
     let font_data = match builder.font {
-        Font::Default => load_font_image(include_bytes!("font1.png"), ImageFormat::Png)?,
+        Font::Builtin(font) => font.font_data()?,
         Font::Custom(font) => font,
+        Font::Set(fonts) => {
+            // Until the window exists we guess that it will open on the
+            // primary monitor.
+            let scale_factor = event_loop
+                .primary_monitor()
+                .map_or(1.0, |monitor| monitor.scale_factor());
+            let set = FontSet::new(fonts, scale_factor);
+            let font = set.current().clone();
+            font_set = Some(set);
+            font
+        }
+        #[cfg(feature = "hot-reload")]
+        Font::Watched(path, font) => {
+            font_watcher = Some(FontWatcher::new(&path)?);
+            font
+        }
     };
 
+    let mut cell_size = (
+        font_data.width * builder.render_options.font_scale,
+        font_data.height * builder.render_options.font_scale,
+    );
+    let size_settings = (builder.min_size, builder.max_size);
+    let (min_size, max_size) = size_limits(size_settings.0, size_settings.1, cell_size);
+
     // Adjust the dimensions of the window to fit character cells exactly.
-    let width =
-        max(20 * font_data.width, builder.inner_size.0 as u32) / font_data.width * font_data.width;
-    let height = max(20 * font_data.height, builder.inner_size.1 as u32) / font_data.height
-        * font_data.height;
+    let (mut width, mut height) = (builder.inner_size.0 as u32, builder.inner_size.1 as u32);
+    if let Some(max_size) = max_size {
+        width = min(width, max_size.0);
+        height = min(height, max_size.1);
+    }
+    let width = max(min_size.0, width) / cell_size.0 * cell_size.0;
+    let height = max(min_size.1, height) / cell_size.1 * cell_size.1;
 
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
+    let mut window_builder = WindowBuilder::new()
         .with_inner_size(PhysicalSize::new(width, height))
         .with_title(builder.title)
-        .with_min_inner_size(PhysicalSize::new(
-            20 * font_data.width,
-            20 * font_data.height,
-        ))
-        .build(&event_loop)?;
+        .with_min_inner_size(PhysicalSize::new(min_size.0, min_size.1));
+    if let Some(max_size) = max_size {
+        // A window whose minimum and maximum sizes match cannot be resized, so
+        // we disable resizing altogether to remove the maximise button too.
+        window_builder = window_builder
+            .with_max_inner_size(PhysicalSize::new(max_size.0, max_size.1))
+            .with_resizable(max_size != min_size);
+    }
+    let window = window_builder.build(&event_loop)?;
+
+    let mut render = RenderState::new(
+        &window,
+        &font_data,
+        builder.bold_font.as_ref(),
+        &builder.render_options,
+    )
+    .await?;
 
-    let mut render = RenderState::new(&window, &font_data).await?;
+    // The window may have opened on a monitor with a different scale factor
+    // than we guessed.
+    if let Some(font) = font_set
+        .as_mut()
+        .and_then(|set| set.select(window.scale_factor()))
+    {
+        render.set_font(font);
+    }
+    let mut chars_size = render.chars_size();
+    app.init_overlay(render.overlay_init());
 
     let mut key_state = KeyState {
         vkey: None,
@@ -67,6 +131,30 @@ pub async fn run_internal(mut app: Box<dyn App>, builder: Builder) -> Result<()>
         code: None,
     };
 
+    // Set when the window must be redrawn even if the app has not changed
+    // anything, such as after the font is switched.
+    let mut force_redraw = false;
+
+    // The number of frames in a row that could not be rendered.
+    let mut failed_frames = 0;
+
+    // The times of the frame being made, and of the one before it that the
+    // app is given.
+    let mut timings = FrameTimings::default();
+    let mut last_timings = FrameTimings::default();
+    let slow_frame = builder
+        .slow_frame_ms
+        .map(|ms| std::time::Duration::from_millis(ms.into()));
+
+    // When the window last changed size, if the grid has not been resized to
+    // fit it since.
+    let mut last_resize: Option<Instant> = None;
+    let resize_debounce = std::time::Duration::from_millis(builder.resize_debounce_ms.into());
+    let exit_on_escape = builder.exit_on_escape;
+
+    #[cfg(feature = "recording")]
+    let mut recorder: Option<Recorder> = None;
+
     event_loop.run(move |event, _, control_flow| {
         *control_flow = ControlFlow::Poll;
 
@@ -104,7 +192,7 @@ pub async fn run_internal(mut app: Box<dyn App>, builder: Builder) -> Result<()>
                                 pressed: true,
                                 vkey: Some(VirtualKeyCode::Escape),
                                 ..
-                            } => {
+                            } if exit_on_escape => {
                                 //
                                 // Exit
                                 //
@@ -116,14 +204,23 @@ pub async fn run_internal(mut app: Box<dyn App>, builder: Builder) -> Result<()>
                                 ctrl: false,
                                 alt: true,
                                 vkey: Some(VirtualKeyCode::Return),
-                                code: None,
+                                ..
                             } => {
                                 //
                                 // Toggle fullscreen
                                 //
+                                // Fullscreen ignores the maximum window size so
+                                // we lift it while fullscreen and restore it
+                                // afterwards.
                                 if window.fullscreen().is_some() {
                                     window.set_fullscreen(None);
+                                    window.set_max_inner_size(
+                                        max_size.map(|(width, height)| {
+                                            PhysicalSize::new(width, height)
+                                        }),
+                                    );
                                 } else if let Some(monitor) = window.current_monitor() {
+                                    window.set_max_inner_size(None::<PhysicalSize<u32>>);
                                     if let Some(video_mode) = monitor.video_modes().next() {
                                         if cfg!(any(target_os = "macos", unix)) {
                                             window.set_fullscreen(Some(Fullscreen::Borderless(
@@ -137,10 +234,44 @@ pub async fn run_internal(mut app: Box<dyn App>, builder: Builder) -> Result<()>
                                     };
                                 };
                             }
+                            KeyState {
+                                pressed: true,
+                                ctrl: true,
+                                alt: false,
+                                vkey:
+                                    Some(VirtualKeyCode::Equals)
+                                    | Some(VirtualKeyCode::Plus)
+                                    | Some(VirtualKeyCode::NumpadAdd),
+                                ..
+                            } => {
+                                //
+                                // Increase font scale
+                                //
+                                render.set_font_scale(render.font_scale() + 1);
+                                force_redraw = true;
+                            }
+                            KeyState {
+                                pressed: true,
+                                ctrl: true,
+                                alt: false,
+                                vkey:
+                                    Some(VirtualKeyCode::Minus) | Some(VirtualKeyCode::NumpadSubtract),
+                                ..
+                            } => {
+                                //
+                                // Decrease font scale
+                                //
+                                render.set_font_scale(render.font_scale() - 1);
+                                force_redraw = true;
+                            }
                             _ => {}
                         }
                     }
                     //
+                    // Typed characters
+                    //
+                    WindowEvent::ReceivedCharacter(ch) => key_state.code = Some(ch),
+                    //
                     // Modifier keys
                     //
                     WindowEvent::ModifiersChanged(mods) => {
@@ -151,9 +282,26 @@ pub async fn run_internal(mut app: Box<dyn App>, builder: Builder) -> Result<()>
                     //
                     // Resizing
                     //
-                    WindowEvent::Resized(new_size) => render.resize(new_size),
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        render.resize(*new_inner_size)
+                    WindowEvent::Resized(new_size) => {
+                        // The surface must always match the window, but while
+                        // the window is being dragged the grid keeps its size
+                        // until the window stops changing.
+                        if resize_debounce.is_zero() {
+                            render.resize(new_size);
+                        } else {
+                            render.resize_surface(new_size);
+                            last_resize = Some(Instant::now());
+                        }
+                        force_redraw = true;
+                    }
+                    WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size,
+                    } => {
+                        render.resize(*new_inner_size);
+                        if let Some(set) = font_set.as_mut() {
+                            set.scale_factor_changed(scale_factor);
+                        }
                     }
 
                     _ => {} // No more windowed events
@@ -163,24 +311,192 @@ pub async fn run_internal(mut app: Box<dyn App>, builder: Builder) -> Result<()>
             // Idle
             //
             Event::MainEventsCleared => {
-                if let TickResult::Stop = tick(app.as_mut(), &render, &key_state) {
+                // The grid is always fitted to the window's latest size, so
+                // it cannot be left at a size the window passed through.
+                if matches!(last_resize, Some(time) if time.elapsed() >= resize_debounce) {
+                    last_resize = None;
+                    render.fit_grid();
+                    force_redraw = true;
+                }
+                if let Some(font) = font_set.as_mut().and_then(FontSet::poll) {
+                    render.set_font(font);
+                    force_redraw = true;
+                }
+                // The limits of the window's size are in whole cells, so they
+                // change with the font and its scale.
+                if render.cell_size() != cell_size {
+                    cell_size = render.cell_size();
+                    let (min_size, max_size) =
+                        size_limits(size_settings.0, size_settings.1, cell_size);
+                    window.set_min_inner_size(Some(PhysicalSize::new(min_size.0, min_size.1)));
+                    window.set_max_inner_size(
+                        max_size.map(|(width, height)| PhysicalSize::new(width, height)),
+                    );
+                    window.set_resizable(max_size != Some(min_size));
+                }
+                if render.chars_size() != chars_size {
+                    chars_size = render.chars_size();
+                    app.on_resize(chars_size.0, chars_size.1);
+                }
+                let mut commands = Commands::new();
+                let start = Instant::now();
+                let result = tick(
+                    app.as_mut(),
+                    &render,
+                    &key_state,
+                    &last_timings,
+                    &mut commands,
+                );
+                timings.tick = start.elapsed();
+                if let TickResult::Stop = result {
                     *control_flow = ControlFlow::Exit;
                 }
+                for command in commands.drain() {
+                    match command {
+                        Command::SetFont(font) => match font.check() {
+                            Ok(()) => {
+                                render.set_font(&font);
+                                force_redraw = true;
+                            }
+                            Err(err) => eprintln!("mterm: ignoring font change, {}", err),
+                        },
+                        Command::SetCentreGrid(centre_grid) => {
+                            render.set_centre_grid(centre_grid);
+                            force_redraw = true;
+                        }
+                        Command::SetPresentMode(present_mode) => {
+                            let applied = render.set_present_mode(present_mode);
+                            app.on_present_mode_changed(present_mode, applied);
+                            force_redraw = true;
+                        }
+                        Command::SetPostEffect(post_effect) => {
+                            render.set_post_effect(post_effect);
+                            force_redraw = true;
+                        }
+                        #[cfg(feature = "recording")]
+                        Command::StartRecording(recording) => {
+                            if let Some(recorder) = recorder.take() {
+                                app.on_recording_finished(recorder.finish());
+                            }
+                            recorder = Some(Recorder::start(recording, render.font().clone()));
+                            // The window as it is now is the first frame.
+                            force_redraw = true;
+                        }
+                        #[cfg(feature = "recording")]
+                        Command::StopRecording => {
+                            if let Some(recorder) = recorder.take() {
+                                app.on_recording_finished(recorder.finish());
+                            }
+                        }
+                    }
+                }
+                #[cfg(feature = "hot-reload")]
+                if let Some(font) = font_watcher.as_ref().and_then(FontWatcher::poll) {
+                    render.set_font(&font);
+                    force_redraw = true;
+                }
+                #[cfg(feature = "recording")]
+                if matches!(&recorder, Some(recorder) if recorder.is_over()) {
+                    if let Some(recorder) = recorder.take() {
+                        app.on_recording_finished(recorder.finish());
+                    }
+                }
                 key_state.pressed = false;
                 key_state.vkey = None;
+                key_state.code = None;
                 window.request_redraw();
             }
             //
             // Redraw
             //
             Event::RedrawRequested(_) => {
-                if let PresentResult::Changed = present(app.as_ref(), &mut render) {
-                    match render.render() {
-                        Ok(_) => {}
-                        Err(SwapChainError::Lost) => render.resize(window.inner_size()),
-                        Err(wgpu::SwapChainError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                        Err(e) => eprintln!("{:?}", e),
-                    };
+                let start = Instant::now();
+                let (changed, dirty, overlay_dirty) = present(app.as_ref(), &mut render);
+                timings.present = start.elapsed();
+                if render.blink_changed() {
+                    force_redraw = true;
+                }
+                if render.is_device_lost() {
+                    // Nothing can be drawn with a lost device, so everything is
+                    // created again and the app told before the next tick.
+                    match block_on(render.rebuild(&window)) {
+                        Ok(()) => {
+                            app.init_overlay(render.overlay_init());
+                            app.on_device_lost();
+                            failed_frames = 0;
+                            force_redraw = true;
+                        }
+                        Err(err) => {
+                            eprintln!("mterm: could not recover from losing the graphics device, {}", err);
+                            *control_flow = ControlFlow::Exit;
+                            return;
+                        }
+                    }
+                }
+                if matches!(changed, PresentResult::Changed)
+                    || dirty.is_some()
+                    || overlay_dirty.is_some()
+                    || force_redraw
+                {
+                    force_redraw = false;
+                    // Without `Changed`, every write was made through the
+                    // `Canvas` methods and is inside the dirty region, so the
+                    // rest of the grid need not be uploaded again.
+                    if let (PresentResult::NoChanges, Some(dirty)) = (&changed, dirty) {
+                        render.set_dirty_region(dirty);
+                    }
+                    if let (PresentResult::NoChanges, Some(dirty)) = (&changed, overlay_dirty) {
+                        render.set_overlay_dirty_region(dirty);
+                    }
+                    match render_frame(&mut render, &window, app.as_mut()) {
+                        Ok(()) => {
+                            failed_frames = 0;
+                            let render_timings = render.frame_timings();
+                            timings.upload = render_timings.upload;
+                            timings.render = render_timings.render;
+                            timings.gpu = render_timings.gpu;
+                            #[cfg(feature = "recording")]
+                            if let Some(recorder) = recorder.as_mut() {
+                                recorder.capture(render.to_image());
+                            }
+                        }
+                        Err(SurfaceError::OutOfMemory) => {
+                            eprintln!("mterm: {}", SurfaceError::OutOfMemory);
+                            *control_flow = ControlFlow::Exit;
+                        }
+                        Err(err) => {
+                            // The frame is skipped, such as after a timeout,
+                            // and tried again next time.  A surface that never
+                            // recovers is given up on rather than retried
+                            // forever.
+                            force_redraw = true;
+                            failed_frames += 1;
+                            if failed_frames >= MAX_FAILED_FRAMES {
+                                eprintln!(
+                                    "mterm: giving up after {} frames in a row failed to render, {}",
+                                    failed_frames, err
+                                );
+                                *control_flow = ControlFlow::Exit;
+                            }
+                        }
+                    }
+                }
+
+                if matches!(slow_frame, Some(threshold) if timings.cpu() > threshold) {
+                    eprintln!("mterm: slow frame, {}", timings);
+                }
+                last_timings = timings;
+                timings = FrameTimings::default();
+            }
+
+            //
+            // Exiting
+            //
+            #[cfg(feature = "recording")]
+            Event::LoopDestroyed => {
+                // Finish the file rather than leaving it cut off.
+                if let Some(recorder) = recorder.take() {
+                    app.on_recording_finished(recorder.finish());
                 }
             }
 
@@ -189,30 +505,149 @@ pub async fn run_internal(mut app: Box<dyn App>, builder: Builder) -> Result<()>
     });
 }
 
-fn tick(app: &mut dyn App, render: &RenderState, key_state: &KeyState) -> TickResult {
+/// Work out the limits of the window size in pixels for a cell size.  If no
+/// minimum size is given we allow the window to go as small as 20x20
+/// characters.  The minimum can never be larger than the maximum.
+fn size_limits(
+    min_size: Option<WindowSize>,
+    max_size: Option<WindowSize>,
+    cell_size: (u32, u32),
+) -> ((u32, u32), Option<(u32, u32)>) {
+    let min_size = min_size.map_or((20 * cell_size.0, 20 * cell_size.1), |size| {
+        size.to_pixels(cell_size)
+    });
+    let max_size = max_size.map(|size| {
+        let (width, height) = size.to_pixels(cell_size);
+        (max(width, cell_size.0), max(height, cell_size.1))
+    });
+    let min_size = match max_size {
+        Some(max_size) => (min(min_size.0, max_size.0), min(min_size.1, max_size.1)),
+        None => min_size,
+    };
+    let min_size = (max(min_size.0, cell_size.0), max(min_size.1, cell_size.1));
+    (min_size, max_size)
+}
+
+/// Render the window.  If the surface has been lost or no longer matches the
+/// window, such as while it is being resized or moved between monitors, it is
+/// configured again and the frame is rendered again.  If that fails too, the
+/// device is taken to be lost and the renderer is rebuilt before the next
+/// frame.
+fn render_frame(
+    render: &mut RenderState,
+    window: &Window,
+    app: &mut dyn App,
+) -> std::result::Result<(), SurfaceError> {
+    let mut overlay = |context: OverlayContext| app.render_overlay(context);
+    match render.render_with_overlay(&mut overlay) {
+        Err(SurfaceError::Lost) | Err(SurfaceError::Outdated) => {
+            render.resize(window.inner_size());
+            let result = render.render_with_overlay(&mut overlay);
+            // A surface that is lost again as soon as it is configured
+            // belongs to a device that has been lost.
+            if let Err(SurfaceError::Lost) = result {
+                render.set_device_lost();
+            }
+            result
+        }
+        result => result,
+    }
+}
+
+fn tick(
+    app: &mut dyn App,
+    render: &RenderState,
+    key_state: &KeyState,
+    frame_timings: &FrameTimings,
+    commands: &mut Commands,
+) -> TickResult {
     let (width, height) = render.chars_size();
     let sim_input = TickInput {
         dt: Duration::zero(),
+        time_ms: render.time_ms(),
         width,
         height,
-        key: (*key_state).clone(),
+        key: *key_state,
         mouse: None,
+        adapter: render.adapter_info(),
+        present_mode: render.present_mode(),
+        frame_stats: render.frame_stats(),
+        frame_timings: *frame_timings,
+        commands,
     };
 
     app.tick(sim_input)
 }
 
-fn present(app: &dyn App, render: &mut RenderState) -> PresentResult {
+/// Ask the app to present, returning its result and the parts of the window
+/// and of the overlay layer that it wrote to.
+fn present(app: &dyn App, render: &mut RenderState) -> (PresentResult, Option<Rect>, Option<Rect>) {
     let (width, height) = render.chars_size();
-    let (fore_image, back_image, text_image) = render.images();
+    let (width, height) = (width as usize, height as usize);
+    let mut clear_colour = render.clear_colour();
+    let mut dirty = None;
+    let mut overlay_dirty = None;
+    let (cells, overlay) = render.layers_mut();
 
     let present_input = PresentInput {
-        width: width as usize,
-        height: height as usize,
-        fore_image,
-        back_image,
-        text_image,
+        width,
+        height,
+        fore_image: &mut cells.fore_image,
+        back_image: &mut cells.back_image,
+        text_image: &mut cells.text_image,
+        clear_colour: &mut clear_colour,
+        overlay: overlay.map(|overlay| CanvasView {
+            width,
+            height,
+            fore_image: &mut overlay.fore_image,
+            back_image: &mut overlay.back_image,
+            text_image: &mut overlay.text_image,
+            dirty: &mut overlay_dirty,
+        }),
+        dirty: &mut dirty,
     };
 
-    app.present(present_input)
+    let result = app.present(present_input);
+    render.set_clear_colour(clear_colour);
+    (result, dirty, overlay_dirty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_limits_follow_the_cell_size() {
+        // The default minimum is 20 cells whatever their size.
+        assert_eq!(size_limits(None, None, (8, 16)), ((160, 320), None));
+        assert_eq!(size_limits(None, None, (16, 32)), ((320, 640), None));
+
+        let chars = (
+            Some(WindowSize::Chars(10, 5)),
+            Some(WindowSize::Chars(40, 20)),
+        );
+        assert_eq!(
+            size_limits(chars.0, chars.1, (8, 16)),
+            ((80, 80), Some((320, 320)))
+        );
+        assert_eq!(
+            size_limits(chars.0, chars.1, (16, 32)),
+            ((160, 160), Some((640, 640)))
+        );
+
+        // Limits in pixels are rounded down to whole cells, but never below
+        // one cell, and the minimum never passes the maximum.
+        let pixels = (
+            Some(WindowSize::Pixels(100, 100)),
+            Some(WindowSize::Pixels(60, 500)),
+        );
+        assert_eq!(
+            size_limits(pixels.0, pixels.1, (16, 32)),
+            ((48, 96), Some((48, 480)))
+        );
+        assert_eq!(
+            size_limits(pixels.0, pixels.1, (80, 160)),
+            ((80, 160), Some((80, 480)))
+        );
+    }
 }
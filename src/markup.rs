@@ -0,0 +1,87 @@
+//
+// Markup
+// Text with inline colour changes, such as "You hit the [red]orc[/]"
+//
+
+use std::mem;
+
+use crate::parse_colour;
+
+/// A run of text drawn in one ink colour, as parsed from markup by
+/// `parse_markup`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub text: String,
+    /// The ink colour, or None for the default ink given when drawing.
+    pub ink: Option<u32>,
+}
+
+/// Split markup into runs of text in the same ink colour.
+///
+/// A tag such as `[red]`, `[dark_grey]` or `[#ff8800]` changes the ink of the
+/// text after it, and `[/]` changes it back to the default.  Tags do not nest,
+/// so a second tag replaces the first.  Colours are understood in the same way
+/// as `parse_colour()`, except that hex colours must start with a `#`, so that
+/// words like "[bad]" are not taken for colours.  Write `[[` for a `[`.
+///
+/// Anything that is not a tag, such as "[oops" or "[not a colour]", is kept
+/// as text, so that a mistake shows up on the screen rather than as an error.
+pub fn parse_markup(markup: &str) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut span = Span {
+        text: String::new(),
+        ink: None,
+    };
+    let mut rest = markup;
+
+    while let Some(start) = rest.find('[') {
+        span.text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("[[") {
+            span.text.push('[');
+            rest = after;
+            continue;
+        }
+
+        let tag = rest[1..].find(']').map(|end| &rest[1..=end]);
+        match tag.and_then(|tag| Some((tag, tag_ink(tag)?))) {
+            Some((tag, ink)) => {
+                let next = Span {
+                    text: String::new(),
+                    ink,
+                };
+                spans.push(mem::replace(&mut span, next));
+                rest = &rest[tag.len() + 2..];
+            }
+            None => {
+                span.text.push('[');
+                rest = &rest[1..];
+            }
+        }
+    }
+    span.text.push_str(rest);
+    spans.push(span);
+
+    // Tags next to each other leave empty spans behind, and tags that change
+    // nothing split spans that can be joined.
+    let mut joined: Vec<Span> = Vec::with_capacity(spans.len());
+    for span in spans.into_iter().filter(|span| !span.text.is_empty()) {
+        match joined.last_mut() {
+            Some(last) if last.ink == span.ink => last.text.push_str(&span.text),
+            _ => joined.push(span),
+        }
+    }
+    joined
+}
+
+/// The ink set by a tag, with None for the default, or None if it is not a
+/// tag.
+fn tag_ink(tag: &str) -> Option<Option<u32>> {
+    if tag == "/" {
+        Some(None)
+    } else if !tag.starts_with('#') && tag.chars().all(|c| c.is_ascii_hexdigit()) {
+        None
+    } else {
+        parse_colour(tag).ok().map(Some)
+    }
+}
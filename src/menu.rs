@@ -0,0 +1,324 @@
+//
+// Menus
+// A vertical list of items with one selected, scrolling when there are more
+// items than fit
+//
+
+use std::{cell::Cell, convert::TryFrom};
+
+use winit::event::VirtualKeyCode;
+
+use crate::{parse_markup, Canvas, Char, KeyState, Point, Rect, Span};
+
+/// What a key did to a `Menu`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEvent {
+    /// The key was not for the menu, or did nothing.
+    Ignored,
+    /// The selection moved, so the menu should be drawn again.
+    Moved,
+    /// The selected item was chosen with Enter.
+    Chosen(usize),
+    /// The menu was dismissed with Escape.  The window closes on Escape
+    /// unless `Builder::with_exit_on_escape(false)` is used, so this is only
+    /// seen with that setting.
+    Cancelled,
+}
+
+/// How `Menu::draw` draws a menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MenuStyle {
+    pub(crate) ink: u32,
+    pub(crate) paper: u32,
+    pub(crate) highlight_ink: u32,
+    pub(crate) highlight_paper: u32,
+    pub(crate) scrollbar: bool,
+}
+
+impl MenuStyle {
+    /// Create a style with items in these colours, and the selected item in
+    /// the same colours swapped.  A scroll bar is shown when some items are
+    /// hidden.
+    pub fn new(ink: impl Into<u32>, paper: impl Into<u32>) -> Self {
+        let (ink, paper) = (ink.into(), paper.into());
+        MenuStyle {
+            ink,
+            paper,
+            highlight_ink: paper,
+            highlight_paper: ink,
+            scrollbar: true,
+        }
+    }
+
+    /// Draw the selected item in these colours.
+    pub fn with_highlight(mut self, ink: impl Into<u32>, paper: impl Into<u32>) -> Self {
+        self.highlight_ink = ink.into();
+        self.highlight_paper = paper.into();
+        self
+    }
+
+    /// Show a scroll bar down the right side when some items are hidden.
+    pub fn with_scrollbar(mut self, scrollbar: bool) -> Self {
+        self.scrollbar = scrollbar;
+        self
+    }
+}
+
+/// A vertical menu, such as a title screen's or an inventory.
+///
+/// Keep it in the app, pass it key presses from `tick` with
+/// `Menu::handle_key`, and draw it in `present` with `Menu::draw`.  Items are
+/// markup, so they can have colours, as with `Canvas::draw_rich`.
+///
+/// When there are more items than rows, the menu scrolls to keep the
+/// selection in view.  It remembers how many rows it was last drawn with, so
+/// that Page Up and Page Down move by a page.
+#[derive(Debug, Clone)]
+pub struct Menu {
+    items: Vec<Vec<Span>>,
+    selected: usize,
+    scroll: Cell<usize>,
+    rows: Cell<usize>,
+}
+
+impl Menu {
+    /// Create a menu with the first item selected.
+    pub fn new<S: AsRef<str>>(items: impl IntoIterator<Item = S>) -> Self {
+        Menu {
+            items: items
+                .into_iter()
+                .map(|item| parse_markup(item.as_ref()))
+                .collect(),
+            selected: 0,
+            scroll: Cell::new(0),
+            rows: Cell::new(1),
+        }
+    }
+
+    /// The number of items.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Return true if there are no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The index of the selected item, or None if there are no items.
+    pub fn selected(&self) -> Option<usize> {
+        if self.items.is_empty() {
+            None
+        } else {
+            Some(self.selected)
+        }
+    }
+
+    /// Select an item.  Indices past the end select the last item.
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+    }
+
+    /// Move the selection in response to a key press.  Up and Down move by an
+    /// item, Page Up and Page Down by a page, and Home and End to the first
+    /// and last items.  Enter chooses the selected item and Escape cancels
+    /// the menu, if the window lets the app see Escape (see
+    /// `Builder::with_exit_on_escape`).  Key releases are ignored.
+    pub fn handle_key(&mut self, key: &KeyState) -> MenuEvent {
+        let vkey = match key.vkey {
+            Some(vkey) if key.pressed => vkey,
+            _ => return MenuEvent::Ignored,
+        };
+        if vkey == VirtualKeyCode::Escape {
+            return MenuEvent::Cancelled;
+        }
+        if self.items.is_empty() {
+            return MenuEvent::Ignored;
+        }
+
+        let (last, page) = (self.items.len() - 1, self.rows.get().max(1));
+        let selected = match vkey {
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                return MenuEvent::Chosen(self.selected)
+            }
+            VirtualKeyCode::Up => self.selected.saturating_sub(1),
+            VirtualKeyCode::Down => (self.selected + 1).min(last),
+            VirtualKeyCode::PageUp => self.selected.saturating_sub(page),
+            VirtualKeyCode::PageDown => self.selected.saturating_add(page).min(last),
+            VirtualKeyCode::Home => 0,
+            VirtualKeyCode::End => last,
+            _ => return MenuEvent::Ignored,
+        };
+        if selected == self.selected {
+            MenuEvent::Ignored
+        } else {
+            self.selected = selected;
+            MenuEvent::Moved
+        }
+    }
+
+    /// Draw the items that fit in a rectangle, one per row, with the selected
+    /// item highlighted across the whole row.  Items too wide for the
+    /// rectangle are cut short.
+    pub fn draw<C: Canvas + ?Sized>(&self, canvas: &mut C, rect: Rect, style: &MenuStyle) {
+        canvas.fill_rect(rect, Char::new(b' ', style.ink, style.paper));
+        let rows = rect.height;
+        self.rows.set(rows);
+        if rows == 0 || self.items.is_empty() {
+            return;
+        }
+
+        // Scroll as little as possible to bring the selection into view,
+        // without leaving rows empty at the end.
+        let mut scroll = self.scroll.get().min(self.selected);
+        if self.selected >= scroll.saturating_add(rows) {
+            scroll = self.selected + 1 - rows;
+        }
+        scroll = scroll.min(self.items.len().saturating_sub(rows));
+        self.scroll.set(scroll);
+
+        let scrollbar = style.scrollbar && self.items.len() > rows && rect.width > 1;
+        let width = rect.width - scrollbar as usize;
+        let visible = self.items.iter().enumerate().skip(scroll).take(rows);
+        for (row, (index, item)) in visible.enumerate() {
+            let y = rect.y.saturating_add(row as i32);
+            let (ink, paper) = if index == self.selected {
+                (style.highlight_ink, style.highlight_paper)
+            } else {
+                (style.ink, style.paper)
+            };
+            canvas.fill_rect(Rect::new(rect.x, y, width, 1), Char::new(b' ', ink, paper));
+            canvas.draw_spans(Point::new(rect.x, y), &truncate(item, width), ink, paper);
+        }
+
+        if scrollbar {
+            // The thumb's size is the fraction of the items shown, and it
+            // moves from the top to the bottom as the menu scrolls.
+            let (len, rows) = (self.items.len() as u128, rows as u128);
+            let thumb = (rows * rows / len).max(1);
+            let top = scroll as u128 * (rows - thumb) / (len - rows);
+            if let Ok(x) = i32::try_from(rect.x as i64 + width as i64) {
+                let track = Char::new(0xb0, style.ink, style.paper);
+                canvas.draw_vline(Point::new(x, rect.y), rows as usize, track);
+                let thumb_y = rect.y.saturating_add(top as i32);
+                let thumb_char = Char::new(0xdb, style.ink, style.paper);
+                canvas.draw_vline(Point::new(x, thumb_y), thumb as usize, thumb_char);
+            }
+        }
+    }
+}
+
+/// Cut spans short so that they are no more than `width` characters long.
+fn truncate(spans: &[Span], width: usize) -> Vec<Span> {
+    let mut left = width;
+    let mut truncated = Vec::new();
+    for span in spans {
+        if left == 0 {
+            break;
+        }
+        let text = span.text.chars().take(left).collect::<String>();
+        left -= text.chars().count();
+        truncated.push(Span {
+            text,
+            ink: span.ink,
+        });
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Image;
+
+    fn press(vkey: VirtualKeyCode) -> KeyState {
+        KeyState {
+            pressed: true,
+            shift: false,
+            ctrl: false,
+            alt: false,
+            vkey: Some(vkey),
+            code: None,
+        }
+    }
+
+    /// Draw a menu in a rectangle `width` by `rows` and return the text.
+    fn drawn(menu: &Menu, width: usize, rows: usize) -> String {
+        let mut image = Image::new(width, rows);
+        let style = MenuStyle::new(1u32, 2u32).with_scrollbar(false);
+        menu.draw(&mut image, Rect::new(0, 0, width, rows), &style);
+        image.to_plain_text()
+    }
+
+    #[test]
+    fn empty_menus_ignore_keys() {
+        let mut menu = Menu::new(Vec::<&str>::new());
+        assert_eq!(menu.selected(), None);
+        for &vkey in &[
+            VirtualKeyCode::Down,
+            VirtualKeyCode::End,
+            VirtualKeyCode::PageDown,
+            VirtualKeyCode::Return,
+        ] {
+            assert_eq!(menu.handle_key(&press(vkey)), MenuEvent::Ignored);
+        }
+        assert_eq!(
+            menu.handle_key(&press(VirtualKeyCode::Escape)),
+            MenuEvent::Cancelled
+        );
+        menu.select(3);
+        assert_eq!(menu.selected(), None);
+        assert_eq!(drawn(&menu, 3, 2), "   \n   \n");
+    }
+
+    #[test]
+    fn selection_stops_at_the_ends() {
+        let mut menu = Menu::new(["a", "b", "c"]);
+        assert_eq!(
+            menu.handle_key(&press(VirtualKeyCode::Up)),
+            MenuEvent::Ignored
+        );
+        assert_eq!(
+            menu.handle_key(&press(VirtualKeyCode::End)),
+            MenuEvent::Moved
+        );
+        assert_eq!(menu.selected(), Some(2));
+        assert_eq!(
+            menu.handle_key(&press(VirtualKeyCode::Down)),
+            MenuEvent::Ignored
+        );
+        assert_eq!(
+            menu.handle_key(&press(VirtualKeyCode::PageDown)),
+            MenuEvent::Ignored
+        );
+        assert_eq!(
+            menu.handle_key(&press(VirtualKeyCode::Return)),
+            MenuEvent::Chosen(2)
+        );
+        menu.select(usize::MAX);
+        assert_eq!(menu.selected(), Some(2));
+    }
+
+    #[test]
+    fn shrinking_keeps_the_selection_in_view() {
+        let mut menu = Menu::new(["a", "b", "c", "d", "e"]);
+        menu.select(3);
+        assert_eq!(drawn(&menu, 1, 5), "a\nb\nc\nd\ne\n");
+        assert_eq!(drawn(&menu, 1, 2), "c\nd\n");
+        assert_eq!(drawn(&menu, 1, 1), "d\n");
+
+        // Growing again fills the rows rather than leaving any empty.
+        assert_eq!(drawn(&menu, 1, 4), "b\nc\nd\ne\n");
+
+        // Page Down moves by the rows last drawn, and nothing is drawn with
+        // no rows at all.
+        menu.select(0);
+        drawn(&menu, 1, 2);
+        assert_eq!(
+            menu.handle_key(&press(VirtualKeyCode::PageDown)),
+            MenuEvent::Moved
+        );
+        assert_eq!(menu.selected(), Some(2));
+        assert_eq!(drawn(&menu, 1, 0), "");
+    }
+}
@@ -0,0 +1,356 @@
+//
+// Palettes
+// Indexed colours for palette effects such as colour cycling
+//
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::{colour, Image, Rgba, PALETTE_CGA};
+
+/// A table of up to 256 colours, looked up by index.
+///
+/// Old-school effects such as waterfalls and fire are done by changing the
+/// colours in a palette rather than redrawing the characters.  To do this,
+/// keep an `Image` whose `fore_image` and `back_image` hold palette indices
+/// instead of colours, and resolve it with `Palette::apply` just before
+/// blitting it to the window.  Changing the palette, for example with
+/// `Palette::rotate_range`, then changes every cell that uses those indices.
+///
+/// Indices beyond the end of the palette are black.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Palette {
+    entries: Vec<u32>,
+}
+
+impl Palette {
+    /// Create a palette from a list of colours.  Only the first 256 are used.
+    pub fn new(colours: impl IntoIterator<Item = u32>) -> Self {
+        Palette {
+            entries: colours.into_iter().take(256).collect(),
+        }
+    }
+
+    /// The number of colours in the palette.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The colours in the palette, in index order.
+    pub fn colours(&self) -> &[u32] {
+        &self.entries
+    }
+
+    /// Return the colour at an index, or black if the palette is not that
+    /// long.
+    pub fn get(&self, index: u8) -> u32 {
+        self.entries
+            .get(index as usize)
+            .copied()
+            .unwrap_or(colour(0, 0, 0))
+    }
+
+    /// Change the colour at an index.  The palette grows to include the index
+    /// if needed, filling the gap with black.
+    pub fn set(&mut self, index: u8, c: impl Into<u32>) {
+        let index = index as usize;
+        if index >= self.entries.len() {
+            self.entries.resize(index + 1, colour(0, 0, 0));
+        }
+        self.entries[index] = c.into();
+    }
+
+    /// Rotate the colours within a range of indices.
+    ///
+    /// Positive steps move each colour up to a higher index, with colours at
+    /// the end of the range wrapping round to the start.  Negative steps move
+    /// them down.  The range is clipped to the palette.
+    pub fn rotate_range(&mut self, range: Range<usize>, steps: i32) {
+        let end = range.end.min(self.entries.len());
+        if range.start >= end {
+            return;
+        }
+        let colours = &mut self.entries[range.start..end];
+        let steps = steps.rem_euclid(colours.len() as i32) as usize;
+        colours.rotate_right(steps);
+    }
+
+    /// Replace the palette indices in an image's `fore_image` and `back_image`
+    /// with their colours.  Only the lowest 8 bits of each value are used as
+    /// the index.
+    pub fn apply(&self, image: &mut Image) {
+        image
+            .fore_image
+            .iter_mut()
+            .chain(image.back_image.iter_mut())
+            .for_each(|c| *c = self.get(*c as u8));
+    }
+
+    /// The 16 colours of the CGA, in its original order starting with black,
+    /// blue, green and cyan.  These are the same as `PALETTE_CGA`.
+    pub fn cga() -> Self {
+        Palette::new(PALETTE_CGA.iter().copied())
+    }
+
+    /// The 256 colours of the VGA's default palette.
+    ///
+    /// The first 16 are the CGA colours, followed by 16 greys and then 9 rings
+    /// of 24 hues, at 3 levels of saturation for each of 3 levels of
+    /// brightness.  The last 8 are black.
+    pub fn vga() -> Self {
+        // The VGA uses 6-bit colour components.
+        const GREYS: [u8; 16] = [0, 5, 8, 11, 14, 17, 20, 24, 28, 32, 36, 40, 45, 50, 56, 63];
+        const RINGS: [[u8; 5]; 9] = [
+            [0, 16, 31, 47, 63],
+            [31, 39, 47, 55, 63],
+            [45, 49, 54, 58, 63],
+            [0, 7, 14, 21, 28],
+            [14, 17, 21, 24, 28],
+            [20, 22, 24, 26, 28],
+            [0, 4, 8, 12, 16],
+            [8, 10, 12, 14, 16],
+            [11, 12, 13, 15, 16],
+        ];
+        let vga = |r: u8, g: u8, b: u8| {
+            let expand = |c: u8| (c << 2) | (c >> 4);
+            colour(expand(r), expand(g), expand(b))
+        };
+
+        let mut palette = Palette::cga();
+        palette
+            .entries
+            .extend(GREYS.iter().map(|&grey| vga(grey, grey, grey)));
+        for levels in RINGS.iter() {
+            // Each ring goes from blue through magenta, red, yellow, green and
+            // cyan, moving one component at a time.
+            let rising = levels.iter().copied();
+            let falling = levels.iter().rev().copied();
+            let (lo, hi) = (levels[0], levels[4]);
+            let ring = rising
+                .clone()
+                .take(4)
+                .map(|r| (r, lo, hi))
+                .chain(falling.clone().take(4).map(|b| (hi, lo, b)))
+                .chain(rising.clone().take(4).map(|g| (hi, g, lo)))
+                .chain(falling.clone().take(4).map(|r| (r, hi, lo)))
+                .chain(rising.take(4).map(|b| (lo, hi, b)))
+                .chain(falling.take(4).map(|g| (lo, g, hi)));
+            palette.entries.extend(ring.map(|(r, g, b)| vga(r, g, b)));
+        }
+        palette.entries.resize(256, colour(0, 0, 0));
+        palette
+    }
+
+    /// The 16 colours of the Tango terminal palette, in ANSI order: black,
+    /// red, green, yellow, blue, magenta, cyan and white, followed by their
+    /// bright versions.
+    pub fn tango() -> Self {
+        Palette::new(
+            [
+                0x2e3436, 0xcc0000, 0x4e9a06, 0xc4a000, 0x3465a4, 0x75507b, 0x06989a, 0xd3d7cf,
+                0x555753, 0xef2929, 0x8ae234, 0xfce94f, 0x729fcf, 0xad7fa8, 0x34e2e2, 0xeeeeec,
+            ]
+            .iter()
+            .map(|&rgb| from_rgb(rgb)),
+        )
+    }
+
+    /// The 16 colours of the Solarized dark terminal palette, in ANSI order.
+    ///
+    /// The background is index 8 and the body text is index 12.
+    pub fn solarized_dark() -> Self {
+        Palette::new(solarized(SOLARIZED_BASE).map(from_rgb))
+    }
+
+    /// The 16 colours of the Solarized light terminal palette, in ANSI order.
+    ///
+    /// This is the same as `Palette::solarized_dark` with the light and dark
+    /// base tones swapped, so the background is still index 8 and the body text
+    /// index 12.
+    pub fn solarized_light() -> Self {
+        let mut base = SOLARIZED_BASE;
+        base.reverse();
+        Palette::new(solarized(base).map(from_rgb))
+    }
+}
+
+/// How the difference between two colours is measured when matching them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourDistance {
+    /// The distance in RGB space, weighted to roughly match how sensitive the
+    /// eye is to each component.  This is quick and works well for most
+    /// palettes.
+    WeightedRgb,
+    /// The distance in the CIELAB colour space, which is designed so that
+    /// equal distances look like equal differences.  This is slower but gives
+    /// better matches for palettes with many similar colours.
+    Lab,
+}
+
+/// Return the index of the palette colour closest to a colour, using
+/// `ColourDistance::WeightedRgb`.
+///
+/// The alpha is ignored.  If several colours are equally close, the lowest
+/// index is returned, and 0 is returned for an empty palette.
+pub fn nearest_colour(target: u32, palette: &[u32]) -> usize {
+    nearest_colour_by(target, palette, ColourDistance::WeightedRgb)
+}
+
+/// Return the index of the palette colour closest to a colour, measuring the
+/// difference between colours in the given way.
+///
+/// The alpha is ignored.  If several colours are equally close, the lowest
+/// index is returned, and 0 is returned for an empty palette.
+pub fn nearest_colour_by(target: u32, palette: &[u32], distance: ColourDistance) -> usize {
+    match distance {
+        ColourDistance::WeightedRgb => {
+            nearest_by_key(palette, |c| weighted_rgb_distance(target, c))
+        }
+        ColourDistance::Lab => {
+            let (l, a, b) = to_lab(target);
+            nearest_by_key(palette, |c| {
+                let (l2, a2, b2) = to_lab(c);
+                (l - l2).powi(2) + (a - a2).powi(2) + (b - b2).powi(2)
+            })
+        }
+    }
+}
+
+/// Replace every ink and paper colour in an image with the closest colour in
+/// a palette, using `nearest_colour()`.
+///
+/// Nothing is changed if the palette is empty.
+pub fn quantise(image: &mut Image, palette: &[u32]) {
+    if palette.is_empty() {
+        return;
+    }
+    // Images tend to use few colours, so each is only matched once.
+    let mut matches = HashMap::new();
+    image
+        .fore_image
+        .iter_mut()
+        .chain(image.back_image.iter_mut())
+        .for_each(|c| {
+            *c = *matches
+                .entry(*c)
+                .or_insert_with(|| palette[nearest_colour(*c, palette)]);
+        });
+}
+
+/// A set of colour replacements, such as to recolour a sprite for each team
+/// in a game.
+///
+/// The sprite is drawn in a few fixed colours, each of which is mapped to the
+/// team's colour with `PresentInput::blit_remap`.  Colours that are not in the
+/// map are left unchanged.  The same works for images holding palette indices
+/// rather than colours.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColourMap {
+    map: HashMap<u32, u32>,
+}
+
+impl ColourMap {
+    pub fn new() -> Self {
+        ColourMap::default()
+    }
+
+    /// Return the map with `from` replaced by `to`.
+    pub fn map(mut self, from: impl Into<u32>, to: impl Into<u32>) -> Self {
+        self.map.insert(from.into(), to.into());
+        self
+    }
+
+    /// Return the colour that replaces `c`, or `c` itself if it is not
+    /// mapped.
+    pub fn get(&self, c: u32) -> u32 {
+        self.map.get(&c).copied().unwrap_or(c)
+    }
+
+    /// The number of colours that are mapped.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+/// Return the index of the first colour with the smallest key.
+fn nearest_by_key<K: PartialOrd>(palette: &[u32], key: impl Fn(u32) -> K) -> usize {
+    let mut nearest = (0, None);
+    for (i, &c) in palette.iter().enumerate() {
+        let k = key(c);
+        if nearest.1.as_ref().map_or(true, |best| k < *best) {
+            nearest = (i, Some(k));
+        }
+    }
+    nearest.0
+}
+
+/// The squared distance between two colours in RGB space, with each component
+/// weighted by how sensitive the eye is to it.  The weights for red and blue
+/// depend on how red the colours are (the "redmean" approximation).
+fn weighted_rgb_distance(a: u32, b: u32) -> u32 {
+    let (a, b) = (Rgba::from(a), Rgba::from(b));
+    let red_mean = (a.r as u32 + b.r as u32) / 2;
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    (((512 + red_mean) * d(a.r, b.r)) >> 8)
+        + 4 * d(a.g, b.g)
+        + (((767 - red_mean) * d(a.b, b.b)) >> 8)
+}
+
+/// Convert a colour to the CIELAB colour space, using the D65 white point.
+fn to_lab(c: u32) -> (f32, f32, f32) {
+    let c = Rgba::from(c);
+    let linear = |v: u8| {
+        let v = v as f32 / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(c.r), linear(c.g), linear(c.b));
+
+    // Convert to XYZ, relative to the white point.
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// The Solarized base tones from darkest (base03) to lightest (base3).
+const SOLARIZED_BASE: [u32; 8] = [
+    0x002b36, 0x073642, 0x586e75, 0x657b83, 0x839496, 0x93a1a1, 0xeee8d5, 0xfdf6e3,
+];
+
+/// Arrange the Solarized base tones and accent colours in ANSI order.
+fn solarized(base: [u32; 8]) -> impl Iterator<Item = u32> {
+    let [base03, base02, base01, base00, base0, base1, base2, base3] = base;
+    let (yellow, orange, red, magenta) = (0xb58900, 0xcb4b16, 0xdc322f, 0xd33682);
+    let (violet, blue, cyan, green) = (0x6c71c4, 0x268bd2, 0x2aa198, 0x859900);
+    vec![
+        base02, red, green, yellow, blue, magenta, cyan, base2, base03, orange, base01, base00,
+        base0, violet, base1, base3,
+    ]
+    .into_iter()
+}
+
+/// Convert a colour written as 0xRRGGBB, as colours usually are on the web, to
+/// the format used by `colour()`.
+fn from_rgb(rgb: u32) -> u32 {
+    colour((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
@@ -0,0 +1,21 @@
+// The vertex shader shared by the post effects, covering the window with a
+// quad for the effect's fragment shader to draw.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+}
+
+@vertex
+fn main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    // The same quad covering the window as the main shader.
+    let i = u32(in_vertex_index);
+    let fx = f32(i & 2u) - 1.0;
+    let fy = f32(i & 1u) * 2.0 - 1.0;
+    out.clip_position = vec4<f32>(fx, fy, 0.0, 1.0);
+
+    return out;
+}
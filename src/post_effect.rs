@@ -0,0 +1,380 @@
+//
+// Post effects
+// Effects drawn over the whole window after the character grid, such as
+// making it look like an old CRT monitor, or given as a custom shader
+//
+
+use std::mem;
+
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+use bytemuck::cast_slice;
+use bytemuck_derive::{Pod, Zeroable};
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferBindingType, BufferDescriptor, BufferUsages, Color, ColorTargetState,
+    ColorWrites, CommandEncoder, Device, Extent3d, FilterMode, FragmentState, FrontFace, LoadOp,
+    MultisampleState, Operations, PipelineLayoutDescriptor, PolygonMode, PrimitiveState,
+    PrimitiveTopology, Queue, RenderPassColorAttachment, RenderPassDescriptor,
+    RenderPassTimestampWrites, RenderPipeline, RenderPipelineDescriptor, Sampler,
+    SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor, ShaderSource, ShaderStages,
+    StoreOp, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+use crate::{Error, Result};
+
+/// An effect drawn over the whole window after the character grid, chosen
+/// with `Builder::with_post_effect` or `Commands::set_post_effect`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PostEffect {
+    /// No effect.  The character grid is drawn straight to the window, with
+    /// no extra cost.
+    #[default]
+    None,
+    /// Make the window look like an old CRT monitor, with scanlines, a curved
+    /// picture, dark corners and glowing phosphors.
+    Crt(CrtParams),
+    /// Draw the window with the shader given to
+    /// `Builder::with_custom_post_shader`.  Without one, there is no effect.
+    Custom,
+}
+
+impl PostEffect {
+    /// The effect's settings, as given to the shader after the window size
+    /// and time.
+    fn params(&self) -> [f32; 4] {
+        match self {
+            PostEffect::Crt(params) => [
+                params.scanlines,
+                params.curvature,
+                params.vignette,
+                params.glow,
+            ],
+            _ => [0.0; 4],
+        }
+    }
+}
+
+/// The settings of the `PostEffect::Crt` effect.  Each is 0 for none of its
+/// part of the effect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrtParams {
+    /// How much every other row of pixels is darkened, from 0 to 1.
+    pub scanlines: f32,
+    /// How much the picture bulges out from the centre, where 1 pushes the
+    /// corners about half as far out again.
+    pub curvature: f32,
+    /// How much the corners are darkened, from 0 to 1.
+    pub vignette: f32,
+    /// How far, in pixels, bright pixels glow into their neighbours.
+    pub glow: f32,
+}
+
+impl Default for CrtParams {
+    fn default() -> Self {
+        CrtParams {
+            scanlines: 0.3,
+            curvature: 0.15,
+            vignette: 0.3,
+            glow: 1.5,
+        }
+    }
+}
+
+/// Check that a custom post shader compiles and has a fragment entry point
+/// called `main`.  An `Error::BadShader` holding the compiler's message is
+/// returned if not.
+pub(crate) fn check_post_shader(source: &str) -> Result<()> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|err| Error::BadShader(err.emit_to_string(source)))?;
+    Validator::new(ValidationFlags::all(), Capabilities::empty())
+        .validate(&module)
+        .map_err(|err| Error::BadShader(err.to_string()))?;
+
+    let has_main = module.entry_points.iter().any(|entry_point| {
+        entry_point.stage == naga::ShaderStage::Fragment && entry_point.name == "main"
+    });
+    if !has_main {
+        return Err(Error::BadShader(
+            "there is no fragment entry point called `main`".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The GPU resources for drawing a post effect.  The character grid is drawn
+/// to the scene texture instead of the window, and then the effect draws the
+/// scene to the window.
+pub(crate) struct PostPass {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    sampler: Sampler,
+    uniform_buffer: Buffer,
+    scene_view: TextureView,
+    format: TextureFormat,
+    size: (u32, u32),
+}
+
+impl PostPass {
+    /// Set up the resources for an effect, for a window of a size and
+    /// format.  There are none for `PostEffect::None`, or for
+    /// `PostEffect::Custom` without a custom shader.
+    pub(crate) fn for_effect(
+        device: &Device,
+        format: TextureFormat,
+        size: (u32, u32),
+        effect: &PostEffect,
+        custom_shader: Option<&str>,
+    ) -> Option<Self> {
+        match effect {
+            PostEffect::None => None,
+            PostEffect::Crt(_) => Some(Self::new(device, format, size, include_str!("crt.wgsl"))),
+            PostEffect::Custom => {
+                custom_shader.map(|source| Self::new(device, format, size, source))
+            }
+        }
+    }
+
+    /// Set up an effect drawn by a fragment shader.  The vertex shader that
+    /// covers the window with it is shared by all the effects.
+    fn new(device: &Device, format: TextureFormat, size: (u32, u32), source: &str) -> Self {
+        let vertex_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Post vertex shader"),
+            source: ShaderSource::Wgsl(include_str!("post.wgsl").into()),
+        });
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Post shader"),
+            source: ShaderSource::Wgsl(source.into()),
+        });
+
+        let entry = |binding, ty| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty,
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Post bind group layout"),
+            entries: &[
+                entry(
+                    0,
+                    BindingType::Texture {
+                        multisampled: false,
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                ),
+                entry(1, BindingType::Sampler(SamplerBindingType::Filtering)),
+                entry(
+                    2,
+                    BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                ),
+            ],
+        });
+
+        // The edges are clamped so that the glow does not wrap around.
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Post sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        // The uniforms are written before each frame is drawn.
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Post uniform buffer"),
+            size: mem::size_of::<PostInfo>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Post pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Post pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &vertex_shader,
+                entry_point: "main",
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                front_face: FrontFace::Cw,
+                cull_mode: None,
+                polygon_mode: PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        });
+
+        let (scene_view, bind_group) = Self::create_scene(
+            device,
+            &bind_group_layout,
+            &sampler,
+            &uniform_buffer,
+            format,
+            size,
+        );
+        PostPass {
+            pipeline,
+            bind_group_layout,
+            bind_group,
+            sampler,
+            uniform_buffer,
+            scene_view,
+            format,
+            size,
+        }
+    }
+
+    /// Create the scene texture for a window size, and the bind group that
+    /// gives it to the shader.
+    fn create_scene(
+        device: &Device,
+        layout: &BindGroupLayout,
+        sampler: &Sampler,
+        uniform_buffer: &Buffer,
+        format: TextureFormat,
+        size: (u32, u32),
+    ) -> (TextureView, BindGroup) {
+        // A minimised window has no pixels, but a texture must have some.
+        let scene = device.create_texture(&TextureDescriptor {
+            label: Some("Post scene texture"),
+            size: Extent3d {
+                width: size.0.max(1),
+                height: size.1.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let scene_view = scene.create_view(&TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Post bind group"),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&scene_view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(sampler),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        (scene_view, bind_group)
+    }
+
+    /// Recreate the scene texture after the window is resized.
+    pub(crate) fn resize(&mut self, device: &Device, size: (u32, u32)) {
+        if size != self.size {
+            let (scene_view, bind_group) = Self::create_scene(
+                device,
+                &self.bind_group_layout,
+                &self.sampler,
+                &self.uniform_buffer,
+                self.format,
+                size,
+            );
+            self.scene_view = scene_view;
+            self.bind_group = bind_group;
+            self.size = size;
+        }
+    }
+
+    /// The view that the character grid is drawn to instead of the window.
+    pub(crate) fn scene_view(&self) -> &TextureView {
+        &self.scene_view
+    }
+
+    /// Draw the scene to the window with the effect, writing the timestamps
+    /// given, if any, to time the frame.
+    pub(crate) fn draw(
+        &self,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        view: &TextureView,
+        effect: &PostEffect,
+        time_ms: u32,
+        timestamp_writes: Option<RenderPassTimestampWrites>,
+    ) {
+        let info = PostInfo {
+            width: self.size.0 as f32,
+            height: self.size.1 as f32,
+            time: time_ms as f32 / 1000.0,
+            padding: 0.0,
+            params: effect.params(),
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, cast_slice(&[info]));
+
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Post render pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..4, 0..1);
+    }
+}
+
+/// The uniforms given to the post shaders, laid out as `PostInfo` in the
+/// shaders and padded to a multiple of 16 bytes.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct PostInfo {
+    width: f32,
+    height: f32,
+    /// Seconds since the window opened.
+    time: f32,
+    padding: f32,
+    /// The effect's settings, from `PostEffect::params`.
+    params: [f32; 4],
+}
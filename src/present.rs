@@ -1,6 +1,12 @@
-use std::cmp::min;
+use std::{
+    cmp::{max, min},
+    convert::TryFrom,
+    fmt,
+};
 
-use crate::PresentInput;
+use crate::{
+    alpha_of, blend_over, desaturate, mul_colour, Canvas, Charmap, ColourMap, PresentInput,
+};
 
 //
 // Implements some methods for the PresentInput structure
@@ -8,19 +14,202 @@ use crate::PresentInput;
 
 impl<'a> PresentInput<'a> {
     pub fn blit(&mut self, p: Point, dst_width: usize, dst_height: usize, image: &Image) {
+        self.blit_with(p, dst_width, dst_height, image, &BlitOptions::default());
+    }
+
+    pub fn blit_screen(&mut self, image: &Image) {
+        self.blit(Point::new(0, 0), self.width, self.height, image);
+    }
+
+    /// Blit an image to the window, skipping the cells whose glyph is `key`
+    /// or whose paper is fully transparent.  The window is left unchanged
+    /// under the skipped cells, so sprites can be drawn over a background.
+    pub fn blit_keyed(&mut self, p: Point, image: &Image, key: u32) {
+        let options = BlitOptions::new().with_key(key);
+        self.blit_with(p, image.width, image.height, image, &options);
+    }
+
+    /// Blit part of an image to the window, such as one sprite from a sheet.
+    /// The region is clipped to the image, with the parts inside it drawn
+    /// where they would have been had the image been bigger.
+    pub fn blit_region(&mut self, p: Point, image: &Image, region: Rect) {
+        let options = BlitOptions::new().with_region(region);
+        self.blit_with(p, region.width, region.height, image, &options);
+    }
+
+    /// Blit an image to the window rotated clockwise.  Only the positions of
+    /// the cells are rotated; the glyphs are still drawn upright.
+    pub fn blit_rotated(&mut self, p: Point, image: &Image, rotation: Rotation) {
+        let (width, height) = match rotation {
+            Rotation::None | Rotation::Cw180 => (image.width, image.height),
+            Rotation::Cw90 | Rotation::Cw270 => (image.height, image.width),
+        };
+        let options = BlitOptions::new().with_rotation(rotation);
+        self.blit_with(p, width, height, image, &options);
+    }
+
+    /// Blit an image to the window with each cell drawn as a `scale` by
+    /// `scale` block, such as for a title.  Use `BlitOptions::with_scale` to
+    /// scale across and down by different amounts.
+    pub fn blit_scaled(&mut self, p: Point, image: &Image, scale: u32) {
+        let size = |n: usize| n.saturating_mul(scale as usize);
+        let options = BlitOptions::new().with_scale(scale, scale);
+        self.blit_with(p, size(image.width), size(image.height), image, &options);
+    }
+
+    /// Blit an image over the window using the alpha of its colours, such as
+    /// to darken the screen behind a menu.  The ink and paper colours are
+    /// drawn over the window's with `blend_over()`.  Only cells with opaque
+    /// paper replace the window's glyph; elsewhere the window's glyph is kept.
+    pub fn blit_blend(&mut self, p: Point, image: &Image) {
+        let options = BlitOptions::new().with_blend(255);
+        self.blit_with(p, image.width, image.height, image, &options);
+    }
+
+    /// Blit an image to the window, replacing its ink and paper colours with
+    /// those in a colour map.
+    pub fn blit_remap(&mut self, p: Point, image: &Image, map: &ColourMap) {
+        self.blit_map_colours(p, image, |c| map.get(c));
+    }
+
+    /// Blit an image to the window, passing each ink and paper colour through
+    /// a function, such as to tint it.
+    pub fn blit_map_colours(&mut self, p: Point, image: &Image, f: impl Fn(u32) -> u32) {
+        let (width, height) = (image.width, image.height);
+        self.blit_mapped(p, width, height, image, &BlitOptions::new(), Some(&f));
+    }
+
+    /// Blit an image to fill a rectangle as a nine-slice frame, such as an
+    /// ornate panel border.  The margins split the image into nine parts: the
+    /// corners are drawn as they are, the edges are repeated along the sides
+    /// and the centre is repeated across the middle.
+    ///
+    /// If the rectangle is smaller than the margins, the corners are cut
+    /// short, keeping their outer cells.
+    pub fn blit_nine_slice(&mut self, dst_rect: Rect, image: &Image, margins: Margins) {
+        self.nine_slice(dst_rect, image, margins, true);
+    }
+
+    /// Blit an image as a nine-slice frame in the same way as
+    /// `blit_nine_slice`, but fill the middle with the top-left cell of the
+    /// image's centre rather than repeating the centre.
+    pub fn blit_nine_slice_fill(&mut self, dst_rect: Rect, image: &Image, margins: Margins) {
+        self.nine_slice(dst_rect, image, margins, false);
+    }
+
+    fn nine_slice(&mut self, dst_rect: Rect, image: &Image, margins: Margins, tile_centre: bool) {
+        // The columns and rows of each part, as the start and size of the
+        // outer edges and the middle, in the image and in the rectangle.  The
+        // margins are cut short to fit both, with each outer edge allowed up
+        // to half of the rectangle.
+        let split = |before: usize, after: usize, src: usize, dst: usize| {
+            let src_before = before.min(src);
+            let src_after = after.min(src - src_before);
+            let dst_after = src_after.min(dst / 2);
+            let dst_before = src_before.min(dst - dst_after);
+            let src_parts = [
+                (0, dst_before),
+                (src_before, src - src_before - src_after),
+                (src - dst_after, dst_after),
+            ];
+            let dst_parts = [
+                (0, dst_before),
+                (dst_before, dst - dst_before - dst_after),
+                (dst - dst_after, dst_after),
+            ];
+            (src_parts, dst_parts)
+        };
+        let (src_xs, dst_xs) = split(margins.left, margins.right, image.width, dst_rect.width);
+        let (src_ys, dst_ys) = split(margins.top, margins.bottom, image.height, dst_rect.height);
+
+        for (row, (&(src_y, src_h), &(dst_y, dst_h))) in src_ys.iter().zip(&dst_ys).enumerate() {
+            for (column, (&(src_x, src_w), &(dst_x, dst_w))) in
+                src_xs.iter().zip(&dst_xs).enumerate()
+            {
+                let offset = |start: i32, by: usize| i32::try_from(start as i64 + by as i64).ok();
+                let (x, y) = match (offset(dst_rect.x, dst_x), offset(dst_rect.y, dst_y)) {
+                    (Some(x), Some(y)) => (x, y),
+                    // Parts beyond the range of an i32 are off the window.
+                    _ => continue,
+                };
+                let area = Rect::new(x, y, dst_w, dst_h);
+                let region = Rect::new(src_x as i32, src_y as i32, src_w, src_h);
+                if (row, column) != (1, 1) || tile_centre {
+                    self.blit_tiled(area, image, region);
+                } else if src_w > 0 && src_h > 0 {
+                    if let Some(ch) = image.get_char(region.pos()) {
+                        self.fill_rect(area, ch);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Blit a region of an image over and over to fill an area, with the
+    /// tiles on the right and bottom cut short.  Only the tiles on the window
+    /// are drawn.
+    fn blit_tiled(&mut self, area: Rect, image: &Image, region: Rect) {
+        if region.width == 0 || region.height == 0 {
+            return;
+        }
+        let (x, y, w, h) = match Canvas::clip(self, area) {
+            Some(clipped) => clipped,
+            None => return,
+        };
+
+        // Start from the first tile that is on the window.
+        let first = |start: i32, visible: usize, size: usize| {
+            let skipped = (visible as i64 - start as i64) / size as i64 * size as i64;
+            start as i64 + skipped
+        };
+        let (end_x, end_y) = ((x + w) as i64, (y + h) as i64);
+        let options = BlitOptions::new().with_region(region);
+        let mut tile_y = first(area.y, y, region.height);
+        while tile_y < end_y {
+            let mut tile_x = first(area.x, x, region.width);
+            while tile_x < end_x {
+                let tile_w = region.width.min((end_x - tile_x) as usize);
+                let tile_h = region.height.min((end_y - tile_y) as usize);
+                let p = Point::new(tile_x as i32, tile_y as i32);
+                self.blit_with(p, tile_w, tile_h, image, &options);
+                tile_x += region.width as i64;
+            }
+            tile_y += region.height as i64;
+        }
+    }
+
+    /// Blit an image to the window with the given options.  The image is
+    /// drawn within the `dst_width` by `dst_height` area at `p`.
+    pub fn blit_with(
+        &mut self,
+        p: Point,
+        dst_width: usize,
+        dst_height: usize,
+        image: &Image,
+        options: &BlitOptions,
+    ) {
+        self.blit_mapped(p, dst_width, dst_height, image, options, None);
+    }
+
+    fn blit_mapped(
+        &mut self,
+        p: Point,
+        dst_width: usize,
+        dst_height: usize,
+        image: &Image,
+        options: &BlitOptions,
+        map: Option<&dyn Fn(u32) -> u32>,
+    ) {
+        let region = options
+            .region
+            .unwrap_or_else(|| Rect::new(0, 0, image.width, image.height));
         let blitops = BlitOps {
             src: BlitRect::new(0, 0, image.width, image.height),
             dst: BlitRect::new(0, 0, self.width, self.height),
-            src_blit: BlitRect::new(0, 0, image.width, image.height),
+            src_blit: BlitRect::new(region.x, region.y, region.width, region.height),
             dst_blit: BlitRect::new(p.x, p.y, dst_width, dst_height),
         };
-        blit(&image.fore_image, &mut self.fore_image, &blitops);
-        blit(&image.back_image, &mut self.back_image, &blitops);
-        blit(&image.text_image, &mut self.text_image, &blitops);
-    }
-
-    pub fn blit_screen(&mut self, image: &Image) {
-        self.blit(Point::new(0, 0), self.width, self.height, image);
+        blit(image, self, &blitops, options, map);
     }
 }
 
@@ -29,7 +218,7 @@ impl<'a> PresentInput<'a> {
 // An X, Y coordinate
 //
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -41,21 +230,232 @@ impl Point {
     }
 }
 
+//
+// Rect
+// A rectangle of characters
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: usize, height: usize) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The top-left corner of the rectangle.
+    pub fn pos(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+
+    /// The smallest rectangle holding both rectangles.  Empty rectangles are
+    /// ignored, so that the union of nothing and a rectangle is that
+    /// rectangle.
+    pub fn union(self, other: Rect) -> Rect {
+        if other.width == 0 || other.height == 0 {
+            return self;
+        }
+        if self.width == 0 || self.height == 0 {
+            return other;
+        }
+        // Work in i64 so that no combination of positions and sizes
+        // overflows, and cut the size short if it does not fit.
+        let axis = |a: (i32, usize), b: (i32, usize)| {
+            let end = |(start, len): (i32, usize)| {
+                (start as i64).saturating_add(i64::try_from(len).unwrap_or(i64::MAX))
+            };
+            let start = a.0.min(b.0);
+            let len = end(a).max(end(b)) - start as i64;
+            (start, usize::try_from(len).unwrap_or(usize::MAX))
+        };
+        let (x, width) = axis((self.x, self.width), (other.x, other.width));
+        let (y, height) = axis((self.y, self.height), (other.y, other.height));
+        Rect::new(x, y, width, height)
+    }
+}
+
+//
+// Margins
+// The widths of the four sides of a frame
+//
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Margins {
+    pub left: usize,
+    pub top: usize,
+    pub right: usize,
+    pub bottom: usize,
+}
+
+impl Margins {
+    pub fn new(left: usize, top: usize, right: usize, bottom: usize) -> Self {
+        Margins {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// Margins that are the same on every side.
+    pub fn uniform(margin: usize) -> Self {
+        Margins::new(margin, margin, margin, margin)
+    }
+}
+
+//
+// Anchor
+// The point of an area that stays put when it changes size
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Centre,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// The offset of the top-left corner of an area of size `to` from that of
+    /// an area of size `from`, when both are placed at this anchor.
+    fn offset(self, from: (usize, usize), to: (usize, usize)) -> Point {
+        // The fraction, in halves, of the difference in sizes.
+        let (across, down) = match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Centre => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        };
+        let axis = |halves: i64, from: usize, to: usize| {
+            let offset = (to as i64 - from as i64) * halves / 2;
+            i32::try_from(offset).unwrap_or(if offset < 0 { i32::MIN } else { i32::MAX })
+        };
+        Point::new(axis(across, from.0, to.0), axis(down, from.1, to.1))
+    }
+}
+
+//
+// Align
+// Where text sits along a line that is longer than it
+//
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Centre,
+    Right,
+}
+
 //
 // Char
 // This represents a single ASCII character with an associated ink and paper colour.
 //
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Char {
-    pub ch: u8,
+    /// The value written to `text_image`.  Bits 0-7 are the character code,
+    /// bits 8-15 are the page, which selects a character beyond the first 256
+    /// in larger fonts, and bits 24-31 are attributes such as `Char::BOLD`.
+    pub ch: u32,
     pub ink: u32,
     pub paper: u32,
 }
 
 impl Char {
+    /// The attribute bit that draws the character in bold.  It uses the bold
+    /// font given to `Builder::with_bold_font`, or brightens the ink colour if
+    /// there is none.
+    pub const BOLD: u32 = 1 << 24;
+
+    /// The attribute bit that draws a line in the ink colour along the bottom
+    /// row of pixels of the character.
+    pub const UNDERLINE: u32 = 1 << 25;
+
+    /// The attribute bit that swaps the ink and paper colours of the
+    /// character when it is drawn, leaving them as they are in the images.
+    pub const REVERSE: u32 = 1 << 26;
+
+    /// The attribute bit that makes the character blink, by hiding its glyph
+    /// and underline for every other quarter of a second.
+    pub const BLINK: u32 = 1 << 27;
+
+    /// The bits of a glyph word that hold attributes.  The rest hold the
+    /// character code and page.
+    pub const ATTRIBUTES: u32 = 0xff00_0000;
+
     pub fn new(ch: u8, ink: u32, paper: u32) -> Self {
-        Char { ch, ink, paper }
+        Char {
+            ch: ch as u32,
+            ink,
+            paper,
+        }
+    }
+
+    /// Create a character from a 16-bit glyph index, for fonts with more than
+    /// 256 characters.  The high byte of the index is stored as the page.
+    pub fn new_glyph(glyph: u16, ink: u32, paper: u32) -> Self {
+        Char {
+            ch: glyph as u32,
+            ink,
+            paper,
+        }
+    }
+
+    /// Return the character drawn in bold.
+    pub fn bold(mut self) -> Self {
+        self.ch |= Char::BOLD;
+        self
+    }
+
+    /// Return the character drawn underlined.
+    pub fn underline(mut self) -> Self {
+        self.ch |= Char::UNDERLINE;
+        self
+    }
+
+    /// Return the character drawn with its ink and paper swapped.
+    pub fn reverse(mut self) -> Self {
+        self.ch |= Char::REVERSE;
+        self
+    }
+
+    /// Return the character drawn blinking.
+    pub fn blink(mut self) -> Self {
+        self.ch |= Char::BLINK;
+        self
+    }
+
+    /// The glyph index, which is the character code and page, without the
+    /// attributes.
+    pub fn glyph(&self) -> u16 {
+        (self.ch & !Char::ATTRIBUTES) as u16
+    }
+
+    /// The attribute bits, such as `Char::BOLD | Char::UNDERLINE`.
+    pub fn attributes(&self) -> u32 {
+        self.ch & Char::ATTRIBUTES
     }
 }
 
@@ -64,6 +464,11 @@ impl Char {
 // This represents a rectangular collection of RogueChars to render sprites and screens.
 //
 
+/// Two images are equal if they are the same size and every cell has the same
+/// ink, paper and glyph word.  Both `Debug` and `Display` show the glyphs as
+/// lines of text, so that tests comparing images with `assert_images_eq!`
+/// print something readable.
+#[derive(Clone, PartialEq, Eq)]
 pub struct Image {
     pub width: usize,
     pub height: usize,
@@ -74,7 +479,7 @@ pub struct Image {
 
 impl Image {
     pub fn new(width: usize, height: usize) -> Self {
-        let size = (width * height) as usize;
+        let size = width * height;
         Image {
             width,
             height,
@@ -84,33 +489,72 @@ impl Image {
         }
     }
 
+    /// Create an image from lines of text, such as a small sprite or a map.
+    /// Shorter lines are padded with spaces to the length of the longest.
+    /// Characters outside ASCII are drawn with their code page 437 glyphs, or
+    /// as '?' if there is none.
+    pub fn from_strings(rows: &[&str], ink: impl Into<u32>, paper: impl Into<u32>) -> Self {
+        let (ink, paper) = (ink.into(), paper.into());
+        let charmap = Charmap::cp437();
+        Image::from_strings_mapped(rows, |c| {
+            Char::new(charmap.glyph_for(c).unwrap_or(b'?'), ink, paper)
+        })
+    }
+
+    /// Create an image from lines of text, turning each character into a
+    /// `Char` with a function, so that each can have its own colours.  For
+    /// example, '#' could be a grey wall and '~' blue water.  Shorter lines
+    /// are padded with spaces to the length of the longest.
+    pub fn from_strings_mapped(rows: &[&str], mut f: impl FnMut(char) -> Char) -> Self {
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut image = Image::new(width, rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            let padding = std::iter::repeat(' ');
+            for (x, c) in row.chars().chain(padding).take(width).enumerate() {
+                image.draw_char(Point::new(x as i32, y as i32), f(c));
+            }
+        }
+        image
+    }
+
+    /// Draw on the image as if it were the window, with the methods of a
+    /// `PresentInput` such as its blits, to build an image out of others.
+    /// The clear colour and dirty region given to `draw` are thrown away.
+    pub fn present<R>(&mut self, draw: impl FnOnce(&mut PresentInput) -> R) -> R {
+        let (mut clear_colour, mut dirty) = (0, None);
+        draw(&mut PresentInput {
+            width: self.width,
+            height: self.height,
+            fore_image: &mut self.fore_image,
+            back_image: &mut self.back_image,
+            text_image: &mut self.text_image,
+            clear_colour: &mut clear_colour,
+            overlay: None,
+            dirty: &mut dirty,
+        })
+    }
+
     pub fn coords_to_index(&self, x: usize, y: usize) -> Option<usize> {
         if x < self.width && y < self.height {
-            Some((y * self.width + x) as usize)
+            Some(y * self.width + x)
         } else {
             None
         }
     }
 
-    pub fn clip(&self, p: Point, width: usize, height: usize) -> (usize, usize, usize, usize) {
-        let mut x = p.x;
-        let mut y = p.y;
-        let mut width = width;
-        let mut height = height;
-        if x < 0 {
-            width += x as usize;
-            x = 0;
-        }
-        if y < 0 {
-            height += y as usize;
-            y = 0;
-        }
-        let x = x as usize;
-        let y = y as usize;
-        width = min(width, self.width - x);
-        height = min(height, self.height - y);
-
-        (x, y, width, height)
+    /// Clip a rectangle to the image, returning the position and size of the
+    /// part inside it, or None if none of it is inside.
+    pub fn clip(
+        &self,
+        p: Point,
+        width: usize,
+        height: usize,
+    ) -> Option<(usize, usize, usize, usize)> {
+        Canvas::clip(self, Rect::new(p.x, p.y, width, height))
     }
 
     pub fn clear(&mut self, ink: u32, paper: u32) {
@@ -122,58 +566,135 @@ impl Image {
         );
     }
 
+    /// Change the size of the image, keeping its contents in the top-left
+    /// corner.  Cells that are cut off are lost, and new cells are set to
+    /// `fill`.
+    pub fn resize(&mut self, width: usize, height: usize, fill: Char) {
+        self.resize_anchored(width, height, Anchor::TopLeft, fill);
+    }
+
+    /// Change the size of the image, keeping its contents at the anchor, such
+    /// as in the centre.  Cells that are cut off are lost, and new cells are
+    /// set to `fill`.
+    pub fn resize_anchored(&mut self, width: usize, height: usize, anchor: Anchor, fill: Char) {
+        let mut resized = Image::new(width, height);
+        resized.fill_rect(Rect::new(0, 0, width, height), fill);
+        let p = anchor.offset((self.width, self.height), (width, height));
+        let all = Rect::new(0, 0, self.width, self.height);
+        self.copy_into(all, &mut resized, p);
+        *self = resized;
+    }
+
+    /// Return a copy of part of the image.  The copy is the size of the
+    /// rectangle, and any of it outside the image is left as zeroes.
+    pub fn clone_region(&self, rect: Rect) -> Image {
+        let mut region = Image::new(rect.width, rect.height);
+        self.copy_into(rect, &mut region, Point::new(0, 0));
+        region
+    }
+
+    /// Copy part of the image into another at a position, clipped to both.
+    fn copy_into(&self, region: Rect, dst: &mut Image, p: Point) {
+        let blitops = BlitOps {
+            src: BlitRect::new(0, 0, self.width, self.height),
+            dst: BlitRect::new(0, 0, dst.width, dst.height),
+            src_blit: BlitRect::new(region.x, region.y, region.width, region.height),
+            dst_blit: BlitRect::new(p.x, p.y, region.width, region.height),
+        };
+        blit(self, dst, &blitops, &BlitOptions::default(), None);
+    }
+
     pub fn draw_char(&mut self, p: Point, ch: Char) {
         if p.x >= 0 && p.y >= 0 {
             if let Some(i) = self.coords_to_index(p.x as usize, p.y as usize) {
                 self.fore_image[i] = ch.ink;
                 self.back_image[i] = ch.paper;
-                self.text_image[i] = ch.ch as u32;
+                self.text_image[i] = ch.ch;
             }
         }
     }
 
+    /// Draw a string on one line, clipped to the image.  Every byte is drawn
+    /// as a glyph, so '\n' is drawn as glyph 10 rather than starting a new
+    /// line.  Use `Canvas::draw_text` for text with several lines.
     pub fn draw_string(&mut self, p: Point, text: &str, ink: u32, paper: u32) {
-        let (x, y, w, _) = self.clip(p, text.len(), 1);
-
-        if let Some(i) = self.coords_to_index(x, y) {
-            let w = w as usize;
+        if let Some((x, y, w, _)) = self.clip(p, text.len(), 1) {
+            // Characters before the left edge are skipped, so the rest of the
+            // string stays where it would have been.
+            let skip = (x as i64 - p.x as i64) as usize;
+            let text = &text.as_bytes()[skip..skip + w];
+            let i = y * self.width + x;
             self.fore_image[i..i + w].iter_mut().for_each(|x| *x = ink);
             self.back_image[i..i + w]
                 .iter_mut()
                 .for_each(|x| *x = paper);
             self.text_image[i..i + w]
                 .iter_mut()
-                .enumerate()
-                .for_each(|(j, x)| *x = (text.as_bytes()[j]) as u32);
+                .zip(text)
+                .for_each(|(x, &ch)| *x = ch as u32);
         }
     }
 
+    /// Multiply the ink and paper colours of every cell by a light level.
+    ///
+    /// The map holds one factor per cell, in the same order as the image's
+    /// arrays, so it should be `width * height` long.  A factor of 1 leaves a
+    /// cell unchanged and 0 makes it black.  If the map is shorter than the
+    /// image, the cells after its end are left unchanged, and any extra factors
+    /// are ignored.
+    pub fn apply_light_map(&mut self, map: &[f32]) {
+        self.fore_image
+            .iter_mut()
+            .zip(self.back_image.iter_mut())
+            .zip(map)
+            .for_each(|((ink, paper), &light)| {
+                *ink = mul_colour(*ink, light);
+                *paper = mul_colour(*paper, light);
+            });
+    }
+
+    /// Remove some of the colour from every ink and paper colour, such as to
+    /// show that a game is paused.  See `desaturate()` for how the amount is
+    /// used.
+    pub fn desaturate(&mut self, amount: f32) {
+        self.fore_image
+            .iter_mut()
+            .chain(self.back_image.iter_mut())
+            .for_each(|c| *c = desaturate(*c, amount));
+    }
+
     pub fn draw_rect(&mut self, p: Point, width: usize, height: usize, ch: Char) {
         if width < 3 || height < 3 {
             self.draw_rect_filled(p, width, height, ch);
         } else {
             // Draw top
             self.draw_rect_filled(p, width, 1, ch);
+            // The far edges may lie beyond the range of an i32, in which case
+            // they are off the image anyway.
+            let far_edge = |start: i32, len: usize| {
+                let len = i64::try_from(len).unwrap_or(i64::MAX);
+                i32::try_from((start as i64).saturating_add(len - 1)).ok()
+            };
+            let right = far_edge(p.x, width);
+            let bottom = far_edge(p.y, height);
+            let below_top = p.y.saturating_add(1);
             // Draw bottom
-            self.draw_rect_filled(Point::new(p.x, p.y + (height as i32) - 1), width, 1, ch);
+            if let Some(bottom) = bottom {
+                self.draw_rect_filled(Point::new(p.x, bottom), width, 1, ch);
+            }
             // Draw left
-            self.draw_rect_filled(Point::new(p.x, p.y + 1), 1, height - 2, ch);
+            self.draw_rect_filled(Point::new(p.x, below_top), 1, height - 2, ch);
             // Draw right
-            self.draw_rect_filled(
-                Point::new(p.x + (width as i32) - 1, p.y + 1),
-                1,
-                height - 2,
-                ch,
-            );
+            if let Some(right) = right {
+                self.draw_rect_filled(Point::new(right, below_top), 1, height - 2, ch);
+            }
         }
     }
 
     pub fn draw_rect_filled(&mut self, p: Point, width: usize, height: usize, ch: Char) {
         // Clip the coords and size to the image
-        let (x, y, width, height) = self.clip(p, width, height);
-
-        if let Some(mut i) = self.coords_to_index(x, y) {
-            let width = width as usize;
+        if let Some((x, y, width, height)) = self.clip(p, width, height) {
+            let mut i = y * self.width + x;
             (0..height).for_each(|_| {
                 // Render a row
                 self.fore_image[i..i + width]
@@ -184,12 +705,146 @@ impl Image {
                     .for_each(|x| *x = ch.paper);
                 self.text_image[i..i + width]
                     .iter_mut()
-                    .for_each(|x| *x = ch.ch as u32);
+                    .for_each(|x| *x = ch.ch);
 
-                i += self.width as usize;
+                i += self.width;
             });
         }
     }
+
+    /// Describe how two images differ, such as "12 cells differ (glyphs in
+    /// 2, ink in 12, paper in 0), first at (3, 4)", or return None if they
+    /// are equal.
+    pub fn describe_differences(&self, other: &Image) -> Option<String> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Some(format!(
+                "sizes differ: {}x{} and {}x{}",
+                self.width, self.height, other.width, other.height
+            ));
+        }
+
+        let differs = |a: &[u32], b: &[u32], i: usize| a[i] != b[i];
+        let (mut cells, mut glyphs, mut inks, mut papers) = (0, 0, 0, 0);
+        let mut first = None;
+        for i in 0..self.text_image.len() {
+            let glyph = differs(&self.text_image, &other.text_image, i);
+            let ink = differs(&self.fore_image, &other.fore_image, i);
+            let paper = differs(&self.back_image, &other.back_image, i);
+            if glyph || ink || paper {
+                cells += 1;
+                first = first.or(Some((i % self.width, i / self.width)));
+            }
+            glyphs += glyph as usize;
+            inks += ink as usize;
+            papers += paper as usize;
+        }
+
+        first.map(|(x, y)| {
+            format!(
+                "{} cell{} differ (glyphs in {}, ink in {}, paper in {}), first at ({}, {})",
+                cells,
+                if cells == 1 { "" } else { "s" },
+                glyphs,
+                inks,
+                papers,
+                x,
+                y
+            )
+        })
+    }
+}
+
+impl fmt::Display for Image {
+    /// Write the glyphs as lines of text, in the same way as
+    /// `Canvas::to_plain_text`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_plain_text())
+    }
+}
+
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = self.to_plain_text();
+        f.debug_struct("Image")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("glyphs", &text.lines().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Assert that two images are equal, as for `assert_eq!`.  If they are not,
+/// the panic message says how many cells differ and shows the glyphs of both
+/// side by side, with the rows that differ marked.
+#[macro_export]
+macro_rules! assert_images_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if left != right {
+                    $crate::assert_images_eq_failed(left, right, None);
+                }
+            }
+        }
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&$left, &$right) {
+            (left, right) => {
+                if left != right {
+                    $crate::assert_images_eq_failed(left, right, Some(format_args!($($arg)+)));
+                }
+            }
+        }
+    };
+}
+
+/// Panic with the message for `assert_images_eq!`.
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_images_eq_failed(left: &Image, right: &Image, args: Option<fmt::Arguments>) -> ! {
+    let left_text = left.to_plain_text();
+    let right_text = right.to_plain_text();
+    let (left_rows, right_rows) = (
+        left_text.lines().collect::<Vec<_>>(),
+        right_text.lines().collect::<Vec<_>>(),
+    );
+
+    // A row is marked if any of its cells differ, in colour or glyph.
+    let row_differs = |y: usize| {
+        let planes = |image: &Image| {
+            let cells = y * image.width..(y + 1) * image.width;
+            [&image.text_image, &image.fore_image, &image.back_image]
+                .iter()
+                .map(|plane| plane.get(cells.clone()).map(<[u32]>::to_vec))
+                .collect::<Vec<_>>()
+        };
+        planes(left) != planes(right)
+    };
+    let width = left.width.max("left".len());
+    let mut grids = format!("   {:w$}   right\n", "left", w = width);
+    for y in 0..left_rows.len().max(right_rows.len()) {
+        grids += &format!(
+            "{} |{:w$}| |{}|\n",
+            if row_differs(y) { '>' } else { ' ' },
+            left_rows.get(y).copied().unwrap_or(""),
+            right_rows.get(y).copied().unwrap_or(""),
+            w = width
+        );
+    }
+
+    let summary = left
+        .describe_differences(right)
+        .unwrap_or_else(|| String::from("images differ"));
+    match args {
+        Some(args) => panic!(
+            "assertion failed: `(left == right)`: {}\n{}\n{}",
+            args, summary, grids
+        ),
+        None => panic!(
+            "assertion failed: `(left == right)`\n{}\n{}",
+            summary, grids
+        ),
+    }
 }
 
 //
@@ -208,8 +863,8 @@ impl BlitRect {
         BlitRect {
             x,
             y,
-            w: width as i32,
-            h: height as i32,
+            w: i32::try_from(width).unwrap_or(i32::MAX),
+            h: i32::try_from(height).unwrap_or(i32::MAX),
         }
     }
 }
@@ -221,60 +876,671 @@ struct BlitOps {
     dst_blit: BlitRect, // Rectangle to blit to within dst rectangle
 }
 
-fn blit<T>(src: &Vec<T>, dst: &mut Vec<T>, ops: &BlitOps)
+/// Options that change how an image is blitted.
+#[derive(Debug, Clone, Copy)]
+pub struct BlitOptions {
+    pub(crate) key: Option<u32>,
+    pub(crate) region: Option<Rect>,
+    pub(crate) flip_x: bool,
+    pub(crate) flip_y: bool,
+    pub(crate) rotation: Rotation,
+    pub(crate) scale: (u32, u32),
+    pub(crate) blend: Option<u8>,
+}
+
+impl BlitOptions {
+    pub fn new() -> Self {
+        BlitOptions::default()
+    }
+
+    /// Skip the cells whose glyph word is `key`, such as `b' ' as u32`, or
+    /// whose paper colour has an alpha of 0.  Nothing in the destination is
+    /// changed under a skipped cell.
+    pub fn with_key(mut self, key: u32) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Only blit this part of the source image.  By default, all of it is
+    /// blitted.
+    pub fn with_region(mut self, region: Rect) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Mirror the source left to right.  The cells are moved, but the glyphs
+    /// themselves are drawn as normal.
+    pub fn with_flip_x(mut self, flip_x: bool) -> Self {
+        self.flip_x = flip_x;
+        self
+    }
+
+    /// Mirror the source top to bottom.  The cells are moved, but the glyphs
+    /// themselves are drawn as normal.
+    pub fn with_flip_y(mut self, flip_y: bool) -> Self {
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Rotate the source clockwise, after any flips.  The area it covers in
+    /// the destination is turned too, so a 90 or 270 degree rotation swaps
+    /// its width and height.
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Draw each source cell as a block of `x` by `y` identical cells, after
+    /// any rotation.  The default is 1 by 1.
+    pub fn with_scale(mut self, x: u32, y: u32) -> Self {
+        self.scale = (x, y);
+        self
+    }
+
+    /// Draw the ink and paper colours over the destination's using their
+    /// alpha, rather than replacing them.  The source glyph replaces the
+    /// destination's only where the source paper's alpha is at least
+    /// `glyph_alpha`, so a translucent overlay leaves the glyphs under it
+    /// showing.
+    pub fn with_blend(mut self, glyph_alpha: u8) -> Self {
+        self.blend = Some(glyph_alpha);
+        self
+    }
+}
+
+impl Default for BlitOptions {
+    fn default() -> Self {
+        BlitOptions {
+            key: None,
+            region: None,
+            flip_x: false,
+            flip_y: false,
+            rotation: Rotation::None,
+            scale: (1, 1),
+            blend: None,
+        }
+    }
+}
+
+/// A clockwise rotation for blitting.  The cells are moved, but the glyphs
+/// themselves are drawn upright.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+/// One axis of the destination of a blit after clipping.  `count` source
+/// cells from `src` are drawn, each `scale` cells long, and mirrored if `flip`
+/// is true.  The first `skip` destination cells were clipped off, and the
+/// remaining `len` cells start at `dst`.
+struct BlitSpan {
+    src: usize,
+    count: usize,
+    skip: usize,
+    dst: usize,
+    len: usize,
+    flip: bool,
+    scale: usize,
+}
+
+impl BlitSpan {
+    /// The source position copied to the `i`th position of the span.
+    fn src_at(&self, i: usize) -> usize {
+        let n = (self.skip + i) / self.scale;
+        if self.flip {
+            self.src + self.count - 1 - n
+        } else {
+            self.src + n
+        }
+    }
+}
+
+/// The part of a blit left after clipping, along the destination's axes.  If
+/// `transpose` is true, the destination's columns come from the source's rows
+/// and its rows from the source's columns.
+struct BlitArea {
+    x: BlitSpan,
+    y: BlitSpan,
+    transpose: bool,
+}
+
+impl BlitArea {
+    /// The source position copied to position (`x`, `y`) of the area.
+    fn src_pos(&self, x: usize, y: usize) -> (usize, usize) {
+        if self.transpose {
+            (self.y.src_at(y), self.x.src_at(x))
+        } else {
+            (self.x.src_at(x), self.y.src_at(y))
+        }
+    }
+}
+
+impl BlitOps {
+    fn clip(&self, options: &BlitOptions) -> Option<BlitArea> {
+        // Rotations are made by swapping the axes and then mirroring them.
+        // Flips in the options mirror the source before it is rotated.
+        let (flip_x, flip_y) = (options.flip_x, options.flip_y);
+        let (transpose, flip_x, flip_y) = match options.rotation {
+            Rotation::None => (false, flip_x, flip_y),
+            Rotation::Cw90 => (true, !flip_y, flip_x),
+            Rotation::Cw180 => (false, !flip_x, !flip_y),
+            Rotation::Cw270 => (true, flip_y, !flip_x),
+        };
+
+        let src_x = (self.src_blit.x, self.src_blit.w, self.src.w);
+        let src_y = (self.src_blit.y, self.src_blit.h, self.src.h);
+        let (src_x, src_y) = if transpose {
+            (src_y, src_x)
+        } else {
+            (src_x, src_y)
+        };
+        let (scale_x, scale_y) = options.scale;
+
+        Some(BlitArea {
+            x: clip_span(
+                src_x,
+                (self.dst_blit.x, self.dst_blit.w, self.dst.w),
+                flip_x,
+                scale_x,
+            )?,
+            y: clip_span(
+                src_y,
+                (self.dst_blit.y, self.dst_blit.h, self.dst.h),
+                flip_y,
+                scale_y,
+            )?,
+            transpose,
+        })
+    }
+}
+
+/// Blit `src` to `dst`.  If there is a `map`, each ink and paper colour is
+/// passed through it before being drawn.
+fn blit(
+    src: &Image,
+    dst: &mut impl Canvas,
+    ops: &BlitOps,
+    options: &BlitOptions,
+    map: Option<&dyn Fn(u32) -> u32>,
+) {
+    let area = match ops.clip(options) {
+        Some(area) => area,
+        None => return,
+    };
+    dst.mark_dirty(Rect::new(
+        area.x.dst as i32,
+        area.y.dst as i32,
+        area.x.len,
+        area.y.len,
+    ));
+    let src_planes = src.planes();
+    let (dst_fore, dst_back, dst_text) = dst.planes_mut();
+
+    // Whole rows of the source can be copied when they are not rearranged
+    // or tested cell by cell.
+    let rows_copyable = options.key.is_none()
+        && map.is_none()
+        && !area.transpose
+        && !area.x.flip
+        && area.x.scale == 1;
+
+    if rows_copyable && options.blend.is_none() {
+        copy_rows(src_planes.0, dst_fore, ops, &area);
+        copy_rows(src_planes.1, dst_back, ops, &area);
+        copy_rows(src_planes.2, dst_text, ops, &area);
+        return;
+    }
+
+    for y in 0..area.y.len {
+        let di = (area.y.dst + y) * ops.dst.w as usize + area.x.dst;
+
+        if rows_copyable {
+            // Blending an opaque row is the same as copying it.
+            let si = area.y.src_at(y) * ops.src.w as usize + area.x.src_at(0);
+            let len = area.x.len;
+            let opaque = |plane: &[u32]| plane[si..si + len].iter().all(|&c| alpha_of(c) == 255);
+            if opaque(src_planes.0) && opaque(src_planes.1) {
+                dst_fore[di..di + len].copy_from_slice(&src_planes.0[si..si + len]);
+                dst_back[di..di + len].copy_from_slice(&src_planes.1[si..si + len]);
+                dst_text[di..di + len].copy_from_slice(&src_planes.2[si..si + len]);
+                continue;
+            }
+        }
+
+        // Each cell has to be tested or moved on its own.
+        for x in 0..area.x.len {
+            let (sx, sy) = area.src_pos(x, y);
+            let (si, di) = (sy * ops.src.w as usize + sx, di + x);
+            let (mut ink, mut paper, glyph) =
+                (src_planes.0[si], src_planes.1[si], src_planes.2[si]);
+            if let Some(key) = options.key {
+                if glyph == key || alpha_of(paper) == 0 {
+                    continue;
+                }
+            }
+            if let Some(map) = map {
+                ink = map(ink);
+                paper = map(paper);
+            }
+            match options.blend {
+                None => {
+                    dst_fore[di] = ink;
+                    dst_back[di] = paper;
+                    dst_text[di] = glyph;
+                }
+                Some(glyph_alpha) => {
+                    dst_fore[di] = blend_over(ink, dst_fore[di]);
+                    dst_back[di] = blend_over(paper, dst_back[di]);
+                    if alpha_of(paper) >= glyph_alpha {
+                        dst_text[di] = glyph;
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn copy_rows<T>(src: &[T], dst: &mut [T], ops: &BlitOps, area: &BlitArea)
 where
     T: Copy,
 {
-    let mut sx = ops.src_blit.x;
-    let mut sy = ops.src_blit.y;
-    let mut sw = ops.src_blit.w;
-    let mut sh = ops.src_blit.h;
-    let mut dx = ops.dst_blit.x;
-    let mut dy = ops.dst_blit.y;
-    let mut dw = ops.dst_blit.w;
-    let mut dh = ops.dst_blit.h;
-
-    // If the source blit area is before the full area, we need to clip to the
-    // edge of the full area.
-    if sx < 0 {
-        sw += dx; // Shrink source
-        dw += dx; // Shrink destination
-        dx -= dx; // Shift the origin to the right
-        sx = 0;
-    }
-    // If the source blit area is over the right edge of the full area, we need
-    // to clip to the right edge of the full area.
-    if sx + sw > ops.src.w {
-        sw = ops.src.w - sx;
-    }
-    // Clip the source to the destination
-    let width = min(sw, dw);
-
-    // Now do the same for the Y axis
-    if sy < 0 {
-        sh += dy; // Shrink source
-        dh += dy; // Shrink destination
-        dy -= dy; // Shift the origin to the right
-        sy = 0;
-    }
-    if sy + sh > ops.src.h {
-        sh = ops.src.h - sy;
-    }
-    let height = min(sh, dh);
-
-    if width > 0 && height > 0 {
-        // Now we copy source into destination
-        let mut si = sy * ops.src.w + sx;
-        let mut di = dy * ops.dst.w + dx;
-
-        (0..height).for_each(|_| {
-            let src_slice = &src[si as usize..(si + width) as usize];
-            let dst_slice = &mut dst[di as usize..(di + width) as usize];
-
-            dst_slice.copy_from_slice(src_slice);
-
-            si += ops.src.w;
-            di += ops.dst.w;
+    let mut di = area.y.dst * ops.dst.w as usize + area.x.dst;
+
+    (0..area.y.len).for_each(|y| {
+        let si = area.y.src_at(y) * ops.src.w as usize + area.x.src_at(0);
+        let src_slice = &src[si..si + area.x.len];
+        let dst_slice = &mut dst[di..di + area.x.len];
+
+        dst_slice.copy_from_slice(src_slice);
+
+        di += ops.dst.w as usize;
+    });
+}
+
+/// Clip a span of a blit along one axis.  The source and destination are each
+/// given as the start and length of the blit area and the length of the full
+/// area.  Each source cell covers `scale` destination cells, and if `flip` is
+/// true, the source is mirrored within the destination area.  Returns None if
+/// nothing is copied.
+fn clip_span(
+    src: (i32, i32, i32),
+    dst: (i32, i32, i32),
+    flip: bool,
+    scale: u32,
+) -> Option<BlitSpan> {
+    // Work in i64 so that positions near the limits of an i32 cannot overflow.
+    let (sx, sw, src_full) = (src.0 as i64, src.1 as i64, src.2 as i64);
+    let (dx, dw, dst_full) = (dst.0 as i64, dst.1 as i64, dst.2 as i64);
+    let scale = scale as i64;
+
+    // The part of the source blit area that is inside the source, and where
+    // it lands in the destination.  The parts outside are not drawn, but
+    // the rest stays where it would have been.
+    let src_start = max(sx, 0);
+    let src_end = min(sx + sw, src_full);
+    let count = src_end - src_start;
+    if count <= 0 {
+        return None;
+    }
+    let offset = if flip {
+        sx + sw - src_end
+    } else {
+        src_start - sx
+    };
+
+    // A scale can push the cells beyond the range of an i64, but only past
+    // the far edge of the destination, so nothing would be drawn anyway.
+    let start = dx.checked_add(offset.checked_mul(scale)?)?;
+    let len = count.checked_mul(scale)?;
+
+    // Stop at the edges of both the destination blit area and the
+    // destination.
+    let dst_start = max(start, max(dx, 0));
+    let dst_end = min(start.saturating_add(len), min(dx + dw, dst_full));
+    if dst_end <= dst_start {
+        return None;
+    }
+
+    Some(BlitSpan {
+        src: src_start as usize,
+        count: count as usize,
+        skip: (dst_start - start) as usize,
+        dst: dst_start as usize,
+        len: (dst_end - dst_start) as usize,
+        flip,
+        scale: scale as usize,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{colour, Colour};
+
+    /// Draw on a window whose cells are all 7, and return it afterwards.
+    fn window(width: usize, height: usize, draw: impl FnOnce(&mut PresentInput)) -> Image {
+        let mut screen = Image::new(width, height);
+        screen.fore_image = vec![7; width * height];
+        screen.back_image = vec![7; width * height];
+        screen.text_image = vec![7; width * height];
+        screen.present(draw);
+        screen
+    }
+
+    /// A 3x2 image with the glyphs 1 to 6 on opaque paper.
+    fn sprite() -> Image {
+        let mut image = Image::new(3, 2);
+        image.fore_image = vec![0xff00_0001; 6];
+        image.back_image = vec![0xff00_0002; 6];
+        image.text_image = vec![1, 2, 3, 4, 5, 6];
+        image
+    }
+
+    /// The glyphs of a row of an image as a string.
+    fn row(image: &Image, y: usize) -> String {
+        image.text_image[y * image.width..(y + 1) * image.width]
+            .iter()
+            .map(|&c| if c == 0 { '.' } else { c as u8 as char })
+            .collect()
+    }
+
+    #[test]
+    fn draw_string_clips_to_each_edge() {
+        let mut image = Image::new(6, 3);
+        image.draw_string(Point::new(-3, 0), "abcdefg", 1u32, 2u32);
+        assert_eq!(row(&image, 0), "defg..");
+        image.draw_string(Point::new(4, 1), "xyz", 1u32, 2u32);
+        assert_eq!(row(&image, 1), "....xy");
+        assert_eq!(image.fore_image[6 + 3], 0);
+        assert_eq!(image.fore_image[6 + 4], 1);
+        assert_eq!(image.back_image[6 + 5], 2);
+
+        // Strings above, below, or wholly to either side draw nothing.
+        image.draw_string(Point::new(0, -1), "nope", 1u32, 2u32);
+        image.draw_string(Point::new(0, 3), "nope", 1u32, 2u32);
+        image.draw_string(Point::new(-4, 2), "nope", 1u32, 2u32);
+        image.draw_string(Point::new(6, 2), "nope", 1u32, 2u32);
+        image.draw_string(Point::new(i32::MIN, 2), "nope", 1u32, 2u32);
+        image.draw_string(Point::new(i32::MAX, 2), "nope", 1u32, 2u32);
+        image.draw_string(Point::new(1, 2), "", 1u32, 2u32);
+        assert_eq!(row(&image, 2), "......");
+        assert!(image.fore_image[12..].iter().all(|&c| c == 0));
+    }
+
+    #[test]
+    fn blit_clips_to_each_edge() {
+        let blit = |x: i32, y: i32| {
+            window(5, 4, |input| input.blit(Point::new(x, y), 5, 4, &sprite())).text_image
+        };
+        #[rustfmt::skip]
+        assert_eq!(blit(1, 0), vec![
+            7, 1, 2, 3, 7,
+            7, 4, 5, 6, 7,
+            7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7,
+        ]);
+        // Off the left and top edges.
+        #[rustfmt::skip]
+        assert_eq!(blit(-2, -1), vec![
+            6, 7, 7, 7, 7,
+            7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7,
+        ]);
+        // Off the right and bottom edges.
+        #[rustfmt::skip]
+        assert_eq!(blit(3, 3), vec![
+            7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7,
+            7, 7, 7, 7, 7,
+            7, 7, 7, 1, 2,
+        ]);
+        // The destination area clips too.
+        let clipped = window(5, 4, |input| input.blit(Point::new(0, 0), 2, 1, &sprite()));
+        assert_eq!(&clipped.text_image[..5], &[1, 2, 7, 7, 7]);
+        assert!(clipped.text_image[5..].iter().all(|&c| c == 7));
+    }
+
+    #[test]
+    fn blit_wholly_outside_draws_nothing() {
+        let positions = [
+            (5, 0),
+            (0, 4),
+            (-3, 0),
+            (0, -2),
+            (i32::MAX, 0),
+            (0, i32::MAX),
+            (i32::MIN, i32::MIN),
+        ];
+        for &(x, y) in &positions {
+            let screen = window(5, 4, |input| input.blit(Point::new(x, y), 5, 4, &sprite()));
+            assert!(screen.text_image.iter().all(|&c| c == 7), "({}, {})", x, y);
+            assert!(screen.fore_image.iter().all(|&c| c == 7), "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn blit_keyed_skips_key_and_transparent_cells() {
+        let mut image = sprite();
+        image.text_image[1] = b' ' as u32;
+        image.back_image[4] = 0x00ff_ffff;
+        let screen = window(4, 3, |input| {
+            input.blit_keyed(Point::new(1, 1), &image, b' ' as u32)
+        });
+        #[rustfmt::skip]
+        assert_eq!(screen.text_image, vec![
+            7, 7, 7, 7,
+            7, 1, 7, 3,
+            7, 4, 7, 6,
+        ]);
+        // Nothing at all is written under a skipped cell.
+        assert_eq!(screen.fore_image[4 + 2], 7);
+        assert_eq!(screen.back_image[8 + 2], 7);
+        assert_eq!(screen.back_image[8 + 1], 0xff00_0002);
+
+        // Keyed blits clip like any other.
+        let screen = window(4, 3, |input| {
+            input.blit_keyed(Point::new(-2, 2), &image, b' ' as u32)
+        });
+        assert_eq!(&screen.text_image[8..], &[3, 7, 7, 7]);
+    }
+
+    #[test]
+    fn blit_region_keeps_clipped_parts_in_place() {
+        let blit = |p: Point, region: Rect| {
+            window(4, 3, |input| input.blit_region(p, &sprite(), region)).text_image
+        };
+        #[rustfmt::skip]
+        assert_eq!(blit(Point::new(0, 0), Rect::new(1, 0, 2, 2)), vec![
+            2, 3, 7, 7,
+            5, 6, 7, 7,
+            7, 7, 7, 7,
+        ]);
+        // Partly off the top left of the image, the part inside stays where
+        // it would have been.
+        #[rustfmt::skip]
+        assert_eq!(blit(Point::new(1, 1), Rect::new(-1, -1, 2, 2)), vec![
+            7, 7, 7, 7,
+            7, 7, 7, 7,
+            7, 7, 1, 7,
+        ]);
+        // Partly off the bottom right of the image.
+        #[rustfmt::skip]
+        assert_eq!(blit(Point::new(0, 0), Rect::new(2, 1, 5, 5)), vec![
+            6, 7, 7, 7,
+            7, 7, 7, 7,
+            7, 7, 7, 7,
+        ]);
+        #[rustfmt::skip]
+        assert_eq!(blit(Point::new(0, 0), Rect::new(0, 0, usize::MAX, usize::MAX)), vec![
+            1, 2, 3, 7,
+            4, 5, 6, 7,
+            7, 7, 7, 7,
+        ]);
+        // Empty regions and regions outside the image draw nothing.
+        assert_eq!(blit(Point::new(0, 0), Rect::new(0, 0, 0, 2)), vec![7; 12]);
+        assert_eq!(blit(Point::new(0, 0), Rect::new(3, 0, 2, 2)), vec![7; 12]);
+        assert_eq!(blit(Point::new(0, 0), Rect::new(-2, 0, 2, 2)), vec![7; 12]);
+    }
+
+    #[test]
+    fn blit_rotated_matches_expected_images() {
+        // Give every cell its own colours, to check that all three planes
+        // are moved together.
+        let paint = |c: char| Char::new(c as u8, 0xff00_0000 | c as u32, 0xff00_0100 | c as u32);
+        let image = Image::from_strings_mapped(&["abc", "def"], paint);
+        let rotated = |rotation: Rotation, width: usize, height: usize| {
+            window(width, height, |input| {
+                input.blit_rotated(Point::new(0, 0), &image, rotation)
+            })
+        };
+        let expected = |rows: &[&str]| Image::from_strings_mapped(rows, paint);
+
+        assert_images_eq!(rotated(Rotation::None, 3, 2), image);
+        assert_images_eq!(rotated(Rotation::Cw90, 2, 3), expected(&["da", "eb", "fc"]));
+        assert_images_eq!(rotated(Rotation::Cw180, 3, 2), expected(&["fed", "cba"]));
+        assert_images_eq!(
+            rotated(Rotation::Cw270, 2, 3),
+            expected(&["cf", "be", "ad"])
+        );
+
+        // Clipping is worked out after the footprint is turned.
+        let clipped = window(2, 2, |input| {
+            input.blit_rotated(Point::new(-1, 1), &image, Rotation::Cw90)
+        });
+        assert_eq!(clipped.text_image, vec![7, 7, b'a' as u32, 7]);
+    }
+
+    #[test]
+    fn blit_rotated_empty_images_draw_nothing() {
+        for &(width, height) in &[(0, 0), (0, 3), (3, 0)] {
+            for &rotation in &[
+                Rotation::None,
+                Rotation::Cw90,
+                Rotation::Cw180,
+                Rotation::Cw270,
+            ] {
+                let screen = window(4, 3, |input| {
+                    input.blit_rotated(Point::new(1, 1), &Image::new(width, height), rotation)
+                });
+                assert!(screen.text_image.iter().all(|&c| c == 7));
+            }
+        }
+    }
+
+    #[test]
+    fn blit_remap_replaces_both_colours() {
+        let (red, blue, green) = (colour(255, 0, 0), colour(0, 0, 255), colour(0, 255, 0));
+        let yellow = Colour::Yellow.to_u32();
+        let mut image = Image::new(2, 1);
+        image.fore_image = vec![red, blue];
+        image.back_image = vec![blue, red];
+        image.text_image = vec![1, 2];
+        let map = ColourMap::new().map(red, green).map(blue, Colour::Yellow);
+
+        let screen = window(3, 1, |input| {
+            input.blit_remap(Point::new(1, 0), &image, &map)
         });
+        assert_eq!(screen.fore_image, vec![7, green, yellow]);
+        assert_eq!(screen.back_image, vec![7, yellow, green]);
+        assert_eq!(screen.text_image, vec![7, 1, 2]);
+
+        // Colours that are not in the map pass through.
+        image.back_image[1] = colour(1, 2, 3);
+        let screen = window(3, 1, |input| {
+            input.blit_remap(Point::new(1, 0), &image, &map)
+        });
+        assert_eq!(screen.back_image, vec![7, yellow, colour(1, 2, 3)]);
+    }
+
+    #[test]
+    fn describe_differences_counts_each_plane() {
+        let a = Image::from_strings(&["ab", "cd", "ef"], 1u32, 2u32);
+        let mut b = a.clone();
+        assert_eq!(a.describe_differences(&b), None);
+        b.set_ink(Point::new(1, 1), 5u32);
+        b.set_glyph(Point::new(0, 2), b'X');
+        assert_eq!(
+            a.describe_differences(&b).unwrap(),
+            "2 cells differ (glyphs in 1, ink in 1, paper in 0), first at (1, 1)"
+        );
+        b.set_paper(Point::new(1, 1), 6u32);
+        assert_eq!(
+            a.describe_differences(&b).unwrap(),
+            "2 cells differ (glyphs in 1, ink in 1, paper in 1), first at (1, 1)"
+        );
+        assert_eq!(
+            a.describe_differences(&Image::new(3, 1)).unwrap(),
+            "sizes differ: 2x3 and 3x1"
+        );
+    }
+
+    /// The message that an `assert_images_eq!` panicked with.
+    fn panic_message(assert: impl FnOnce() + std::panic::UnwindSafe) -> String {
+        let payload = std::panic::catch_unwind(assert).unwrap_err();
+        payload.downcast_ref::<String>().unwrap().clone()
+    }
+
+    #[test]
+    fn assert_images_eq_shows_both_images() {
+        let a = Image::from_strings(&["ab", "cd", "ef"], 1u32, 2u32);
+        let mut b = a.clone();
+        assert_images_eq!(a, b);
+        b.set_ink(Point::new(1, 1), 5u32);
+        b.set_glyph(Point::new(0, 2), b'X');
+
+        let (left, right) = (a.clone(), b.clone());
+        assert_eq!(
+            panic_message(move || assert_images_eq!(left, right)),
+            concat!(
+                "assertion failed: `(left == right)`\n",
+                "2 cells differ (glyphs in 1, ink in 1, paper in 0), first at (1, 1)\n",
+                "   left   right\n",
+                "  |ab  | |ab|\n",
+                "> |cd  | |cd|\n",
+                "> |ef  | |Xf|\n",
+            )
+        );
+        let (left, right) = (a.clone(), b);
+        assert!(
+            panic_message(move || assert_images_eq!(left, right, "frame {}", 3))
+                .starts_with("assertion failed: `(left == right)`: frame 3\n2 cells differ")
+        );
+        assert!(
+            panic_message(move || assert_images_eq!(a, Image::new(3, 1)))
+                .contains("sizes differ: 2x3 and 3x1")
+        );
+    }
+
+    #[test]
+    fn from_strings_builds_a_smiley() {
+        // The short middle row is padded with a space.
+        let smiley = Image::from_strings(&["o o", " >", "\\_/"], 1u32, 2u32);
+        assert_eq!((smiley.width, smiley.height), (3, 3));
+        let glyphs = concat!("o o", " > ", "\\_/")
+            .bytes()
+            .map(u32::from)
+            .collect::<Vec<_>>();
+        assert_eq!(smiley.text_image, glyphs);
+        assert_eq!(smiley.fore_image, vec![1; 9]);
+        assert_eq!(smiley.back_image, vec![2; 9]);
+
+        // Each character can have its own colours, and characters outside
+        // ASCII use their code page 437 glyphs.
+        let smiley =
+            Image::from_strings_mapped(&["\u{263a} \u{263a}", " \u{20ac}", "\\_/"], |c| match c {
+                '\u{263a}' => Char::new(1, 3u32, 4u32),
+                '\u{20ac}' => Char::new(b'?', 5u32, 6u32),
+                c => Char::new(c as u8, 1u32, 2u32),
+            });
+        assert_eq!(smiley.text_image, vec![1, 32, 1, 32, 63, 32, 92, 95, 47]);
+        assert_eq!(smiley.fore_image, vec![3, 1, 3, 1, 5, 1, 1, 1, 1]);
+        assert_eq!(smiley.back_image, vec![4, 2, 4, 2, 6, 2, 2, 2, 2]);
+        let smiley = Image::from_strings(&["\u{263a}\u{20ac}"], 1u32, 2u32);
+        assert_eq!(smiley.text_image, vec![1, b'?' as u32]);
     }
 }
@@ -0,0 +1,187 @@
+//
+// Progress bars
+// Health, loading and experience bars, with partly filled cells for smooth
+// movement
+//
+
+use std::convert::TryFrom;
+
+use crate::{charmap::cp437_glyph, Canvas, Point, Rect};
+
+/// How `Canvas::draw_progress` draws a bar.
+///
+/// Filled cells are drawn as a solid block and empty cells as a light shade,
+/// both in the fill colour on the back colour.  The cell where the bar ends
+/// can be partly filled with glyphs such as ▌, so that the bar moves in
+/// steps smaller than a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BarStyle {
+    pub(crate) fill: u32,
+    pub(crate) back: u32,
+    pub(crate) filled_glyph: u8,
+    pub(crate) empty_glyph: u8,
+    pub(crate) partial_glyphs: Vec<u8>,
+}
+
+impl BarStyle {
+    /// Create a style for a bar in the fill colour on the back colour, with
+    /// half-filled cells drawn as ▌.
+    pub fn new(fill: impl Into<u32>, back: impl Into<u32>) -> Self {
+        BarStyle {
+            fill: fill.into(),
+            back: back.into(),
+            filled_glyph: 0xdb,
+            empty_glyph: 0xb0,
+            partial_glyphs: vec![0xdd],
+        }
+    }
+
+    /// Draw filled and empty cells with these glyphs, rather than █ and ░.
+    pub fn with_glyphs(mut self, filled: u8, empty: u8) -> Self {
+        self.filled_glyph = filled;
+        self.empty_glyph = empty;
+        self
+    }
+
+    /// Draw partly filled cells with these glyphs, from the emptiest to the
+    /// fullest, such as the seven glyphs from ▏ to ▉ in fonts that have them.
+    /// With n glyphs, the bar moves in steps of 1/(n + 1) of a cell, and with
+    /// none it moves a whole cell at a time.
+    pub fn with_partial_glyphs(mut self, glyphs: &[u8]) -> Self {
+        self.partial_glyphs = glyphs.to_vec();
+        self
+    }
+}
+
+/// Draw a progress bar into a rectangle, with an optional label in the centre
+/// of its middle row.
+pub(crate) fn draw_progress<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    rect: Rect,
+    fraction: f32,
+    label: Option<&str>,
+    style: &BarStyle,
+) {
+    // Each cell is split into steps, one for each partial glyph and one for
+    // the full cell.
+    let steps = style.partial_glyphs.len() as u64 + 1;
+    let fraction = if fraction.is_nan() {
+        0.0
+    } else {
+        fraction.clamp(0.0, 1.0) as f64
+    };
+    let filled = (fraction * rect.width as f64 * steps as f64).round() as u64;
+    let steps_in = |column: usize| {
+        filled
+            .saturating_sub((column as u64).saturating_mul(steps))
+            .min(steps)
+    };
+
+    if let Some((x, y, w, h)) = canvas.clip(rect) {
+        let skip = (x as i64 - rect.x as i64) as usize;
+        let glyphs = (skip..skip + w)
+            .map(|column| match steps_in(column) {
+                0 => style.empty_glyph,
+                n if n == steps => style.filled_glyph,
+                n => style.partial_glyphs[n as usize - 1],
+            })
+            .collect::<Vec<_>>();
+        canvas.for_each_row(Rect::new(x as i32, y as i32, w, h), |fore, back, text| {
+            fore.fill(style.fill);
+            back.fill(style.back);
+            text.iter_mut()
+                .zip(&glyphs)
+                .for_each(|(text, &glyph)| *text = glyph as u32);
+        });
+    }
+
+    // The label is drawn in the back colour on the fill colour where the bar
+    // is mostly filled, and the other way round elsewhere, so that it can be
+    // read anywhere and the bar's shape is kept.
+    let label = match label {
+        Some(label) if rect.height > 0 => label,
+        _ => return,
+    };
+    let len = label.chars().count().min(rect.width);
+    let start = (rect.width - len) / 2;
+    let middle = i64::try_from((rect.height - 1) / 2).unwrap_or(i64::MAX);
+    let y = (rect.y as i64).saturating_add(middle);
+    for (i, ch) in label.chars().take(len).enumerate() {
+        let column = start + i;
+        let x = (rect.x as i64).saturating_add(column as i64);
+        let (ink, paper) = if steps_in(column) * 2 >= steps {
+            (style.back, style.fill)
+        } else {
+            (style.fill, style.back)
+        };
+        // Cells beyond the range of an i32 are off the canvas anyway.
+        if let (Ok(x), Ok(y)) = (i32::try_from(x), i32::try_from(y)) {
+            if let Some(i) = canvas.index_of(Point::new(x, y)) {
+                canvas.mark_dirty(Rect::new(x, y, 1, 1));
+                let (fore, back, text) = canvas.planes_mut();
+                fore[i] = ink;
+                back[i] = paper;
+                text[i] = cp437_glyph(ch).unwrap_or(b'?') as u32;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BarStyle, Canvas, Image, Rect};
+
+    /// Draw a 4x1 bar and return its glyphs.
+    fn bar(fraction: f32, style: &BarStyle) -> Vec<u32> {
+        let mut image = Image::new(4, 1);
+        image.draw_progress(Rect::new(0, 0, 4, 1), fraction, None, style);
+        image.text_image
+    }
+
+    #[test]
+    fn empty_and_full_bars() {
+        let style = BarStyle::new(1u32, 2u32);
+        assert_eq!(bar(0.0, &style), vec![0xb0; 4]);
+        assert_eq!(bar(1.0, &style), vec![0xdb; 4]);
+        // Fractions outside 0 to 1 are clamped, and NaN is empty.
+        assert_eq!(bar(-1.0, &style), vec![0xb0; 4]);
+        assert_eq!(bar(2.0, &style), vec![0xdb; 4]);
+        assert_eq!(bar(f32::NAN, &style), vec![0xb0; 4]);
+
+        let mut image = Image::new(4, 1);
+        image.draw_progress(Rect::new(0, 0, 4, 1), 0.0, None, &style);
+        assert_eq!(
+            (image.fore_image, image.back_image),
+            (vec![1; 4], vec![2; 4])
+        );
+    }
+
+    #[test]
+    fn bars_ending_partway_through_a_cell() {
+        // 3/8 of four cells is one and a half cells.
+        let style = BarStyle::new(1u32, 2u32);
+        assert_eq!(bar(0.375, &style), vec![0xdb, 0xdd, 0xb0, 0xb0]);
+
+        // With seven partial glyphs, 0.3 of four cells is 1.2 cells, which is
+        // rounded to 1 and 2/8.
+        let style = style.with_partial_glyphs(&[1, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(bar(0.3, &style), vec![0xdb, 2, 0xb0, 0xb0]);
+
+        // With none, the bar moves a whole cell at a time.
+        let style = BarStyle::new(1u32, 2u32).with_partial_glyphs(&[]);
+        assert_eq!(bar(0.375, &style), vec![0xdb, 0xdb, 0xb0, 0xb0]);
+    }
+
+    #[test]
+    fn labels_swap_colours_over_the_fill() {
+        let mut image = Image::new(4, 3);
+        let style = BarStyle::new(1u32, 2u32);
+        image.draw_progress(Rect::new(0, 0, 4, 3), 0.5, Some("ab"), &style);
+        assert_eq!(
+            image.to_plain_text().lines().nth(1),
+            Some("\u{2588}ab\u{2591}")
+        );
+        assert_eq!(&image.fore_image[4..8], &[1, 2, 1, 1]);
+        assert_eq!(&image.back_image[4..8], &[2, 1, 2, 2]);
+    }
+}
@@ -0,0 +1,196 @@
+//
+// PSF (Linux console font) loading
+//
+
+use crate::{Charmap, FontData, FontError, Result};
+
+/// Load a PC Screen Font (PSF version 1 or 2), as used by the Linux console,
+/// and generate a FontData structure.
+///
+/// # Notes
+///
+/// If the font contains a Unicode table, it is used to place the characters in
+/// code page 437 order.  Otherwise the first 256 characters are used as they
+/// are.  Use `load_font_psf_with_charmap` to choose a different order.
+pub fn load_font_psf(data: &[u8]) -> Result<FontData> {
+    load_font_psf_with_charmap(data, &Charmap::cp437())
+}
+
+/// Load a PC Screen Font (PSF version 1 or 2), choosing which characters to use
+/// with a character map, and generate a FontData structure.
+///
+/// # Notes
+///
+/// If the font contains a Unicode table, each of the 256 characters is the one
+/// the table maps to the character map's character, or blank if the font does
+/// not have one.  Fonts without a Unicode table ignore the character map and
+/// use their first 256 characters.
+pub fn load_font_psf_with_charmap(data: &[u8], charmap: &Charmap) -> Result<FontData> {
+    let psf = Psf::parse(data).map_err(|message| FontError::Malformed(message.to_string()))?;
+    let bytes_per_row = psf.width.div_ceil(8) as usize;
+
+    // Work out which of the font's characters goes in each of the 256 slots.
+    let glyphs = (0..=255u8)
+        .map(|slot| match &psf.unicode {
+            Some(unicode) => {
+                let ch = charmap.char_for(slot);
+                unicode.iter().position(|chars| chars.contains(&ch))
+            }
+            None if (slot as usize) < psf.count => Some(slot as usize),
+            None => None,
+        })
+        .collect::<Vec<_>>();
+
+    FontData::check_size(psf.width, psf.height, 16, 16)?;
+    Ok(FontData::from_fn(psf.width, psf.height, |slot, x, y| {
+        let lit = glyphs[slot as usize].is_some_and(|glyph| {
+            let offset = glyph * psf.glyph_size + y as usize * bytes_per_row + x as usize / 8;
+            psf.glyphs[offset] & (0x80 >> (x % 8)) != 0
+        });
+        if lit {
+            0xffffffff
+        } else {
+            0xff000000
+        }
+    }))
+}
+
+/// The parts of a PSF file needed to build a font.
+struct Psf<'a> {
+    width: u32,
+    height: u32,
+    /// The number of characters in the font.
+    count: usize,
+    /// The number of bytes used by each character's bitmap.
+    glyph_size: usize,
+    /// The bitmaps of all the characters.
+    glyphs: &'a [u8],
+    /// The Unicode characters that each character represents, if the font has a
+    /// Unicode table.
+    unicode: Option<Vec<Vec<char>>>,
+}
+
+impl<'a> Psf<'a> {
+    fn parse(data: &'a [u8]) -> std::result::Result<Self, &'static str> {
+        if data.starts_with(&[0x36, 0x04]) {
+            Self::parse_v1(data)
+        } else if data.starts_with(&[0x72, 0xb5, 0x4a, 0x86]) {
+            Self::parse_v2(data)
+        } else {
+            Err("not a PSF file")
+        }
+    }
+
+    fn parse_v1(data: &'a [u8]) -> std::result::Result<Self, &'static str> {
+        let header = data.get(..4).ok_or("PSF1 header is truncated")?;
+        let mode = header[2];
+        let height = header[3] as usize;
+        if height == 0 {
+            return Err("PSF1 header has a character height of zero");
+        }
+        let count = if mode & 0x01 != 0 { 512 } else { 256 };
+        let glyphs = data
+            .get(4..4 + count * height)
+            .ok_or("PSF1 character bitmaps are truncated")?;
+
+        // The Unicode table has a list of little-endian UCS-2 values for each
+        // character, ended with 0xffff.  Multi-character sequences start with
+        // 0xfffe and are skipped.
+        let unicode = if mode & 0x06 != 0 {
+            let table = &data[4 + count * height..];
+            let values = table
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+            let mut entries = vec![vec![]; count];
+            let mut glyph = 0;
+            let mut in_sequence = false;
+            for value in values {
+                if glyph == count {
+                    break;
+                }
+                match value {
+                    0xffff => {
+                        glyph += 1;
+                        in_sequence = false;
+                    }
+                    0xfffe => in_sequence = true,
+                    _ if !in_sequence => {
+                        entries[glyph].extend(std::char::from_u32(value as u32));
+                    }
+                    _ => {}
+                }
+            }
+            Some(entries)
+        } else {
+            None
+        };
+
+        Ok(Psf {
+            width: 8,
+            height: height as u32,
+            count,
+            glyph_size: height,
+            glyphs,
+            unicode,
+        })
+    }
+
+    fn parse_v2(data: &'a [u8]) -> std::result::Result<Self, &'static str> {
+        let header = data.get(..32).ok_or("PSF2 header is truncated")?;
+        let field = |i: usize| {
+            u32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]) as usize
+        };
+        let header_size = field(8);
+        let flags = field(12);
+        let count = field(16);
+        let glyph_size = field(20);
+        let height = field(24);
+        let width = field(28);
+        if width == 0 || height == 0 {
+            return Err("PSF2 header has a zero character size");
+        }
+        if count == 0 {
+            return Err("PSF2 header has no characters");
+        }
+        if glyph_size < height * width.div_ceil(8) {
+            return Err("PSF2 header's character size is too small for its dimensions");
+        }
+        let glyphs_end = count
+            .checked_mul(glyph_size)
+            .and_then(|size| size.checked_add(header_size))
+            .ok_or("PSF2 header sizes are too large")?;
+        let glyphs = data
+            .get(header_size..glyphs_end)
+            .ok_or("PSF2 character bitmaps are truncated")?;
+
+        // The Unicode table has a list of UTF-8 characters for each character,
+        // ended with 0xff.  Multi-character sequences start with 0xfe and are
+        // skipped.
+        let unicode = if flags & 0x01 != 0 {
+            let table = &data[glyphs_end..];
+            let entries = table
+                .split(|&byte| byte == 0xff)
+                .take(count)
+                .map(|entry| {
+                    let singles = entry.split(|&byte| byte == 0xfe).next().unwrap_or(&[]);
+                    String::from_utf8_lossy(singles)
+                        .chars()
+                        .filter(|&ch| ch != std::char::REPLACEMENT_CHARACTER)
+                        .collect()
+                })
+                .collect();
+            Some(entries)
+        } else {
+            None
+        };
+
+        Ok(Psf {
+            width: width as u32,
+            height: height as u32,
+            count,
+            glyph_size,
+            glyphs,
+            unicode,
+        })
+    }
+}
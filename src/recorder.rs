@@ -0,0 +1,296 @@
+//
+// Recorder
+// Captures the frames shown in the window to an animated GIF or a numbered
+// sequence of PNG files, encoded on a background thread
+//
+
+use std::{
+    convert::TryFrom,
+    fs::{self, File},
+    io::BufWriter,
+    panic,
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, SyncSender, TrySendError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+    Delay, DynamicImage, Frame, ImageError, ImageFormat, RgbImage, Rgba, RgbaImage,
+};
+
+use crate::{screenshot::render_image, Error, FontData, Image, Result};
+
+/// The number of captured frames that can wait to be encoded.  Frames
+/// captured while the queue is full are dropped, so the memory used stays the
+/// same however far behind the encoder falls.
+const QUEUE_LENGTH: usize = 8;
+
+/// How hard the GIF encoder works to choose each frame's palette, from 1 for
+/// the best colours to 30 for the quickest.
+const GIF_SPEED: i32 = 10;
+
+/// The shortest time, in hundredths of a second, that a GIF frame is shown
+/// for.  Many viewers slow down frames with shorter delays, so quicker frames
+/// are merged into the frame after them.
+const MIN_GIF_DELAY: u128 = 2;
+
+/// The kind of file a recording is saved as, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordingFormat {
+    /// An animated GIF that loops forever, with each frame shown for as long
+    /// as it was in the window.  The colours of each frame are reduced to a
+    /// palette of 256.
+    Gif(PathBuf),
+    /// A directory of PNG files, one for each frame, named `frame_00000.png`,
+    /// `frame_00001.png` and so on.  The directory is created if it does not
+    /// exist.
+    PngSequence(PathBuf),
+}
+
+/// Settings for recording the window, started with
+/// `Commands::start_recording`.
+///
+/// Each frame is drawn on the CPU with the font in use when the recording
+/// starts, in the same way as `render_image_to_png`.  Frames are encoded on a
+/// background thread, and any that arrive while it is behind are dropped
+/// rather than slowing down the window.
+#[derive(Debug, Clone)]
+pub struct Recording {
+    format: RecordingFormat,
+    duration: Option<Duration>,
+    downscale: u32,
+}
+
+impl Recording {
+    /// Record to an animated GIF file.
+    pub fn gif(path: impl AsRef<Path>) -> Self {
+        Recording::new(RecordingFormat::Gif(path.as_ref().to_path_buf()))
+    }
+
+    /// Record to a PNG file for each frame in a directory.
+    pub fn png_sequence(dir: impl AsRef<Path>) -> Self {
+        Recording::new(RecordingFormat::PngSequence(dir.as_ref().to_path_buf()))
+    }
+
+    /// Record in a format, at full size, until `Commands::stop_recording`.
+    pub fn new(format: RecordingFormat) -> Self {
+        Recording {
+            format,
+            duration: None,
+            downscale: 1,
+        }
+    }
+
+    /// Stop recording by itself after this long.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Shrink each frame by this factor, such as 2 for half the width and
+    /// height, to make smaller files.  A factor of 0 or 1 keeps the frames at
+    /// full size.
+    pub fn with_downscale(mut self, downscale: u32) -> Self {
+        self.downscale = downscale.max(1);
+        self
+    }
+}
+
+/// How a finished recording went, given to `App::on_recording_finished`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingSummary {
+    /// The number of frames saved.  Frames shown too briefly for a GIF are
+    /// merged into the next, so this can be less than the number captured.
+    pub frames: usize,
+    /// The number of frames dropped because the encoder was behind.
+    pub dropped: usize,
+}
+
+/// A message sent to the encoding thread.
+enum Message {
+    /// A frame, and when it was shown.
+    Frame(Image, Instant),
+    /// The end of the recording, and when it ended.
+    Stop(Instant),
+}
+
+/// A recording in progress, with the thread encoding its frames.
+pub(crate) struct Recorder {
+    sender: SyncSender<Message>,
+    thread: JoinHandle<Result<usize>>,
+    stop_at: Option<Instant>,
+    dropped: usize,
+}
+
+impl Recorder {
+    /// Start encoding the frames that are captured, drawn with a font.
+    pub(crate) fn start(recording: Recording, font: FontData) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_LENGTH);
+        let stop_at = recording.duration.map(|duration| Instant::now() + duration);
+        let thread = thread::spawn(move || encode(recording, &font, receiver));
+        Recorder {
+            sender,
+            thread,
+            stop_at,
+            dropped: 0,
+        }
+    }
+
+    /// Return true if the recording has run for as long as it was asked to.
+    pub(crate) fn is_over(&self) -> bool {
+        matches!(self.stop_at, Some(stop_at) if Instant::now() >= stop_at)
+    }
+
+    /// Queue a frame that has just been shown, or drop it if the encoder is
+    /// behind.
+    pub(crate) fn capture(&mut self, image: Image) {
+        match self.sender.try_send(Message::Frame(image, Instant::now())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => self.dropped += 1,
+            // The encoder has stopped with an error, which `finish` returns.
+            Err(TrySendError::Disconnected(_)) => {}
+        }
+    }
+
+    /// End the recording, waiting for the queued frames to be encoded.
+    pub(crate) fn finish(self) -> Result<RecordingSummary> {
+        // Unlike a frame, the stop message waits for room in the queue.  It
+        // can only fail if the encoder has already stopped with an error.
+        let _ = self.sender.send(Message::Stop(Instant::now()));
+        let frames = match self.thread.join() {
+            Ok(result) => result?,
+            Err(payload) => panic::resume_unwind(payload),
+        };
+        Ok(RecordingSummary {
+            frames,
+            dropped: self.dropped,
+        })
+    }
+}
+
+/// Encode the frames sent to a recorder until it stops, returning the number
+/// of frames saved.
+fn encode(recording: Recording, font: &FontData, receiver: Receiver<Message>) -> Result<usize> {
+    let mut output = Output::new(&recording.format)?;
+    let draw = |image: &Image| draw_frame(image, font, recording.downscale);
+
+    // Each frame is saved once the next one arrives, when it is known how
+    // long it was shown for.
+    let mut pending: Option<(Image, Instant)> = None;
+    let mut start = None;
+    let mut frames = 0;
+    // Receiving fails if the recorder is dropped without being finished.
+    while let Ok(message) = receiver.recv() {
+        let (next, time) = match message {
+            Message::Frame(image, time) => (Some(image), time),
+            Message::Stop(time) => (None, time),
+        };
+        // The times are rounded to hundredths of a second from the start, as
+        // GIF delays are, so that rounding errors do not build up.
+        let start = *start.get_or_insert(time);
+        let hundredths = |time: Instant| ((time - start).as_millis() + 5) / 10;
+
+        if let Some((image, shown)) = pending.take() {
+            let delay = hundredths(time) - hundredths(shown);
+            match next {
+                // A frame shown too briefly for a GIF is replaced by the next,
+                // which is shown from the same time.
+                Some(next) if output.is_gif() && delay < MIN_GIF_DELAY => {
+                    pending = Some((next, shown));
+                    continue;
+                }
+                _ => {
+                    output.write(draw(&image)?, delay)?;
+                    frames += 1;
+                }
+            }
+        }
+        match next {
+            Some(image) => pending = Some((image, time)),
+            None => break,
+        }
+    }
+    Ok(frames)
+}
+
+/// Draw a frame with a font, shrinking it by a factor.
+fn draw_frame(image: &Image, font: &FontData, downscale: u32) -> Result<RgbImage> {
+    let pixels = render_image(image, font)?;
+    if downscale == 1 {
+        return Ok(pixels);
+    }
+    let width = (pixels.width() / downscale).max(1);
+    let height = (pixels.height() / downscale).max(1);
+    Ok(imageops::resize(
+        &pixels,
+        width,
+        height,
+        FilterType::Triangle,
+    ))
+}
+
+/// Where the frames of a recording are saved.
+enum Output {
+    /// A GIF being written, and the size of its frames once the first is
+    /// written.
+    Gif(GifEncoder<BufWriter<File>>, Option<(u32, u32)>),
+    /// A directory of PNG files, and the number of the next.
+    Png(PathBuf, usize),
+}
+
+impl Output {
+    fn new(format: &RecordingFormat) -> Result<Self> {
+        let io_error = |err| Error::SaveFailed(ImageError::IoError(err));
+        match format {
+            RecordingFormat::Gif(path) => {
+                let file = File::create(path).map_err(io_error)?;
+                let mut encoder = GifEncoder::new_with_speed(BufWriter::new(file), GIF_SPEED);
+                encoder
+                    .set_repeat(Repeat::Infinite)
+                    .map_err(Error::SaveFailed)?;
+                Ok(Output::Gif(encoder, None))
+            }
+            RecordingFormat::PngSequence(dir) => {
+                fs::create_dir_all(dir).map_err(io_error)?;
+                Ok(Output::Png(dir.clone(), 0))
+            }
+        }
+    }
+
+    fn is_gif(&self) -> bool {
+        matches!(self, Output::Gif(..))
+    }
+
+    /// Save a frame that was shown for a number of hundredths of a second.
+    fn write(&mut self, pixels: RgbImage, delay: u128) -> Result<()> {
+        match self {
+            Output::Gif(encoder, size) => {
+                // A GIF's frames all have the size of the first, so frames
+                // after the window is resized are cut short or padded.
+                let (width, height) = *size.get_or_insert(pixels.dimensions());
+                let mut frame = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+                imageops::replace(
+                    &mut frame,
+                    &DynamicImage::ImageRgb8(pixels).into_rgba8(),
+                    0,
+                    0,
+                );
+                let delay_ms = u32::try_from(delay * 10).unwrap_or(u32::MAX);
+                let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+                encoder
+                    .encode_frame(Frame::from_parts(frame, 0, 0, delay))
+                    .map_err(Error::SaveFailed)
+            }
+            Output::Png(dir, next) => {
+                let path = dir.join(format!("frame_{:05}.png", next));
+                *next += 1;
+                pixels
+                    .save_with_format(path, ImageFormat::Png)
+                    .map_err(Error::SaveFailed)
+            }
+        }
+    }
+}
@@ -2,28 +2,52 @@
 // ASCII renderer
 //
 
-use std::num::NonZeroU32;
+use std::{
+    convert::TryFrom,
+    env, fmt,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use bytemuck::cast_slice;
 use bytemuck_derive::{Pod, Zeroable};
+use futures::executor::block_on;
 use thiserror::Error;
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
-    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
-    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, BufferBindingType, BufferUsage,
-    Color, ColorTargetState, ColorWrite, CommandEncoderDescriptor, Device, DeviceDescriptor,
-    Extent3d, Features, FragmentState, FrontFace, ImageCopyTexture, ImageDataLayout, Instance,
-    Limits, LoadOp, MultisampleState, Operations, Origin3d, PipelineLayoutDescriptor, PolygonMode,
-    PowerPreference, PresentMode, PrimitiveState, PrimitiveTopology, Queue,
-    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
-    RequestAdapterOptions, RequestDeviceError, ShaderFlags, ShaderModuleDescriptor, ShaderSource,
-    ShaderStage, Surface, SwapChain, SwapChainDescriptor, SwapChainError, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureSampleType, TextureUsage, TextureViewDescriptor,
-    TextureViewDimension, VertexState,
+    Adapter, AdapterInfo, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferAsyncError, BufferBindingType, BufferDescriptor, BufferUsages, Color,
+    ColorTargetState, ColorWrites, CommandEncoderDescriptor, CompositeAlphaMode,
+    CreateSurfaceError, Device, DeviceDescriptor, ErrorFilter, Extent3d, Features, FragmentState,
+    FrontFace, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, Instance, InstanceDescriptor,
+    Limits, LoadOp, Maintain, MapMode, MultisampleState, Operations, Origin3d,
+    PipelineLayoutDescriptor, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
+    PrimitiveTopology, Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, RequestAdapterOptions, RequestDeviceError, ShaderModuleDescriptor,
+    ShaderSource, ShaderStages, StoreOp, Surface, SurfaceConfiguration, SurfaceError,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 use winit::{dpi::PhysicalSize, window::Window};
 
-use crate::FontData;
+use crate::{
+    alpha_of, blend_over, colour,
+    diagnostics::{describe_adapters, enumerate_adapters},
+    gpu_timer::GpuTimer,
+    post_effect::PostPass,
+    srgb_to_linear, AdapterReport, Char, DeviceLimits, FontData, GpuReport, Image, OverlayContext,
+    OverlayInit, PostEffect, Rect,
+};
+
+/// How long, in milliseconds, characters with `Char::BLINK` are shown for and
+/// then hidden for.  The shader uses the same time.
+const BLINK_MS: u32 = 250;
 
 //
 // Rendering system errors that are passed into Results
@@ -31,124 +55,516 @@ use crate::FontData;
 
 #[derive(Error, Debug)]
 pub enum RenderError {
-    #[error("Appropriate graphics device was not found")]
-    AdapterNotFound,
+    #[error(
+        "Appropriate graphics device was not found for the {0} back-end.  {}",
+        describe_adapters(.1)
+    )]
+    AdapterNotFound(Backend, Vec<AdapterReport>),
 
     #[error(transparent)]
     BadDevice(#[from] RequestDeviceError),
 
-    #[error("Could not find a texture format compatible with the swap chain")]
-    BadSwapChainFormat,
+    #[error("Could not create a surface for the window")]
+    BadSurface(#[from] CreateSurfaceError),
+
+    #[error("Could not find a texture format compatible with the surface")]
+    BadSurfaceFormat,
+
+    #[error("Only a headless renderer can be read back")]
+    NotHeadless,
+
+    #[error("Could not read back the rendered pixels")]
+    ReadbackFailed(#[from] BufferAsyncError),
 }
 
 pub type RenderResult<T> = Result<T, RenderError>;
 
+//
+// Graphics back-ends
+//
+
+/// The graphics API used to render the window.
+///
+/// The back-end can also be chosen at runtime by setting the `MTERM_BACKEND`
+/// environment variable to `auto`, `vulkan`, `dx12`, `metal` or `gl`, which
+/// overrides the one given to the `Builder`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Let WGPU choose the best back-end for the platform.
+    Auto,
+    /// Use Vulkan.
+    Vulkan,
+    /// Use DirectX 12.
+    Dx12,
+    /// Use Metal.
+    Metal,
+    /// Use OpenGL.
+    Gl,
+}
+
+impl Backend {
+    /// The name of the environment variable that overrides the back-end.
+    pub const ENV_VAR: &'static str = "MTERM_BACKEND";
+
+    /// Read the back-end from the `MTERM_BACKEND` environment variable, if it
+    /// is set to a recognised value.
+    pub fn from_env() -> Option<Backend> {
+        let name = env::var(Self::ENV_VAR).ok()?;
+        let backend = Self::from_name(&name);
+        if backend.is_none() {
+            eprintln!(
+                "mterm: unknown back-end \"{}\" in {}, ignoring it",
+                name,
+                Self::ENV_VAR
+            );
+        }
+        backend
+    }
+
+    /// Convert a back-end name, ignoring case, into a back-end.
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name.trim().to_lowercase().as_str() {
+            "auto" => Some(Backend::Auto),
+            "vulkan" => Some(Backend::Vulkan),
+            "dx12" => Some(Backend::Dx12),
+            "metal" => Some(Backend::Metal),
+            "gl" => Some(Backend::Gl),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn backends(self) -> wgpu::Backends {
+        match self {
+            Backend::Auto => wgpu::Backends::PRIMARY,
+            Backend::Vulkan => wgpu::Backends::VULKAN,
+            Backend::Dx12 => wgpu::Backends::DX12,
+            Backend::Metal => wgpu::Backends::METAL,
+            Backend::Gl => wgpu::Backends::GL,
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Backend::Auto => "Auto",
+            Backend::Vulkan => "Vulkan",
+            Backend::Dx12 => "DX12",
+            Backend::Metal => "Metal",
+            Backend::Gl => "GL",
+        };
+        f.write_str(name)
+    }
+}
+
+//
+// Rendering options provided by the builder
+//
+
+/// Configuration used when creating the renderer.
+///
+/// This is built up by the `Builder` and consumed by `RenderState::new`.
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// The graphics API used for rendering.
+    pub(crate) backend: Backend,
+    /// The power preference used when requesting a graphics adapter.
+    pub(crate) power_preference: PowerPreference,
+    /// If set, an adapter whose name contains this string is chosen in favour
+    /// of the default one.
+    pub(crate) adapter_name: Option<String>,
+    /// If set, the adapter at this index in `GpuReport::adapters` is chosen in
+    /// favour of the default one.
+    pub(crate) adapter_index: Option<usize>,
+    /// The colour used to clear the window before the characters are drawn.
+    pub(crate) clear_colour: u32,
+    /// The integer scale each font character is drawn at.
+    pub(crate) font_scale: u32,
+    /// Whether font pixels blend the ink and paper colours by their coverage.
+    pub(crate) font_smoothing: bool,
+    /// Whether colours are treated as sRGB and converted for sRGB surfaces.
+    pub(crate) srgb_conversion: bool,
+    /// Whether the character grid is centred in the window, rather than
+    /// placed at its top-left corner.
+    pub(crate) centre_grid: bool,
+    /// How finished frames are shown, such as waiting for vertical sync.
+    pub(crate) present_mode: PresentMode,
+    /// The effect drawn over the window after the characters.
+    pub(crate) post_effect: PostEffect,
+    /// The WGSL source of the shader drawn by `PostEffect::Custom`.
+    pub(crate) custom_post_shader: Option<String>,
+    /// Whether there is a second layer of characters drawn over the first.
+    pub(crate) overlay_layer: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            backend: Backend::Auto,
+            power_preference: PowerPreference::default(),
+            adapter_name: None,
+            adapter_index: None,
+            clear_colour: colour(0, 0, 0),
+            font_scale: 1,
+            font_smoothing: true,
+            srgb_conversion: true,
+            centre_grid: false,
+            present_mode: PresentMode::Fifo,
+            post_effect: PostEffect::None,
+            custom_post_shader: None,
+            overlay_layer: false,
+        }
+    }
+}
+
 //
 // Rendering state and interface
 //
 
 pub struct RenderState {
-    surface: Surface,
+    target: Target,
     device: Device,
     queue: Queue,
-    swapchain_desc: SwapChainDescriptor,
-    swapchain: SwapChain,
+    /// Describes the frames rendered, including their size and format, for
+    /// offscreen targets as well as window surfaces.
+    config: SurfaceConfiguration,
     render_pipeline: RenderPipeline,
+    adapter_info: AdapterInfo,
+    /// Whether the device was created with the default or downlevel limits.
+    device_limits: DeviceLimits,
 
-    fg_texture: Texture,
-    bg_texture: Texture,
-    chars_texture: Texture,
+    cells: CellTexture,
+    /// The layer drawn over the cells, if there is one.
+    overlay: Option<CellTexture>,
     font_texture: Texture,
+    bold_font_texture: Option<Texture>,
     texture_bind_group_layout: BindGroupLayout,
     texture_bind_group: BindGroup,
 
+    uniform_buffer: Buffer,
     uniform_bind_group: BindGroup,
 
+    /// The fonts and options the renderer was created with, kept so that it
+    /// can be created again if the graphics device is lost.
+    font: FontData,
+    bold_font: Option<FontData>,
+    options: RenderOptions,
+    /// Set by the device's error handler when the device is lost.
+    device_lost: Arc<AtomicBool>,
+    /// When the renderer was first created, which the time given to the
+    /// shader is measured from.
+    start: Instant,
+    post_effect: PostEffect,
+    /// The resources for drawing the post effect, if there is one.
+    post_pass: Option<PostPass>,
+    /// Times the frames on the graphics card, if it has timestamp queries.
+    gpu_timer: Option<GpuTimer>,
+    /// Whether blinking characters were hidden in the last frame drawn.
+    blink_hidden: bool,
+    frame_stats: FrameStats,
+    /// How long the renderer's parts of the last frame took.
+    frame_timings: FrameTimings,
+    /// The part of the grid changed since the last upload, if it is known.
+    dirty_region: Option<Rect>,
+    /// The part of the overlay layer changed since the last upload, if it is
+    /// known.
+    overlay_dirty_region: Option<Rect>,
+
     font_char_size: (u32, u32),
+    font_grid: (u32, u32),
+    font_glyph_count: u32,
+    font_scale: u32,
+    font_smoothing: bool,
+    centre_grid: bool,
     size: (u32, u32),
+    clear_colour: u32,
+    /// True if the colours are decoded from sRGB before they are written.
+    decode_srgb: bool,
 }
 
 impl RenderState {
-    pub async fn new(window: &Window, font: &FontData) -> RenderResult<Self> {
+    pub async fn new(
+        window: &Window,
+        font: &FontData,
+        bold_font: Option<&FontData>,
+        options: &RenderOptions,
+    ) -> RenderResult<Self> {
         let inner_size = window.inner_size();
+        let (instance, backend) = Self::create_instance(options);
 
-        // An instance represents access to the WGPU API.  Here we decide which
-        // back-end to use (Vulkan, DX12, Metal etc), but we let WGPU decide by
-        // stating PRIMARY.
-        let instance = Instance::new(wgpu::BackendBit::PRIMARY);
+        // The surface is an interface to the OS window that will host the
+        // rendering.  This is unsafe because the surface must not outlive the
+        // window, which holds since the main loop owns the window and drops
+        // the renderer first.
+        let surface = unsafe { instance.create_surface(window) }?;
+
+        let (adapter, device, queue, device_limits) =
+            Self::request_device(&instance, backend, Some(&surface), options).await?;
+
+        // The surface's capabilities on this adapter say which formats and
+        // present modes it can use.  An sRGB format is preferred, since the
+        // colours are converted for it.
+        let capabilities = surface.get_capabilities(&adapter);
+        let format = capabilities
+            .formats
+            .iter()
+            .copied()
+            .find(|format| is_srgb_format(*format))
+            .or_else(|| capabilities.formats.first().copied())
+            .ok_or(RenderError::BadSurfaceFormat)?;
+        let present_modes = capabilities.present_modes;
+
+        // We keep the configuration around because we need to configure the
+        // surface again every time the window resizes.
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: inner_size.width,
+            height: inner_size.height,
+            present_mode: supported_present_mode(options.present_mode, &present_modes),
+            alpha_mode: CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
 
-        // This can be unsafe since we know the window has a valid window
-        // handle, otherwise we wouldn't get here.  The surface is an interface
-        // to the OS window that will host the rendering.
-        let surface = unsafe { instance.create_surface(window) };
+        // A minimised window has no pixels, and the surface is configured
+        // when it is restored instead.
+        if config.width > 0 && config.height > 0 {
+            surface.configure(&device, &config);
+        }
 
+        let target = Target::Window {
+            surface,
+            present_modes,
+        };
+        Ok(Self::with_target(
+            adapter.get_info(),
+            device_limits,
+            device,
+            queue,
+            target,
+            config,
+            font,
+            bold_font,
+            options,
+        ))
+    }
+
+    /// Create a renderer that draws into a texture rather than a window, so
+    /// that it can run without a display, such as in tests on a CI machine.
+    /// The texture is `width` by `height` pixels, and the frames rendered
+    /// into it are read back with `render_to_buffer`.
+    ///
+    /// The default settings are used, as with `Builder::new`, and can be
+    /// changed afterwards with the `set_` methods.  The colours are converted
+    /// for an sRGB target, as they are for most windows.
+    pub async fn new_headless(width: u32, height: u32, font: &FontData) -> RenderResult<Self> {
+        let options = RenderOptions::default();
+
+        // The offscreen texture is described the same way as a window surface
+        // so that the rest of the renderer treats them alike.
+        let config = SurfaceConfiguration {
+            usage: TextureUsages::RENDER_ATTACHMENT,
+            format: TextureFormat::Rgba8UnormSrgb,
+            width,
+            height,
+            present_mode: options.present_mode,
+            alpha_mode: CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+        Self::new_offscreen(config, font, None, &options).await
+    }
+
+    /// Create a renderer that draws into a texture with the size and format
+    /// of a surface configuration.
+    async fn new_offscreen(
+        config: SurfaceConfiguration,
+        font: &FontData,
+        bold_font: Option<&FontData>,
+        options: &RenderOptions,
+    ) -> RenderResult<Self> {
+        let (instance, backend) = Self::create_instance(options);
+        let (adapter, device, queue, device_limits) =
+            Self::request_device(&instance, backend, None, options).await?;
+        let target = Target::Offscreen(create_offscreen_texture(&device, &config));
+        Ok(Self::with_target(
+            adapter.get_info(),
+            device_limits,
+            device,
+            queue,
+            target,
+            config,
+            font,
+            bold_font,
+            options,
+        ))
+    }
+
+    /// Create the WGPU instance for the back-end chosen by the options or the
+    /// environment.
+    fn create_instance(options: &RenderOptions) -> (Instance, Backend) {
+        // An instance represents access to the WGPU API.  Here we decide which
+        // back-end to use (Vulkan, DX12, Metal etc).  Unless one was chosen by
+        // the builder or the environment, we let WGPU decide by stating
+        // PRIMARY.  An adapter chosen by index is counted across every
+        // back-end, as in the GPU report.
+        let backend = Backend::from_env().unwrap_or(options.backend);
+        let backends = match options.adapter_index {
+            Some(_) => wgpu::Backends::all(),
+            None => backend.backends(),
+        };
+        let instance = Instance::new(InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        (instance, backend)
+    }
+
+    /// Choose a graphics adapter that can render to the surface, if there is
+    /// one, and create a device and queue on it, along with the limits the
+    /// device was created with.
+    async fn request_device(
+        instance: &Instance,
+        backend: Backend,
+        surface: Option<&Surface>,
+        options: &RenderOptions,
+    ) -> RenderResult<(Adapter, Device, Queue, DeviceLimits)> {
         // The adapter represents a physical graphics/compute device.  We need a
-        // device that can handle the surface we will be rendering to.
-        let adapter = instance
-            .request_adapter(&RequestAdapterOptions {
-                power_preference: PowerPreference::default(),
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .ok_or(RenderError::AdapterNotFound)?;
+        // device that can handle the surface we will be rendering to.  If the
+        // user asked for a particular adapter by index or name we look for
+        // that first, otherwise we let WGPU choose based on the power
+        // preference.
+        let backends = backend.backends();
+        let adapter = match (options.adapter_index, &options.adapter_name) {
+            (Some(index), _) => Self::find_adapter_by_index(instance, surface, index),
+            (None, Some(name)) => Self::find_adapter(instance, backends, surface, name),
+            (None, None) => None,
+        };
+        let adapter = match adapter {
+            Some(adapter) => adapter,
+            None => instance
+                .request_adapter(&RequestAdapterOptions {
+                    power_preference: options.power_preference,
+                    force_fallback_adapter: false,
+                    compatible_surface: surface,
+                })
+                .await
+                .ok_or_else(|| RenderError::AdapterNotFound(backend, enumerate_adapters()))?,
+        };
 
         // Now we create the device and queue from the adapter.  A device is a
         // logical software construct around the physical device.  It serves as
         // the interface for creating many resources.  A queue is used to
         // deliver commands to the GPU to carry out actions, such as writing to
         // texture buffers.
-        let (device, queue) = adapter
+        //
+        // The default limits ask for more than some older GPUs and the GL
+        // back-end offer, and far more than mterm needs.  They are tried
+        // first, and if the adapter does not support them or asking for them
+        // fails, the downlevel limits are asked for instead.
+        let supported = adapter.limits();
+        let mut created = None;
+        if Limits::default().check_limits(&supported) {
+            match Self::create_device(&adapter, Limits::default()).await {
+                Ok(device) => created = Some((device, DeviceLimits::Default)),
+                Err(err) => eprintln!(
+                    "mterm: could not create a device with the default limits, {}, trying the downlevel limits",
+                    err
+                ),
+            }
+        }
+        let ((device, queue), device_limits) = match created {
+            Some(created) => created,
+            None => (
+                Self::create_device(&adapter, downlevel_limits(supported)).await?,
+                DeviceLimits::Downlevel,
+            ),
+        };
+
+        Ok((adapter, device, queue, device_limits))
+    }
+
+    /// Create a device and queue on an adapter, with some limits.  Timestamp
+    /// queries are turned on if the adapter has them, to time the frames.
+    async fn create_device(
+        adapter: &Adapter,
+        limits: Limits,
+    ) -> Result<(Device, Queue), RequestDeviceError> {
+        adapter
             .request_device(
                 &DeviceDescriptor {
                     label: Some("Render device"),
-                    features: Features::empty(),
-                    limits: Limits::default(),
+                    features: adapter.features() & Features::TIMESTAMP_QUERY,
+                    limits,
                 },
                 None,
             )
-            .await?;
-
-        // We create the swap chain descriptor that provides the configuration
-        // for creating the swap chain.  However, we keep it around because we
-        // need to recreate the swap chain every time the window resizes.
-        let swapchain_desc = SwapChainDescriptor {
-            usage: TextureUsage::RENDER_ATTACHMENT,
-            format: adapter
-                .get_swap_chain_preferred_format(&surface)
-                .ok_or(RenderError::BadSwapChainFormat)?,
-            width: inner_size.width,
-            height: inner_size.height,
-            present_mode: PresentMode::Fifo,
-        };
+            .await
+    }
+
+    /// Set up the textures, uniforms and pipeline that render the characters
+    /// to a target, which is shared by windows and offscreen textures.
+    #[allow(clippy::too_many_arguments)]
+    fn with_target(
+        adapter_info: AdapterInfo,
+        device_limits: DeviceLimits,
+        device: Device,
+        queue: Queue,
+        target: Target,
+        config: SurfaceConfiguration,
+        font: &FontData,
+        bold_font: Option<&FontData>,
+        options: &RenderOptions,
+    ) -> Self {
+        let inner_size = PhysicalSize::new(config.width, config.height);
 
-        // Now we create the swap chain that will target a particular surface.
-        let swapchain = device.create_swap_chain(&surface, &swapchain_desc);
+        // A driver reset or GPU timeout loses the device, after which every
+        // use of it fails.  Errors that say so are noted so that the renderer
+        // can be rebuilt, and any other error panics as it would by default.
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let lost = Arc::clone(&device_lost);
+        device.on_uncaptured_error(Box::new(move |error| {
+            if is_device_lost_error(&error) {
+                lost.store(true, Ordering::Relaxed);
+            } else {
+                panic!("wgpu error: {}", error);
+            }
+        }));
+
+        // The colours are sRGB values.  An sRGB surface expects linear
+        // colours from the shader and encodes them itself, so the shader
+        // decodes the ink and paper colours first.  Otherwise the colours are
+        // written unchanged, which is already correct for other surfaces.
+        let decode_srgb = options.srgb_conversion && is_srgb_format(config.format);
 
         // Set up the textures we will use to render the ASCII graphics.  There are four:
         //
-        // * Foreground colours.  Each pixel represents the ink colour of a character on the screen.
-        // * Background colours.  Each pixel represents the paper colour of a character on the screen.
-        // * ASCII characters.  Each red channel of a pixel represents the ASCII code.
-        // * Font texture.  A 16x16 character grid of the font texture.
+        // * Cells.  Each texel holds the character, ink colour and paper colour of a character
+        //   on the screen.
+        // * Overlay.  The same for the layer drawn over the cells, if there is one.
+        // * Font texture.  A grid of characters, normally 16x16, of the font texture.
+        // * Bold font texture.  The same grid of characters in bold, if a bold font was given.
+        let font_scale = options.font_scale.max(1);
         let size = (
-            inner_size.width / font.width,
-            inner_size.height / font.height,
+            inner_size.width / (font.width * font_scale),
+            inner_size.height / (font.height * font_scale),
         );
-        let fg_texture = Texture::new(&device, size);
-        let bg_texture = Texture::new(&device, size);
-        let chars_texture = Texture::new(&device, size);
-        let mut font_texture = Texture::new(&device, (16 * font.width, 16 * font.height));
+        let cells = CellTexture::new(&device, size);
+        let overlay = if options.overlay_layer {
+            Some(CellTexture::new(&device, size))
+        } else {
+            None
+        };
+        let font_texture = Texture::from_font(&device, &queue, font);
 
-        // Load the font data into the font texture
-        font_texture.storage.copy_from_slice(font.data.as_slice());
-        font_texture.update(&queue);
+        // The bold font, if given, has its own texture.  Bold characters are
+        // brightened instead when there is no bold font.
+        let bold_font_texture = bold_font.map(|font| Texture::from_font(&device, &queue, font));
 
         // Now we load the shader in that contains both the vertex and fragment
         // shaders as a single WGSL file.
         let shader_src = include_str!("shader.wgsl");
-        let shader = device.create_shader_module(&ShaderModuleDescriptor {
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("ASCII engine shader"),
-            flags: ShaderFlags::all(),
             source: ShaderSource::Wgsl(shader_src.into()),
         });
 
@@ -160,17 +576,17 @@ impl RenderState {
                 entries: &[
                     BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: ShaderStage::FRAGMENT,
+                        visibility: ShaderStages::FRAGMENT,
                         ty: BindingType::Texture {
                             multisampled: false,
-                            sample_type: TextureSampleType::Float { filterable: false },
+                            sample_type: TextureSampleType::Uint,
                             view_dimension: TextureViewDimension::D2,
                         },
                         count: None,
                     },
                     BindGroupLayoutEntry {
                         binding: 1,
-                        visibility: ShaderStage::FRAGMENT,
+                        visibility: ShaderStages::FRAGMENT,
                         ty: BindingType::Texture {
                             multisampled: false,
                             sample_type: TextureSampleType::Float { filterable: false },
@@ -180,7 +596,7 @@ impl RenderState {
                     },
                     BindGroupLayoutEntry {
                         binding: 2,
-                        visibility: ShaderStage::FRAGMENT,
+                        visibility: ShaderStages::FRAGMENT,
                         ty: BindingType::Texture {
                             multisampled: false,
                             sample_type: TextureSampleType::Float { filterable: false },
@@ -190,10 +606,10 @@ impl RenderState {
                     },
                     BindGroupLayoutEntry {
                         binding: 3,
-                        visibility: ShaderStage::FRAGMENT,
+                        visibility: ShaderStages::FRAGMENT,
                         ty: BindingType::Texture {
                             multisampled: false,
-                            sample_type: TextureSampleType::Float { filterable: false },
+                            sample_type: TextureSampleType::Uint,
                             view_dimension: TextureViewDimension::D2,
                         },
                         count: None,
@@ -203,29 +619,46 @@ impl RenderState {
         let texture_bind_group = Self::create_texture_bind_group(
             &device,
             &texture_bind_group_layout,
-            &fg_texture,
-            &bg_texture,
-            &chars_texture,
+            &cells,
+            overlay.as_ref(),
             &font_texture,
+            bold_font_texture.as_ref().unwrap_or(&font_texture),
         );
 
         // Next is to create the uniform buffer based on RenderInfo struct.
+        let origin = grid_origin(
+            (inner_size.width, inner_size.height),
+            size,
+            (font.width * font_scale, font.height * font_scale),
+            options.centre_grid,
+        );
         let uniforms = RenderInfo {
             font_width: font.width,
             font_height: font.height,
-            _padding: [0; 2],
+            font_scale,
+            font_columns: font.columns,
+            font_rows: font.rows,
+            font_smoothing: options.font_smoothing as u32,
+            font_glyph_count: font.glyph_count,
+            has_bold_font: bold_font.is_some() as u32,
+            origin_x: origin.0,
+            origin_y: origin.1,
+            decode_srgb: decode_srgb as u32,
+            time_ms: 0,
+            has_overlay: overlay.is_some() as u32,
+            padding: [0; 3],
         };
         let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: Some("Uniform buffer"),
             contents: cast_slice(&[uniforms]),
-            usage: BufferUsage::UNIFORM | BufferUsage::COPY_DST,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
         });
         let uniform_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
                 label: Some("Uniforms bin group layout"),
                 entries: &[BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: ShaderStage::FRAGMENT,
+                    visibility: ShaderStages::FRAGMENT,
                     ty: BindingType::Buffer {
                         ty: BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -261,17 +694,17 @@ impl RenderState {
             layout: Some(&render_pipeline_layout),
             vertex: VertexState {
                 module: &shader,
-                entry_point: "main",
+                entry_point: "vs_main",
                 buffers: &[],
             },
             fragment: Some(FragmentState {
                 module: &shader,
-                entry_point: "main",
-                targets: &[ColorTargetState {
-                    format: swapchain_desc.format,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format: config.format,
                     blend: Some(BlendState::REPLACE),
-                    write_mask: ColorWrite::ALL,
-                }],
+                    write_mask: ColorWrites::ALL,
+                })],
             }),
             primitive: PrimitiveState {
                 topology: PrimitiveTopology::TriangleStrip,
@@ -279,7 +712,7 @@ impl RenderState {
                 front_face: FrontFace::Cw,
                 cull_mode: None,
                 polygon_mode: PolygonMode::Fill,
-                clamp_depth: false,
+                unclipped_depth: false,
                 conservative: false,
             },
             depth_stencil: None,
@@ -288,37 +721,174 @@ impl RenderState {
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
+            multiview: None,
         });
 
-        Ok(RenderState {
-            surface,
+        // A post effect needs a texture to draw the characters to, and a
+        // pipeline of its own.  They are only created when there is one.
+        let post_pass = PostPass::for_effect(
+            &device,
+            config.format,
+            (config.width, config.height),
+            &options.post_effect,
+            options.custom_post_shader.as_deref(),
+        );
+        let gpu_timer = GpuTimer::new(&device, &queue);
+
+        RenderState {
+            target,
             device,
             queue,
-            swapchain_desc,
-            swapchain,
+            config,
             render_pipeline,
+            adapter_info,
+            device_limits,
 
-            fg_texture,
-            bg_texture,
-            chars_texture,
+            cells,
+            overlay,
             font_texture,
+            bold_font_texture,
             texture_bind_group_layout,
             texture_bind_group,
 
+            uniform_buffer,
             uniform_bind_group,
 
+            font: font.clone(),
+            bold_font: bold_font.cloned(),
+            options: options.clone(),
+            device_lost,
+
             font_char_size: (font.width, font.height),
+            font_grid: (font.columns, font.rows),
+            font_glyph_count: font.glyph_count,
+            font_scale,
+            font_smoothing: options.font_smoothing,
+            centre_grid: options.centre_grid,
             size,
-        })
+            clear_colour: options.clear_colour,
+            decode_srgb,
+            start: Instant::now(),
+            post_effect: options.post_effect,
+            post_pass,
+            gpu_timer,
+            blink_hidden: false,
+            frame_stats: FrameStats::default(),
+            frame_timings: FrameTimings::default(),
+            dirty_region: None,
+            overlay_dirty_region: None,
+        }
+    }
+
+    /// Return true if the graphics device has been lost, such as after a
+    /// driver reset, and the renderer must be rebuilt with `rebuild`.
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    /// Note that the graphics device has been lost, when it is found out from
+    /// the way rendering fails rather than from an error.
+    pub(crate) fn set_device_lost(&mut self) {
+        self.device_lost.store(true, Ordering::Relaxed);
+    }
+
+    /// Create the renderer again from scratch, with a new adapter, device and
+    /// textures, after the graphics device has been lost.
+    ///
+    /// The current fonts and settings are kept, and so are the contents of
+    /// the character grid, which are uploaded to the new textures on the next
+    /// render.  A headless renderer is rebuilt at the same size, and the
+    /// window is not used.
+    pub async fn rebuild(&mut self, window: &Window) -> RenderResult<()> {
+        let options = RenderOptions {
+            clear_colour: self.clear_colour,
+            font_scale: self.font_scale,
+            centre_grid: self.centre_grid,
+            present_mode: self.config.present_mode,
+            post_effect: self.post_effect,
+            ..self.options.clone()
+        };
+        let mut render = match self.target {
+            Target::Window { .. } => {
+                Self::new(window, &self.font, self.bold_font.as_ref(), &options).await?
+            }
+            Target::Offscreen(_) => {
+                Self::new_offscreen(
+                    self.config.clone(),
+                    &self.font,
+                    self.bold_font.as_ref(),
+                    &options,
+                )
+                .await?
+            }
+        };
+
+        // The window has not changed size, so the grid is normally the same
+        // size and its contents can be moved across.
+        if render.size == self.size {
+            render.cells.swap_images(&mut self.cells);
+            if let (Some(new), Some(old)) = (render.overlay.as_mut(), self.overlay.as_mut()) {
+                new.swap_images(old);
+            }
+        }
+        render.start = self.start;
+        render.frame_stats = self.frame_stats;
+        *self = render;
+        Ok(())
+    }
+
+    fn find_adapter(
+        instance: &Instance,
+        backends: wgpu::Backends,
+        surface: Option<&Surface>,
+        name: &str,
+    ) -> Option<Adapter> {
+        let name = name.to_lowercase();
+        let adapter = instance.enumerate_adapters(backends).find(|adapter| {
+            adapter.get_info().name.to_lowercase().contains(&name)
+                && !matches!(surface, Some(surface)
+                    if !adapter.is_surface_supported(surface))
+        });
+
+        if adapter.is_none() {
+            eprintln!(
+                "mterm: no graphics adapter matching \"{}\" was found, using the default adapter",
+                name
+            );
+        }
+
+        adapter
+    }
+
+    fn find_adapter_by_index(
+        instance: &Instance,
+        surface: Option<&Surface>,
+        index: usize,
+    ) -> Option<Adapter> {
+        let adapter = instance
+            .enumerate_adapters(wgpu::Backends::all())
+            .nth(index)
+            .filter(|adapter| {
+                !matches!(surface, Some(surface) if !adapter.is_surface_supported(surface))
+            });
+
+        if adapter.is_none() {
+            eprintln!(
+                "mterm: graphics adapter {} was not found or cannot render to the window, using the default adapter",
+                index
+            );
+        }
+
+        adapter
     }
 
     fn create_texture_bind_group(
         device: &Device,
         texture_bind_group_layout: &BindGroupLayout,
-        fore_image: &Texture,
-        back_image: &Texture,
-        text_image: &Texture,
+        cells: &CellTexture,
+        overlay: Option<&CellTexture>,
         font_image: &Texture,
+        bold_font_image: &Texture,
     ) -> BindGroup {
         device.create_bind_group(&BindGroupDescriptor {
             label: Some("Texture bind group"),
@@ -327,15 +897,13 @@ impl RenderState {
                 BindGroupEntry {
                     binding: 0,
                     resource: BindingResource::TextureView(
-                        &fore_image
-                            .texture
-                            .create_view(&TextureViewDescriptor::default()),
+                        &cells.texture.create_view(&TextureViewDescriptor::default()),
                     ),
                 },
                 BindGroupEntry {
                     binding: 1,
                     resource: BindingResource::TextureView(
-                        &back_image
+                        &font_image
                             .texture
                             .create_view(&TextureViewDescriptor::default()),
                     ),
@@ -343,15 +911,18 @@ impl RenderState {
                 BindGroupEntry {
                     binding: 2,
                     resource: BindingResource::TextureView(
-                        &text_image
+                        &bold_font_image
                             .texture
                             .create_view(&TextureViewDescriptor::default()),
                     ),
                 },
+                // Without an overlay layer, the shader never reads it, but
+                // something must still be bound.
                 BindGroupEntry {
                     binding: 3,
                     resource: BindingResource::TextureView(
-                        &font_image
+                        &overlay
+                            .unwrap_or(cells)
                             .texture
                             .create_view(&TextureViewDescriptor::default()),
                     ),
@@ -361,44 +932,212 @@ impl RenderState {
     }
 
     pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        self.swapchain_desc.width = new_size.width;
-        self.swapchain_desc.height = new_size.height;
-        self.swapchain = self
-            .device
-            .create_swap_chain(&self.surface, &self.swapchain_desc);
+        self.resize_target(new_size);
+        self.resize_grid();
+        // The leftover pixels, and so the grid's position, change with the
+        // window size even if the grid does not.
+        self.update_uniforms();
+    }
+
+    /// Resize the surface to fit the window, but keep the character grid the
+    /// size it is until `fit_grid` is called.  Until then the old grid is
+    /// drawn in the window with the clear colour around it, or cut off if the
+    /// window has shrunk.
+    ///
+    /// This is cheaper than `resize` while the window is being dragged to a
+    /// new size, as the grid's textures are not created again each time.
+    pub fn resize_surface(&mut self, new_size: PhysicalSize<u32>) {
+        self.resize_target(new_size);
+        self.update_uniforms();
+    }
+
+    /// Resize the character grid to fit the window, after the surface was
+    /// resized by `resize_surface`.  Nothing is recreated if the grid is
+    /// already the right size.
+    pub fn fit_grid(&mut self) {
+        self.resize_grid();
+        self.update_uniforms();
+    }
+
+    /// Resize the target, and the post effect's textures, to a new window
+    /// size.
+    fn resize_target(&mut self, new_size: PhysicalSize<u32>) {
+        self.config.width = new_size.width;
+        self.config.height = new_size.height;
+        self.recreate_target();
+        if self.is_minimised() {
+            return;
+        }
+        if let Some(post) = self.post_pass.as_mut() {
+            post.resize(&self.device, (new_size.width, new_size.height));
+        }
+    }
+
+    /// Configure the surface again, or create the offscreen texture again,
+    /// after its description has changed.
+    fn recreate_target(&mut self) {
+        // A minimised window has no pixels, and textures cannot be empty, so
+        // the target is created again when the window is restored.
+        if self.is_minimised() {
+            return;
+        }
+        match &mut self.target {
+            Target::Window { surface, .. } => {
+                surface.configure(&self.device, &self.config);
+            }
+            Target::Offscreen(texture) => {
+                *texture = create_offscreen_texture(&self.device, &self.config);
+            }
+        }
+    }
 
+    /// Returns true if the window has no pixels, as when it is minimised.
+    /// Nothing is drawn, and the grid keeps its size, until it has some
+    /// again.
+    fn is_minimised(&self) -> bool {
+        self.config.width == 0 || self.config.height == 0
+    }
+
+    /// Recalculate the size of the character grid from the window size and
+    /// recreate the cells and overlay textures if it has changed.  A window
+    /// too small to hold a single cell keeps the grid it has, as the textures
+    /// cannot be empty.
+    fn resize_grid(&mut self) {
+        let (cell_width, cell_height) = self.cell_size();
         let chars_size = (
-            new_size.width / self.font_char_size.0,
-            new_size.height / self.font_char_size.1,
+            self.config.width / cell_width,
+            self.config.height / cell_height,
         );
 
-        if chars_size != self.size {
+        if chars_size != self.size && chars_size.0 > 0 && chars_size.1 > 0 {
             self.size = chars_size;
-            self.fg_texture = Texture::new(&self.device, self.size);
-            self.bg_texture = Texture::new(&self.device, self.size);
-            self.chars_texture = Texture::new(&self.device, self.size);
+            self.cells = CellTexture::new(&self.device, self.size);
+            if self.overlay.is_some() {
+                self.overlay = Some(CellTexture::new(&self.device, self.size));
+            }
 
             self.texture_bind_group = Self::create_texture_bind_group(
                 &self.device,
                 &self.texture_bind_group_layout,
-                &self.fg_texture,
-                &self.bg_texture,
-                &self.chars_texture,
+                &self.cells,
+                self.overlay.as_ref(),
                 &self.font_texture,
+                self.bold_font_texture
+                    .as_ref()
+                    .unwrap_or(&self.font_texture),
             );
         }
     }
 
-    pub fn render(&mut self) -> Result<(), SwapChainError> {
-        // Update the textures
-        self.fg_texture.update(&self.queue);
-        self.bg_texture.update(&self.queue);
-        self.chars_texture.update(&self.queue);
+    /// Write the current font information and time to the uniform buffer
+    /// used by the shader.
+    fn update_uniforms(&self) {
+        let origin = self.grid_origin();
+        let uniforms = RenderInfo {
+            font_width: self.font_char_size.0,
+            font_height: self.font_char_size.1,
+            font_scale: self.font_scale,
+            font_columns: self.font_grid.0,
+            font_rows: self.font_grid.1,
+            font_smoothing: self.font_smoothing as u32,
+            font_glyph_count: self.font_glyph_count,
+            has_bold_font: self.bold_font_texture.is_some() as u32,
+            origin_x: origin.0,
+            origin_y: origin.1,
+            decode_srgb: self.decode_srgb as u32,
+            time_ms: self.time_ms(),
+            has_overlay: self.overlay.is_some() as u32,
+            padding: [0; 3],
+        };
+        self.queue
+            .write_buffer(&self.uniform_buffer, 0, cast_slice(&[uniforms]));
+    }
 
-        // First, we fetch the current frame from the swap chain that we will
-        // render to.  The frame will have the view that covers the whole
-        // window.  We will use this later for the render pass.
-        let frame = self.swapchain.get_current_frame()?.output;
+    pub fn render(&mut self) -> Result<(), SurfaceError> {
+        self.render_with_overlay(&mut |_| {})
+    }
+
+    /// Render the window like `render`, and then call `overlay` to record its
+    /// own drawing on top, submitted together with the characters.  This is
+    /// how `App::render_overlay` is called.
+    pub fn render_with_overlay(
+        &mut self,
+        overlay: &mut dyn FnMut(OverlayContext),
+    ) -> Result<(), SurfaceError> {
+        let start = Instant::now();
+        self.upload_textures();
+        self.frame_timings = FrameTimings {
+            upload: start.elapsed(),
+            ..FrameTimings::default()
+        };
+        self.update_uniforms();
+        self.blink_hidden = self.is_blink_hidden();
+
+        // There is nothing to draw to while the window is minimised.
+        if self.is_minimised() {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        match &self.target {
+            Target::Window { surface, .. } => {
+                // First, we fetch the current texture from the surface that we
+                // will render to.  Its view covers the whole window, and it is
+                // shown once the frame has been drawn to it.
+                let frame = surface.get_current_texture()?;
+                self.draw(
+                    &frame.texture.create_view(&TextureViewDescriptor::default()),
+                    overlay,
+                );
+                frame.present();
+            }
+            Target::Offscreen(texture) => {
+                self.draw(
+                    &texture.create_view(&TextureViewDescriptor::default()),
+                    overlay,
+                );
+            }
+        }
+        self.frame_timings.render = start.elapsed();
+        self.frame_timings.gpu = self.gpu_timer.as_ref().and_then(GpuTimer::last);
+
+        Ok(())
+    }
+
+    /// Copy the parts of the character grid that have changed to the GPU.
+    /// Redraws forced by resizing or uncovering the window often change
+    /// nothing, so only the rows that differ from the last copy are copied.
+    /// If a dirty region was given, only its rows are compared.  The overlay
+    /// layer is copied in the same way, on its own.
+    fn upload_textures(&mut self) {
+        let height = self.size.1;
+        let stats = &mut self.frame_stats;
+        stats.frames += 1;
+        let mut count = |upload: Option<u64>| match upload {
+            Some(bytes) => {
+                stats.uploads += 1;
+                stats.bytes_uploaded += bytes;
+            }
+            None => stats.skipped_uploads += 1,
+        };
+
+        let rows = dirty_rows(self.dirty_region.take(), height);
+        count(self.cells.update_rows(&self.queue, rows));
+        if let Some(overlay) = self.overlay.as_mut() {
+            let rows = dirty_rows(self.overlay_dirty_region.take(), height);
+            count(overlay.update_rows(&self.queue, rows));
+        }
+    }
+
+    /// Render the characters to a view of the target, followed by the
+    /// overlay.
+    fn draw(&self, view: &TextureView, overlay: &mut dyn FnMut(OverlayContext)) {
+        // The graphics card's time is measured from the start of the first
+        // render pass to the end of the last, not counting the overlay.
+        let timer = self.gpu_timer.as_ref();
+        if let Some(timer) = timer {
+            timer.begin_frame(&self.device);
+        }
 
         // Now we construct an encoder that acts like a factory for commands to
         // be sent to the device.
@@ -408,24 +1147,28 @@ impl RenderState {
                 label: Some("Render encoder"),
             });
 
+        // With a post effect, the characters are drawn to its scene texture,
+        // and the effect then draws the scene to the target.
+        let (grid_view, post) = match &self.post_pass {
+            Some(post) => (post.scene_view(), Some(post)),
+            None => (view, None),
+        };
+
         {
             // A render pass describes the attachments that will be referenced during rendering.
             let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
                 label: Some("Main render pass"),
-                color_attachments: &[RenderPassColorAttachment {
-                    view: &frame.view,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: grid_view,
                     resolve_target: None,
                     ops: Operations {
-                        load: LoadOp::Clear(Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
-                        store: true,
+                        load: LoadOp::Clear(packed_to_color(self.clear_colour, self.decode_srgb)),
+                        store: StoreOp::Store,
                     },
-                }],
+                })],
                 depth_stencil_attachment: None,
+                timestamp_writes: timer.and_then(|timer| timer.writes(true, post.is_none())),
+                occlusion_query_set: None,
             });
 
             render_pass.set_pipeline(&self.render_pipeline);
@@ -434,22 +1177,805 @@ impl RenderState {
             render_pass.draw(0..4, 0..1);
         }
 
+        if let Some(post) = post {
+            post.draw(
+                &self.queue,
+                &mut encoder,
+                view,
+                &self.post_effect,
+                self.time_ms(),
+                timer.and_then(|timer| timer.writes(false, true)),
+            );
+        }
+        if let Some(timer) = timer {
+            timer.resolve(&mut encoder);
+        }
+        let grid = encoder.finish();
+
+        // The overlay records into its own encoder, so that a mistake in it
+        // loses the overlay but not the characters below.  Its validation
+        // errors are caught here rather than reaching the device's error
+        // handler, which would panic.
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Overlay encoder"),
+            });
+        self.device.push_error_scope(ErrorFilter::Validation);
+        overlay(OverlayContext {
+            device: &self.device,
+            queue: &self.queue,
+            encoder: &mut encoder,
+            view,
+            format: self.config.format,
+            width: self.config.width,
+            height: self.config.height,
+        });
+        let overlay = encoder.finish();
+        match block_on(self.device.pop_error_scope()) {
+            None => self.queue.submit([grid, overlay]),
+            Some(error) => {
+                eprintln!("mterm: the overlay was not drawn, {}", error);
+                self.queue.submit(std::iter::once(grid))
+            }
+        };
+        if let Some(timer) = timer {
+            timer.end_frame();
+        }
+    }
+
+    /// Render a headless renderer's characters and read back the pixels, as
+    /// rows of RGBA bytes from the top-left corner.  The colours are sRGB, so
+    /// they can be compared with those of an image file.  A renderer resized
+    /// to nothing has no pixels to return.
+    ///
+    /// Returns `RenderError::NotHeadless` for a renderer drawing to a window.
+    pub fn render_to_buffer(&mut self) -> RenderResult<Vec<u8>> {
+        self.render_to_buffer_with_overlay(&mut |_| {})
+    }
+
+    /// Render a headless renderer's characters with an overlay on top, as
+    /// `render_with_overlay` does, and read back the pixels as
+    /// `render_to_buffer` does.
+    pub fn render_to_buffer_with_overlay(
+        &mut self,
+        overlay: &mut dyn FnMut(OverlayContext),
+    ) -> RenderResult<Vec<u8>> {
+        let view = match &self.target {
+            Target::Offscreen(texture) => texture.create_view(&TextureViewDescriptor::default()),
+            Target::Window { .. } => return Err(RenderError::NotHeadless),
+        };
+        if self.is_minimised() {
+            return Ok(Vec::new());
+        }
+        self.upload_textures();
+        self.update_uniforms();
+        self.draw(&view, overlay);
+        let texture = match &self.target {
+            Target::Offscreen(texture) => texture,
+            Target::Window { .. } => unreachable!(),
+        };
+
+        // Rows copied out of a texture must start at a multiple of 256 bytes,
+        // so each row is padded and the padding removed afterwards.
+        let (width, height) = (self.config.width, self.config.height);
+        let row_bytes = 4 * width;
+        let padded_row_bytes =
+            row_bytes.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = self.device.create_buffer(&BufferDescriptor {
+            label: Some("Readback buffer"),
+            size: padded_row_bytes as u64 * height as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Readback encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                },
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
         self.queue.submit(std::iter::once(encoder.finish()));
 
-        Ok(())
+        // Waiting for the device finishes the copy and the mapping.
+        let slice = buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(Maintain::Wait);
+        receiver.recv().unwrap_or(Err(BufferAsyncError))?;
+
+        let padded = slice.get_mapped_range();
+        let pixels = padded
+            .chunks(padded_row_bytes as usize)
+            .flat_map(|row| &row[..row_bytes as usize])
+            .copied()
+            .collect();
+        drop(padded);
+        buffer.unmap();
+        Ok(pixels)
     }
 
     pub fn images(&mut self) -> (&mut Vec<u32>, &mut Vec<u32>, &mut Vec<u32>) {
         (
-            &mut self.fg_texture.storage,
-            &mut self.bg_texture.storage,
-            &mut self.chars_texture.storage,
+            &mut self.cells.fore_image,
+            &mut self.cells.back_image,
+            &mut self.cells.text_image,
         )
     }
 
+    /// The character grid and the overlay layer, if there is one, for the
+    /// app to write to together.
+    pub(crate) fn layers_mut(&mut self) -> (&mut CellTexture, Option<&mut CellTexture>) {
+        (&mut self.cells, self.overlay.as_mut())
+    }
+
+    /// A copy of the characters in the window, as last presented.
+    ///
+    /// The overlay layer is drawn over the characters as closely as a single
+    /// grid allows.  Its colours are blended over those below, and its glyph
+    /// replaces the one below unless it is a space on a translucent paper.
+    pub fn to_image(&self) -> Image {
+        let mut image = Image {
+            width: self.size.0 as usize,
+            height: self.size.1 as usize,
+            fore_image: self.cells.fore_image.clone(),
+            back_image: self.cells.back_image.clone(),
+            text_image: self.cells.text_image.clone(),
+        };
+        if let Some(overlay) = &self.overlay {
+            for i in 0..overlay.text_image.len() {
+                let (ink, paper, glyph) = (
+                    overlay.fore_image[i],
+                    overlay.back_image[i],
+                    overlay.text_image[i],
+                );
+                if glyph == 0 {
+                    continue;
+                }
+                image.fore_image[i] = blend_over(ink, image.fore_image[i]);
+                image.back_image[i] = blend_over(paper, image.back_image[i]);
+                if glyph & 0xffff != b' ' as u32 || alpha_of(paper) == 255 {
+                    image.text_image[i] = glyph;
+                }
+            }
+        }
+        image
+    }
+
+    /// The font used to render the characters.
+    pub fn font(&self) -> &FontData {
+        &self.font
+    }
+
     pub fn chars_size(&self) -> (u32, u32) {
         self.size
     }
+
+    /// The size of a character cell in pixels, taking the font scale into
+    /// account.
+    pub fn cell_size(&self) -> (u32, u32) {
+        (
+            self.font_char_size.0 * self.font_scale,
+            self.font_char_size.1 * self.font_scale,
+        )
+    }
+
+    /// The integer scale the font is drawn at.
+    pub fn font_scale(&self) -> u32 {
+        self.font_scale
+    }
+
+    /// Change the integer scale the font is drawn at.
+    ///
+    /// The scale is limited so that at least one character fits in the window.
+    /// The character grid is resized to fit the new cell size.
+    pub fn set_font_scale(&mut self, font_scale: u32) {
+        let font_scale = self.fit_font_scale(font_scale);
+        if font_scale != self.font_scale {
+            self.font_scale = font_scale;
+            self.resize_grid();
+            self.update_uniforms();
+        }
+    }
+
+    /// Reduce a font scale until at least one character fits in the window.
+    fn fit_font_scale(&self, font_scale: u32) -> u32 {
+        let mut font_scale = font_scale.max(1);
+        while font_scale > 1
+            && (self.font_char_size.0 * font_scale > self.config.width
+                || self.font_char_size.1 * font_scale > self.config.height)
+        {
+            font_scale -= 1;
+        }
+        font_scale
+    }
+
+    /// Replace the font used to render the characters.
+    ///
+    /// The font texture is recreated and the character grid is resized to fit
+    /// the new character size.  The font scale is kept, unless it has to be
+    /// reduced so that at least one character fits in the window.  The bold
+    /// font is kept only if the new font has the same layout.
+    pub fn set_font(&mut self, font: &FontData) {
+        self.font_texture = Texture::from_font(&self.device, &self.queue, font);
+        self.font = font.clone();
+
+        // A bold font only works with fonts of the same layout, so it is
+        // dropped if the new font is different and bold characters are
+        // brightened instead.
+        if self.font_char_size != (font.width, font.height)
+            || self.font_grid != (font.columns, font.rows)
+        {
+            self.bold_font_texture = None;
+            self.bold_font = None;
+        }
+
+        self.font_char_size = (font.width, font.height);
+        self.font_grid = (font.columns, font.rows);
+        self.font_glyph_count = font.glyph_count;
+        self.font_scale = self.fit_font_scale(self.font_scale);
+
+        // The bind group refers to the old font texture, so it must be rebuilt
+        // even if the grid stays the same size.
+        let old_size = self.size;
+        self.resize_grid();
+        self.update_uniforms();
+        if self.size == old_size {
+            self.texture_bind_group = Self::create_texture_bind_group(
+                &self.device,
+                &self.texture_bind_group_layout,
+                &self.cells,
+                self.overlay.as_ref(),
+                &self.font_texture,
+                self.bold_font_texture
+                    .as_ref()
+                    .unwrap_or(&self.font_texture),
+            );
+        }
+    }
+
+    /// How finished frames are shown, as last chosen.
+    pub fn present_mode(&self) -> PresentMode {
+        self.config.present_mode
+    }
+
+    /// Change how finished frames are shown, such as turning vertical sync
+    /// off with `PresentMode::Immediate`.  The surface is configured again,
+    /// and the window must be rendered again to show its contents.
+    ///
+    /// A mode that the window's surface does not support is replaced by
+    /// `PresentMode::Fifo`, which is always supported, and a warning is
+    /// logged.  The mode actually used is returned.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> PresentMode {
+        let present_mode = match &self.target {
+            Target::Window { present_modes, .. } => {
+                supported_present_mode(present_mode, present_modes)
+            }
+            Target::Offscreen(_) => present_mode,
+        };
+        if present_mode != self.config.present_mode {
+            self.config.present_mode = present_mode;
+            self.recreate_target();
+        }
+        present_mode
+    }
+
+    /// The effect drawn over the window, as last chosen.
+    pub fn post_effect(&self) -> PostEffect {
+        self.post_effect
+    }
+
+    /// Choose the effect drawn over the window, or change its settings.  The
+    /// resources an effect needs are created when it is first chosen, and
+    /// freed when `PostEffect::None` is chosen.
+    ///
+    /// `PostEffect::Custom` has no effect if the renderer was not given a
+    /// custom shader.
+    pub fn set_post_effect(&mut self, post_effect: PostEffect) {
+        if std::mem::discriminant(&post_effect) != std::mem::discriminant(&self.post_effect) {
+            if post_effect == PostEffect::Custom && self.options.custom_post_shader.is_none() {
+                eprintln!("mterm: there is no custom post shader, so no effect is drawn");
+            }
+            self.post_pass = PostPass::for_effect(
+                &self.device,
+                self.config.format,
+                (self.config.width, self.config.height),
+                &post_effect,
+                self.options.custom_post_shader.as_deref(),
+            );
+        }
+        self.post_effect = post_effect;
+    }
+
+    /// Return true if the character grid is centred in the window.
+    pub fn centre_grid(&self) -> bool {
+        self.centre_grid
+    }
+
+    /// Choose whether the character grid is centred in the window, with the
+    /// leftover pixels shared equally between the four sides, or placed at
+    /// the top-left corner with them all on the right and bottom.
+    pub fn set_centre_grid(&mut self, centre_grid: bool) {
+        if centre_grid != self.centre_grid {
+            self.centre_grid = centre_grid;
+            self.update_uniforms();
+        }
+    }
+
+    /// The position, in pixels, of the top-left corner of the character grid
+    /// in the window.
+    pub fn grid_origin(&self) -> (u32, u32) {
+        grid_origin(
+            (self.config.width, self.config.height),
+            self.size,
+            self.cell_size(),
+            self.centre_grid,
+        )
+    }
+
+    /// Convert a position in the window, in pixels, such as that of the mouse
+    /// pointer, into the character cell under it.  None is returned if the
+    /// position is in the border around the grid or outside the window.
+    pub fn cell_at(&self, x: i32, y: i32) -> Option<(u32, u32)> {
+        let (origin_x, origin_y) = self.grid_origin();
+        let (cell_width, cell_height) = self.cell_size();
+        let x = u32::try_from(i64::from(x) - i64::from(origin_x)).ok()? / cell_width;
+        let y = u32::try_from(i64::from(y) - i64::from(origin_y)).ok()? / cell_height;
+        if x < self.size.0 && y < self.size.1 {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    /// The colour used to clear the window, which shows as a border around
+    /// the characters when the window does not divide evenly into cells.
+    pub fn clear_colour(&self) -> u32 {
+        self.clear_colour
+    }
+
+    /// Change the colour used to clear the window.  It takes effect on the next
+    /// render.
+    pub fn set_clear_colour(&mut self, clear_colour: u32) {
+        self.clear_colour = clear_colour;
+    }
+
+    /// The device, queue and frame format, for creating the resources drawn
+    /// with `render_with_overlay`.  They change when the renderer is rebuilt.
+    pub fn overlay_init(&self) -> OverlayInit<'_> {
+        OverlayInit {
+            device: &self.device,
+            queue: &self.queue,
+            format: self.config.format,
+        }
+    }
+
+    /// Information about the graphics adapter that was chosen for rendering,
+    /// such as its name and back-end.
+    pub fn adapter_info(&self) -> &AdapterInfo {
+        &self.adapter_info
+    }
+
+    /// Report the graphics adapters on the machine, the one chosen for
+    /// rendering, and the format and present modes of the window's surface.
+    /// The adapters are listed again each time, so this is best called once,
+    /// such as when the app is asked for its GPU information.
+    pub fn gpu_report(&self) -> GpuReport {
+        let (surface_format, present_modes) = match &self.target {
+            Target::Window { present_modes, .. } => {
+                (Some(self.config.format), present_modes.clone())
+            }
+            Target::Offscreen(_) => (None, Vec::new()),
+        };
+        GpuReport::new(
+            Some(&self.adapter_info),
+            Some(self.device_limits),
+            surface_format,
+            present_modes,
+        )
+    }
+
+    /// Tell the renderer that the characters outside a region of the grid
+    /// have not changed since the last render, so that only the rows inside
+    /// it need to be compared and uploaded, as after a frame in which only
+    /// a blinking cursor changed.  Regions given before a render are joined.
+    /// Changes outside the region may not be shown until they are next
+    /// written.
+    pub fn set_dirty_region(&mut self, region: Rect) {
+        self.dirty_region = Some(match self.dirty_region {
+            Some(dirty) => dirty.union(region),
+            None => region,
+        });
+    }
+
+    /// Note the part of the overlay layer that has changed, in the same way
+    /// as `set_dirty_region` does for the grid below it.
+    pub fn set_overlay_dirty_region(&mut self, region: Rect) {
+        self.overlay_dirty_region = Some(match self.overlay_dirty_region {
+            Some(dirty) => dirty.union(region),
+            None => region,
+        });
+    }
+
+    /// The number of milliseconds since the renderer was created, as given to
+    /// the shader for the frame being drawn.  It wraps around to 0 after
+    /// about 49 days.
+    pub fn time_ms(&self) -> u32 {
+        self.start.elapsed().as_millis() as u32
+    }
+
+    /// Return true if blinking characters are hidden at the moment.
+    fn is_blink_hidden(&self) -> bool {
+        (self.time_ms() / BLINK_MS) % 2 == 1
+    }
+
+    /// Return true if the window must be drawn again because characters with
+    /// `Char::BLINK` have been shown or hidden since it was last drawn.
+    pub fn blink_changed(&self) -> bool {
+        self.is_blink_hidden() != self.blink_hidden
+            && std::iter::once(&self.cells)
+                .chain(&self.overlay)
+                .flat_map(|cells| &cells.text_image)
+                .any(|&glyph| glyph & Char::BLINK != 0)
+    }
+
+    /// Counts of the work done to render the window so far.
+    pub fn frame_stats(&self) -> FrameStats {
+        self.frame_stats
+    }
+
+    /// How long the renderer's parts of the last frame rendered took.  The
+    /// `tick` and `present` times are measured by the main loop, so they are
+    /// zero here.
+    pub fn frame_timings(&self) -> FrameTimings {
+        self.frame_timings
+    }
+}
+
+/// Where a renderer draws its frames.
+enum Target {
+    /// A window's surface, and the present modes it supports.
+    Window {
+        surface: Surface,
+        present_modes: Vec<PresentMode>,
+    },
+    /// A texture, read back with `RenderState::render_to_buffer`.
+    Offscreen(wgpu::Texture),
+}
+
+/// Create a texture for a headless renderer to draw into, with the size and
+/// format of a surface configuration.
+fn create_offscreen_texture(device: &Device, desc: &SurfaceConfiguration) -> wgpu::Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some("Offscreen target"),
+        size: Extent3d {
+            width: desc.width,
+            height: desc.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: desc.format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// The limits asked for when the default ones cannot be had.  They are those
+/// of WebGL 2, which older GPUs and the GL back-end can all offer, with the
+/// adapter's own texture sizes.  The renderer needs at most two bind groups,
+/// four textures and one uniform buffer in its fragment shader, well within
+/// them.
+fn downlevel_limits(supported: Limits) -> Limits {
+    let limits = Limits::downlevel_webgl2_defaults().using_resolution(supported);
+    debug_assert!(
+        limits.max_bind_groups >= 2
+            && limits.max_sampled_textures_per_shader_stage >= 4
+            && limits.max_uniform_buffers_per_shader_stage >= 1
+    );
+    limits
+}
+
+/// The present mode to use in place of one chosen for a surface that supports
+/// only some modes.  `PresentMode::Fifo` is always supported, so it replaces a
+/// mode that is not, with a warning.
+fn supported_present_mode(present_mode: PresentMode, supported: &[PresentMode]) -> PresentMode {
+    if supported.contains(&present_mode) {
+        return present_mode;
+    }
+    eprintln!(
+        "mterm: the present mode {:?} is not supported by the window, using {:?}",
+        present_mode,
+        PresentMode::Fifo
+    );
+    PresentMode::Fifo
+}
+
+/// Returns true if an error reported by the graphics device, or one of the
+/// errors that caused it, says that the device has been lost.  WGPU has no
+/// error variant for this, so the messages are checked instead.
+fn is_device_lost_error(error: &wgpu::Error) -> bool {
+    let mut cause: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(err) = cause {
+        if err.to_string().contains("device is lost") {
+            return true;
+        }
+        cause = err.source();
+    }
+    false
+}
+
+/// The position, in pixels, of the top-left corner of a character grid in a
+/// window.  When centred, the leftover pixels are split equally between the
+/// sides, with any odd pixel on the right or bottom.
+fn grid_origin(
+    window_size: (u32, u32),
+    chars_size: (u32, u32),
+    cell_size: (u32, u32),
+    centred: bool,
+) -> (u32, u32) {
+    if !centred {
+        return (0, 0);
+    }
+    let leftover = |window: u32, chars: u32, cell: u32| window.saturating_sub(chars * cell) / 2;
+    (
+        leftover(window_size.0, chars_size.0, cell_size.0),
+        leftover(window_size.1, chars_size.1, cell_size.1),
+    )
+}
+
+/// The rows of a grid that a dirty region covers, clamped to the grid, or all
+/// of them if there is no dirty region.
+fn dirty_rows(region: Option<Rect>, height: u32) -> Range<u32> {
+    match region {
+        Some(region) => {
+            let clamp = |y: i64| y.max(0).min(height as i64) as u32;
+            let top = region.y as i64;
+            clamp(top)..clamp(top.saturating_add(region.height as i64))
+        }
+        None => 0..height,
+    }
+}
+
+/// Convert a colour packed in the same format as `colour()` into a WGPU
+/// colour.
+///
+/// The clear colour is written to the surface the same way as the shader's
+/// output, so when the paper colours are decoded from sRGB (`linear` is true)
+/// the clear colour must be as well.  This is what makes the border match the
+/// paper colours exactly.
+fn packed_to_color(c: u32, linear: bool) -> Color {
+    let component = |shift: u32| {
+        let value = ((c >> shift) & 0xff) as f64 / 255.0;
+        if linear {
+            srgb_to_linear(value)
+        } else {
+            value
+        }
+    };
+    Color {
+        r: component(0),
+        g: component(8),
+        b: component(16),
+        a: ((c >> 24) & 0xff) as f64 / 255.0,
+    }
+}
+
+/// Returns true if the hardware encodes colours written to a texture of this
+/// format as sRGB.
+fn is_srgb_format(format: TextureFormat) -> bool {
+    format.is_srgb()
+}
+
+//
+// Frame statistics
+//
+
+/// Counts of the work done to render the window since it was opened, given
+/// in each `TickInput`.  Comparing the counts from one tick to the next shows
+/// how much was done for the frames in between.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// The number of frames rendered.
+    pub frames: u64,
+    /// The number of times the character grid was copied to the graphics
+    /// card.  The overlay layer, if there is one, is counted separately, so
+    /// there can be two a frame.
+    pub uploads: u64,
+    /// The number of times the character grid, or the overlay layer, was not
+    /// copied because it had not changed since the last frame.
+    pub skipped_uploads: u64,
+    /// The number of bytes copied to the graphics card for the character
+    /// grid, which is 16 for each cell copied.
+    pub bytes_uploaded: u64,
+}
+
+/// How long the parts of a frame took, given in each `TickInput` for the
+/// frame before.  The times are measured on the CPU, apart from `gpu`, which
+/// is measured by the graphics card on adapters with timestamp queries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameTimings {
+    /// The time spent in `App::tick`.
+    pub tick: Duration,
+    /// The time spent in `App::present`.
+    pub present: Duration,
+    /// The time spent copying the character grid and overlay layer to the
+    /// graphics card.
+    pub upload: Duration,
+    /// The time spent recording and submitting the frame's drawing, including
+    /// `App::render_overlay`, and waiting for the window's next texture.
+    pub render: Duration,
+    /// The time the graphics card spent drawing the characters and the post
+    /// effect, not counting the overlay.  It is only known on adapters with
+    /// timestamp queries, and arrives a frame or two after the CPU times.
+    pub gpu: Option<Duration>,
+}
+
+impl FrameTimings {
+    /// The total time spent on the CPU.
+    pub fn cpu(&self) -> Duration {
+        self.tick + self.present + self.upload + self.render
+    }
+}
+
+impl fmt::Display for FrameTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "tick {:.2}ms, present {:.2}ms, upload {:.2}ms, render {:.2}ms",
+            ms(self.tick),
+            ms(self.present),
+            ms(self.upload),
+            ms(self.render)
+        )?;
+        match self.gpu {
+            Some(gpu) => write!(f, ", gpu {:.2}ms", ms(gpu)),
+            None => Ok(()),
+        }
+    }
+}
+
+//
+// Character grid
+//
+
+/// The character grid, kept as the ink, paper and character images that the
+/// app writes to, and as a texture with the three packed into each texel for
+/// the shader.  Packing them means one upload and one texture read a cell.
+pub(crate) struct CellTexture {
+    pub(crate) size: (u32, u32),
+    pub(crate) fore_image: Vec<u32>,
+    pub(crate) back_image: Vec<u32>,
+    pub(crate) text_image: Vec<u32>,
+    /// The texels as they were last uploaded, holding the character, ink and
+    /// paper of each cell.  It is empty until the first upload.
+    uploaded: Vec<[u32; 4]>,
+    texture: wgpu::Texture,
+}
+
+impl CellTexture {
+    fn new(device: &Device, size: (u32, u32)) -> Self {
+        let vec_size = (size.0 * size.1) as usize;
+
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Cells texture"),
+            size: Extent3d {
+                width: size.0,
+                height: size.1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba32Uint,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        CellTexture {
+            size,
+            fore_image: vec![0; vec_size],
+            back_image: vec![0; vec_size],
+            text_image: vec![0; vec_size],
+            uploaded: Vec::new(),
+            texture,
+        }
+    }
+
+    /// The texel for a cell, as the shader expects it.
+    fn texel(&self, i: usize) -> [u32; 4] {
+        [
+            self.text_image[i],
+            self.fore_image[i],
+            self.back_image[i],
+            0,
+        ]
+    }
+
+    /// Upload the rows of the grid that have changed since the last upload,
+    /// looking only at a range of rows, and return the number of bytes
+    /// uploaded.  The changed rows are uploaded together, from the first to
+    /// the last.  The whole grid is uploaded if it never has been.
+    fn update_rows(&mut self, queue: &Queue, rows: Range<u32>) -> Option<u64> {
+        let len = self.fore_image.len();
+        if self.uploaded.len() != len {
+            self.uploaded = (0..len).map(|i| self.texel(i)).collect();
+            self.write_rows(queue, 0, self.size.1);
+            return Some(16 * len as u64);
+        }
+
+        let width = self.size.0 as usize;
+        let span = |first: u32, last: u32| first as usize * width..(last as usize + 1) * width;
+        let changed = |y: &u32| span(*y, *y).any(|i| self.uploaded[i] != self.texel(i));
+        let first = rows.clone().find(changed)?;
+        let last = rows.rev().find(changed).unwrap_or(first);
+
+        let span = span(first, last);
+        for i in span.clone() {
+            self.uploaded[i] = self.texel(i);
+        }
+        self.write_rows(queue, first, last - first + 1);
+        Some(16 * span.len() as u64)
+    }
+
+    /// Swap the images with those of another grid of the same size, so that
+    /// they are uploaded to its texture instead.
+    fn swap_images(&mut self, other: &mut CellTexture) {
+        std::mem::swap(&mut self.fore_image, &mut other.fore_image);
+        std::mem::swap(&mut self.back_image, &mut other.back_image);
+        std::mem::swap(&mut self.text_image, &mut other.text_image);
+    }
+
+    /// Upload a number of rows of the last uploaded texels, starting from row
+    /// `y`.
+    fn write_rows(&self, queue: &Queue, y: u32, height: u32) {
+        let width = self.size.0;
+        let start = (y * width) as usize;
+        let end = ((y + height) * width) as usize;
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: Origin3d { x: 0, y, z: 0 },
+                aspect: TextureAspect::All,
+            },
+            cast_slice(&self.uploaded[start..end]),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(16 * width),
+                rows_per_image: Some(height),
+            },
+            Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
 }
 
 //
@@ -463,7 +1989,7 @@ pub(crate) struct Texture {
 }
 
 impl Texture {
-    fn new(device: &Device, size: (u32, u32)) -> Self {
+    fn new(device: &Device, size: (u32, u32), format: TextureFormat) -> Self {
         let vec_size = (size.0 * size.1) as usize;
         let storage = vec![0; vec_size];
 
@@ -479,8 +2005,9 @@ impl Texture {
             mip_level_count: 1,
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8Unorm,
-            usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+            format,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
         });
 
         Texture {
@@ -490,6 +2017,18 @@ impl Texture {
         }
     }
 
+    /// Create a texture containing a font's characters.
+    fn from_font(device: &Device, queue: &Queue, font: &FontData) -> Self {
+        let mut texture = Texture::new(
+            device,
+            (font.columns * font.width, font.rows * font.height),
+            TextureFormat::Rgba8Unorm,
+        );
+        texture.storage.copy_from_slice(font.data.as_slice());
+        texture.update(queue);
+        texture
+    }
+
     fn update(&mut self, queue: &Queue) {
         let (width, height) = self.size;
         queue.write_texture(
@@ -497,12 +2036,13 @@ impl Texture {
                 texture: &self.texture,
                 mip_level: 0,
                 origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
             },
             cast_slice(&self.storage),
             ImageDataLayout {
                 offset: 0,
-                bytes_per_row: NonZeroU32::new(4 * width),
-                rows_per_image: NonZeroU32::new(height),
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
             },
             Extent3d {
                 width,
@@ -513,10 +2053,25 @@ impl Texture {
     }
 }
 
+/// The uniforms given to the shader, laid out as `Uniforms` in the shader.
+/// Every field is a `u32`, so there is no padding, and there are a multiple
+/// of 4 of them so that the size is a multiple of 16 bytes, as some back-ends
+/// require of uniform buffers.
 #[repr(C)]
 #[derive(Copy, Clone, Pod, Zeroable)]
 struct RenderInfo {
-    font_width: u32,  // Width of the font characters
-    font_height: u32, // Height of the font characters
-    _padding: [u32; 2],
+    font_width: u32,       // Width of the font characters
+    font_height: u32,      // Height of the font characters
+    font_scale: u32,       // Integer scale the font characters are drawn at
+    font_columns: u32,     // Number of characters across the font texture
+    font_rows: u32,        // Number of characters down the font texture
+    font_smoothing: u32,   // Non-zero to blend ink and paper by font coverage
+    font_glyph_count: u32, // Number of characters in the font
+    has_bold_font: u32,    // Non-zero if bold characters use the bold font texture
+    origin_x: u32,         // Pixels from the left of the window to the grid
+    origin_y: u32,         // Pixels from the top of the window to the grid
+    decode_srgb: u32,      // Non-zero to decode the ink and paper colours from sRGB
+    time_ms: u32,          // Milliseconds since the renderer was created
+    has_overlay: u32,      // Non-zero if the overlay layer is drawn over the cells
+    padding: [u32; 3],
 }
@@ -1,7 +1,8 @@
+use image::ImageError;
 use thiserror::Error;
 use winit::error::OsError;
 
-use crate::RenderError;
+use crate::{FontError, RenderError};
 
 /// All the possible errors that can occur from mterm.
 ///
@@ -17,8 +18,39 @@ pub enum Error {
     #[error(transparent)]
     WgpuError(#[from] RenderError),
 
-    #[error("Unable to read font data")]
-    BadFont,
+    /// A font could not be loaded or used.
+    #[error("Unable to use font: {0}")]
+    BadFont(#[from] FontError),
+
+    /// The font file could not be watched for changes.
+    #[cfg(feature = "hot-reload")]
+    #[error("Unable to watch font file: {0}")]
+    FontWatch(#[from] notify::Error),
+
+    /// A REXPaint image could not be loaded.
+    #[cfg(feature = "rexpaint")]
+    #[error("Unable to load REXPaint image: {0}")]
+    BadXp(String),
+
+    /// An image could not be encoded or saved, such as by
+    /// `render_image_to_png`.
+    #[error("Unable to save image: {0}")]
+    SaveFailed(ImageError),
+
+    /// A custom post shader could not be compiled.  The message is the WGSL
+    /// compiler's.
+    #[error("Unable to compile post shader: {0}")]
+    BadShader(String),
+
+    /// The `Builder` was given settings that cannot produce a working window.
+    #[error("Invalid configuration: {0}")]
+    BadConfig(String),
+}
+
+impl From<ImageError> for Error {
+    fn from(err: ImageError) -> Self {
+        Error::BadFont(FontError::DecodeFailed(err))
+    }
 }
 
 /// A result that can possible return an `mterm::Error`.
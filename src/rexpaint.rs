@@ -0,0 +1,220 @@
+//
+// REXPaint images
+// Reads and writes the .xp files saved by the REXPaint ASCII art editor
+//
+
+use std::{
+    convert::TryFrom,
+    io::{Read, Write},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::{alpha_of, colour, Error, Image, Result};
+
+/// The version written at the start of the files saved by current versions
+/// of REXPaint.
+const XP_VERSION: i32 = -1;
+
+/// The paper colour that REXPaint uses to mark a cell as transparent.
+const XP_TRANSPARENT: [u8; 3] = [255, 0, 255];
+
+/// The size of a cell in a file: the glyph as a `u32`, then the red, green
+/// and blue of the ink and the paper.
+const XP_CELL_SIZE: usize = 10;
+
+impl Image {
+    /// Load an image saved by REXPaint, flattening its layers into one.
+    ///
+    /// Cells in the magenta paper colour (255, 0, 255) are transparent, so the
+    /// layers below show through them.  Cells that are transparent in every
+    /// layer are left as zeroes, so they have a transparent paper colour and
+    /// are skipped by `PresentInput::blit_keyed`.
+    pub fn load_xp(bytes: &[u8]) -> Result<Image> {
+        let mut layers = Image::load_xp_layers(bytes)?.into_iter();
+        let mut image = layers.next().ok_or_else(|| bad_xp("it has no layers"))?;
+        for layer in layers {
+            if (layer.width, layer.height) != (image.width, image.height) {
+                return Err(bad_xp("its layers are different sizes"));
+            }
+            for (i, &paper) in layer.back_image.iter().enumerate() {
+                if alpha_of(paper) != 0 {
+                    image.fore_image[i] = layer.fore_image[i];
+                    image.back_image[i] = paper;
+                    image.text_image[i] = layer.text_image[i];
+                }
+            }
+        }
+        Ok(image)
+    }
+
+    /// Load each layer of an image saved by REXPaint, from the bottom up.
+    /// Transparent cells are left as zeroes, as with `Image::load_xp`.
+    pub fn load_xp_layers(bytes: &[u8]) -> Result<Vec<Image>> {
+        let mut data = Vec::new();
+        GzDecoder::new(bytes)
+            .read_to_end(&mut data)
+            .map_err(|err| bad_xp(&err.to_string()))?;
+        let mut reader = XpReader { data: &data };
+
+        // Only one version of the layout is in use, so the version is skipped.
+        let _version = reader.i32()?;
+        let count = usize::try_from(reader.i32()?).map_err(|_| bad_xp("bad number of layers"))?;
+
+        let mut layers = Vec::new();
+        for _ in 0..count {
+            let width = usize::try_from(reader.i32()?).map_err(|_| bad_xp("bad width"))?;
+            let height = usize::try_from(reader.i32()?).map_err(|_| bad_xp("bad height"))?;
+            // Check the cells are there before making room for them.
+            let cells = width
+                .checked_mul(height)
+                .filter(|cells| cells.saturating_mul(XP_CELL_SIZE) <= reader.data.len())
+                .ok_or_else(|| bad_xp("the file is too short"))?;
+
+            let mut layer = Image::new(width, height);
+            // Cells are stored a column at a time.
+            for i in 0..cells {
+                let (x, y) = (i / height, i % height);
+                let glyph = reader.i32()? as u32;
+                let cell = reader.bytes(6)?;
+                let (ink, paper) = (&cell[0..3], &cell[3..6]);
+                if paper != XP_TRANSPARENT {
+                    let j = y * width + x;
+                    layer.fore_image[j] = colour(ink[0], ink[1], ink[2]);
+                    layer.back_image[j] = colour(paper[0], paper[1], paper[2]);
+                    layer.text_image[j] = glyph;
+                }
+            }
+            layers.push(layer);
+        }
+        Ok(layers)
+    }
+
+    /// Save the image in REXPaint's format, as a single layer.
+    ///
+    /// The alpha of the colours is lost, except that cells whose paper is
+    /// fully transparent are saved as transparent cells.  Only the character
+    /// and page of each glyph are saved, not attributes such as `Char::BOLD`.
+    pub fn save_xp(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(16 + self.text_image.len() * XP_CELL_SIZE);
+        data.extend_from_slice(&XP_VERSION.to_le_bytes());
+        data.extend_from_slice(&1i32.to_le_bytes());
+        data.extend_from_slice(&(self.width as i32).to_le_bytes());
+        data.extend_from_slice(&(self.height as i32).to_le_bytes());
+
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let i = y * self.width + x;
+                let (ink, paper) = (self.fore_image[i], self.back_image[i]);
+                data.extend_from_slice(&(self.text_image[i] & 0xffff).to_le_bytes());
+                data.extend_from_slice(&ink.to_le_bytes()[0..3]);
+                if alpha_of(paper) == 0 {
+                    data.extend_from_slice(&XP_TRANSPARENT);
+                } else {
+                    data.extend_from_slice(&paper.to_le_bytes()[0..3]);
+                }
+            }
+        }
+
+        // Writing to a Vec cannot fail.
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        encoder.finish().unwrap()
+    }
+}
+
+/// Reads the values in an uncompressed .xp file in order.
+struct XpReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> XpReader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(bad_xp("the file is too short"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        let bytes = self.bytes(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+fn bad_xp(message: &str) -> Error {
+    Error::BadXp(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{colour_rgba, Canvas, Char, Point};
+
+    /// A 3x2 file of two layers.  The bottom layer says "abc" over "def" on
+    /// dark paper, and the top one has a white 'X' on navy over the 'b', with
+    /// the rest of it transparent.
+    const TWO_LAYERS: &[u8] = include_bytes!("../tests/fixtures/two_layers.xp");
+
+    #[test]
+    fn loads_each_layer() {
+        let layers = Image::load_xp_layers(TWO_LAYERS).unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].to_plain_text(), "abc\ndef\n");
+        assert_eq!(layers[0].fore_image[4], colour(10, 20, 30));
+        assert_eq!(layers[0].back_image[4], colour(1, 2, 3));
+        assert_eq!(layers[1].text_image, vec![0, b'X' as u32, 0, 0, 0, 0]);
+        assert_eq!(layers[1].back_image[1], colour(0, 0, 128));
+    }
+
+    #[test]
+    fn loads_the_layers_flattened() {
+        let image = Image::load_xp(TWO_LAYERS).unwrap();
+        assert_eq!(image.to_plain_text(), "aXc\ndef\n");
+        assert_eq!(image.fore_image[1], colour(255, 255, 255));
+        assert_eq!(image.fore_image[2], colour(20, 0, 30));
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let mut image = Image::from_strings(&["ab", "c"], colour(1, 2, 3), colour(4, 5, 6));
+        image.draw_char(
+            Point::new(1, 1),
+            Char::new(b'z', colour(7, 8, 9), colour_rgba(0, 0, 0, 0)),
+        );
+        let loaded = Image::load_xp(&image.save_xp()).unwrap();
+        assert_eq!(loaded.to_plain_text(), "ab\nc \n");
+        assert_eq!(loaded.fore_image[..3], image.fore_image[..3]);
+        assert_eq!(loaded.back_image[..3], image.back_image[..3]);
+        // The transparent cell is saved as transparent, so loads as zeroes.
+        assert_eq!(
+            (
+                loaded.fore_image[3],
+                loaded.back_image[3],
+                loaded.text_image[3]
+            ),
+            (0, 0, 0)
+        );
+
+        // The fixture saved again loads the same as its flattened layers.
+        let flat = Image::load_xp(TWO_LAYERS).unwrap();
+        assert_eq!(Image::load_xp(&flat.save_xp()).unwrap(), flat);
+    }
+
+    #[test]
+    fn bad_files_are_errors() {
+        assert!(Image::load_xp(&TWO_LAYERS[..TWO_LAYERS.len() - 9]).is_err());
+        assert!(Image::load_xp(b"nope").is_err());
+
+        // A layer that claims to be enormous.
+        let mut data = Vec::new();
+        for value in &[-1, 1, i32::MAX, i32::MAX] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        assert!(Image::load_xp(&encoder.finish().unwrap()).is_err());
+    }
+}
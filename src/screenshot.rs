@@ -0,0 +1,149 @@
+//
+// Screenshots
+// Draws an image with a font on the CPU, in the same way as the shader, so it
+// can be saved without a window
+//
+
+use std::{convert::TryFrom, fs, path::Path};
+
+use image::{
+    error::{LimitError, LimitErrorKind},
+    DynamicImage, ImageError, ImageOutputFormat, RgbImage,
+};
+
+use crate::{linear_to_srgb, srgb_to_linear, Char, Error, FontData, Image, Result};
+
+/// Draw an image with a font and encode it as a PNG, without using the GPU.
+///
+/// The pixels match what the window shows with the default settings: font
+/// smoothing and sRGB conversion are on, and as there is no bold font, bold
+/// characters brighten their ink.  Underlined and reversed characters are
+/// drawn as they are in the window, and blinking characters are shown.  Each
+/// character is drawn at the font's size, and the alpha of the colours is
+/// ignored, as it is on the screen.
+pub fn render_image_to_png(image: &Image, font: &FontData) -> Result<Vec<u8>> {
+    let pixels = DynamicImage::ImageRgb8(render_image(image, font)?);
+    let mut png = Vec::new();
+    pixels
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .map_err(Error::SaveFailed)?;
+    Ok(png)
+}
+
+/// Draw an image with a font and save it as a PNG file, in the same way as
+/// `render_image_to_png`.
+pub fn render_image_to_png_file(
+    image: &Image,
+    font: &FontData,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let png = render_image_to_png(image, font)?;
+    fs::write(path, png).map_err(|err| Error::SaveFailed(ImageError::IoError(err)))
+}
+
+/// Draw an image with a font into a buffer of pixels.
+pub(crate) fn render_image(image: &Image, font: &FontData) -> Result<RgbImage> {
+    font.check()?;
+    let (cell_width, cell_height) = (font.width as usize, font.height as usize);
+    let size = |cells: usize, cell_size: usize| {
+        cells
+            .checked_mul(cell_size)
+            .and_then(|pixels| u32::try_from(pixels).ok())
+            .ok_or_else(|| {
+                let kind = LimitErrorKind::DimensionError;
+                Error::SaveFailed(ImageError::Limits(LimitError::from_kind(kind)))
+            })
+    };
+    let mut pixels = RgbImage::new(
+        size(image.width, cell_width)?,
+        size(image.height, cell_height)?,
+    );
+    let stride = font.columns as usize * cell_width;
+
+    let cells = image
+        .fore_image
+        .iter()
+        .zip(&image.back_image)
+        .zip(&image.text_image);
+    for (i, ((&ink, &paper), &glyph)) in cells.enumerate() {
+        let (left, top) = (i % image.width * cell_width, i / image.width * cell_height);
+        let (mut fore, mut back) = (to_linear(ink), to_linear(paper));
+        if glyph & Char::REVERSE != 0 {
+            std::mem::swap(&mut fore, &mut back);
+        }
+        if glyph & Char::BOLD != 0 {
+            fore.iter_mut().for_each(|c| *c += (1.0 - *c) * 0.5);
+        }
+
+        // Characters beyond the end of the font are drawn as blank.
+        let code = glyph & 0xffff;
+        let font_pos = if code < font.glyph_count {
+            let (column, row) = (code % font.columns, code / font.columns);
+            Some(row as usize * cell_height * stride + column as usize * cell_width)
+        } else {
+            None
+        };
+
+        let underline = glyph & Char::UNDERLINE != 0;
+        for y in 0..cell_height {
+            for x in 0..cell_width {
+                // The coverage is how much ink the pixel contains, from the
+                // font pixel's grey level and its alpha.  An underline covers
+                // the bottom row completely.
+                let coverage = match font_pos {
+                    _ if underline && y == cell_height - 1 => 1.0,
+                    Some(pos) => {
+                        let font_pix = font.data[pos + y * stride + x];
+                        (font_pix & 0xff) as f64 / 255.0 * (font_pix >> 24) as f64 / 255.0
+                    }
+                    None => 0.0,
+                };
+                let mix = |c: usize| {
+                    let value = back[c] + (fore[c] - back[c]) * coverage;
+                    (linear_to_srgb(value) * 255.0).round() as u8
+                };
+                let pixel = image::Rgb([mix(0), mix(1), mix(2)]);
+                pixels.put_pixel((left + x) as u32, (top + y) as u32, pixel);
+            }
+        }
+    }
+
+    Ok(pixels)
+}
+
+/// Convert the red, green and blue of a colour to linear light.
+fn to_linear(c: u32) -> [f64; 3] {
+    let component = |shift: u32| srgb_to_linear(((c >> shift) & 0xff) as f64 / 255.0);
+    [component(0), component(8), component(16)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Canvas, Colour, Rect};
+
+    #[test]
+    fn half_coverage_blends_to_the_midpoint() {
+        // A single 8x8 glyph whose every texel is 50% grey.
+        let font = FontData {
+            data: vec![0xff80_8080; 8 * 8],
+            width: 8,
+            height: 8,
+            columns: 1,
+            rows: 1,
+            glyph_count: 1,
+        };
+        let mut image = Image::new(1, 1);
+        image.fill_rect(
+            Rect::new(0, 0, 1, 1),
+            Char::new(0, Colour::Red.into(), Colour::Blue.into()),
+        );
+
+        // Half of red and half of blue in linear space, encoded back to sRGB.
+        let pixels = render_image(&image, &font).unwrap();
+        for pixel in pixels.pixels() {
+            let [r, g, b] = pixel.0;
+            assert!((r as i32 - 188).abs() <= 1 && g == 0 && (b as i32 - 188).abs() <= 1);
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//
+// Serde support
+// Colours are written as hex strings, such as "#FF8800", but integers are
+// still read for files written before colours had a readable form
+//
+// Images are written with a version, so that their layout can change
+//
+
+use std::{collections::BTreeMap, convert::TryFrom, fmt};
+
+use serde::{
+    de::{self, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{format_colour, parse_colour, Colour, Image, Rgba, Theme};
+
+/// A colour as written in a file, before it has been checked.
+enum RawColour {
+    Text(String),
+    Number(u64),
+}
+
+impl RawColour {
+    /// Convert the colour to the same format as `colour()`, describing the
+    /// problem if it is not a valid colour.
+    fn to_u32(&self) -> Result<u32, String> {
+        match self {
+            RawColour::Text(text) => parse_colour(text).map_err(|err| err.to_string()),
+            RawColour::Number(n) => {
+                u32::try_from(*n).map_err(|_| format!("{} is too large to be a colour", n))
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for RawColour {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RawColourVisitor;
+
+        impl<'de> Visitor<'de> for RawColourVisitor {
+            type Value = RawColour;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a colour as a string, such as \"#FF8800\", or an integer")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<RawColour, E> {
+                Ok(RawColour::Text(v.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<RawColour, E> {
+                Ok(RawColour::Number(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<RawColour, E> {
+                u64::try_from(v)
+                    .map(RawColour::Number)
+                    .map_err(|_| E::custom(format!("{} is not a colour", v)))
+            }
+        }
+
+        deserializer.deserialize_any(RawColourVisitor)
+    }
+}
+
+//
+// Rgba
+//
+
+impl Serialize for Rgba {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_colour((*self).into()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgba {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawColour::deserialize(deserializer)?;
+        raw.to_u32().map(Rgba::from).map_err(de::Error::custom)
+    }
+}
+
+//
+// Colour
+// Written as its name, such as "dark_grey".  Any colour that is exactly one of
+// the 16 colours is read.
+//
+
+impl Serialize for Colour {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Colour {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawColour::deserialize(deserializer)?;
+        if let RawColour::Text(text) = &raw {
+            if let Some(c) = Colour::from_name(text) {
+                return Ok(c);
+            }
+        }
+        let value = raw.to_u32().map_err(de::Error::custom)?;
+        Colour::iter()
+            .find(|&c| u32::from(c) == value)
+            .ok_or_else(|| {
+                de::Error::custom(format!(
+                    "{} is not one of the 16 colours",
+                    format_colour(value)
+                ))
+            })
+    }
+}
+
+//
+// Theme
+// Written as a map from names to colours, sorted by name so that files do not
+// change needlessly.
+//
+
+impl Serialize for Theme {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sorted = self.iter().collect::<BTreeMap<_, _>>();
+        let mut map = serializer.serialize_map(Some(sorted.len()))?;
+        for (name, c) in sorted {
+            map.serialize_entry(name, &format_colour(c))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Theme {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ThemeVisitor;
+
+        impl<'de> Visitor<'de> for ThemeVisitor {
+            type Value = Theme;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of names to colours")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Theme, A::Error> {
+                let mut theme = Theme::new();
+                while let Some(name) = map.next_key::<String>()? {
+                    let c = map.next_value::<RawColour>()?.to_u32().map_err(|err| {
+                        de::Error::custom(format!("bad colour for \"{}\": {}", name, err))
+                    })?;
+                    theme.set(name, c);
+                }
+                Ok(theme)
+            }
+        }
+
+        deserializer.deserialize_map(ThemeVisitor)
+    }
+}
+
+//
+// Image
+// Written as a struct with a version, the size and the three planes.  In
+// formats meant for people, such as JSON, each plane is a base64 string of
+// its little-endian bytes; otherwise it is an array of integers.
+//
+
+/// The version of the layout written for images.
+const IMAGE_VERSION: u32 = 1;
+
+const IMAGE_FIELDS: &[&str] = &["version", "width", "height", "fore", "back", "text"];
+
+/// A plane of an image as it is written in a file.  It is borrowed for
+/// writing and owned for reading.
+struct Plane<T>(T);
+
+impl Serialize for Plane<&[u32]> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            let bytes = self
+                .0
+                .iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<_>>();
+            serializer.serialize_str(&base64::encode(bytes))
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Plane<Vec<u32>> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let text = String::deserialize(deserializer)?;
+            let bytes = base64::decode(text).map_err(de::Error::custom)?;
+            if bytes.len() % 4 != 0 {
+                return Err(de::Error::custom("plane is not a whole number of cells"));
+            }
+            let plane = bytes
+                .chunks_exact(4)
+                .map(|v| u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+                .collect();
+            Ok(Plane(plane))
+        } else {
+            Vec::deserialize(deserializer).map(Plane)
+        }
+    }
+}
+
+impl Serialize for Image {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut image = serializer.serialize_struct("Image", IMAGE_FIELDS.len())?;
+        image.serialize_field("version", &IMAGE_VERSION)?;
+        image.serialize_field("width", &self.width)?;
+        image.serialize_field("height", &self.height)?;
+        image.serialize_field("fore", &Plane(&self.fore_image[..]))?;
+        image.serialize_field("back", &Plane(&self.back_image[..]))?;
+        image.serialize_field("text", &Plane(&self.text_image[..]))?;
+        image.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Image {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ImageVisitor;
+
+        impl<'de> Visitor<'de> for ImageVisitor {
+            type Value = Image;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an image")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Image, A::Error> {
+                let missing = |index| de::Error::invalid_length(index, &"an image");
+                let version = seq.next_element()?.ok_or_else(|| missing(0))?;
+                let width = seq.next_element()?.ok_or_else(|| missing(1))?;
+                let height = seq.next_element()?.ok_or_else(|| missing(2))?;
+                let Plane(fore) = seq.next_element()?.ok_or_else(|| missing(3))?;
+                let Plane(back) = seq.next_element()?.ok_or_else(|| missing(4))?;
+                let Plane(text) = seq.next_element()?.ok_or_else(|| missing(5))?;
+                check_image(version, width, height, [&fore, &back, &text])?;
+                Ok(image(width, height, fore, back, text))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Image, A::Error> {
+                let (mut version, mut width, mut height) = (None, None, None);
+                let (mut fore, mut back, mut text) = (None, None, None);
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "version" => version = Some(map.next_value()?),
+                        "width" => width = Some(map.next_value()?),
+                        "height" => height = Some(map.next_value()?),
+                        "fore" => fore = Some(map.next_value::<Plane<_>>()?.0),
+                        "back" => back = Some(map.next_value::<Plane<_>>()?.0),
+                        "text" => text = Some(map.next_value::<Plane<_>>()?.0),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                let version = version.ok_or_else(|| de::Error::missing_field("version"))?;
+                let width = width.ok_or_else(|| de::Error::missing_field("width"))?;
+                let height = height.ok_or_else(|| de::Error::missing_field("height"))?;
+                let fore = fore.ok_or_else(|| de::Error::missing_field("fore"))?;
+                let back = back.ok_or_else(|| de::Error::missing_field("back"))?;
+                let text = text.ok_or_else(|| de::Error::missing_field("text"))?;
+                check_image(version, width, height, [&fore, &back, &text])?;
+                Ok(image(width, height, fore, back, text))
+            }
+        }
+
+        deserializer.deserialize_struct("Image", IMAGE_FIELDS, ImageVisitor)
+    }
+}
+
+/// Check that an image read from a file can be used, so that it cannot cause
+/// a panic when it is drawn.
+fn check_image<E: de::Error>(
+    version: u32,
+    width: usize,
+    height: usize,
+    planes: [&Vec<u32>; 3],
+) -> Result<(), E> {
+    if version != IMAGE_VERSION {
+        return Err(E::custom(format!("unknown image version {}", version)));
+    }
+    let cells = width
+        .checked_mul(height)
+        .ok_or_else(|| E::custom(format!("image size {}x{} is too large", width, height)))?;
+    for (name, plane) in ["fore", "back", "text"].iter().zip(planes.iter()) {
+        if plane.len() != cells {
+            return Err(E::custom(format!(
+                "{} plane has {} cells, but a {}x{} image has {}",
+                name,
+                plane.len(),
+                width,
+                height,
+                cells
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn image(width: usize, height: usize, fore: Vec<u32>, back: Vec<u32>, text: Vec<u32>) -> Image {
+    Image {
+        width,
+        height,
+        fore_image: fore,
+        back_image: back,
+        text_image: text,
+    }
+}
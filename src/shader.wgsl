@@ -1,6 +1,6 @@
 struct VertexOutput {
-    [[builtin(position)]] clip_position: vec4<f32>;
-};
+    @builtin(position) clip_position: vec4<f32>,
+}
 
 // 1+-------+3
 //  |       |
@@ -14,32 +14,47 @@ struct VertexOutput {
 // 2        1, 0
 // 3        1, 1
 
-// Foreground texture
-[[group(0), binding(0)]]
-var t_fore: texture_2d<f32>;
-// Background texture
-[[group(0), binding(1)]]
-var t_back: texture_2d<f32>;
-// ASCII chars texture
-[[group(0), binding(2)]]
-var t_text: texture_2d<f32>;
+// Cells texture.  Each texel holds a character's glyph and attributes, ink
+// colour and paper colour, as they are in the images given to the app.
+@group(0) @binding(0)
+var t_cells: texture_2d<u32>;
 // Font texture
-[[group(0), binding(3)]]
+@group(0) @binding(1)
 var t_font: texture_2d<f32>;
+// Bold font texture (the same as the font texture if there is no bold font)
+@group(0) @binding(2)
+var t_bold: texture_2d<f32>;
+// Overlay layer texture, laid out as the cells texture and drawn over it (the
+// same as the cells texture if there is no overlay layer)
+@group(0) @binding(3)
+var t_overlay: texture_2d<u32>;
 
-[[block]]
 struct Uniforms {
-    font_width: u32;
-    font_height: u32;
-};
+    font_width: u32,
+    font_height: u32,
+    font_scale: u32,
+    font_columns: u32,
+    font_rows: u32,
+    font_smoothing: u32,
+    font_glyph_count: u32,
+    has_bold_font: u32,
+    origin_x: u32,
+    origin_y: u32,
+    decode_srgb: u32,
+    time_ms: u32,
+    has_overlay: u32,
+    padding_0: u32,
+    padding_1: u32,
+    padding_2: u32,
+}
 
-[[group(1), binding(0)]]
+@group(1) @binding(0)
 var<uniform> uniforms: Uniforms;
 
 
-[[stage(vertex)]]
-fn main(
-    [[builtin(vertex_index)]] in_vertex_index: u32,
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
 ) -> VertexOutput {
     var out: VertexOutput;
 
@@ -60,40 +75,152 @@ fn main(
     return out;
 }
 
-[[stage(fragment)]]
+// Convert an sRGB colour component to linear, as the hardware does when
+// sampling an sRGB texture.
+fn srgb_to_linear(c: f32) -> f32 {
+    if (c <= 0.04045) {
+        return c / 12.92;
+    }
+    return pow((c + 0.055) / 1.055, 2.4);
+}
 
-fn main([[builtin(position)]] pos: vec4<f32>) -> [[location(0)]] vec4<f32> {
-    // Calculate the pixel coords
-    let p = vec2<f32>(pos.x - 0.5, pos.y - 0.5);
+// Unpack a colour from the red, green, blue and alpha bytes of a u32, with red
+// in the lowest byte.  The colour is decoded from sRGB if the surface
+// expects linear colours.
+fn unpack_colour(c: u32) -> vec4<f32> {
+    let r = f32(c & 255u) / 255.0;
+    let g = f32((c >> 8u) & 255u) / 255.0;
+    let b = f32((c >> 16u) & 255u) / 255.0;
+    let a = f32(c >> 24u) / 255.0;
+    if (uniforms.decode_srgb == 0u) {
+        return vec4<f32>(r, g, b, a);
+    }
+    return vec4<f32>(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a);
+}
 
-    // Calculate the char coords and the local coords inside a character block
-    let cp = vec2<i32>(i32(p.x / f32(uniforms.font_width)), i32(p.y / f32(uniforms.font_height)));
-    let lp = vec2<i32>(i32(p.x) % i32(uniforms.font_width), i32(p.y) % i32(uniforms.font_height));
+// Draw a pixel of a cell, from the cell's texel and the local coords of the
+// pixel inside it.
+fn draw_cell(cell: vec4<u32>, lp: vec2<i32>) -> vec4<f32> {
+    // Unpack the cell's colours
+    let text = cell.x;
+    var fore: vec4<f32> = unpack_colour(cell.y);
+    var back: vec4<f32> = unpack_colour(cell.z);
+
+    // The attributes are in the top byte, in the same order as the
+    // `Char::BOLD`, `Char::UNDERLINE`, `Char::REVERSE` and `Char::BLINK` bits.
+    let attributes = text >> 24u;
+    let bold = (attributes & 1u) != 0u;
+    let underline = (attributes & 2u) != 0u;
+    let reverse = (attributes & 4u) != 0u;
+    let blink = (attributes & 8u) != 0u;
+
+    // Reversed characters swap their ink and paper.
+    if (reverse) {
+        let ink = fore;
+        fore = back;
+        back = ink;
+    }
 
-    // Look up the textures
-    let fore = textureLoad(t_fore, cp, 0);
-    let back = textureLoad(t_back, cp, 0);
-    let text = textureLoad(t_text, cp, 0);
+    // Bold uses the bold font if there is one, or brightens the ink halfway
+    // to white if not.
+    if (bold && uniforms.has_bold_font == 0u) {
+        fore = vec4<f32>(mix(fore.rgb, vec3<f32>(1.0, 1.0, 1.0), vec3<f32>(0.5, 0.5, 0.5)), fore.a);
+    }
 
-    // Calculate the ASCII character code
-    let c = i32(text.x * 255.0);
+    // Blinking characters hide their glyph and underline for every other
+    // 250ms, which must match `BLINK_MS` in render.rs.
+    if (blink && (uniforms.time_ms / 250u) % 2u == 1u) {
+        return back;
+    }
 
-    // Calculate the character coords in the font texture.  We expect the font
-    // texture to be 16*16 characters.
-    let fx: i32 = c % 16;
-    let fy: i32 = c / 16;
+    // Underlined characters fill the bottom row of font pixels with ink.
+    if (underline && lp.y == i32(uniforms.font_height) - 1) {
+        return fore;
+    }
+
+    // Calculate the character code from the ASCII code in the lowest byte and
+    // the page in the next.
+    let c = i32(text & 65535u);
+
+    // Characters beyond the end of the font are drawn as blank.
+    if (c >= i32(uniforms.font_glyph_count)) {
+        return back;
+    }
+
+    // Calculate the character coords in the font texture.  The font texture
+    // is a grid of font_columns*font_rows characters.
+    let fx: i32 = c % i32(uniforms.font_columns);
+    let fy: i32 = c / i32(uniforms.font_columns);
 
     // Calculate the pixel coords within the font texture
     let lx = fx * i32(uniforms.font_width) + lp.x;
     let ly = fy * i32(uniforms.font_height) + lp.y;
 
     // Fetch the pixel in the font texture
-    let font_pix = textureLoad(t_font, vec2<i32>(lx, ly), 0);
-
-    if (font_pix.r < 0.5) {
-        return back;
+    var font_pix: vec4<f32>;
+    if (bold && uniforms.has_bold_font != 0u) {
+        font_pix = textureLoad(t_bold, vec2<i32>(lx, ly), 0);
     } else {
-        return fore;
+        font_pix = textureLoad(t_font, vec2<i32>(lx, ly), 0);
+    }
+
+    // The coverage is how much ink the pixel contains, from its grey level and
+    // its alpha.  Transparent pixels, such as those made by a colour key, have
+    // no coverage so always show the paper colour.
+    let coverage = font_pix.r * font_pix.a;
+
+    if (uniforms.font_smoothing == 0u) {
+        if (coverage < 0.5) {
+            return back;
+        } else {
+            return fore;
+        }
     }
+
+    // Blend the paper and ink by the coverage.  Fonts with only black and
+    // white pixels have a coverage of exactly 0 or 1, so are unaffected.
+    return mix(back, fore, vec4<f32>(coverage, coverage, coverage, coverage));
 }
 
+@fragment
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    // Calculate the pixel coords relative to the top-left corner of the
+    // character grid, which is moved away from the corner of the window when
+    // the grid is centred.
+    let p = vec2<f32>(
+        pos.x - 0.5 - f32(uniforms.origin_x),
+        pos.y - 0.5 - f32(uniforms.origin_y));
+
+    // Calculate the size of a character block on the screen, which is the font
+    // character size multiplied by the font scale.
+    let cell_width = i32(uniforms.font_width * uniforms.font_scale);
+    let cell_height = i32(uniforms.font_height * uniforms.font_scale);
+
+    // Calculate the char coords and the local coords inside a character block.
+    // The local coords are divided by the scale so that each font pixel covers
+    // a scale*scale block of screen pixels.
+    let cp = vec2<i32>(i32(p.x / f32(cell_width)), i32(p.y / f32(cell_height)));
+    let lp = vec2<i32>(
+        (i32(p.x) % cell_width) / i32(uniforms.font_scale),
+        (i32(p.y) % cell_height) / i32(uniforms.font_scale));
+
+    // Pixels outside the grid are left as the clear colour, forming a border.
+    let grid_size = vec2<i32>(textureDimensions(t_cells));
+    if (p.x < 0.0 || p.y < 0.0 || cp.x >= grid_size.x || cp.y >= grid_size.y) {
+        discard;
+    }
+
+    let colour = draw_cell(textureLoad(t_cells, cp, 0), lp);
+    if (uniforms.has_overlay == 0u) {
+        return colour;
+    }
+
+    // The overlay layer is blended over the cells by the alpha of its colours,
+    // except where its glyph word is 0, which is left fully transparent.
+    let overlay_cell = textureLoad(t_overlay, cp, 0);
+    if (overlay_cell.x == 0u) {
+        return colour;
+    }
+    let overlay = draw_cell(overlay_cell, lp);
+    return vec4<f32>(mix(colour.rgb, overlay.rgb, vec3<f32>(overlay.a, overlay.a, overlay.a)), colour.a);
+}
@@ -0,0 +1,346 @@
+//
+// Text fields
+// A single line of editable text, such as a character's name or the name of
+// a saved game
+//
+
+use std::cell::Cell;
+
+use winit::event::VirtualKeyCode;
+
+use crate::{charmap::cp437_glyph, Canvas, Char, KeyState, Point, Rect};
+
+/// What a key did to a `TextField`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldEvent {
+    /// The key was not for the field, or did nothing.
+    Ignored,
+    /// The cursor moved, so the field should be drawn again.
+    Moved,
+    /// The text was edited.
+    Changed,
+    /// The text was accepted with Enter.
+    Submitted,
+    /// The field was dismissed with Escape.  The window closes on Escape
+    /// unless `Builder::with_exit_on_escape(false)` is used, so this is only
+    /// seen with that setting.
+    Cancelled,
+}
+
+/// How `TextField::draw` draws a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldStyle {
+    pub(crate) ink: u32,
+    pub(crate) paper: u32,
+    pub(crate) cursor_ink: u32,
+    pub(crate) cursor_paper: u32,
+}
+
+impl FieldStyle {
+    /// Create a style with the text in these colours, and the cursor in the
+    /// same colours swapped.
+    pub fn new(ink: impl Into<u32>, paper: impl Into<u32>) -> Self {
+        let (ink, paper) = (ink.into(), paper.into());
+        FieldStyle {
+            ink,
+            paper,
+            cursor_ink: paper,
+            cursor_paper: ink,
+        }
+    }
+
+    /// Draw the cursor in these colours.
+    pub fn with_cursor(mut self, ink: impl Into<u32>, paper: impl Into<u32>) -> Self {
+        self.cursor_ink = ink.into();
+        self.cursor_paper = paper.into();
+        self
+    }
+}
+
+/// A single line of editable text.
+///
+/// Keep it in the app, pass it key presses from `tick` with
+/// `TextField::handle_key`, and draw it in `present` with `TextField::draw`.
+/// Typed characters are inserted at the cursor, which is moved with Left,
+/// Right, Home and End.
+///
+/// When the text is wider than the field, the field scrolls to keep the
+/// cursor in view.
+#[derive(Debug, Clone, Default)]
+pub struct TextField {
+    text: String,
+    cursor: usize,
+    max_len: Option<usize>,
+    scroll: Cell<usize>,
+}
+
+impl TextField {
+    /// Create a field holding some text, with the cursor at the end.
+    pub fn new(text: &str) -> Self {
+        TextField {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+            max_len: None,
+            scroll: Cell::new(0),
+        }
+    }
+
+    /// Limit the text to a number of characters.  Longer text is cut short.
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self.set_text(&self.text.clone());
+        self
+    }
+
+    /// The text in the field.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, and move the cursor to the end.
+    pub fn set_text(&mut self, text: &str) {
+        let len = self.max_len.unwrap_or(usize::MAX);
+        self.text = text.chars().take(len).collect();
+        self.cursor = self.len();
+    }
+
+    /// The position of the cursor, in characters from the start.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Move the cursor.  Positions past the end move it to the end.
+    pub fn set_cursor(&mut self, cursor: usize) {
+        self.cursor = cursor.min(self.len());
+    }
+
+    /// Insert text at the cursor, such as text pasted from the clipboard,
+    /// and move the cursor past it.  Control characters such as newlines are
+    /// left out, as is any text past the maximum length.  Returns true if
+    /// the text changed.
+    pub fn insert_str(&mut self, text: &str) -> bool {
+        let room = self
+            .max_len
+            .map_or(usize::MAX, |max_len| max_len.saturating_sub(self.len()));
+        let inserted = text
+            .chars()
+            .filter(|ch| !ch.is_control())
+            .take(room)
+            .collect::<String>();
+        if inserted.is_empty() {
+            return false;
+        }
+        let i = self.byte_index(self.cursor);
+        self.text.insert_str(i, &inserted);
+        self.cursor += inserted.chars().count();
+        true
+    }
+
+    /// Edit the text in response to a key.  Typed characters are inserted,
+    /// Backspace and Delete remove the character before and after the
+    /// cursor, and Left, Right, Home and End move the cursor.  Enter submits
+    /// the text and Escape cancels the field, if the window lets the app see
+    /// Escape (see `Builder::with_exit_on_escape`).
+    pub fn handle_key(&mut self, key: &KeyState) -> FieldEvent {
+        // Typed characters arrive in `KeyState::code`, while keys such as
+        // Backspace come as control characters, and are handled below.
+        if let Some(ch) = key.code.filter(|ch| !ch.is_control()) {
+            let mut buffer = [0; 4];
+            return if self.insert_str(ch.encode_utf8(&mut buffer)) {
+                FieldEvent::Changed
+            } else {
+                FieldEvent::Ignored
+            };
+        }
+
+        let vkey = match key.vkey {
+            Some(vkey) if key.pressed => vkey,
+            _ => return FieldEvent::Ignored,
+        };
+        let len = self.len();
+        match vkey {
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => FieldEvent::Submitted,
+            VirtualKeyCode::Escape => FieldEvent::Cancelled,
+            VirtualKeyCode::Back if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.remove(self.cursor);
+                FieldEvent::Changed
+            }
+            VirtualKeyCode::Delete if self.cursor < len => {
+                self.remove(self.cursor);
+                FieldEvent::Changed
+            }
+            VirtualKeyCode::Left if self.cursor > 0 => {
+                self.cursor -= 1;
+                FieldEvent::Moved
+            }
+            VirtualKeyCode::Right if self.cursor < len => {
+                self.cursor += 1;
+                FieldEvent::Moved
+            }
+            VirtualKeyCode::Home if self.cursor > 0 => {
+                self.cursor = 0;
+                FieldEvent::Moved
+            }
+            VirtualKeyCode::End if self.cursor < len => {
+                self.cursor = len;
+                FieldEvent::Moved
+            }
+            _ => FieldEvent::Ignored,
+        }
+    }
+
+    /// Draw the part of the text that fits in a field `width` cells wide,
+    /// with the cell under the cursor in the cursor colours.  The cursor can
+    /// be after the last character, so a cell is kept free for it there.
+    pub fn draw<C: Canvas + ?Sized>(
+        &self,
+        canvas: &mut C,
+        p: Point,
+        width: usize,
+        style: &FieldStyle,
+    ) {
+        canvas.fill_rect(
+            Rect::new(p.x, p.y, width, 1),
+            Char::new(b' ', style.ink, style.paper),
+        );
+        if width == 0 {
+            return;
+        }
+
+        // Scroll as little as possible to bring the cursor into view, without
+        // leaving cells empty at the end.
+        let mut scroll = self.scroll.get().min(self.cursor);
+        if self.cursor >= scroll.saturating_add(width) {
+            scroll = self.cursor + 1 - width;
+        }
+        scroll = scroll.min((self.len() + 1).saturating_sub(width));
+        self.scroll.set(scroll);
+
+        let visible = self.text.chars().skip(scroll).take(width);
+        canvas.draw_str_unicode(p, &visible.collect::<String>(), style.ink, style.paper);
+
+        let under = self.text.chars().nth(self.cursor).unwrap_or(' ');
+        let x = (p.x as i64).saturating_add((self.cursor - scroll) as i64);
+        if x <= i32::MAX as i64 {
+            canvas.fill_rect(
+                Rect::new(x as i32, p.y, 1, 1),
+                Char::new(
+                    cp437_glyph(under).unwrap_or(b'?'),
+                    style.cursor_ink,
+                    style.cursor_paper,
+                ),
+            );
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    /// The byte index of a character in the text, or the length of the text
+    /// if it is past the end.
+    fn byte_index(&self, index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(index)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    fn remove(&mut self, index: usize) {
+        let i = self.byte_index(index);
+        self.text.remove(i);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Image;
+
+    fn press(vkey: VirtualKeyCode) -> KeyState {
+        KeyState {
+            pressed: true,
+            shift: false,
+            ctrl: false,
+            alt: false,
+            vkey: Some(vkey),
+            code: None,
+        }
+    }
+
+    fn typed(ch: char) -> KeyState {
+        KeyState {
+            code: Some(ch),
+            ..press(VirtualKeyCode::A)
+        }
+    }
+
+    /// Draw a field `width` cells wide, returning the text and the x of the
+    /// cursor, which is drawn in colour 3.
+    fn drawn(field: &TextField, width: usize) -> (String, Option<usize>) {
+        let mut image = Image::new(width, 1);
+        let style = FieldStyle::new(1u32, 2u32).with_cursor(4u32, 3u32);
+        field.draw(&mut image, Point::new(0, 0), width, &style);
+        let cursor = image.back_image.iter().position(|&paper| paper == 3);
+        (image.to_plain_text(), cursor)
+    }
+
+    #[test]
+    fn cursor_at_the_end() {
+        let mut field = TextField::new("abc");
+        assert_eq!(field.cursor(), 3);
+        assert_eq!(
+            field.handle_key(&press(VirtualKeyCode::Right)),
+            FieldEvent::Ignored
+        );
+        assert_eq!(
+            field.handle_key(&press(VirtualKeyCode::End)),
+            FieldEvent::Ignored
+        );
+        assert_eq!(
+            field.handle_key(&press(VirtualKeyCode::Delete)),
+            FieldEvent::Ignored
+        );
+        assert_eq!(field.handle_key(&typed('d')), FieldEvent::Changed);
+        assert_eq!((field.text(), field.cursor()), ("abcd", 4));
+        assert_eq!(
+            field.handle_key(&press(VirtualKeyCode::Back)),
+            FieldEvent::Changed
+        );
+        assert_eq!((field.text(), field.cursor()), ("abc", 3));
+        field.set_cursor(usize::MAX);
+        assert_eq!(field.cursor(), 3);
+
+        // A cell is kept free after the text for the cursor.
+        assert_eq!(drawn(&field, 4), ("abc \n".to_string(), Some(3)));
+        assert_eq!(drawn(&field, 3), ("bc \n".to_string(), Some(2)));
+    }
+
+    #[test]
+    fn one_cell_fields_show_the_cursor() {
+        let mut field = TextField::new("abc");
+        assert_eq!(drawn(&field, 1), (" \n".to_string(), Some(0)));
+        field.set_cursor(1);
+        assert_eq!(drawn(&field, 1), ("b\n".to_string(), Some(0)));
+        field.handle_key(&press(VirtualKeyCode::Home));
+        assert_eq!(drawn(&field, 1), ("a\n".to_string(), Some(0)));
+        assert_eq!(drawn(&field, 0), (String::new(), None));
+    }
+
+    #[test]
+    fn max_len_and_escape() {
+        let mut field = TextField::new("abcdef").with_max_len(4);
+        assert_eq!((field.text(), field.cursor()), ("abcd", 4));
+        assert_eq!(field.handle_key(&typed('x')), FieldEvent::Ignored);
+        assert!(!field.insert_str("\n"));
+        assert_eq!(
+            field.handle_key(&press(VirtualKeyCode::Escape)),
+            FieldEvent::Cancelled
+        );
+        assert_eq!(
+            field.handle_key(&press(VirtualKeyCode::Return)),
+            FieldEvent::Submitted
+        );
+    }
+}
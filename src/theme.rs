@@ -0,0 +1,78 @@
+//
+// Themes
+// Named colours for each part of an application's user interface
+//
+
+use std::collections::HashMap;
+
+use crate::Rgba;
+
+/// A set of colours, each looked up by a name such as "menu.ink" or
+/// "accent".
+///
+/// Applications can keep the colours of their user interface in a theme so
+/// that users can change them.  With the `serde` feature, a theme can be read
+/// from and written to files such as JSON, with the colours written as hex
+/// strings like "#FF8800" so that they are easy to edit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Theme(HashMap<String, u32>);
+
+impl Theme {
+    pub fn new() -> Self {
+        Theme(HashMap::new())
+    }
+
+    /// Return the colour with the given name, in the same format as
+    /// `colour()`.
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.0.get(name).copied()
+    }
+
+    /// Return the colour with the given name, or a default if the theme does
+    /// not have it.
+    pub fn get_or(&self, name: &str, default: impl Into<u32>) -> u32 {
+        self.get(name).unwrap_or_else(|| default.into())
+    }
+
+    /// Return the colour with the given name, split into its components.
+    pub fn get_rgba(&self, name: &str) -> Option<Rgba> {
+        self.get(name).map(Rgba::from)
+    }
+
+    /// Add a colour to the theme, replacing any colour with the same name.
+    pub fn set(&mut self, name: impl Into<String>, c: impl Into<u32>) {
+        self.0.insert(name.into(), c.into());
+    }
+
+    /// Remove a colour from the theme, returning it if it was there.
+    pub fn remove(&mut self, name: &str) -> Option<u32> {
+        self.0.remove(name)
+    }
+
+    /// The number of colours in the theme.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the names and colours in the theme, in no particular
+    /// order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.0.iter().map(|(name, &c)| (name.as_str(), c))
+    }
+}
+
+impl From<HashMap<String, u32>> for Theme {
+    fn from(colours: HashMap<String, u32>) -> Self {
+        Theme(colours)
+    }
+}
+
+impl From<Theme> for HashMap<String, u32> {
+    fn from(theme: Theme) -> Self {
+        theme.0
+    }
+}
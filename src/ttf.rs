@@ -0,0 +1,129 @@
+//
+// TrueType and OpenType font rasterisation
+//
+
+use fontdue::{Font, FontSettings, Metrics};
+
+use crate::{Charmap, FontData, FontError, Result};
+
+//
+// Implements TrueType loading for the FontData structure
+//
+
+impl FontData {
+    /// Rasterise a TrueType or OpenType font into a 16x16 grid of characters.
+    ///
+    /// # Arguments
+    ///
+    /// * __bytes__ - The contents of the TTF or OTF file.
+    /// * __px_height__ - The size of the font in pixels.
+    /// * __charmap__ - Maps each of the 256 characters in the grid to the
+    ///   Unicode character that is rasterised for it.  `Charmap::default()`
+    ///   uses code page 437.
+    ///
+    /// # Notes
+    ///
+    /// The characters are made monospaced by using the widest character's
+    /// advance as the width of every cell.  Each pixel holds the
+    /// anti-aliased coverage of the character as a grey level.  Characters
+    /// that the font does not contain are drawn as a box, while control
+    /// characters are left blank.
+    ///
+    /// This function is only available with the `ttf` feature.
+    pub fn from_ttf(bytes: &[u8], px_height: u32, charmap: &Charmap) -> Result<FontData> {
+        if px_height == 0 {
+            return Err(FontError::CellTooSmall {
+                width: 0,
+                height: 0,
+            }
+            .into());
+        }
+        let font = Font::from_bytes(bytes, FontSettings::default())
+            .map_err(|err| FontError::Malformed(err.to_string()))?;
+        let px = px_height as f32;
+
+        // Work out where the baseline sits in the cell, and how tall the cell
+        // must be to fit the ascent and descent of the font.
+        let (baseline, height) = match font.horizontal_line_metrics(px) {
+            Some(line) => (
+                line.ascent.ceil() as i32,
+                (line.ascent.ceil() - line.descent.floor()) as u32,
+            ),
+            None => ((px * 0.8).ceil() as i32, px_height),
+        };
+
+        // Rasterise every character, leaving control characters blank and
+        // marking characters missing from the font so they can be drawn as a
+        // box.
+        let glyphs = (0..=255u8)
+            .map(|glyph| {
+                let ch = charmap.char_for(glyph);
+                if ch.is_control() {
+                    Glyph::Blank
+                } else if font.lookup_glyph_index(ch) == 0 {
+                    Glyph::Missing
+                } else {
+                    let (metrics, bitmap) = font.rasterize(ch, px);
+                    Glyph::Bitmap(metrics, bitmap)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // The width of the cell is the widest advance of all the characters.
+        let width = glyphs
+            .iter()
+            .filter_map(|glyph| match glyph {
+                Glyph::Bitmap(metrics, _) => Some(
+                    (metrics.advance_width.ceil() as i32)
+                        .max(metrics.xmin.max(0) + metrics.width as i32),
+                ),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+            .max(1) as u32;
+
+        FontData::check_size(width, height, 16, 16)?;
+        Ok(FontData::from_fn(width, height, |glyph, x, y| {
+            let coverage = match &glyphs[glyph as usize] {
+                Glyph::Blank => 0,
+                Glyph::Missing => {
+                    // Draw a box inset by a pixel for missing characters.
+                    let (right, bottom) = (width.saturating_sub(2), height.saturating_sub(2));
+                    let on_edge = x == 1 || x == right || y == 1 || y == bottom;
+                    if on_edge && x >= 1 && x <= right && y >= 1 && y <= bottom {
+                        255
+                    } else {
+                        0
+                    }
+                }
+                Glyph::Bitmap(metrics, bitmap) => {
+                    // Find the pixel in the character's bitmap, which sits on
+                    // the baseline offset by its bounds.
+                    let bx = x as i32 - metrics.xmin;
+                    let by = y as i32 - (baseline - metrics.ymin - metrics.height as i32);
+                    if bx >= 0
+                        && by >= 0
+                        && (bx as usize) < metrics.width
+                        && (by as usize) < metrics.height
+                    {
+                        bitmap[by as usize * metrics.width + bx as usize] as u32
+                    } else {
+                        0
+                    }
+                }
+            };
+            0xff000000 | (coverage << 16) | (coverage << 8) | coverage
+        }))
+    }
+}
+
+/// A rasterised character ready to be placed in the font's grid.
+enum Glyph {
+    /// The character is a control character and is left blank.
+    Blank,
+    /// The font does not contain the character.
+    Missing,
+    /// The character's metrics and coverage bitmap.
+    Bitmap(Metrics, Vec<u8>),
+}
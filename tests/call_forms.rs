@@ -0,0 +1,44 @@
+//
+// Call form tests
+// The ways of passing colours that worked before `Colour` and `Rgba` could be
+// converted, which must keep compiling.
+//
+
+use mterm::*;
+
+#[test]
+fn untyped_literals_are_u32_colours() {
+    let ch = Char::new(b'x', 0xff00ff00, 0);
+    assert_eq!((ch.ink, ch.paper), (0xff00_ff00, 0));
+    let ch = Char::new_glyph(0x1ab, 0xff00ff00, 0);
+    assert_eq!((ch.ch, ch.ink, ch.paper), (0x1ab, 0xff00_ff00, 0));
+
+    let mut image = Image::new(4, 1);
+    image.clear(0xffffffff, 0);
+    image.draw_string(Point::new(0, 0), "ab", 0xff0000ff, 0xff000000);
+    assert_eq!(
+        image.fore_image,
+        [0xff00_00ff, 0xff00_00ff, 0xffff_ffff, 0xffff_ffff]
+    );
+    assert_eq!(image.back_image, [0xff00_0000, 0xff00_0000, 0, 0]);
+
+    let _ = Builder::new().with_clear_colour(0xff202020);
+}
+
+#[test]
+fn colours_convert_with_into() {
+    let mut image = Image::new(7, 1);
+    image.clear(Colour::White.into(), Colour::Black.into());
+    image.draw_string(
+        Point::new(1, 0),
+        "Hello",
+        Colour::Yellow.into(),
+        Colour::Blue.into(),
+    );
+    let ch = Char::new(b'x', Rgba::new(1, 2, 3, 4).into(), Colour::Red.into());
+    assert_eq!((ch.ink, ch.paper), (0x0403_0201, colour(255, 0, 0)));
+    assert_eq!(image.fore_image[0], Colour::White.into());
+    assert_eq!(image.back_image[1], u32::from(Colour::Blue));
+
+    let _ = Builder::new().with_clear_colour(Colour::DarkBlue.into());
+}
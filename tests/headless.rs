@@ -0,0 +1,440 @@
+//
+// Headless rendering tests
+// Render small grids on the graphics card without a window and check the
+// pixels read back.  They are skipped on machines with no graphics adapter;
+// set MTERM_BACKEND=gl to run them on a software GL driver.
+//
+
+use futures::executor::block_on;
+use mterm::*;
+use winit::dpi::PhysicalSize;
+
+/// Create a headless renderer big enough for a grid of characters in the
+/// default font, or None if there is no adapter to render with.
+fn renderer(columns: u32, rows: u32) -> Option<(RenderState, FontData)> {
+    renderer_with_font(BuiltinFont::Default.font_data().unwrap(), columns, rows)
+}
+
+/// Create a headless renderer big enough for a grid of characters in the
+/// given font, or None if there is no adapter to render with.
+fn renderer_with_font(font: FontData, columns: u32, rows: u32) -> Option<(RenderState, FontData)> {
+    match block_on(RenderState::new_headless(
+        columns * font.width,
+        rows * font.height,
+        &font,
+    )) {
+        Ok(render) => Some((render, font)),
+        Err(RenderError::AdapterNotFound(..)) => {
+            eprintln!("skipping, there is no graphics adapter");
+            None
+        }
+        Err(err) => panic!("could not create a headless renderer, {}", err),
+    }
+}
+
+/// Copy an image into the renderer's grid.
+fn load(render: &mut RenderState, image: &Image) {
+    let (fore_image, back_image, text_image) = render.images();
+    fore_image.copy_from_slice(&image.fore_image);
+    back_image.copy_from_slice(&image.back_image);
+    text_image.copy_from_slice(&image.text_image);
+}
+
+/// The RGB of the pixel at (x, y) in a buffer read back from a renderer.
+fn pixel(buffer: &[u8], width: u32, x: u32, y: u32) -> [u8; 3] {
+    let i = ((y * width + x) * 4) as usize;
+    [buffer[i], buffer[i + 1], buffer[i + 2]]
+}
+
+#[test]
+fn renders_known_cells() {
+    let (mut render, _) = match renderer(3, 1) {
+        Some(render) => render,
+        None => return,
+    };
+    let mut image = Image::new(3, 1);
+    image.fill_rect(
+        Rect::new(0, 0, 1, 1),
+        Char::new(b' ', Colour::White.into(), Colour::Red.into()),
+    );
+    image.fill_rect(
+        Rect::new(1, 0, 1, 1),
+        Char::new(b' ', Colour::White.into(), Colour::Blue.into()),
+    );
+    image.fill_rect(
+        Rect::new(2, 0, 1, 1),
+        Char::new(219, Colour::Green.into(), Colour::Black.into()),
+    );
+    load(&mut render, &image);
+
+    let buffer = render.render_to_buffer().unwrap();
+    let (cell_width, cell_height) = render.cell_size();
+    let width = 3 * cell_width;
+    assert_eq!(buffer.len(), (width * cell_height * 4) as usize);
+    for y in 0..cell_height {
+        for x in 0..cell_width {
+            assert_eq!(pixel(&buffer, width, x, y), [255, 0, 0]);
+            assert_eq!(pixel(&buffer, width, cell_width + x, y), [0, 0, 255]);
+            assert_eq!(pixel(&buffer, width, 2 * cell_width + x, y), [0, 255, 0]);
+        }
+    }
+}
+
+#[test]
+fn matches_the_cpu_renderer() {
+    let (mut render, font) = match renderer(16, 4) {
+        Some(render) => render,
+        None => return,
+    };
+    let mut image = Image::new(16, 4);
+    image.fill_with(Rect::new(0, 0, 16, 4), |x, y| {
+        let i = (y * 16 + x) as usize;
+        Char::new(
+            b'A' + i as u8 % 26,
+            PALETTE_CGA[i % 16],
+            PALETTE_CGA[(i + 8) % 16],
+        )
+    });
+    load(&mut render, &image);
+
+    let gpu = render.render_to_buffer().unwrap();
+    let cpu = image::load_from_memory(&render_image_to_png(&image, &font).unwrap())
+        .unwrap()
+        .to_rgba8();
+    assert_eq!(gpu.len(), cpu.as_raw().len());
+    let worst = gpu
+        .iter()
+        .zip(cpu.as_raw())
+        .map(|(g, c)| (*g as i32 - *c as i32).abs())
+        .max()
+        .unwrap();
+    assert!(worst <= 2, "a component differs by {}", worst);
+}
+
+#[test]
+fn half_coverage_blends_to_the_midpoint() {
+    // A single 8x8 glyph whose every texel is 50% grey.
+    let font = FontData {
+        data: vec![0xff80_8080; 8 * 8],
+        width: 8,
+        height: 8,
+        columns: 1,
+        rows: 1,
+        glyph_count: 1,
+    };
+    let (mut render, _) = match renderer_with_font(font, 1, 1) {
+        Some(render) => render,
+        None => return,
+    };
+    let mut image = Image::new(1, 1);
+    image.fill_rect(
+        Rect::new(0, 0, 1, 1),
+        Char::new(0, Colour::Red.into(), Colour::Blue.into()),
+    );
+    load(&mut render, &image);
+
+    // Half of red and half of blue in linear space, encoded back to sRGB.
+    let buffer = render.render_to_buffer().unwrap();
+    for y in 0..8 {
+        for x in 0..8 {
+            let [r, g, b] = pixel(&buffer, 8, x, y);
+            assert!(
+                (r as i32 - 188).abs() <= 1 && g == 0 && (b as i32 - 188).abs() <= 1,
+                "({}, {}) is {:?}",
+                x,
+                y,
+                [r, g, b]
+            );
+        }
+    }
+}
+
+/// A font of two 8x8 glyphs: 0 is blank and 1 has its left half filled.
+fn half_font() -> FontData {
+    let data = (0..16 * 8)
+        .map(|i| {
+            if i % 16 >= 8 && i % 16 < 12 {
+                0xffff_ffff
+            } else {
+                0
+            }
+        })
+        .collect();
+    FontData {
+        data,
+        width: 8,
+        height: 8,
+        columns: 2,
+        rows: 1,
+        glyph_count: 2,
+    }
+}
+
+/// Draw one character of `half_font` with a renderer and return its 8x8
+/// pixels, along with the renderer's time from before and after drawing.
+fn draw_cell(render: &mut RenderState, ch: Char) -> (Vec<[u8; 3]>, u32, u32) {
+    let mut image = Image::new(1, 1);
+    image.fill_rect(Rect::new(0, 0, 1, 1), ch);
+    load(render, &image);
+    let before = render.time_ms();
+    let buffer = render.render_to_buffer().unwrap();
+    let after = render.time_ms();
+    let pixels = (0..64).map(|i| pixel(&buffer, 8, i % 8, i / 8)).collect();
+    (pixels, before, after)
+}
+
+/// Draw one character of `half_font` in a new renderer and return its 8x8
+/// pixels, or None if there is no adapter to render with.
+fn render_cell(ch: Char) -> Option<Vec<[u8; 3]>> {
+    let (mut render, _) = renderer_with_font(half_font(), 1, 1)?;
+    Some(draw_cell(&mut render, ch).0)
+}
+
+/// Check pixels against a golden picture of a cell, where '#' is `ink` and
+/// '.' is `paper`.  Each component may be out by 1.
+fn assert_cell(pixels: &[[u8; 3]], golden: [&str; 8], ink: [u8; 3], paper: [u8; 3]) {
+    for (y, row) in golden.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            let expected = if c == '#' { ink } else { paper };
+            let actual = pixels[y * 8 + x];
+            let close = actual
+                .iter()
+                .zip(&expected)
+                .all(|(a, e)| (*a as i32 - *e as i32).abs() <= 1);
+            assert!(close, "({}, {}) is {:?}, not {:?}", x, y, actual, expected);
+        }
+    }
+}
+
+// Golden pictures of a cell of `half_font`, where '#' is ink and '.' is paper.
+#[rustfmt::skip]
+const PLAIN: [&str; 8] = [
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+];
+#[rustfmt::skip]
+const UNDERLINED: [&str; 8] = [
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+    "####....",
+    "########",
+];
+#[rustfmt::skip]
+const BLANK: [&str; 8] = [
+    "........",
+    "........",
+    "........",
+    "........",
+    "........",
+    "........",
+    "........",
+    "........",
+];
+const RED: [u8; 3] = [255, 0, 0];
+const BLUE: [u8; 3] = [0, 0, 255];
+
+#[test]
+fn draws_plain_characters() {
+    if let Some(pixels) = render_cell(Char::new(1, Colour::Red.into(), Colour::Blue.into())) {
+        assert_cell(&pixels, PLAIN, RED, BLUE);
+    }
+}
+
+#[test]
+fn draws_bold_characters() {
+    // With no bold font, the ink is brightened halfway to white in linear
+    // light, which is 188 in sRGB.
+    if let Some(pixels) = render_cell(Char::new(1, Colour::Red.into(), Colour::Blue.into()).bold())
+    {
+        assert_cell(&pixels, PLAIN, [255, 188, 188], BLUE);
+    }
+}
+
+#[test]
+fn draws_underlined_characters() {
+    if let Some(pixels) =
+        render_cell(Char::new(1, Colour::Red.into(), Colour::Blue.into()).underline())
+    {
+        assert_cell(&pixels, UNDERLINED, RED, BLUE);
+    }
+}
+
+#[test]
+fn draws_reversed_characters() {
+    if let Some(pixels) =
+        render_cell(Char::new(1, Colour::Red.into(), Colour::Blue.into()).reverse())
+    {
+        assert_cell(&pixels, PLAIN, BLUE, RED);
+    }
+}
+
+#[test]
+fn draws_blinking_characters() {
+    // Blinking characters are hidden for every other 250ms, so draw until
+    // both halves have been seen within a single half.
+    let (mut render, _) = match renderer_with_font(half_font(), 1, 1) {
+        Some(render) => render,
+        None => return,
+    };
+    let ch = Char::new(1, Colour::Red.into(), Colour::Blue.into())
+        .blink()
+        .underline();
+    let (mut shown, mut hidden) = (false, false);
+    for _ in 0..40 {
+        let (pixels, before, after) = draw_cell(&mut render, ch);
+        if before / 250 == after / 250 {
+            if (before / 250) % 2 == 1 {
+                assert_cell(&pixels, BLANK, RED, BLUE);
+                hidden = true;
+            } else {
+                assert_cell(&pixels, UNDERLINED, RED, BLUE);
+                shown = true;
+            }
+            if shown && hidden {
+                return;
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(130));
+    }
+    panic!("could not draw in both halves of the blink");
+}
+
+#[test]
+fn matches_the_cpu_renderer_with_attributes() {
+    let font = half_font();
+    let (mut render, _) = match renderer_with_font(font.clone(), 4, 1) {
+        Some(render) => render,
+        None => return,
+    };
+    let ch = Char::new(1, Colour::Yellow.into(), Colour::DarkBlue.into());
+    let mut image = Image::new(4, 1);
+    image.draw_char(Point::new(0, 0), ch);
+    image.draw_char(Point::new(1, 0), ch.bold());
+    image.draw_char(Point::new(2, 0), ch.underline());
+    image.draw_char(Point::new(3, 0), ch.reverse().bold());
+    load(&mut render, &image);
+
+    let gpu = render.render_to_buffer().unwrap();
+    let cpu = image::load_from_memory(&render_image_to_png(&image, &font).unwrap())
+        .unwrap()
+        .to_rgba8();
+    let worst = gpu
+        .iter()
+        .zip(cpu.as_raw())
+        .map(|(g, c)| (*g as i32 - *c as i32).abs())
+        .max()
+        .unwrap();
+    assert!(worst <= 1, "a component differs by {}", worst);
+}
+
+#[test]
+fn minimising_and_restoring_keeps_the_grid() {
+    let (mut render, font) = match renderer(3, 2) {
+        Some(render) => render,
+        None => return,
+    };
+    let size = PhysicalSize::new(3 * font.width, 2 * font.height);
+    let mut image = Image::new(3, 2);
+    image.fill_rect(
+        Rect::new(0, 0, 3, 2),
+        Char::new(b' ', Colour::White.into(), Colour::Red.into()),
+    );
+    load(&mut render, &image);
+
+    // A minimised window is resized to nothing, all at once or while being
+    // dragged, and the grid keeps its size until it is restored.
+    render.resize(PhysicalSize::new(0, 0));
+    assert_eq!(render.chars_size(), (3, 2));
+    assert!(render.render_to_buffer().unwrap().is_empty());
+    render.resize(size);
+    render.resize_surface(PhysicalSize::new(0, 0));
+    render.fit_grid();
+    render.resize_surface(PhysicalSize::new(1, 0));
+    render.fit_grid();
+    render.resize_surface(size);
+    render.fit_grid();
+
+    assert_eq!(render.chars_size(), (3, 2));
+    let pixels = render.render_to_buffer().unwrap();
+    assert_eq!(pixels.len(), (4 * size.width * size.height) as usize);
+    assert!(pixels.chunks(4).all(|p| p[..3] == [255, 0, 0]));
+}
+
+#[test]
+fn set_present_mode_returns_the_mode_used() {
+    let (mut render, _) = match renderer(2, 2) {
+        Some(render) => render,
+        None => return,
+    };
+    // An offscreen target has no surface, so any mode is kept.
+    let mode = wgpu::PresentMode::Immediate;
+    assert_eq!(render.set_present_mode(mode), mode);
+    assert_eq!(render.present_mode(), mode);
+}
+
+/// Render a red grid with an overlay, returning the pixels read back.
+fn render_red_with(overlay: &mut dyn FnMut(OverlayContext)) -> Option<Vec<u8>> {
+    let (mut render, _) = renderer(2, 1)?;
+    let mut image = Image::new(2, 1);
+    image.fill_rect(
+        Rect::new(0, 0, 2, 1),
+        Char::new(b' ', Colour::White.into(), Colour::Red.into()),
+    );
+    load(&mut render, &image);
+    Some(render.render_to_buffer_with_overlay(overlay).unwrap())
+}
+
+#[test]
+fn overlay_draws_over_the_grid() {
+    let pixels = render_red_with(&mut |context| {
+        context
+            .encoder
+            .begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Test overlay"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: context.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLUE),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+    });
+    if let Some(pixels) = pixels {
+        assert!(pixels.chunks(4).all(|p| p[..3] == [0, 0, 255]));
+    }
+}
+
+#[test]
+fn invalid_overlay_keeps_the_grid() {
+    let pixels = render_red_with(&mut |context| {
+        // Copying out of a buffer that cannot be copied from is a validation
+        // error.
+        let buffer = context.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Test buffer"),
+            size: 4,
+            usage: wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        context
+            .encoder
+            .copy_buffer_to_buffer(&buffer, 0, &buffer, 0, 4);
+    });
+    if let Some(pixels) = pixels {
+        assert!(pixels.chunks(4).all(|p| p[..3] == [255, 0, 0]));
+    }
+}